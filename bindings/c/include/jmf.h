@@ -0,0 +1,46 @@
+/* C interface to Java Method Finder. */
+
+#ifndef JMF_H
+#define JMF_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A call to one of the targets. Strings stay valid until the results are freed. */
+typedef struct jmf_call {
+    /* Dotted caller class, e.g. "com.example.Caller" */
+    const char *class_name;
+    const char *method_name;
+    uint32_t line_number;
+    /* Invoked method, e.g. "java.util.Arrays#asList"; NULL for a method handle reference */
+    const char *callee;
+} jmf_call;
+
+typedef struct jmf_results jmf_results;
+
+/*
+ * Scans a class file, a jar, or the class files and jars below a directory for calls to `targets`
+ * (`target_count` strings of the form "Class#method" or "package.*"), skipping the class files that
+ * cannot be analyzed. Returns NULL on failure, see jmf_last_error().
+ */
+jmf_results *jmf_scan(const char *path, const char *const *targets, size_t target_count);
+
+size_t jmf_results_len(const jmf_results *results);
+
+/* The call at `index`, or NULL past the end. */
+const jmf_call *jmf_results_get(const jmf_results *results, size_t index);
+
+void jmf_results_free(jmf_results *results);
+
+/* Message of the last failure on the calling thread, or NULL. Valid until the next jmf_scan. */
+const char *jmf_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,140 @@
+//! A stable C interface to the scanner, declared in `include/jmf.h`: scan, iterate the results by
+//! index, free them.
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    path::Path,
+    ptr,
+};
+
+use anyhow::{bail, Context, Result};
+use java_method_finder::{analysis::Analyzer, policy::Rule};
+
+/// A call as seen from C; the strings belong to the [`jmf_results`] holding it.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct jmf_call {
+    pub class_name: *const c_char,
+    pub method_name: *const c_char,
+    pub line_number: u32,
+    /// Invoked method, e.g. `java.util.Arrays#asList`; null for a method handle reference
+    pub callee: *const c_char,
+}
+
+#[allow(non_camel_case_types)]
+pub struct jmf_results {
+    calls: Vec<jmf_call>,
+    /// Keeps the strings of `calls` alive
+    _strings: Vec<CString>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(error: Option<String>) {
+    let error = error.map(|error| CString::new(error.replace('\0', " ")).unwrap_or_default());
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = error);
+}
+
+/// # Safety
+///
+/// `path` must be a NUL-terminated string and `targets` must point to `target_count` of them.
+unsafe fn scan(path: *const c_char, targets: *const *const c_char, target_count: usize) -> Result<jmf_results> {
+    if path.is_null() || (targets.is_null() && target_count > 0) {
+        bail!("path and targets must not be null");
+    }
+    let path = CStr::from_ptr(path).to_str().context("path is not UTF-8")?;
+    let targets = if target_count == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(targets, target_count)
+    };
+    let rules = targets
+        .iter()
+        .map(|&target| {
+            if target.is_null() {
+                bail!("targets must not be null");
+            }
+            Rule::parse(CStr::from_ptr(target).to_str().context("target is not UTF-8")?)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut strings = Vec::new();
+    let mut string = |text: String| {
+        let string = CString::new(text).unwrap_or_default();
+        let pointer = string.as_ptr();
+        strings.push(string);
+        pointer
+    };
+    let calls = Analyzer::new(rules)
+        .scan(Path::new(path))?
+        .into_iter()
+        .map(|call| jmf_call {
+            class_name: string(call.class_name.replace('/', ".")),
+            method_name: string(call.method_name.to_string()),
+            line_number: u32::from(call.line_number),
+            callee: call.callee.map_or(ptr::null(), &mut string),
+        })
+        .collect();
+    Ok(jmf_results {
+        calls,
+        _strings: strings,
+    })
+}
+
+/// # Safety
+///
+/// `path` must be a NUL-terminated string and `targets` must point to `target_count` of them.
+#[no_mangle]
+pub unsafe extern "C" fn jmf_scan(
+    path: *const c_char,
+    targets: *const *const c_char,
+    target_count: usize,
+) -> *mut jmf_results {
+    match scan(path, targets, target_count) {
+        Ok(results) => {
+            set_last_error(None);
+            Box::into_raw(Box::new(results))
+        }
+        Err(e) => {
+            set_last_error(Some(format!("{:#}", e)));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+///
+/// `results` must come from [`jmf_scan`] and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn jmf_results_len(results: *const jmf_results) -> usize {
+    results.as_ref().map_or(0, |results| results.calls.len())
+}
+
+/// # Safety
+///
+/// `results` must come from [`jmf_scan`] and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn jmf_results_get(results: *const jmf_results, index: usize) -> *const jmf_call {
+    results
+        .as_ref()
+        .and_then(|results| results.calls.get(index))
+        .map_or(ptr::null(), |call| call as *const jmf_call)
+}
+
+/// # Safety
+///
+/// `results` must come from [`jmf_scan`] and not have been freed before.
+#[no_mangle]
+pub unsafe extern "C" fn jmf_results_free(results: *mut jmf_results) {
+    if !results.is_null() {
+        drop(Box::from_raw(results));
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn jmf_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ref().map_or(ptr::null(), |error| error.as_ptr()))
+}
@@ -0,0 +1,71 @@
+use std::{
+    ffi::{CStr, CString},
+    ptr,
+};
+
+use jmf_capi::{jmf_last_error, jmf_results_free, jmf_results_get, jmf_results_len, jmf_scan};
+
+#[test]
+fn should_scan_through_the_c_interface() {
+    let path = CString::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../tests/resources/example.jar"
+    ))
+    .unwrap();
+    let target = CString::new("java.util.Arrays#asList").unwrap();
+    let targets = [target.as_ptr()];
+
+    unsafe {
+        let results = jmf_scan(path.as_ptr(), targets.as_ptr(), targets.len());
+        assert!(!results.is_null());
+        assert_eq!(jmf_results_len(results), 1);
+        let call = &*jmf_results_get(results, 0);
+        assert_eq!(CStr::from_ptr(call.class_name).to_str(), Ok("com.example.TestClass"));
+        assert_eq!(CStr::from_ptr(call.method_name).to_str(), Ok("testMethod"));
+        assert_eq!(call.line_number, 9);
+        assert_eq!(CStr::from_ptr(call.callee).to_str(), Ok("java.util.Arrays#asList"));
+        assert!(jmf_results_get(results, 1).is_null());
+        jmf_results_free(results);
+
+        let target = CString::new("java.util.Arrays").unwrap();
+        let targets = [target.as_ptr()];
+        assert!(jmf_scan(path.as_ptr(), targets.as_ptr(), targets.len()).is_null());
+        assert_eq!(
+            CStr::from_ptr(jmf_last_error()).to_str(),
+            Ok("Invalid target 'java.util.Arrays', expected <class>#<method> or <package>.*")
+        );
+        assert!(jmf_scan(ptr::null(), ptr::null(), 0).is_null());
+    }
+}
+
+#[test]
+fn should_scan_interfaces_and_abstract_classes_through_the_c_interface() {
+    let path = CString::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../../tests/resources/abstract")).unwrap();
+    let target = CString::new("java.lang.String#trim").unwrap();
+    let targets = [target.as_ptr()];
+
+    unsafe {
+        let results = jmf_scan(path.as_ptr(), targets.as_ptr(), targets.len());
+        assert!(!results.is_null());
+        let callers: Vec<String> = (0..jmf_results_len(results))
+            .map(|index| {
+                let call = &*jmf_results_get(results, index);
+                format!(
+                    "{}#{} -> {}",
+                    CStr::from_ptr(call.class_name).to_str().unwrap(),
+                    CStr::from_ptr(call.method_name).to_str().unwrap(),
+                    CStr::from_ptr(call.callee).to_str().unwrap()
+                )
+            })
+            .collect();
+        assert_eq!(
+            callers,
+            [
+                "com.example.abstracts.Api#describe -> java.lang.String#trim",
+                "com.example.abstracts.Impl#run -> java.lang.String#trim",
+                "com.example.abstracts.Stripped#strip -> java.lang.String#trim",
+            ]
+        );
+        jmf_results_free(results);
+    }
+}
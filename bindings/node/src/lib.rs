@@ -0,0 +1,138 @@
+//! The `@jmf/core` Node.js addon:
+//!
+//! ```js
+//! const { scan } = require('@jmf/core');
+//!
+//! const calls = await scan('build/libs/app.jar', ['java.lang.String#toString']);
+//! ```
+//!
+//! `scan` runs on the libuv thread pool so that a server is not blocked while artifacts are scanned;
+//! `scanSync` returns the calls directly.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use java_method_finder::{
+    analysis::{self, Analyzer},
+    policy::Rule,
+    relocation::Relocation,
+};
+use napi::{bindgen_prelude::AsyncTask, Env, Error, Result, Status, Task};
+use napi_derive::napi;
+
+#[napi(object)]
+pub struct ScanOptions {
+    /// `original->relocated` package specs, as for `--relocation`
+    pub relocations: Option<Vec<String>>,
+    /// Time budget per class file, in milliseconds
+    pub file_timeout: Option<u32>,
+}
+
+/// A call to one of the targets.
+#[napi(object)]
+pub struct FoundCall {
+    pub class_name: String,
+    pub method_name: String,
+    pub line_number: u32,
+    /// First and last lines of the caller method
+    pub method_first_line: Option<u32>,
+    pub method_last_line: Option<u32>,
+    /// Path of the caller's source file relative to a source root
+    pub source_path: String,
+    pub java_release: String,
+    /// Invoked method, e.g. `java.util.Arrays#asList`
+    pub callee: Option<String>,
+    pub relocated: Option<String>,
+    pub in_loop: bool,
+    pub synchronized: bool,
+    pub handlers: Vec<String>,
+    /// Local variable or field the method is called on
+    pub receiver: Option<String>,
+}
+
+impl From<analysis::FoundCall> for FoundCall {
+    fn from(call: analysis::FoundCall) -> Self {
+        Self {
+            class_name: call.class_name.replace('/', "."),
+            source_path: call.source_path(),
+            method_name: call.method_name.to_string(),
+            line_number: u32::from(call.line_number),
+            method_first_line: call.method_lines.map(|lines| u32::from(lines.first)),
+            method_last_line: call.method_lines.map(|lines| u32::from(lines.last)),
+            java_release: call.java_release.to_string(),
+            callee: call.callee,
+            relocated: call.relocated,
+            in_loop: call.context.in_loop,
+            synchronized: call.context.synchronized,
+            handlers: call.context.handlers,
+            receiver: call.context.receiver,
+        }
+    }
+}
+
+fn invalid_arg(message: String) -> Error {
+    Error::new(Status::InvalidArg, message)
+}
+
+fn analyzer(targets: &[String], options: Option<ScanOptions>) -> Result<Analyzer> {
+    let rules = targets
+        .iter()
+        .map(|target| Rule::parse(target))
+        .collect::<anyhow::Result<Vec<_>>>()
+        .map_err(|e| invalid_arg(e.to_string()))?;
+    let mut analyzer = Analyzer::new(rules);
+    if let Some(options) = options {
+        analyzer.relocations = options
+            .relocations
+            .unwrap_or_default()
+            .iter()
+            .map(|spec| Relocation::parse(spec))
+            .collect::<std::result::Result<_, _>>()
+            .map_err(invalid_arg)?;
+        analyzer.file_timeout = options.file_timeout.map(|millis| Duration::from_millis(millis.into()));
+    }
+    Ok(analyzer)
+}
+
+fn run(analyzer: &Analyzer, path: &Path) -> Result<Vec<FoundCall>> {
+    let calls = analyzer
+        .scan(path)
+        .map_err(|e| Error::from_reason(format!("{:#}", e)))?;
+    Ok(calls.into_iter().map(FoundCall::from).collect())
+}
+
+pub struct ScanTask {
+    analyzer: Analyzer,
+    path: PathBuf,
+}
+
+impl Task for ScanTask {
+    type Output = Vec<FoundCall>;
+    type JsValue = Vec<FoundCall>;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        run(&self.analyzer, &self.path)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+/// Calls to `targets` (`Class#method` or `package.*`) in a class file, a jar, or the class files and
+/// jars below a directory.
+#[napi(ts_return_type = "Promise<FoundCall[]>")]
+pub fn scan(path: String, targets: Vec<String>, options: Option<ScanOptions>) -> Result<AsyncTask<ScanTask>> {
+    Ok(AsyncTask::new(ScanTask {
+        analyzer: analyzer(&targets, options)?,
+        path: PathBuf::from(path),
+    }))
+}
+
+/// `scan`, blocking until the calls are found.
+#[napi]
+pub fn scan_sync(path: String, targets: Vec<String>, options: Option<ScanOptions>) -> Result<Vec<FoundCall>> {
+    run(&analyzer(&targets, options)?, Path::new(&path))
+}
@@ -0,0 +1,55 @@
+use std::{env, fs, path::PathBuf, process::Command};
+
+use tempfile::TempDir;
+
+/// The addon cargo built for the tests, e.g. `target/debug/libjmf_node.so`.
+fn addon() -> PathBuf {
+    let exe = env::current_exe().unwrap();
+    let target_dir = exe.parent().and_then(|deps| deps.parent()).unwrap();
+    target_dir.join(format!(
+        "{}jmf_node{}",
+        env::consts::DLL_PREFIX,
+        env::consts::DLL_SUFFIX
+    ))
+}
+
+#[test]
+fn should_scan_from_node() {
+    let addon_dir = TempDir::new().unwrap();
+    // Node.js loads addons from `.node` files
+    let addon_path = addon_dir.path().join("jmf.node");
+    fs::copy(addon(), &addon_path).unwrap();
+    let resources = concat!(env!("CARGO_MANIFEST_DIR"), "/../../tests/resources/abstract");
+
+    let script = r#"
+const { scan, scanSync } = require(process.argv[1]);
+
+const print = (calls) => {
+  for (const call of calls) {
+    console.log(`${call.className}#${call.methodName} (L${call.lineNumber}) ${call.callee}`);
+  }
+};
+print(scanSync(process.argv[2], ['java.lang.String#trim']));
+scan(process.argv[2], ['java.lang.String#trim']).then((calls) => console.log(`${calls.length} calls`));
+"#;
+    let output = match Command::new("node")
+        .args(["-e", script])
+        .arg(&addon_path)
+        .arg(resources)
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("Skipped, node cannot be run: {}", e);
+            return;
+        }
+    };
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "com.example.abstracts.Api#describe (L7) java.lang.String#trim\n\
+         com.example.abstracts.Impl#run (L10) java.lang.String#trim\n\
+         com.example.abstracts.Stripped#strip (L0) java.lang.String#trim\n\
+         3 calls\n"
+    );
+}
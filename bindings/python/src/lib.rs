@@ -0,0 +1,127 @@
+//! The `jmf` Python module:
+//!
+//! ```python
+//! import jmf
+//!
+//! for call in jmf.scan("build/libs/app.jar", ["java.lang.String#toString"]):
+//!     print(call.class_name, call.method_name, call.line_number)
+//! ```
+//!
+//! `options` is a dict with `relocations` (a list of `original->relocated` specs) and `file_timeout`
+//! (seconds per class file), both optional.
+
+use std::{path::PathBuf, time::Duration};
+
+use java_method_finder::{
+    analysis::{self, Analyzer},
+    policy::Rule,
+    relocation::Relocation,
+};
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    prelude::*,
+    types::PyDict,
+};
+
+/// A call to one of the targets.
+#[pyclass(name = "FoundCall", module = "jmf", frozen, get_all)]
+struct FoundCall {
+    class_name: String,
+    method_name: String,
+    line_number: u16,
+    /// First and last lines of the caller method
+    method_first_line: Option<u16>,
+    method_last_line: Option<u16>,
+    /// Path of the caller's source file relative to a source root
+    source_path: String,
+    java_release: String,
+    /// Invoked method, e.g. `java.util.Arrays#asList`
+    callee: Option<String>,
+    relocated: Option<String>,
+    in_loop: bool,
+    synchronized: bool,
+    handlers: Vec<String>,
+    /// Local variable or field the method is called on
+    receiver: Option<String>,
+}
+
+#[pymethods]
+impl FoundCall {
+    fn __repr__(&self) -> String {
+        format!(
+            "FoundCall({}#{} (L{}))",
+            self.class_name, self.method_name, self.line_number
+        )
+    }
+}
+
+impl From<analysis::FoundCall> for FoundCall {
+    fn from(call: analysis::FoundCall) -> Self {
+        Self {
+            class_name: call.class_name.replace('/', "."),
+            source_path: call.source_path(),
+            method_name: call.method_name.to_string(),
+            line_number: call.line_number,
+            method_first_line: call.method_lines.map(|lines| lines.first),
+            method_last_line: call.method_lines.map(|lines| lines.last),
+            java_release: call.java_release.to_string(),
+            callee: call.callee,
+            relocated: call.relocated,
+            in_loop: call.context.in_loop,
+            synchronized: call.context.synchronized,
+            handlers: call.context.handlers,
+            receiver: call.context.receiver,
+        }
+    }
+}
+
+/// Calls to `targets` (`Class#method` or `package.*`) in a class file, a jar, or the class files and
+/// jars below a directory.
+#[pyfunction]
+#[pyo3(signature = (path, targets, options = None))]
+fn scan(
+    py: Python<'_>,
+    path: PathBuf,
+    targets: Vec<String>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<Vec<FoundCall>> {
+    let rules = targets
+        .iter()
+        .map(|target| Rule::parse(target))
+        .collect::<anyhow::Result<Vec<_>>>()
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let mut analyzer = Analyzer::new(rules);
+    if let Some(options) = options {
+        for (key, value) in options {
+            match key.extract::<String>()?.as_str() {
+                "relocations" => {
+                    analyzer.relocations = value
+                        .extract::<Vec<String>>()?
+                        .iter()
+                        .map(|spec| Relocation::parse(spec))
+                        .collect::<Result<_, _>>()
+                        .map_err(PyValueError::new_err)?;
+                }
+                "file_timeout" => {
+                    let seconds: f64 = value.extract()?;
+                    let timeout = Duration::try_from_secs_f64(seconds).map_err(|_| {
+                        PyValueError::new_err(format!("Invalid file_timeout {}, expected seconds", seconds))
+                    })?;
+                    analyzer.file_timeout = Some(timeout);
+                }
+                other => return Err(PyValueError::new_err(format!("Unknown option '{}'", other))),
+            }
+        }
+    }
+    let calls = py
+        .allow_threads(|| analyzer.scan(&path))
+        .map_err(|e| PyRuntimeError::new_err(format!("{:#}", e)))?;
+    Ok(calls.into_iter().map(FoundCall::from).collect())
+}
+
+#[pymodule]
+fn jmf(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<FoundCall>()?;
+    module.add_function(wrap_pyfunction!(scan, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use std::{env, fs, path::PathBuf, process::Command};
+
+use tempfile::TempDir;
+
+/// The extension module cargo built for the tests, e.g. `target/debug/libjmf.so`.
+fn extension_module() -> PathBuf {
+    let exe = env::current_exe().unwrap();
+    let target_dir = exe.parent().and_then(|deps| deps.parent()).unwrap();
+    target_dir.join(format!("{}jmf{}", env::consts::DLL_PREFIX, env::consts::DLL_SUFFIX))
+}
+
+/// A directory with the extension module, importable as `jmf` with it as `PYTHONPATH`.
+fn module_dir() -> TempDir {
+    let module_dir = TempDir::new().unwrap();
+    // Python imports extension modules by their module name
+    let suffix = if cfg!(windows) { "pyd" } else { "so" };
+    fs::copy(extension_module(), module_dir.path().join(format!("jmf.{}", suffix))).unwrap();
+    module_dir
+}
+
+#[test]
+fn should_scan_from_python() {
+    let module_dir = module_dir();
+    let resources = concat!(env!("CARGO_MANIFEST_DIR"), "/../../tests/resources/abstract");
+
+    let script = r#"
+import sys
+
+import jmf
+
+for call in jmf.scan(sys.argv[1], ["java.lang.String#trim"]):
+    print(call, call.callee, call.receiver)
+"#;
+    let output = match Command::new("python3")
+        .args(["-c", script, resources])
+        .env("PYTHONPATH", module_dir.path())
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("Skipped, python3 cannot be run: {}", e);
+            return;
+        }
+    };
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "FoundCall(com.example.abstracts.Api#describe (L7)) java.lang.String#trim None\n\
+         FoundCall(com.example.abstracts.Impl#run (L10)) java.lang.String#trim str\n\
+         FoundCall(com.example.abstracts.Stripped#strip (L0)) java.lang.String#trim None\n"
+    );
+}
+
+#[test]
+fn should_reject_invalid_file_timeouts_from_python() {
+    let module_dir = module_dir();
+    let resources = concat!(env!("CARGO_MANIFEST_DIR"), "/../../tests/resources/abstract");
+
+    let script = r#"
+import sys
+
+import jmf
+
+for timeout in [-1, float("nan"), float("inf")]:
+    try:
+        jmf.scan(sys.argv[1], ["java.lang.String#trim"], {"file_timeout": timeout})
+    except ValueError as e:
+        print("ValueError", e)
+"#;
+    let output = match Command::new("python3")
+        .args(["-c", script, resources])
+        .env("PYTHONPATH", module_dir.path())
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("Skipped, python3 cannot be run: {}", e);
+            return;
+        }
+    };
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "ValueError Invalid file_timeout -1, expected seconds\n\
+         ValueError Invalid file_timeout NaN, expected seconds\n\
+         ValueError Invalid file_timeout inf, expected seconds\n"
+    );
+}
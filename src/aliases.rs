@@ -0,0 +1,53 @@
+//! `--alias-file`: short names for commonly audited targets, so that `jmf find logger-debug` searches what a
+//! team's shared `aliases.toml` says it stands for.
+//!
+//! ```toml
+//! [aliases]
+//! logger-debug = "org.slf4j.Logger#debug"
+//! string-format = "java.lang.String#format(Ljava/lang/String;[Ljava/lang/Object;)Ljava/lang/String;"
+//! raw-sql = { query = 'calls("java.sql.Statement#execute") and not caller(package ~ "com.app.db.**")' }
+//! ```
+//!
+//! An alias is a target as `-c` and `-m` or `--package` give it, `Class#method` with an optional descriptor
+//! or `package.*`, or a table taking the same keys as a policy rule.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use crate::policy::{Rule, RuleEntry};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AliasFile {
+    #[serde(default)]
+    aliases: BTreeMap<String, AliasEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum AliasEntry {
+    Target(String),
+    Rule(Box<RuleEntry>),
+}
+
+/// The rule an alias of the file stands for.
+pub fn resolve(path: &Path, name: &str) -> Result<Rule> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut file: AliasFile =
+        toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+    let Some(entry) = file.aliases.remove(name) else {
+        bail!(
+            "No alias '{}' in {}; available: {}",
+            name,
+            path.display(),
+            file.aliases.keys().cloned().collect::<Vec<_>>().join(", ")
+        );
+    };
+    let context = || format!("Alias '{}' in {}", name, path.display());
+    match entry {
+        AliasEntry::Target(target) => Rule::parse(&target).with_context(context),
+        AliasEntry::Rule(entry) => entry.into_rule(context),
+    }
+}
@@ -0,0 +1,847 @@
+//! Finding rule matches in parsed class files, independent of how the class bytes were obtained.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs,
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use cafebabe::{
+    attributes::AttributeData, bytecode::Opcode, parse_class, ClassAccessFlags, ClassFile, MethodAccessFlags,
+    MethodInfo,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::debug;
+
+use crate::{
+    color,
+    context::CallContext,
+    disasm, graph, groovy,
+    policy::{Caller, Rule, RuleIndex},
+    relocation::{self, Relocation},
+    scala,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FoundCall {
+    /// Shared by the calls of a class, as are the method name, Java release and source file by those of a
+    /// method, so that many hits cost little more than one
+    pub class_name: Arc<str>,
+    pub method_name: Arc<str>,
+    pub line_number: u16,
+    /// First and last lines of the caller method, from its `LineNumberTable`, which span its whole body
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method_lines: Option<LineRange>,
+    /// Bytecode offset of the call in the caller method
+    pub offset: usize,
+    /// Stable identity of the call site across builds: a hash of the caller method and its descriptor, the
+    /// target and the position of the call among the caller's calls to the target, which line shifts leave alone
+    pub fingerprint: String,
+    /// Position among the calls matching the same rule on the line, when there are several
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub occurrence: Option<usize>,
+    #[serde(flatten)]
+    pub context: CallContext,
+    /// Major class file version of the caller class
+    pub class_version: u16,
+    /// Java release of `class_version`, e.g. `8`
+    pub java_release: Arc<str>,
+    /// Relocated class the call was found on, when it matched through `--relocation`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relocated: Option<String>,
+    /// Whether the target is referenced through a method handle, e.g. a method reference, rather than called
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub handle_reference: bool,
+    /// Whether the call is a dynamic Groovy call of a method of the target's name, found by `--groovy`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dynamic: bool,
+    /// How Scala compiled the caller, when it is reported as the definition it belongs to, e.g.
+    /// `anonymous function $anonfun$run$1`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scala: Option<String>,
+    /// What the compiler generated the invoked member for, e.g. `enum values()`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generated: Option<String>,
+    /// Commit that last changed the line of the call, from `--blame`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blame: Option<Blame>,
+    /// Owners of the caller source file, from `--owners`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub owners: Vec<String>,
+    /// What suppresses the call: an annotation of the caller method or class from `--suppress-annotation`,
+    /// as `@com.example.ApprovedUsage`, or a `// jmf:ignore` comment in its source
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suppressed: Option<String>,
+    /// JPMS module of the caller, from the `module-info.class` of its jar or directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub module: Option<String>,
+    /// Why the JPMS module of the callee does not let the caller access it, e.g.
+    /// `com.example.app does not export com.example.app.internal`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub illegal_access: Option<String>,
+    /// Label of the scan root or jar the caller was found in, when several are scanned
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    /// Labels of the other scan roots or jars with an identical copy of the caller class, which is analyzed
+    /// and reported once
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub also_in: Vec<String>,
+    /// Instructions around the call from `--explain`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disassembly: Vec<String>,
+    /// Index of the matched rule
+    #[serde(skip)]
+    pub rule: usize,
+    /// `SourceFile` attribute of the caller class
+    #[serde(skip)]
+    pub source_file: Option<Arc<str>>,
+    /// Invoked method at the call site, e.g. `java.util.Arrays#asList`
+    #[serde(skip)]
+    pub callee: Option<String>,
+    /// Descriptor of the compiled caller method, e.g. `(Ljava/lang/String;)V`, telling its overloads apart
+    #[serde(skip)]
+    pub method_descriptor: Arc<str>,
+}
+
+impl FoundCall {
+    pub fn new(
+        class_name: impl Into<Arc<str>>,
+        method_name: impl Into<Arc<str>>,
+        line_number: u16,
+        context: CallContext,
+    ) -> Self {
+        Self {
+            class_name: class_name.into(),
+            method_name: method_name.into(),
+            line_number,
+            method_lines: None,
+            offset: 0,
+            fingerprint: String::new(),
+            occurrence: None,
+            context,
+            class_version: 0,
+            java_release: Arc::from(""),
+            relocated: None,
+            handle_reference: false,
+            dynamic: false,
+            scala: None,
+            generated: None,
+            blame: None,
+            owners: Vec::new(),
+            suppressed: None,
+            module: None,
+            illegal_access: None,
+            origin: None,
+            also_in: Vec::new(),
+            disassembly: Vec::new(),
+            rule: 0,
+            source_file: None,
+            callee: None,
+            method_descriptor: Arc::from(""),
+        }
+    }
+
+    /// Path of the caller's source file relative to a source root, e.g. `com/example/Caller.java`.
+    pub fn source_path(&self) -> String {
+        let (package, simple_name) = self.class_name.rsplit_once('/').unwrap_or(("", &self.class_name));
+        let file_name = match &self.source_file {
+            Some(source_file) => source_file.to_string(),
+            None => format!("{}.java", simple_name.split('$').next().unwrap_or(simple_name)),
+        };
+        if package.is_empty() {
+            file_name
+        } else {
+            format!("{}/{}", package, file_name)
+        }
+    }
+}
+
+/// Lines of source, both included.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct LineRange {
+    pub first: u16,
+    pub last: u16,
+}
+
+/// Last change of a line of source, as `git blame` reports it.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Blame {
+    pub commit: String,
+    pub author: String,
+    pub author_mail: String,
+    /// First line of the commit message
+    pub summary: String,
+}
+
+impl Blame {
+    pub fn short_commit(&self) -> &str {
+        &self.commit[..self.commit.len().min(7)]
+    }
+}
+
+impl std::fmt::Display for FoundCall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}#{} {}",
+            color::cyan(self.class_name.replace('/', ".")),
+            self.method_name,
+            color::dim(match self.occurrence {
+                Some(occurrence) => format!("(L{} #{} @{})", self.line_number, occurrence, self.offset),
+                None => format!("(L{})", self.line_number),
+            })
+        )?;
+        for marker in self.context.markers() {
+            write!(f, " [{}]", marker)?;
+        }
+        if let Some(relocated) = &self.relocated {
+            write!(f, " [relocated: {}]", relocated)?;
+        }
+        if self.handle_reference {
+            write!(f, " [handle reference]")?;
+        }
+        if self.dynamic {
+            write!(f, " [groovy dynamic call]")?;
+        }
+        if let Some(scala) = &self.scala {
+            write!(f, " [scala: {}]", scala)?;
+        }
+        if let Some(generated) = &self.generated {
+            write!(f, " [generated: {}]", generated)?;
+        }
+        if let Some(blame) = &self.blame {
+            write!(f, " [blame: {} {}]", blame.short_commit(), blame.author)?;
+        }
+        if !self.owners.is_empty() {
+            write!(f, " [owners: {}]", self.owners.join(" "))?;
+        }
+        if let Some(suppressed) = &self.suppressed {
+            write!(f, " [suppressed by {}]", suppressed)?;
+        }
+        if let Some(module) = &self.module {
+            write!(f, " [module: {}]", module)?;
+        }
+        if let Some(illegal_access) = &self.illegal_access {
+            write!(f, " [illegal access: {}]", illegal_access)?;
+        }
+        if let Some(origin) = &self.origin {
+            write!(f, " [origin: {}]", origin)?;
+        }
+        if !self.also_in.is_empty() {
+            write!(f, " [also in: {}]", self.also_in.join(", "))?;
+        }
+        for instruction in &self.disassembly {
+            write!(f, "\n   {}", instruction)?;
+        }
+        Ok(())
+    }
+}
+
+/// The calls of a `scan_all` and the class files it skipped.
+#[derive(Debug, Default)]
+pub struct Scan {
+    pub calls: Vec<FoundCall>,
+    pub errors: Vec<ClassError>,
+}
+
+/// A class file that could not be analyzed, which scans go on past.
+#[derive(Debug, Serialize, Clone, thiserror::Error)]
+#[error("{location}: {message}")]
+pub struct ClassError {
+    /// Path of the class file, followed by `!/` and the entry for one in a jar
+    pub location: String,
+    /// The error with its causes
+    pub message: String,
+}
+
+impl ClassError {
+    fn new(location: String, error: &anyhow::Error) -> Self {
+        Self {
+            location,
+            message: format!("{:#}", error),
+        }
+    }
+}
+
+/// Why analysis gave up on a parsed class file, found in the chain of its error with `downcast_ref`.
+#[derive(Debug, thiserror::Error)]
+pub enum AnalysisError {
+    /// The class file took longer than its `Deadline`
+    #[error("Timed out after {0:?}")]
+    Timeout(Duration),
+    /// A concrete method has no `Code` attribute
+    #[error("Code attribute not found in method {0}")]
+    MissingCode(String),
+    /// A method's `Code` attribute holds no bytecode that could be parsed
+    #[error("No bytecode found in method {0}")]
+    MissingBytecode(String),
+}
+
+/// Per-file time budget from `--file-timeout`, checked between units of work so that one pathological
+/// class file cannot stall the whole scan. Reading and parsing count towards it but are not interrupted:
+/// the budget is checked once each is done.
+pub struct Deadline {
+    timeout: Option<(Duration, Instant)>,
+}
+
+impl Deadline {
+    /// A timeout too long for an `Instant` never passes.
+    pub fn new(timeout: Option<Duration>) -> Self {
+        Self {
+            timeout: timeout.and_then(|timeout| Some((timeout, Instant::now().checked_add(timeout)?))),
+        }
+    }
+
+    pub fn check(&self) -> Result<()> {
+        match self.timeout {
+            Some((timeout, deadline)) if Instant::now() >= deadline => Err(AnalysisError::Timeout(timeout).into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Java release of a major class file version, e.g. `8` for 52 and `1.4` for 48.
+pub fn java_release(major_version: u16) -> String {
+    match major_version {
+        0..=44 => format!("1.0 ({})", major_version),
+        45..=48 => format!("1.{}", major_version - 44),
+        _ => (major_version - 44).to_string(),
+    }
+}
+
+/// Whether a path names a jar, zip or Android library (`.aar`) archive by its extension.
+pub fn is_archive(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == "jar" || ext == "zip" || ext == "aar")
+}
+
+/// Most bytes reserved up front for an archive entry.
+const MAX_ENTRY_CAPACITY: u64 = 1 << 20;
+
+/// Bytes to reserve for reading an archive entry of the size its header declares, which a corrupt or
+/// hostile archive can set to anything; the buffer grows past it as the entry is read.
+pub fn entry_capacity(size: u64) -> usize {
+    size.min(MAX_ENTRY_CAPACITY) as usize
+}
+
+/// `path` itself, or the class files and jars below it when it is a directory, sorted. Symbolic links to
+/// directories below it are not followed, so that a link cycle cannot recurse without end.
+pub fn scanned_files(path: &Path) -> Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut entries = fs::read_dir(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    let mut files = Vec::new();
+    for entry in entries {
+        if entry.is_symlink() && entry.is_dir() {
+            continue;
+        } else if entry.is_dir() {
+            files.extend(scanned_files(&entry)?);
+        } else if is_archive(&entry) || entry.extension().is_some_and(|ext| ext == "class") {
+            files.push(entry);
+        }
+    }
+    Ok(files)
+}
+
+/// How far a caller method is visible: its access modifier, narrowed to `package` when its class is not public.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Visibility {
+    Public,
+    Protected,
+    Package,
+    Private,
+}
+
+impl Visibility {
+    pub fn of(class_file: &ClassFile, method: &MethodInfo) -> Self {
+        let flags = method.access_flags;
+        if flags.contains(MethodAccessFlags::PRIVATE) {
+            Visibility::Private
+        } else if !flags.intersects(MethodAccessFlags::PUBLIC | MethodAccessFlags::PROTECTED)
+            || !class_file.access_flags.contains(ClassAccessFlags::PUBLIC)
+        {
+            Visibility::Package
+        } else if flags.contains(MethodAccessFlags::PUBLIC) {
+            Visibility::Public
+        } else {
+            Visibility::Protected
+        }
+    }
+}
+
+/// Rules to find, along with the relocations they see through.
+pub struct Analyzer {
+    /// Indexed at the first analysis, so they are not to change after it
+    pub rules: Vec<Rule>,
+    pub relocations: Vec<Relocation>,
+    /// Time budget per class file for the `find_calls` functions and `scan`
+    pub file_timeout: Option<Duration>,
+    /// Disassemble the instructions around each call
+    pub explain: bool,
+    /// Also match dynamic Groovy calls by method name
+    pub groovy: bool,
+    /// Annotations, by dotted or simple name, whose callers' calls are reported as suppressed
+    pub suppress_annotations: Vec<String>,
+    /// Visibilities of the caller methods to report the calls of, or all of them when empty
+    pub caller_visibilities: Vec<Visibility>,
+    rule_index: OnceLock<RuleIndex>,
+}
+
+impl Analyzer {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self {
+            rules,
+            relocations: Vec::new(),
+            file_timeout: None,
+            explain: false,
+            groovy: false,
+            suppress_annotations: Vec::new(),
+            caller_visibilities: Vec::new(),
+            rule_index: OnceLock::new(),
+        }
+    }
+
+    /// Calls matching any rule in the bytes of a single class file.
+    pub fn find_calls(&self, class_data: &[u8]) -> Result<Vec<FoundCall>> {
+        let deadline = Deadline::new(self.file_timeout);
+        let class_file = parse_class(class_data).context("Failed to parse class file")?;
+        deadline.check()?;
+        self.analyze(&class_file, &deadline)
+    }
+
+    /// Calls matching any rule in a class file, a jar, or the class files and jars below a directory,
+    /// one file after the other, skipping the class files that cannot be analyzed, which `scan_all` also
+    /// returns.
+    pub fn scan(&self, path: &Path) -> Result<Vec<FoundCall>> {
+        Ok(self.scan_all(path)?.calls)
+    }
+
+    /// `scan`, with the class files that could not be analyzed.
+    pub fn scan_all(&self, path: &Path) -> Result<Scan> {
+        let mut calls = Vec::new();
+        let errors = self.scan_each(path, &mut |call| {
+            calls.push(call);
+            Ok(())
+        })?;
+        Ok(Scan { calls, errors })
+    }
+
+    /// `scan`, handing each call to `f` as soon as its class file is analyzed and returning the class
+    /// files that could not be. Stops at the first error of `f` or of reading a file.
+    pub fn scan_each(&self, path: &Path, f: &mut dyn FnMut(FoundCall) -> Result<()>) -> Result<Vec<ClassError>> {
+        let mut errors = Vec::new();
+        for file in scanned_files(path)? {
+            errors.extend(self.scan_file(&file, f)?);
+        }
+        Ok(errors)
+    }
+
+    /// Hands the calls in one class file or jar to `f`, returning the class files that could not be
+    /// analyzed.
+    pub fn scan_file(&self, path: &Path, f: &mut dyn FnMut(FoundCall) -> Result<()>) -> Result<Vec<ClassError>> {
+        let data = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        if is_archive(path) {
+            let mut errors = self.jar_each(&data, f)?;
+            for error in &mut errors {
+                error.location = if error.location.is_empty() {
+                    path.display().to_string()
+                } else {
+                    format!("{}!/{}", path.display(), error.location)
+                };
+            }
+            return Ok(errors);
+        }
+        match self.find_calls(&data) {
+            Ok(calls) => calls.into_iter().try_for_each(&mut *f)?,
+            Err(e) => return Ok(vec![ClassError::new(path.display().to_string(), &e)]),
+        }
+        Ok(Vec::new())
+    }
+
+    /// Calls matching any rule in the class files of the bytes of a jar, in entry order, skipping the
+    /// class files that cannot be analyzed.
+    pub fn find_calls_in_jar(&self, jar_data: &[u8]) -> Result<Vec<FoundCall>> {
+        let mut found_calls = Vec::new();
+        for error in self.jar_each(jar_data, &mut |call| {
+            found_calls.push(call);
+            Ok(())
+        })? {
+            debug!("Skipped {}", error);
+        }
+        Ok(found_calls)
+    }
+
+    /// The class files that could not be analyzed are returned by their entry names, or with an empty
+    /// name for the whole jar when it cannot be opened.
+    fn jar_each(&self, jar_data: &[u8], f: &mut dyn FnMut(FoundCall) -> Result<()>) -> Result<Vec<ClassError>> {
+        let mut archive = match zip::ZipArchive::new(Cursor::new(jar_data)) {
+            Ok(archive) => archive,
+            Err(e) => {
+                return Ok(vec![ClassError::new(
+                    String::new(),
+                    &anyhow::Error::new(e).context("Failed to open jar"),
+                )])
+            }
+        };
+        let mut errors = Vec::new();
+        for index in 0..archive.len() {
+            let mut entry = match archive.by_index(index) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    // The entries past a corrupt one cannot be found either
+                    errors.push(ClassError::new(
+                        String::new(),
+                        &anyhow::Error::new(e).context("Failed to read jar"),
+                    ));
+                    break;
+                }
+            };
+            if !entry.is_file() || !entry.name().ends_with(".class") {
+                continue;
+            }
+            let name = entry.name().to_string();
+            let mut class_data = Vec::with_capacity(entry_capacity(entry.size()));
+            let calls = entry
+                .read_to_end(&mut class_data)
+                .context("Failed to read the entry")
+                .and_then(|_| self.find_calls(&class_data));
+            match calls {
+                Ok(calls) => calls.into_iter().try_for_each(&mut *f)?,
+                Err(e) => errors.push(ClassError::new(name, &e)),
+            }
+        }
+        Ok(errors)
+    }
+
+    /// Calls matching any rule in a parsed class file.
+    pub fn analyze(&self, class_file: &ClassFile, deadline: &Deadline) -> Result<Vec<FoundCall>> {
+        let mut found_calls = Vec::new();
+        let class_name = &class_file.this_class;
+        let class_version = class_file.major_version;
+        let shared_class_name: Arc<str> = Arc::from(class_name.as_ref());
+        let release: Arc<str> = java_release(class_version).into();
+        let source_file: Option<Arc<str>> = class_file.attributes.iter().find_map(|attr| match &attr.data {
+            AttributeData::SourceFile(source_file) => Some(Arc::from(source_file.as_ref())),
+            _ => None,
+        });
+
+        let class_annotations = graph::annotations(&class_file.attributes);
+        let class_suppressions = suppressions(&class_file.attributes, &self.suppress_annotations);
+        let bootstrap_methods = class_file
+            .attributes
+            .iter()
+            .find_map(|attr| match &attr.data {
+                AttributeData::BootstrapMethods(entries) => Some(&entries[..]),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let is_scala = scala::is_scala(class_file);
+        let call_site_names = if self.groovy {
+            groovy::call_site_names(class_file)
+        } else {
+            Vec::new()
+        };
+
+        // Every rule is matched in one pass over the opcodes of each method
+        let rule_index = self.rule_index.get_or_init(|| RuleIndex::new(&self.rules));
+
+        debug!("Visiting class: {}", class_name);
+
+        for method in &class_file.methods {
+            deadline.check()?;
+            if !self.caller_visibilities.is_empty()
+                && !self.caller_visibilities.contains(&Visibility::of(class_file, method))
+            {
+                continue;
+            }
+            // Abstract and native methods have no code to call anything from
+            if method
+                .access_flags
+                .intersects(MethodAccessFlags::ABSTRACT | MethodAccessFlags::NATIVE)
+            {
+                continue;
+            }
+            let method_name = &method.name;
+
+            let code_attr = method
+                .attributes
+                .iter()
+                .find_map(|attr| {
+                    if let AttributeData::Code(code) = &attr.data {
+                        Some(code)
+                    } else {
+                        None
+                    }
+                })
+                .ok_or_else(|| AnalysisError::MissingCode(format!("{}#{}", class_name, method_name)))?;
+
+            // Classes compiled without debug information have no line numbers, which their calls report as 0
+            let line_number_table = code_attr
+                .attributes
+                .iter()
+                .find_map(|attr| {
+                    if let AttributeData::LineNumberTable(lnt) = &attr.data {
+                        Some(&lnt[..])
+                    } else {
+                        None
+                    }
+                })
+                .unwrap_or_default();
+            let method_lines = line_number_table
+                .iter()
+                .map(|entry| entry.line_number)
+                .min()
+                .zip(line_number_table.iter().map(|entry| entry.line_number).max())
+                .map(|(first, last)| LineRange { first, last });
+
+            if let Some(bytecode) = &code_attr.bytecode {
+                debug!("Visiting method: {}#{}", class_name, method_name);
+
+                // Rules see relocated names in their original form
+                let (match_class_name, match_opcodes) = if self.relocations.is_empty() {
+                    (class_name.to_string(), Cow::Borrowed(&bytecode.opcodes[..]))
+                } else {
+                    (
+                        relocation::original_name(&self.relocations, class_name)
+                            .unwrap_or_else(|| class_name.to_string()),
+                        Cow::Owned(relocation::unrelocate(&self.relocations, &bytecode.opcodes)),
+                    )
+                };
+                let annotations: Vec<String> = graph::annotations(&method.attributes)
+                    .into_iter()
+                    .chain(class_annotations.iter().cloned())
+                    .collect();
+                let dynamic_calls = if self.groovy {
+                    groovy::dynamic_calls(&bytecode.opcodes, &call_site_names, bootstrap_methods)
+                } else {
+                    Vec::new()
+                };
+                let caller = Caller {
+                    class_name: &match_class_name,
+                    method_name,
+                    annotations: &annotations,
+                    bootstrap_methods,
+                    dynamic_calls: &dynamic_calls,
+                    synchronized: method.access_flags.contains(MethodAccessFlags::SYNCHRONIZED),
+                };
+                let matches = rule_index.find_matches(&self.rules, &caller, &match_opcodes)?;
+                let method_suppressions = suppressions(&method.attributes, &self.suppress_annotations);
+
+                let logical_caller = if is_scala {
+                    scala::logical_caller(class_file, method)
+                } else {
+                    None
+                };
+                let (caller_class, caller_method, scala): (Arc<str>, Arc<str>, _) = match logical_caller {
+                    Some(logical) => (
+                        logical.class_name.into(),
+                        logical.method_name.into(),
+                        Some(logical.note),
+                    ),
+                    None => (shared_class_name.clone(), Arc::from(method_name.as_ref()), None),
+                };
+
+                let method_descriptor: Arc<str> = disasm::method_descriptor(&method.descriptor).into();
+                let first = found_calls.len();
+                for (rule, offset) in matches {
+                    deadline.check()?;
+                    let index = line_number_table.partition_point(|entry| entry.start_pc <= offset as u16);
+
+                    if index > 0 || line_number_table.is_empty() {
+                        let line_number = index
+                            .checked_sub(1)
+                            .map_or(0, |index| line_number_table[index].line_number);
+                        let context = CallContext::analyze(method, code_attr, &bytecode.opcodes, offset);
+                        let opcode_index = bytecode
+                            .opcodes
+                            .binary_search_by_key(&offset, |(offset, _)| *offset)
+                            .ok();
+                        let callee = opcode_index.and_then(|index| invoked_method(&match_opcodes[index].1));
+                        let relocated = opcode_index.and_then(|index| {
+                            relocation::relocated_class(&self.relocations, &bytecode.opcodes[index].1)
+                        });
+                        let handle_reference = opcode_index.is_some_and(|index| {
+                            matches!(
+                                bytecode.opcodes[index].1,
+                                Opcode::Ldc(_) | Opcode::LdcW(_) | Opcode::Ldc2W(_) | Opcode::Invokedynamic(_)
+                            )
+                        });
+                        let dynamic = dynamic_calls
+                            .iter()
+                            .any(|(dynamic_offset, _)| *dynamic_offset == offset);
+                        let disassembly = match opcode_index {
+                            Some(index) if self.explain => disasm::window(&bytecode.opcodes, index, disasm::RADIUS),
+                            _ => Vec::new(),
+                        };
+                        let suppressed = method_suppressions
+                            .iter()
+                            .chain(&class_suppressions)
+                            .find(|suppression| suppression.applies_to(&self.rules[rule]))
+                            .map(|suppression| format!("@{}", suppression.annotation));
+                        let found_call = FoundCall {
+                            suppressed,
+                            class_version,
+                            java_release: release.clone(),
+                            relocated,
+                            handle_reference,
+                            dynamic,
+                            scala: scala.clone(),
+                            rule,
+                            source_file: source_file.clone(),
+                            callee,
+                            method_descriptor: method_descriptor.clone(),
+                            offset,
+                            method_lines,
+                            disassembly,
+                            ..FoundCall::new(caller_class.clone(), caller_method.clone(), line_number, context)
+                        };
+                        debug!("Found method call: {}", found_call);
+                        found_calls.push(found_call);
+                    }
+                }
+                number_occurrences(&mut found_calls[first..]);
+                // The compiled method, which Scala callers are not reported as
+                let compiled_method = format!("{}#{}{}", class_name, method_name, method_descriptor);
+                fingerprint(&mut found_calls[first..], &self.rules, &compiled_method);
+            } else {
+                return Err(AnalysisError::MissingBytecode(format!("{}#{}", class_name, method_name)).into());
+            }
+        }
+
+        Ok(found_calls)
+    }
+}
+
+/// A suppressing annotation of a caller, for the rules its `value` names or for every rule without one.
+struct Suppression {
+    annotation: String,
+    rule_ids: Option<Vec<String>>,
+}
+
+impl Suppression {
+    /// Rule ids may be prefixed with `jmf:`, as in `@SuppressWarnings`-style annotations.
+    fn applies_to(&self, rule: &Rule) -> bool {
+        let id = rule.id();
+        self.rule_ids.as_ref().is_none_or(|rule_ids| {
+            rule_ids
+                .iter()
+                .any(|rule_id| rule_id.strip_prefix("jmf:").unwrap_or(rule_id) == id)
+        })
+    }
+}
+
+/// The annotations among `attributes` that are in `names`, by dotted or simple name, with the rule ids of
+/// their string or string array `value`.
+fn suppressions(attributes: &[cafebabe::attributes::AttributeInfo], names: &[String]) -> Vec<Suppression> {
+    use cafebabe::attributes::AnnotationElementValue;
+    if names.is_empty() {
+        return Vec::new();
+    }
+    attributes
+        .iter()
+        .flat_map(|attr| match &attr.data {
+            AttributeData::RuntimeVisibleAnnotations(annotations)
+            | AttributeData::RuntimeInvisibleAnnotations(annotations) => annotations.as_slice(),
+            _ => &[],
+        })
+        .filter_map(|annotation| {
+            let name = graph::annotation_name(annotation)?;
+            let simple_name = name.rsplit('.').next().unwrap_or(&name);
+            if !names
+                .iter()
+                .any(|candidate| *candidate == name || candidate == simple_name)
+            {
+                return None;
+            }
+            let string = |value: &AnnotationElementValue| match value {
+                AnnotationElementValue::StringConstant(string) => Some(string.to_string()),
+                _ => None,
+            };
+            let rule_ids = annotation
+                .elements
+                .iter()
+                .find(|element| element.name == "value")
+                .map(|element| match &element.value {
+                    AnnotationElementValue::ArrayValue(values) => values.iter().filter_map(string).collect(),
+                    value => string(value).into_iter().collect(),
+                });
+            Some(Suppression {
+                annotation: name,
+                rule_ids,
+            })
+        })
+        .collect()
+}
+
+/// Numbers the calls of one method to the same method on the same line, such as the links of a builder
+/// chain, in bytecode order.
+fn number_occurrences(calls: &mut [FoundCall]) {
+    fn key(call: &FoundCall) -> (usize, Option<String>, u16) {
+        (call.rule, call.callee.clone(), call.line_number)
+    }
+    let mut lines: HashMap<_, usize> = HashMap::new();
+    for call in calls.iter() {
+        *lines.entry(key(call)).or_default() += 1;
+    }
+    calls.sort_by_key(|call| (call.rule, call.offset));
+    let mut seen: HashMap<_, usize> = HashMap::new();
+    for call in calls.iter_mut() {
+        let key = key(call);
+        if lines[&key] > 1 {
+            let occurrence = seen.entry(key).or_default();
+            *occurrence += 1;
+            call.occurrence = Some(*occurrence);
+        }
+    }
+}
+
+/// Sets the fingerprints of the calls of one method, in bytecode order. The offset of a call is normalized
+/// to its position among the calls to the same target, so that code added elsewhere in the method does not
+/// change it.
+fn fingerprint(calls: &mut [FoundCall], rules: &[Rule], method: &str) {
+    let mut positions: HashMap<(usize, Option<String>), usize> = HashMap::new();
+    for call in calls.iter_mut() {
+        let target = match &call.callee {
+            Some(callee) if rules[call.rule].per_edge() => callee.clone(),
+            _ => rules[call.rule].target(),
+        };
+        let position = positions.entry((call.rule, call.callee.clone())).or_default();
+        let mut hasher = Sha256::new();
+        for part in [method, &target, &position.to_string()] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        *position += 1;
+        call.fingerprint = hasher.finalize()[..8]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+    }
+}
+
+/// Dotted `Class#method` invoked by an invoke opcode.
+fn invoked_method(opcode: &Opcode) -> Option<String> {
+    match opcode {
+        Opcode::Invokespecial(member_ref)
+        | Opcode::Invokestatic(member_ref)
+        | Opcode::Invokevirtual(member_ref)
+        | Opcode::Invokeinterface(member_ref, _) => Some(format!(
+            "{}#{}",
+            member_ref.class_name.replace('/', "."),
+            member_ref.name_and_type.name
+        )),
+        _ => None,
+    }
+}
@@ -0,0 +1,210 @@
+//! `--kind annotation`: the classes, fields, methods and parameters carrying an annotation instead of the
+//! calls to a method, optionally only where its element values meet `--where` conditions, e.g. the
+//! endpoints whose `@Path` is under `/admin`.
+
+use anyhow::Result;
+use cafebabe::{
+    attributes::{Annotation, AttributeData, AttributeInfo},
+    ClassFile,
+};
+use serde::Serialize;
+
+use crate::{
+    color,
+    disasm::{field_descriptor, method_descriptor},
+    elements::{self, ElementFilter},
+    graph::annotation_name,
+    scan::{ScanArgs, Scanner},
+};
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Element {
+    Class,
+    Field,
+    Method,
+    Parameter,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ElementValue {
+    pub name: String,
+    /// As Java source writes it, e.g. `"/admin"`
+    pub value: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnnotationUse {
+    pub class_name: String,
+    /// Field or method carrying the annotation, or declaring the parameter that does
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub member: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub descriptor: Option<String>,
+    pub element: Element,
+    /// Position of the parameter among those of the method, counted from 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameter: Option<usize>,
+    /// First line of the method, from its `LineNumberTable`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_number: Option<u16>,
+    /// Dotted name of the annotation, which `-c` may give by its simple name
+    pub annotation: String,
+    /// Elements given a value, in declaration order; those left to their defaults are not in class files
+    pub elements: Vec<ElementValue>,
+}
+
+impl std::fmt::Display for AnnotationUse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.class_name)?;
+        if let Some(member) = &self.member {
+            write!(f, "#{}", member)?;
+        }
+        if let Some(parameter) = self.parameter {
+            write!(f, " parameter {}", parameter)?;
+        }
+        if let Some(line_number) = self.line_number {
+            write!(f, " (L{})", line_number)?;
+        }
+        if !self.elements.is_empty() {
+            let elements: Vec<String> = self
+                .elements
+                .iter()
+                .map(|element| format!("{} = {}", element.name, element.value))
+                .collect();
+            write!(f, " {}", color::dim(format!("[{}]", elements.join(", "))))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnnotationSearch {
+    pub annotation: String,
+    #[serde(rename = "where", skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    pub uses: Vec<AnnotationUse>,
+}
+
+impl AnnotationSearch {
+    /// The uses of the annotation, by dotted or simple name, in class name order and then in the order their
+    /// class declares them.
+    pub fn run(scan: ScanArgs, annotation: &str, filter: Option<ElementFilter>) -> Result<Self> {
+        let scanner = Scanner::new(scan)?;
+        let wanted = |candidate: &Annotation| {
+            annotation_name(candidate)
+                .is_some_and(|name| name == annotation || name.rsplit('.').next() == Some(annotation))
+                && filter.as_ref().is_none_or(|filter| filter.matches(candidate))
+        };
+        let mut classes = scanner.map_classes(|_, class_file| uses(class_file, &wanted))?;
+        classes.sort_by(|a, b| {
+            a.first()
+                .map(|use_| &use_.class_name)
+                .cmp(&b.first().map(|use_| &use_.class_name))
+        });
+        Ok(Self {
+            annotation: annotation.to_string(),
+            filter: filter.map(|filter| filter.to_string()),
+            uses: classes.into_iter().flatten().collect(),
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut heading = format!("@{}", self.annotation);
+        if let Some(filter) = &self.filter {
+            heading.push_str(&format!(" where {}", filter));
+        }
+        let mut output = vec![color::bold(heading)];
+        if self.uses.is_empty() {
+            output.push("No results".to_string());
+        }
+        output.extend(self.uses.iter().map(|use_| format!(" - {}", use_)));
+        output.join("\n")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
+
+/// The annotations of an element, visible at runtime or not.
+fn annotations<'a>(attributes: &'a [AttributeInfo<'a>]) -> impl Iterator<Item = &'a Annotation<'a>> {
+    attributes.iter().flat_map(|attr| match &attr.data {
+        AttributeData::RuntimeVisibleAnnotations(annotations)
+        | AttributeData::RuntimeInvisibleAnnotations(annotations) => annotations.as_slice(),
+        _ => &[],
+    })
+}
+
+fn uses(class_file: &ClassFile, wanted: &impl Fn(&Annotation) -> bool) -> Vec<AnnotationUse> {
+    let class_name = class_file.this_class.replace('/', ".");
+    let use_ = |annotation: &Annotation, element| AnnotationUse {
+        class_name: class_name.clone(),
+        member: None,
+        descriptor: None,
+        element,
+        parameter: None,
+        line_number: None,
+        annotation: annotation_name(annotation).unwrap_or_default(),
+        elements: annotation
+            .elements
+            .iter()
+            .map(|element| ElementValue {
+                name: element.name.to_string(),
+                value: elements::display(&element.value),
+            })
+            .collect(),
+    };
+    let mut found: Vec<AnnotationUse> = annotations(&class_file.attributes)
+        .filter(|annotation| wanted(annotation))
+        .map(|annotation| use_(annotation, Element::Class))
+        .collect();
+    for field in &class_file.fields {
+        found.extend(
+            annotations(&field.attributes)
+                .filter(|annotation| wanted(annotation))
+                .map(|annotation| AnnotationUse {
+                    member: Some(field.name.to_string()),
+                    descriptor: Some(field_descriptor(&field.descriptor)),
+                    ..use_(annotation, Element::Field)
+                }),
+        );
+    }
+    for method in &class_file.methods {
+        let line_number = method.attributes.iter().find_map(|attr| match &attr.data {
+            AttributeData::Code(code) => code.attributes.iter().find_map(|attr| match &attr.data {
+                AttributeData::LineNumberTable(table) => table.iter().map(|entry| entry.line_number).min(),
+                _ => None,
+            }),
+            _ => None,
+        });
+        let method_use = |annotation, element, parameter| AnnotationUse {
+            member: Some(method.name.to_string()),
+            descriptor: Some(method_descriptor(&method.descriptor)),
+            parameter,
+            line_number,
+            ..use_(annotation, element)
+        };
+        found.extend(
+            annotations(&method.attributes)
+                .filter(|annotation| wanted(annotation))
+                .map(|annotation| method_use(annotation, Element::Method, None)),
+        );
+        for attr in &method.attributes {
+            if let AttributeData::RuntimeVisibleParameterAnnotations(parameters)
+            | AttributeData::RuntimeInvisibleParameterAnnotations(parameters) = &attr.data
+            {
+                for (index, parameter) in parameters.iter().enumerate() {
+                    found.extend(
+                        parameter
+                            .annotations
+                            .iter()
+                            .filter(|annotation| wanted(annotation))
+                            .map(|annotation| method_use(annotation, Element::Parameter, Some(index + 1))),
+                    );
+                }
+            }
+        }
+    }
+    found
+}
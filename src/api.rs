@@ -0,0 +1,304 @@
+//! `jmf api`: the declared API of a jar or directory, its classes with their fields and methods, descriptors
+//! and access flags. It is what `jmf impact` diffs between two versions of a library.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use cafebabe::{
+    attributes::{AttributeData, AttributeInfo},
+    ClassAccessFlags, ClassFile, FieldAccessFlags, MethodAccessFlags,
+};
+use clap::Parser;
+use serde::Serialize;
+
+use crate::{
+    color,
+    disasm::{field_descriptor, method_descriptor},
+    graph::annotation_name,
+    modules::ModuleInfo,
+    resolve::{self, java_type},
+    scan::{ScanArgs, Scanner},
+};
+
+#[derive(clap::Args, Debug)]
+pub struct ApiArgs {
+    /// Jar, directory or any other scan root to list the API of (repeatable)
+    #[arg(value_name = "PATH", required = true)]
+    pub roots: Vec<PathBuf>,
+
+    /// Only list the public and protected members of public classes, which other code can call
+    #[arg(long = "public-only")]
+    pub public_only: bool,
+
+    #[arg(short = 'f', long = "format", value_enum, default_value_t = crate::ReportFormatter::Txt)]
+    pub format: crate::ReportFormatter,
+}
+
+/// Scan arguments of an API listing: its roots, with the defaults of every other option.
+#[derive(Parser)]
+struct ApiScan {
+    #[command(flatten)]
+    scan: ScanArgs,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Member {
+    pub name: String,
+    pub descriptor: String,
+    /// Java declaration, e.g. `public static int count()`
+    pub declaration: String,
+    pub access_flags: Vec<&'static str>,
+    /// Whether it has the `Deprecated` attribute or `@Deprecated`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub deprecated: bool,
+    /// Raw access flags, which the names above spell out
+    #[serde(skip)]
+    pub flags: u16,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Class {
+    pub name: String,
+    pub access_flags: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub super_class: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub interfaces: Vec<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub deprecated: bool,
+    pub fields: Vec<Member>,
+    pub methods: Vec<Member>,
+    #[serde(skip)]
+    pub flags: u16,
+}
+
+impl Class {
+    pub fn is_public(&self) -> bool {
+        ClassAccessFlags::from_bits_truncate(self.flags).contains(ClassAccessFlags::PUBLIC)
+    }
+
+    pub fn method(&self, name: &str, descriptor: &str) -> Option<&Member> {
+        self.methods
+            .iter()
+            .find(|method| method.name == name && method.descriptor == descriptor)
+    }
+}
+
+/// Whether code outside the library can call a method: public or protected, and not generated by the compiler.
+pub fn is_exported(flags: u16) -> bool {
+    let flags = MethodAccessFlags::from_bits_truncate(flags);
+    flags.intersects(MethodAccessFlags::PUBLIC | MethodAccessFlags::PROTECTED)
+        && !flags.intersects(MethodAccessFlags::SYNTHETIC | MethodAccessFlags::BRIDGE)
+}
+
+#[derive(Debug, Serialize)]
+pub struct Api {
+    pub classes: Vec<Class>,
+    /// Modules the `module-info` classes among the roots declare
+    #[serde(skip)]
+    pub modules: Vec<ModuleInfo>,
+}
+
+/// What a class file of the roots declares: a class, or the module of a `module-info` class file.
+enum Declared {
+    Class(Class),
+    Module(Option<ModuleInfo>),
+}
+
+const CLASS_FLAGS: [(ClassAccessFlags, &str); 9] = [
+    (ClassAccessFlags::PUBLIC, "ACC_PUBLIC"),
+    (ClassAccessFlags::FINAL, "ACC_FINAL"),
+    (ClassAccessFlags::SUPER, "ACC_SUPER"),
+    (ClassAccessFlags::INTERFACE, "ACC_INTERFACE"),
+    (ClassAccessFlags::ABSTRACT, "ACC_ABSTRACT"),
+    (ClassAccessFlags::SYNTHETIC, "ACC_SYNTHETIC"),
+    (ClassAccessFlags::ANNOTATION, "ACC_ANNOTATION"),
+    (ClassAccessFlags::ENUM, "ACC_ENUM"),
+    (ClassAccessFlags::MODULE, "ACC_MODULE"),
+];
+
+const FIELD_FLAGS: [(FieldAccessFlags, &str); 9] = [
+    (FieldAccessFlags::PUBLIC, "ACC_PUBLIC"),
+    (FieldAccessFlags::PRIVATE, "ACC_PRIVATE"),
+    (FieldAccessFlags::PROTECTED, "ACC_PROTECTED"),
+    (FieldAccessFlags::STATIC, "ACC_STATIC"),
+    (FieldAccessFlags::FINAL, "ACC_FINAL"),
+    (FieldAccessFlags::VOLATILE, "ACC_VOLATILE"),
+    (FieldAccessFlags::TRANSIENT, "ACC_TRANSIENT"),
+    (FieldAccessFlags::SYNTHETIC, "ACC_SYNTHETIC"),
+    (FieldAccessFlags::ENUM, "ACC_ENUM"),
+];
+
+impl Api {
+    /// The classes of the roots in name order, their members in declaration order.
+    pub fn read(roots: &[PathBuf], public_only: bool) -> Result<Self> {
+        let mut command_line = vec!["jmf".into()];
+        for root in roots {
+            command_line.extend(["-s".into(), root.as_os_str().to_os_string()]);
+        }
+        let scanner = Scanner::new(ApiScan::try_parse_from(command_line)?.scan)?;
+        let mut classes = Vec::new();
+        let mut modules = Vec::new();
+        for declared in scanner.map_classes(|source, class_file| {
+            if class_file.access_flags.contains(ClassAccessFlags::MODULE) {
+                Declared::Module(ModuleInfo::from_class(class_file, source.artifact()))
+            } else {
+                Declared::Class(class(class_file))
+            }
+        })? {
+            match declared {
+                Declared::Class(class) => classes.push(class),
+                Declared::Module(module) => modules.extend(module),
+            }
+        }
+        classes.sort_by(|a, b| a.name.cmp(&b.name));
+        if public_only {
+            classes.retain(Class::is_public);
+            for class in &mut classes {
+                class.fields.retain(|field| {
+                    let flags = FieldAccessFlags::from_bits_truncate(field.flags);
+                    flags.intersects(FieldAccessFlags::PUBLIC | FieldAccessFlags::PROTECTED)
+                        && !flags.contains(FieldAccessFlags::SYNTHETIC)
+                });
+                class.methods.retain(|method| is_exported(method.flags));
+            }
+        }
+        Ok(Self { classes, modules })
+    }
+
+    pub fn load(root: &Path) -> Result<Self> {
+        Self::read(&[root.to_path_buf()], false)
+    }
+
+    pub fn class(&self, name: &str) -> Option<&Class> {
+        self.classes
+            .binary_search_by(|class| class.name.as_str().cmp(name))
+            .ok()
+            .map(|index| &self.classes[index])
+    }
+
+    /// A javap-like listing: each class with its fields, then its methods, and their descriptors.
+    pub fn to_text(&self) -> String {
+        if self.classes.is_empty() {
+            return "No classes".to_string();
+        }
+        let mut output = Vec::new();
+        for class in &self.classes {
+            let mut heading = color::bold(&class.name);
+            if let Some(super_class) = class.super_class.as_deref().filter(|name| *name != "java.lang.Object") {
+                heading.push_str(&format!(" extends {}", super_class));
+            }
+            if !class.interfaces.is_empty() {
+                heading.push_str(&format!(" implements {}", class.interfaces.join(", ")));
+            }
+            output.push(format!(
+                "{} [{}]{}",
+                heading,
+                class.access_flags.join(", "),
+                deprecated_note(class.deprecated)
+            ));
+            for member in class.fields.iter().chain(&class.methods) {
+                output.push(format!(
+                    "  {}  {}{}",
+                    member.declaration,
+                    color::dim(&member.descriptor),
+                    deprecated_note(member.deprecated)
+                ));
+            }
+        }
+        output.join("\n")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
+
+fn deprecated_note(deprecated: bool) -> &'static str {
+    if deprecated {
+        " [deprecated]"
+    } else {
+        ""
+    }
+}
+
+/// Whether the attributes of a class or member mark it deprecated, as `javac` does for `@deprecated` Javadoc
+/// tags and `@Deprecated` annotations.
+fn is_deprecated(attributes: &[AttributeInfo]) -> bool {
+    attributes.iter().any(|attr| match &attr.data {
+        AttributeData::Deprecated => true,
+        AttributeData::RuntimeVisibleAnnotations(annotations) => annotations
+            .iter()
+            .any(|annotation| annotation_name(annotation).as_deref() == Some("java.lang.Deprecated")),
+        _ => false,
+    })
+}
+
+fn class(class_file: &ClassFile) -> Class {
+    let name = class_file.this_class.replace('/', ".");
+    Class {
+        access_flags: CLASS_FLAGS
+            .iter()
+            .filter(|(flag, _)| class_file.access_flags.contains(*flag))
+            .map(|(_, name)| *name)
+            .collect(),
+        super_class: class_file.super_class.as_ref().map(|name| name.replace('/', ".")),
+        interfaces: class_file
+            .interfaces
+            .iter()
+            .map(|name| name.replace('/', "."))
+            .collect(),
+        deprecated: is_deprecated(&class_file.attributes),
+        fields: class_file
+            .fields
+            .iter()
+            .map(|field| Member {
+                name: field.name.to_string(),
+                descriptor: field_descriptor(&field.descriptor),
+                declaration: field_declaration(field.access_flags, &field.name, &java_type(&field.descriptor)),
+                access_flags: FIELD_FLAGS
+                    .iter()
+                    .filter(|(flag, _)| field.access_flags.contains(*flag))
+                    .map(|(_, name)| *name)
+                    .collect(),
+                deprecated: is_deprecated(&field.attributes),
+                flags: field.access_flags.bits(),
+            })
+            .collect(),
+        methods: class_file
+            .methods
+            .iter()
+            .filter(|method| method.name != "<clinit>")
+            .map(|method| Member {
+                name: method.name.to_string(),
+                descriptor: method_descriptor(&method.descriptor),
+                declaration: resolve::declaration(&name, method),
+                access_flags: resolve::access_flags(method.access_flags),
+                deprecated: is_deprecated(&method.attributes),
+                flags: method.access_flags.bits(),
+            })
+            .collect(),
+        flags: class_file.access_flags.bits(),
+        name,
+    }
+}
+
+/// The field as javap declares it, e.g. `public static final int MAX`.
+fn field_declaration(flags: FieldAccessFlags, name: &str, java_type: &str) -> String {
+    const MODIFIERS: [(FieldAccessFlags, &str); 7] = [
+        (FieldAccessFlags::PUBLIC, "public"),
+        (FieldAccessFlags::PRIVATE, "private"),
+        (FieldAccessFlags::PROTECTED, "protected"),
+        (FieldAccessFlags::STATIC, "static"),
+        (FieldAccessFlags::FINAL, "final"),
+        (FieldAccessFlags::VOLATILE, "volatile"),
+        (FieldAccessFlags::TRANSIENT, "transient"),
+    ];
+    let mut words: Vec<&str> = MODIFIERS
+        .iter()
+        .filter(|(flag, _)| flags.contains(*flag))
+        .map(|(_, modifier)| *modifier)
+        .collect();
+    words.extend([java_type, name]);
+    words.join(" ")
+}
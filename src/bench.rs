@@ -0,0 +1,210 @@
+//! `jmf bench`: the scan of a search run several times, reporting its throughput, the time spent parsing and
+//! matching and its allocations, so that performance regressions across releases can be measured.
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{
+    allocations::{self, AllocationStats},
+    color,
+    policy::Rule,
+    profile::{self, Phase},
+    scan::{self, ScanArgs},
+    Args, MethodFinder, ReportFormatter,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct BenchArgs {
+    /// Class of the method to search for; without it, the scan matches a method nothing calls
+    #[arg(short = 'c', long = "class", requires = "target_method")]
+    pub target_class: Option<String>,
+
+    #[arg(short = 'm', long = "method", requires = "target_class")]
+    pub target_method: Option<String>,
+
+    /// Number of measured scans
+    #[arg(long = "iterations", value_name = "N", default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+    pub iterations: u32,
+
+    /// Number of scans run before measuring, which warm the file system cache
+    #[arg(long = "warmup", value_name = "N", default_value_t = 1)]
+    pub warmup: u32,
+
+    #[command(flatten)]
+    pub scan: ScanArgs,
+
+    #[arg(short = 'f', long = "format", value_enum, default_value_t = ReportFormatter::Txt)]
+    pub format: ReportFormatter,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Iteration {
+    pub wall_nanos: u64,
+    pub parse_nanos: u64,
+    pub match_nanos: u64,
+    #[serde(flatten)]
+    pub allocations: AllocationStats,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Bench {
+    pub target: String,
+    pub class_files: usize,
+    pub class_bytes: u64,
+    /// Class files that could not be read; the scans report them as a search does and go on
+    pub skipped: usize,
+    pub calls: usize,
+    pub warmup: u32,
+    pub iterations: Vec<Iteration>,
+    pub classes_per_second: f64,
+    pub bytes_per_second: f64,
+}
+
+impl Bench {
+    pub fn run(mut args: BenchArgs) -> Result<Self> {
+        // Cached results would only measure reads of the cache after the first scan
+        args.scan.result_cache = None;
+        let rule = Rule::method(
+            args.target_class.as_deref().unwrap_or_default(),
+            args.target_method.as_deref().unwrap_or_default(),
+        );
+        let profiling = args.scan.profile;
+        let finder = MethodFinder::with_rules(Args::defaults(args.scan), vec![rule])?;
+        let class_files = finder.scanner.class_files()?;
+        let mut class_bytes = 0;
+        let mut skipped = 0;
+        for source in &class_files {
+            match source.read() {
+                Ok(data) => class_bytes += data.len() as u64,
+                Err(_) => skipped += 1,
+            }
+        }
+        let class_count = class_files.len() - skipped;
+        profile::enable();
+        allocations::enable();
+        let mut calls = 0;
+        for _ in 0..args.warmup {
+            finder.scan_folder()?;
+        }
+        let mut iterations = Vec::new();
+        for _ in 0..args.iterations {
+            if scan::interrupted() {
+                break;
+            }
+            profile::reset();
+            allocations::reset();
+            let start = Instant::now();
+            calls = finder.scan_folder()?.0.len();
+            let wall = start.elapsed();
+            iterations.push(Iteration {
+                wall_nanos: wall.as_nanos() as u64,
+                parse_nanos: profile::elapsed(Phase::Parse).as_nanos() as u64,
+                match_nanos: profile::elapsed(Phase::Match).as_nanos() as u64,
+                allocations: allocations::stats(),
+            });
+        }
+        // `--profile` reports the last iteration
+        if !profiling {
+            profile::disable();
+        }
+        let wall_seconds = iterations
+            .iter()
+            .map(|iteration| iteration.wall_nanos as f64 / 1e9)
+            .sum::<f64>();
+        let per_second = |amount: f64| {
+            if wall_seconds > 0.0 {
+                amount * iterations.len() as f64 / wall_seconds
+            } else {
+                0.0
+            }
+        };
+        Ok(Self {
+            target: match args.target_class {
+                Some(_) => finder.target(),
+                None => "no target".to_string(),
+            },
+            class_files: class_count,
+            class_bytes,
+            skipped,
+            calls,
+            warmup: args.warmup,
+            classes_per_second: per_second(class_count as f64),
+            bytes_per_second: per_second(class_bytes as f64),
+            iterations,
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mean = |value: fn(&Iteration) -> u64| {
+            self.iterations.iter().map(value).sum::<u64>() / (self.iterations.len().max(1) as u64)
+        };
+        let walls = self.iterations.iter().map(|iteration| iteration.wall_nanos);
+        let (min, max) = (walls.clone().min().unwrap_or_default(), walls.max().unwrap_or_default());
+        let plural = |count: usize, noun: &str| format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" });
+        let mut output = vec![color::bold(format!(
+            "Benchmark of {} over {} ({}), {} after {}:",
+            self.target,
+            plural(self.class_files, "class file"),
+            bytes(self.class_bytes as f64),
+            plural(self.iterations.len(), "iteration"),
+            plural(self.warmup as usize, "warmup"),
+        ))];
+        output.push(format!(
+            " - wall: {:.3?} mean, {:.3?} min, {:.3?} max",
+            nanos(mean(|iteration| iteration.wall_nanos)),
+            nanos(min),
+            nanos(max)
+        ));
+        output.push(format!(
+            " - throughput: {:.0} classes/sec, {}/sec",
+            self.classes_per_second,
+            bytes(self.bytes_per_second)
+        ));
+        // Both run on every thread, so they can exceed the wall-clock time
+        output.push(format!(
+            " - parse: {:.3?}, match: {:.3?} per iteration, across threads",
+            nanos(mean(|iteration| iteration.parse_nanos)),
+            nanos(mean(|iteration| iteration.match_nanos))
+        ));
+        output.push(format!(
+            " - allocations: {} ({}) per iteration, peak {}",
+            mean(|iteration| iteration.allocations.allocations),
+            bytes(mean(|iteration| iteration.allocations.allocated_bytes) as f64),
+            bytes(mean(|iteration| iteration.allocations.peak_bytes) as f64)
+        ));
+        output.push(format!(" - calls found: {}", self.calls));
+        if self.skipped > 0 {
+            output.push(format!(
+                " - skipped: {} that could not be read",
+                plural(self.skipped, "class file")
+            ));
+        }
+        output.join("\n")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
+
+fn nanos(nanos: u64) -> Duration {
+    Duration::from_nanos(nanos)
+}
+
+/// Bytes in the largest unit under which they stay at least 1, as `3.42 MB`.
+fn bytes(bytes: f64) -> String {
+    let units = ["B", "KB", "MB", "GB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < units.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{:.0} {}", value, units[unit])
+    } else {
+        format!("{:.2} {}", value, units[unit])
+    }
+}
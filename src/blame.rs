@@ -0,0 +1,93 @@
+//! `--blame`: the commit that last changed the line of each call, from `git blame` on the caller's source
+//! file under the `--source-root` directories.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{Context, Result};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use tracing::debug;
+
+use crate::analysis::{Blame, FoundCall};
+
+/// Sets the blame of the calls whose source file is found under a source root and tracked by git.
+pub fn annotate(calls: &mut [FoundCall], source_roots: &[PathBuf]) -> Result<()> {
+    let mut files: HashMap<PathBuf, Vec<usize>> = HashMap::new();
+    for (index, call) in calls.iter().enumerate() {
+        let source_path = call.source_path();
+        if let Some(file) = source_roots
+            .iter()
+            .map(|source_root| source_root.join(&source_path))
+            .find(|file| file.is_file())
+        {
+            files.entry(file).or_default().push(index);
+        }
+    }
+    let blamed = files
+        .into_par_iter()
+        .map(|(file, indices)| Ok((blame(&file)?, indices)))
+        .collect::<Result<Vec<_>>>()?;
+    for (lines, indices) in blamed {
+        for index in indices {
+            let call = &mut calls[index];
+            let line = usize::from(call.line_number).checked_sub(1);
+            call.blame = line.and_then(|line| lines.get(line)).cloned();
+        }
+    }
+    Ok(())
+}
+
+/// The blame of every line of a file, empty when git does not track it.
+fn blame(file: &Path) -> Result<Vec<Blame>> {
+    // Run in the directory of the file, which finds its repository
+    let directory = file.parent().filter(|directory| !directory.as_os_str().is_empty());
+    let mut command = Command::new("git");
+    command
+        .args(["blame", "--line-porcelain", "--"])
+        .arg(file.file_name().unwrap_or_default());
+    if let Some(directory) = directory {
+        command.current_dir(directory);
+    }
+    let output = command.output().context("Failed to run git, which --blame needs")?;
+    if !output.status.success() {
+        debug!(
+            "git blame {} failed: {}",
+            file.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return Ok(Vec::new());
+    }
+    Ok(parse(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Every line of `--line-porcelain` output starts with a header naming its commit, followed by the
+/// commit's details and then the line itself after a tab.
+fn parse(porcelain: &str) -> Vec<Blame> {
+    let mut lines = Vec::new();
+    let mut commit = None;
+    let mut author = String::new();
+    let mut author_mail = String::new();
+    let mut summary = String::new();
+    for line in porcelain.lines() {
+        if line.starts_with('\t') {
+            lines.push(Blame {
+                commit: commit.take().unwrap_or_default(),
+                author: std::mem::take(&mut author),
+                author_mail: std::mem::take(&mut author_mail),
+                summary: std::mem::take(&mut summary),
+            });
+        } else if commit.is_none() {
+            commit = line.split(' ').next().map(str::to_string);
+        } else if let Some(value) = line.strip_prefix("author ") {
+            author = value.to_string();
+        } else if let Some(value) = line.strip_prefix("author-mail ") {
+            author_mail = value.trim_start_matches('<').trim_end_matches('>').to_string();
+        } else if let Some(value) = line.strip_prefix("summary ") {
+            summary = value.to_string();
+        }
+    }
+    lines
+}
@@ -0,0 +1,94 @@
+//! `--changed-since`: the source files git reports as changed since a revision, and the class files
+//! compiled from them. A class belongs to a changed source file when its `SourceFile` attribute names the
+//! file (or, without the attribute, its outermost class does) and its package is the tail of the file's
+//! directory, as `com/example` is of `src/main/java/com/example/Foo.java`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    process::Command,
+};
+
+use anyhow::{Context, Result};
+use cafebabe::{attributes::AttributeData, ClassFile};
+
+#[derive(Debug, Default)]
+pub struct ChangedSources {
+    /// Directories of the changed files, by file name
+    directories: HashMap<String, Vec<String>>,
+    /// Last components of those directories, to skip class files before parsing them
+    packages: HashSet<String>,
+}
+
+impl ChangedSources {
+    /// The files changed since `revision`, committed or not, and the untracked ones.
+    pub fn since(revision: &str) -> Result<Self> {
+        let mut changes = Self::default();
+        let changed = git(&["diff", "--name-only", revision, "--"])?;
+        let untracked = git(&["ls-files", "--others", "--exclude-standard"])?;
+        for path in changed.iter().chain(&untracked) {
+            changes.insert(path);
+        }
+        Ok(changes)
+    }
+
+    fn insert(&mut self, path: &str) {
+        let (directory, file_name) = path.rsplit_once('/').unwrap_or(("", path));
+        self.packages
+            .insert(directory.rsplit('/').next().unwrap_or_default().to_string());
+        self.directories
+            .entry(file_name.to_string())
+            .or_default()
+            .push(directory.to_string());
+    }
+
+    /// Whether a class file at `name` in a scan root may belong to a changed file, judged by the last
+    /// directory of its path; classes at the top of a root may be in any package.
+    pub fn may_contain(&self, name: &str) -> bool {
+        match name.rsplit_once('/') {
+            Some((directory, _)) => self.packages.contains(directory.rsplit('/').next().unwrap_or_default()),
+            None => true,
+        }
+    }
+
+    /// Whether a class was compiled from a changed file.
+    pub fn contains(&self, class_file: &ClassFile) -> bool {
+        let (package, simple_name) = class_file
+            .this_class
+            .rsplit_once('/')
+            .unwrap_or(("", &class_file.this_class));
+        let source_file = class_file
+            .attributes
+            .iter()
+            .find_map(|attr| match &attr.data {
+                AttributeData::SourceFile(source_file) => Some(source_file.to_string()),
+                _ => None,
+            })
+            .unwrap_or_else(|| format!("{}.java", simple_name.split('$').next().unwrap_or(simple_name)));
+        self.directories.get(&source_file).is_some_and(|directories| {
+            directories.iter().any(|directory| {
+                package.is_empty()
+                    || directory == package
+                    || directory.strip_suffix(package).is_some_and(|rest| rest.ends_with('/'))
+            })
+        })
+    }
+}
+
+/// The lines git prints for a command, run in the current directory.
+fn git(args: &[&str]) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .context("Failed to run git, which --changed-since needs")?;
+    anyhow::ensure!(
+        output.status.success(),
+        "git {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
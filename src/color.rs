@@ -0,0 +1,72 @@
+//! ANSI colors for text output, for `--color`.
+
+use std::{
+    env,
+    fmt::Display,
+    io::{self, IsTerminal},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ColorChoice {
+    /// Color when standard output is a terminal and `NO_COLOR` is not set
+    #[cfg_attr(feature = "cli", value(name = "auto"))]
+    Auto,
+    #[cfg_attr(feature = "cli", value(name = "always"))]
+    Always,
+    #[cfg_attr(feature = "cli", value(name = "never"))]
+    Never,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn init(choice: ColorChoice) {
+    let enabled = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) && io::stdout().is_terminal(),
+    };
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+fn paint(code: &str, text: impl Display) -> String {
+    if ENABLED.load(Ordering::Relaxed) {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Searched targets and rule headings.
+pub fn bold(text: impl Display) -> String {
+    paint("1", text)
+}
+
+/// Class names.
+pub fn cyan(text: impl Display) -> String {
+    paint("36", text)
+}
+
+/// Line numbers.
+pub fn dim(text: impl Display) -> String {
+    paint("2", text)
+}
+
+/// Error severities.
+pub fn red(text: impl Display) -> String {
+    paint("31", text)
+}
+
+/// Warning severities.
+pub fn yellow(text: impl Display) -> String {
+    paint("33", text)
+}
+
+/// Renders without colors whatever `--color` says, e.g. for a report written to a file.
+pub fn plain<T>(render: impl FnOnce() -> T) -> T {
+    let enabled = ENABLED.swap(false, Ordering::Relaxed);
+    let result = render();
+    ENABLED.store(enabled, Ordering::Relaxed);
+    result
+}
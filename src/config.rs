@@ -0,0 +1,123 @@
+//! Project configuration in `jmf.toml`, holding named queries for `jmf run` and the schedules of
+//! `jmf serve`.
+//!
+//! ```toml
+//! [queries.payment-audit]
+//! query = 'calls("com.pay.Gateway#charge") and not caller(package ~ "com.app.payments.**")'
+//! severity = "warn"
+//! message = "charges must go through the payments module"
+//!
+//! [schedules.nightly]
+//! cron = "0 2 * * *"
+//! roots = ["build/libs"]
+//! queries = ["payment-audit"]
+//! ```
+//!
+//! A saved query takes the same keys as a policy rule, named by its table. A schedule runs the saved
+//! queries it names, or all of them, on its scan roots.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use crate::{
+    cron::Cron,
+    policy::{Rule, RuleEntry},
+};
+
+#[derive(Debug, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    queries: BTreeMap<String, RuleEntry>,
+    #[serde(default)]
+    schedules: BTreeMap<String, ScheduleEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScheduleEntry {
+    cron: String,
+    roots: Vec<PathBuf>,
+    /// Every saved query when unset
+    queries: Option<Vec<String>>,
+}
+
+/// A scheduled scan of `jmf serve`.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    pub name: String,
+    pub cron: Cron,
+    pub roots: Vec<PathBuf>,
+    pub rules: Vec<Rule>,
+}
+
+fn load(path: &Path) -> Result<ConfigFile> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Saved queries by name, in name order.
+pub fn load_queries(path: &Path) -> Result<BTreeMap<String, Rule>> {
+    queries(load(path)?.queries, path)
+}
+
+fn queries(entries: BTreeMap<String, RuleEntry>, path: &Path) -> Result<BTreeMap<String, Rule>> {
+    entries
+        .into_iter()
+        .map(|(name, entry)| {
+            let mut rule = entry.into_rule(|| format!("Query '{}' in {}", name, path.display()))?;
+            rule.id.get_or_insert_with(|| name.clone());
+            Ok((name, rule))
+        })
+        .collect()
+}
+
+/// The saved queries called `names`, or all of them with `all`.
+pub fn select(queries: BTreeMap<String, Rule>, names: &[String], all: bool, path: &Path) -> Result<Vec<Rule>> {
+    if all {
+        return Ok(queries.into_values().collect());
+    }
+    names
+        .iter()
+        .map(|name| match queries.get(name) {
+            Some(rule) => Ok(rule.clone()),
+            None => bail!(
+                "No query '{}' in {}; available: {}",
+                name,
+                path.display(),
+                queries.keys().cloned().collect::<Vec<_>>().join(", ")
+            ),
+        })
+        .collect()
+}
+
+/// Schedules in name order, with the saved queries they run.
+pub fn load_schedules(path: &Path) -> Result<Vec<Schedule>> {
+    let config = load(path)?;
+    let queries = queries(config.queries, path)?;
+    config
+        .schedules
+        .into_iter()
+        .map(|(name, entry)| {
+            let context = || format!("Schedule '{}' in {}", name, path.display());
+            let cron = Cron::parse(&entry.cron).with_context(context)?;
+            let rules = match &entry.queries {
+                Some(names) => select(queries.clone(), names, false, path).with_context(context)?,
+                None => queries.values().cloned().collect(),
+            };
+            anyhow::ensure!(!rules.is_empty(), "{} has no queries to run", context());
+            anyhow::ensure!(!entry.roots.is_empty(), "{} has no roots to scan", context());
+            Ok(Schedule {
+                name,
+                cron,
+                roots: entry.roots,
+                rules,
+            })
+        })
+        .collect()
+}
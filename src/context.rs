@@ -0,0 +1,233 @@
+//! Control-flow context of a call site within its method.
+
+use cafebabe::{
+    attributes::{AttributeData, CodeData},
+    bytecode::Opcode,
+    MethodAccessFlags, MethodInfo,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::pattern::opcode_name;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CallContext {
+    pub in_loop: bool,
+    /// Exception types of the handlers covering the call site; `any` stands for a catch-all such as
+    /// `finally`. The handler javac adds to release the monitor of a `synchronized` block is left out.
+    pub handlers: Vec<String>,
+    pub synchronized: bool,
+    /// Local variable (`this` included) or field the invoked method is called on, for `invokevirtual` and
+    /// `invokeinterface`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receiver: Option<String>,
+}
+
+impl CallContext {
+    pub fn analyze(method: &MethodInfo, code: &CodeData, opcodes: &[(usize, Opcode)], offset: usize) -> Self {
+        let handlers = code
+            .exception_table
+            .iter()
+            .filter(|entry| usize::from(entry.start_pc) <= offset && offset < usize::from(entry.end_pc))
+            // javac guards a `synchronized` block with a catch-all that only releases the monitor
+            .filter(|entry| entry.catch_type.is_some() || !releases_monitor(opcodes, usize::from(entry.handler_pc)))
+            .map(|entry| match &entry.catch_type {
+                Some(catch_type) => catch_type.replace('/', "."),
+                None => "any".to_string(),
+            })
+            .fold(Vec::new(), |mut handlers, handler| {
+                if !handlers.contains(&handler) {
+                    handlers.push(handler);
+                }
+                handlers
+            });
+
+        Self {
+            in_loop: backward_branches(opcodes).any(|(target, source)| target <= offset && offset <= source),
+            handlers,
+            synchronized: method.access_flags.contains(MethodAccessFlags::SYNCHRONIZED)
+                || monitor_depth(opcodes, offset) > 0,
+            receiver: receiver(code, opcodes, offset),
+        }
+    }
+
+    /// Short markers appended to a call in text output, e.g. `[loop]`.
+    pub fn markers(&self) -> Vec<String> {
+        let mut markers = Vec::new();
+        if self.in_loop {
+            markers.push("loop".to_string());
+        }
+        if !self.handlers.is_empty() {
+            markers.push(format!("try: {}", self.handlers.join(", ")));
+        }
+        if self.synchronized {
+            markers.push("synchronized".to_string());
+        }
+        if let Some(receiver) = &self.receiver {
+            markers.push(format!("receiver: {}", receiver));
+        }
+        markers
+    }
+}
+
+/// Yields `(target, source)` offsets of every branch that jumps backwards. A call whose offset lies
+/// within such a range sits in the body of a loop.
+fn backward_branches<'a>(opcodes: &'a [(usize, Opcode)]) -> impl Iterator<Item = (usize, usize)> + 'a {
+    opcodes.iter().filter_map(|(source, opcode)| {
+        let jump = match opcode {
+            Opcode::Goto(jump)
+            | Opcode::IfAcmpeq(jump)
+            | Opcode::IfAcmpne(jump)
+            | Opcode::IfIcmpeq(jump)
+            | Opcode::IfIcmpge(jump)
+            | Opcode::IfIcmpgt(jump)
+            | Opcode::IfIcmple(jump)
+            | Opcode::IfIcmplt(jump)
+            | Opcode::IfIcmpne(jump)
+            | Opcode::Ifeq(jump)
+            | Opcode::Ifge(jump)
+            | Opcode::Ifgt(jump)
+            | Opcode::Ifle(jump)
+            | Opcode::Iflt(jump)
+            | Opcode::Ifne(jump)
+            | Opcode::Ifnonnull(jump)
+            | Opcode::Ifnull(jump) => *jump,
+            _ => return None,
+        };
+        let target = usize::try_from(*source as i64 + jump as i64).ok()?;
+        (target <= *source).then_some((target, *source))
+    })
+}
+
+/// Number of monitors held at `offset`, counting `monitorenter`/`monitorexit` in code order. The
+/// duplicate `monitorexit` javac emits on the exceptional path is absorbed by saturating at zero.
+pub(crate) fn monitor_depth(opcodes: &[(usize, Opcode)], offset: usize) -> usize {
+    opcodes
+        .iter()
+        .take_while(|(position, _)| *position < offset)
+        .fold(0, |depth: usize, (_, opcode)| match opcode {
+            Opcode::Monitorenter => depth + 1,
+            Opcode::Monitorexit => depth.saturating_sub(1),
+            _ => depth,
+        })
+}
+
+/// Whether the handler at `handler` is the one javac emits for a `synchronized` block: it stores the
+/// exception, then loads the local the lock was saved to on `monitorenter` and exits its monitor.
+fn releases_monitor(opcodes: &[(usize, Opcode)], handler: usize) -> bool {
+    let Ok(index) = opcodes.binary_search_by_key(&handler, |(offset, _)| *offset) else {
+        return false;
+    };
+    let [(_, Opcode::Astore(_)), (_, Opcode::Aload(lock)), (_, Opcode::Monitorexit), ..] = &opcodes[index..] else {
+        return false;
+    };
+    opcodes
+        .windows(2)
+        .any(|pair| matches!(pair, [(_, Opcode::Astore(slot)), (_, Opcode::Monitorenter)] if slot == lock))
+}
+
+/// Name of the receiver of the `invokevirtual` or `invokeinterface` at `offset`: the local variable it was
+/// loaded from, as named by the `LocalVariableTable`, or the field it was read from. Found by walking back
+/// over the instructions that push the arguments; gives up at control flow or stack shuffling it cannot
+/// follow in a straight line.
+fn receiver(code: &CodeData, opcodes: &[(usize, Opcode)], offset: usize) -> Option<String> {
+    let index = opcodes.binary_search_by_key(&offset, |(offset, _)| *offset).ok()?;
+    let (Opcode::Invokevirtual(member_ref) | Opcode::Invokeinterface(member_ref, _)) = &opcodes[index].1 else {
+        return None;
+    };
+    // Values pushed after the receiver
+    let mut above = parameter_count(&member_ref.name_and_type.descriptor)?;
+    for (position, opcode) in opcodes[..index].iter().rev() {
+        if matches!(opcode, Opcode::Dup) {
+            // Both copies are the same value
+            above = if above < 2 { 0 } else { above - 1 };
+            continue;
+        }
+        let (pops, pushes) = stack_effect(opcode)?;
+        if above < pushes {
+            return match opcode {
+                Opcode::Aload(slot) => local_variable_name(code, *position, *slot),
+                Opcode::Getfield(field) | Opcode::Getstatic(field) => Some(field.name_and_type.name.to_string()),
+                _ => None,
+            };
+        }
+        above = above - pushes + pops;
+    }
+    None
+}
+
+fn local_variable_name(code: &CodeData, offset: usize, slot: u16) -> Option<String> {
+    code.attributes.iter().find_map(|attr| match &attr.data {
+        AttributeData::LocalVariableTable(entries) => entries
+            .iter()
+            .find(|entry| {
+                entry.index == slot
+                    && usize::from(entry.start_pc) <= offset
+                    && offset <= usize::from(entry.start_pc) + usize::from(entry.length)
+            })
+            .map(|entry| entry.name.to_string()),
+        _ => None,
+    })
+}
+
+/// Number of parameters of a method descriptor such as `(I[Ljava/lang/String;J)V`.
+fn parameter_count(descriptor: &str) -> Option<usize> {
+    let parameters = descriptor.strip_prefix('(')?.split_once(')')?.0;
+    let mut count = 0;
+    let mut chars = parameters.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '[' => continue,
+            'L' => {
+                chars.find(|&c| c == ';')?;
+            }
+            _ => {}
+        }
+        count += 1;
+    }
+    Some(count)
+}
+
+/// Values an instruction pops and pushes, counting a `long` or `double` as one value, or `None` for
+/// instructions the receiver search does not walk past.
+fn stack_effect(opcode: &Opcode) -> Option<(usize, usize)> {
+    let returns = |descriptor: &str| usize::from(!descriptor.ends_with(")V"));
+    let effect = match opcode {
+        Opcode::Invokestatic(member_ref) => (
+            parameter_count(&member_ref.name_and_type.descriptor)?,
+            returns(&member_ref.name_and_type.descriptor),
+        ),
+        Opcode::Invokevirtual(member_ref)
+        | Opcode::Invokespecial(member_ref)
+        | Opcode::Invokeinterface(member_ref, _) => (
+            parameter_count(&member_ref.name_and_type.descriptor)? + 1,
+            returns(&member_ref.name_and_type.descriptor),
+        ),
+        Opcode::Invokedynamic(invoke_dynamic) => (
+            parameter_count(&invoke_dynamic.name_and_type.descriptor)?,
+            returns(&invoke_dynamic.name_and_type.descriptor),
+        ),
+        Opcode::Multianewarray(_, dimensions) => (usize::from(*dimensions), 1),
+        _ => match opcode_name(opcode).as_str() {
+            "nop" | "iinc" => (0, 0),
+            "aconstnull" | "iconstm1" | "iconst0" | "iconst1" | "iconst2" | "iconst3" | "iconst4" | "iconst5"
+            | "lconst0" | "lconst1" | "fconst0" | "fconst1" | "fconst2" | "dconst0" | "dconst1" | "bipush"
+            | "sipush" | "ldc" | "ldcw" | "ldc2w" | "iload" | "lload" | "fload" | "dload" | "aload" | "new"
+            | "getstatic" => (0, 1),
+            "istore" | "lstore" | "fstore" | "dstore" | "astore" | "pop" | "putstatic" | "monitorenter"
+            | "monitorexit" => (1, 0),
+            "getfield" | "checkcast" | "instanceof" | "arraylength" | "newarray" | "anewarray" | "ineg" | "lneg"
+            | "fneg" | "dneg" | "i2l" | "i2f" | "i2d" | "l2i" | "l2f" | "l2d" | "f2i" | "f2l" | "f2d" | "d2i"
+            | "d2l" | "d2f" | "i2b" | "i2c" | "i2s" => (1, 1),
+            "iadd" | "ladd" | "fadd" | "dadd" | "isub" | "lsub" | "fsub" | "dsub" | "imul" | "lmul" | "fmul"
+            | "dmul" | "idiv" | "ldiv" | "fdiv" | "ddiv" | "irem" | "lrem" | "frem" | "drem" | "ishl" | "lshl"
+            | "ishr" | "lshr" | "iushr" | "lushr" | "iand" | "land" | "ior" | "lor" | "ixor" | "lxor" | "lcmp"
+            | "fcmpl" | "fcmpg" | "dcmpl" | "dcmpg" | "iaload" | "laload" | "faload" | "daload" | "aaload"
+            | "baload" | "caload" | "saload" => (2, 1),
+            "putfield" => (2, 0),
+            "iastore" | "lastore" | "fastore" | "dastore" | "aastore" | "bastore" | "castore" | "sastore" => (3, 0),
+            // Branches, returns, throws, switches and the dup/swap family
+            _ => return None,
+        },
+    };
+    Some(effect)
+}
@@ -0,0 +1,144 @@
+//! Five-field cron expressions, `minute hour day-of-month month day-of-week`, for the schedules of
+//! `jmf serve`. Fields take `*`, numbers, ranges `a-b`, steps `*/n` or `a-b/n` and comma-separated lists
+//! of those; as in cron, a day matches when either day field does if both are restricted. Times are UTC.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Result};
+
+use crate::trend::civil_from_days;
+
+#[derive(Debug, Clone)]
+pub struct Cron {
+    expression: String,
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+    /// Whether the day fields are `*`
+    any_day: bool,
+    any_weekday: bool,
+}
+
+/// A minute of UTC time, broken down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Minute {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    /// 0 for Sunday
+    pub weekday: u32,
+}
+
+impl Minute {
+    pub fn now() -> Self {
+        Self::from_unix(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        )
+    }
+
+    pub fn from_unix(seconds: u64) -> Self {
+        let days = (seconds / 86_400) as i64;
+        let (year, month, day) = civil_from_days(days);
+        Self {
+            year,
+            month,
+            day,
+            hour: (seconds % 86_400 / 3_600) as u32,
+            minute: (seconds % 3_600 / 60) as u32,
+            // 1970-01-01 was a Thursday
+            weekday: (days + 4).rem_euclid(7) as u32,
+        }
+    }
+}
+
+impl Cron {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            bail!(
+                "Invalid cron expression '{}': expected 5 fields, found {}",
+                expression,
+                fields.len()
+            );
+        };
+        let parse = |field: &str, name: &str, min: u32, max: u32| {
+            parse_field(field, min, max)
+                .with_context(|| format!("Invalid {} '{}' in cron expression '{}'", name, field, expression))
+        };
+        let mut weekdays = parse(weekday, "day of week", 0, 7)?;
+        // Both 0 and 7 are Sunday
+        if weekdays[7] {
+            weekdays[0] = true;
+        }
+        Ok(Self {
+            expression: expression.to_string(),
+            minutes: parse(minute, "minute", 0, 59)?,
+            hours: parse(hour, "hour", 0, 23)?,
+            days: parse(day, "day of month", 1, 31)?,
+            months: parse(month, "month", 1, 12)?,
+            weekdays,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+
+    pub fn matches(&self, time: &Minute) -> bool {
+        let day = self.days[time.day as usize];
+        let weekday = self.weekdays[time.weekday as usize];
+        let day_matches = match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        };
+        self.minutes[time.minute as usize]
+            && self.hours[time.hour as usize]
+            && self.months[time.month as usize]
+            && day_matches
+    }
+}
+
+impl std::fmt::Display for Cron {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+/// The values of a field, indexed by value up to `max`.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<Vec<bool>> {
+    let mut values = vec![false; max as usize + 1];
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().context("Invalid step")?),
+            None => (part, 1),
+        };
+        anyhow::ensure!(step > 0, "The step must not be 0");
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (start.parse()?, end.parse()?),
+                // `a/n` runs from `a` to the end of the field
+                None if part.contains('/') => (range.parse()?, max),
+                None => {
+                    let value = range.parse()?;
+                    (value, value)
+                }
+            },
+        };
+        anyhow::ensure!(
+            min <= start && start <= end && end <= max,
+            "Values must be from {} to {}",
+            min,
+            max
+        );
+        for value in (start..=end).step_by(step as usize) {
+            values[value as usize] = true;
+        }
+    }
+    Ok(values)
+}
@@ -0,0 +1,55 @@
+//! POSTs made with `curl`, whose URL and headers are given in a config on its standard input rather than
+//! on its command line: webhook URLs and API key headers are secrets, and every local user can read the
+//! command lines of processes.
+
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+use anyhow::{Context, Result};
+
+/// POSTs a JSON `payload` to `url` with extra `headers`, for `purpose` in messages, e.g. `--notify-webhook`.
+/// The payload is read from a temporary file only its owner can read.
+pub fn post_json(url: &str, headers: &[(String, String)], payload: &str, purpose: &str) -> Result<Output> {
+    let mut body = tempfile::NamedTempFile::new().context("Failed to write the request body")?;
+    body.write_all(payload.as_bytes())
+        .context("Failed to write the request body")?;
+    let mut config = format!("url = {}\n", quote(url));
+    for (key, value) in [("Content-Type".to_string(), "application/json".to_string())]
+        .iter()
+        .chain(headers)
+    {
+        config.push_str(&format!("header = {}\n", quote(&format!("{}: {}", key, value))));
+    }
+
+    let mut child = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--config", "-", "--data-binary"])
+        .arg(format!("@{}", body.path().display()))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run curl, which {} needs", purpose))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(config.as_bytes())?;
+    }
+    Ok(child.wait_with_output()?)
+}
+
+/// A string of a curl config, quoted with the escapes curl reads back.
+fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for character in value.chars() {
+        match character {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            character => quoted.push(character),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
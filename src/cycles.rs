@@ -0,0 +1,247 @@
+//! `jmf cycles`: the strongly connected components of the dependency graph of `jmf deps`, each with the
+//! references making its edges, so that breaking a cycle starts from the lines to change.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{
+    color,
+    deps::{self, Kind, Level},
+    scan::{ScanArgs, Scanner},
+};
+
+#[derive(clap::Args, Debug)]
+pub struct CyclesArgs {
+    /// Whether the cycles are between classes or between packages
+    #[arg(long = "level", value_enum, default_value_t = Level::Package)]
+    pub level: Level,
+
+    #[command(flatten)]
+    pub scan: ScanArgs,
+
+    #[arg(short = 'f', long = "format", value_enum, default_value_t = crate::ReportFormatter::Txt)]
+    pub format: crate::ReportFormatter,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CycleReference {
+    pub class_name: String,
+    /// Method making the reference, unless it is the superclass or an interface of the class
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_number: Option<u16>,
+    pub kind: Kind,
+    /// Class referred to, with the method or field for invocations and field accesses
+    pub target: String,
+}
+
+impl std::fmt::Display for CycleReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.class_name)?;
+        if let Some(method_name) = &self.method_name {
+            write!(f, "#{}", method_name)?;
+        }
+        if let Some(line_number) = self.line_number {
+            write!(f, " (L{})", line_number)?;
+        }
+        let verb = match self.kind {
+            Kind::Invocation => "calls",
+            Kind::Field => "accesses",
+            Kind::Type => "references",
+        };
+        write!(f, " {} {}", verb, self.target)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CycleEdge {
+    pub from: String,
+    pub to: String,
+    pub references: Vec<CycleReference>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Cycle {
+    /// Classes or packages of the component, in name order
+    pub elements: Vec<String>,
+    /// Edges between them, each of which takes part in a cycle
+    pub edges: Vec<CycleEdge>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Cycles {
+    pub level: String,
+    pub cycles: Vec<Cycle>,
+}
+
+impl Cycles {
+    /// The components of more than one element, the largest first.
+    pub fn run(args: CyclesArgs) -> Result<Self> {
+        let scanner = Scanner::new(args.scan)?;
+        let level = args.level;
+        let classes = scanner.map_classes(|_, class_file| {
+            let class_name = class_file.this_class.replace('/', ".");
+            let from = deps::element(&class_file.this_class, level);
+            let references: Vec<(String, CycleReference)> = deps::references(class_file)
+                .into_iter()
+                .filter_map(|reference| {
+                    let to = deps::element(&reference.class, level);
+                    let target = match &reference.member {
+                        Some(member) => format!("{}#{}", reference.class.replace('/', "."), member),
+                        None => reference.class.replace('/', "."),
+                    };
+                    (to != from).then(|| {
+                        (
+                            to,
+                            CycleReference {
+                                class_name: class_name.clone(),
+                                method_name: reference.method,
+                                line_number: reference.line_number,
+                                kind: reference.kind,
+                                target,
+                            },
+                        )
+                    })
+                })
+                .collect();
+            (from, references)
+        })?;
+        let mut edges: BTreeMap<(String, String), Vec<CycleReference>> = BTreeMap::new();
+        let mut nodes: BTreeSet<String> = BTreeSet::new();
+        for (from, references) in classes {
+            nodes.insert(from.clone());
+            for (to, reference) in references {
+                edges.entry((from.clone(), to)).or_default().push(reference);
+            }
+        }
+        // Classes that are not scanned depend on nothing, so take no part in cycles
+        edges.retain(|(_, to), _| nodes.contains(to));
+
+        let mut cycles: Vec<Cycle> = components(&nodes, edges.keys())
+            .into_iter()
+            .filter(|component| component.len() > 1)
+            .map(|component| Cycle {
+                edges: edges
+                    .iter_mut()
+                    .filter(|((from, to), _)| component.contains(from) && component.contains(to))
+                    .map(|((from, to), references)| CycleEdge {
+                        from: from.clone(),
+                        to: to.clone(),
+                        references: std::mem::take(references),
+                    })
+                    .collect(),
+                elements: component.into_iter().collect(),
+            })
+            .collect();
+        cycles.sort_by(|a, b| {
+            b.elements
+                .len()
+                .cmp(&a.elements.len())
+                .then(a.elements.cmp(&b.elements))
+        });
+        Ok(Self {
+            level: match level {
+                Level::Class => "class",
+                Level::Package => "package",
+            }
+            .to_string(),
+            cycles,
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        if self.cycles.is_empty() {
+            return "No results".to_string();
+        }
+        let level = match self.level.as_str() {
+            "class" => "classes",
+            _ => "packages",
+        };
+        let mut output = Vec::new();
+        for cycle in &self.cycles {
+            output.push(color::bold(format!(
+                "{} {} in a cycle: {}",
+                cycle.elements.len(),
+                level,
+                cycle.elements.join(", ")
+            )));
+            for edge in &cycle.edges {
+                output.push(format!(" {} -> {}", edge.from, edge.to));
+                output.extend(edge.references.iter().map(|reference| format!("  - {}", reference)));
+            }
+        }
+        output.join("\n")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
+
+/// The strongly connected components of the graph, by Tarjan's algorithm, kept iterative so that long
+/// chains of classes do not overflow the stack.
+fn components<'a>(
+    nodes: &'a BTreeSet<String>,
+    edges: impl Iterator<Item = &'a (String, String)>,
+) -> Vec<BTreeSet<String>> {
+    let index_of: BTreeMap<&str, usize> = nodes.iter().enumerate().map(|(i, node)| (node.as_str(), i)).collect();
+    let names: Vec<&String> = nodes.iter().collect();
+    let mut successors = vec![Vec::new(); nodes.len()];
+    for (from, to) in edges {
+        successors[index_of[from.as_str()]].push(index_of[to.as_str()]);
+    }
+
+    let mut index = vec![usize::MAX; nodes.len()];
+    let mut low = vec![0; nodes.len()];
+    let mut on_stack = vec![false; nodes.len()];
+    let mut stack = Vec::new();
+    let mut next = 0;
+    let mut components = Vec::new();
+    for root in 0..nodes.len() {
+        if index[root] != usize::MAX {
+            continue;
+        }
+        // Each frame is a node and the position of the next of its successors to visit
+        let mut frames = vec![(root, 0)];
+        index[root] = next;
+        low[root] = next;
+        next += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        while let Some(&mut (node, ref mut position)) = frames.last_mut() {
+            if let Some(&successor) = successors[node].get(*position) {
+                *position += 1;
+                if index[successor] == usize::MAX {
+                    index[successor] = next;
+                    low[successor] = next;
+                    next += 1;
+                    stack.push(successor);
+                    on_stack[successor] = true;
+                    frames.push((successor, 0));
+                } else if on_stack[successor] {
+                    low[node] = low[node].min(index[successor]);
+                }
+                continue;
+            }
+            frames.pop();
+            if let Some(&(parent, _)) = frames.last() {
+                low[parent] = low[parent].min(low[node]);
+            }
+            if low[node] == index[node] {
+                let mut component = BTreeSet::new();
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    component.insert(names[member].clone());
+                    if member == node {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
@@ -0,0 +1,294 @@
+//! `jmf deps`: the dependencies between the scanned classes or packages as a matrix, counting the method
+//! invocations, field accesses and type references of each on each other, for dependency structure matrix
+//! tools.
+//!
+//! Type references are the superclass and interfaces of a class and the classes its instructions create,
+//! cast to, test against, make arrays of or load as constants.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use cafebabe::{
+    attributes::AttributeData,
+    bytecode::Opcode,
+    constant_pool::{Loadable, ObjectArrayType},
+    descriptors::FieldType,
+    ClassFile,
+};
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::{
+    metrics::csv_field,
+    scan::{ScanArgs, Scanner},
+};
+
+#[derive(clap::Args, Debug)]
+pub struct DepsArgs {
+    /// Whether the matrix is between classes or between packages
+    #[arg(long = "level", value_enum, default_value_t = Level::Package)]
+    pub level: Level,
+
+    /// Also count the dependencies on classes that are not scanned, such as the JDK's
+    #[arg(long = "external")]
+    pub external: bool,
+
+    #[command(flatten)]
+    pub scan: ScanArgs,
+
+    #[arg(short = 'f', long = "format", value_enum, default_value_t = DepsFormatter::Csv)]
+    pub format: DepsFormatter,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, ValueEnum)]
+pub enum Level {
+    #[value(name = "class")]
+    Class,
+    #[value(name = "package")]
+    Package,
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum)]
+pub enum DepsFormatter {
+    /// A square matrix of the total references, a row per dependent and a column per dependency
+    #[value(name = "csv")]
+    Csv,
+    #[value(name = "json")]
+    Json,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct References {
+    pub invocations: usize,
+    pub fields: usize,
+    pub types: usize,
+}
+
+impl References {
+    pub fn total(&self) -> usize {
+        self.invocations + self.fields + self.types
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Dependency {
+    pub from: String,
+    pub to: String,
+    #[serde(flatten)]
+    pub references: References,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Deps {
+    /// Classes or packages of the matrix, in name order
+    pub elements: Vec<String>,
+    pub dependencies: Vec<Dependency>,
+}
+
+/// What a reference of a class to another is, counted in its own column.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Kind {
+    Invocation,
+    Field,
+    Type,
+}
+
+/// A reference of a class to another, where it is made.
+#[derive(Debug)]
+pub(crate) struct Reference {
+    pub kind: Kind,
+    /// Internal name of the class referred to
+    pub class: String,
+    /// Method or field referred to, for invocations and field accesses
+    pub member: Option<String>,
+    /// Method making the reference, unless it is the superclass or an interface of the class
+    pub method: Option<String>,
+    pub line_number: Option<u16>,
+}
+
+impl Deps {
+    pub fn run(args: DepsArgs) -> Result<Self> {
+        let scanner = Scanner::new(args.scan)?;
+        let level = args.level;
+        let classes = scanner.map_classes(|_, class_file| {
+            let from = element(&class_file.this_class, level);
+            let mut counts: BTreeMap<String, References> = BTreeMap::new();
+            for reference in references(class_file) {
+                let to = element(&reference.class, level);
+                if to == from {
+                    continue;
+                }
+                let references = counts.entry(to).or_default();
+                match reference.kind {
+                    Kind::Invocation => references.invocations += 1,
+                    Kind::Field => references.fields += 1,
+                    Kind::Type => references.types += 1,
+                }
+            }
+            (from, counts)
+        })?;
+        let scanned: BTreeSet<String> = classes.iter().map(|(from, _)| from.clone()).collect();
+        let mut matrix: BTreeMap<(String, String), References> = BTreeMap::new();
+        for (from, counts) in classes {
+            for (to, counts) in counts {
+                if !args.external && !scanned.contains(&to) {
+                    continue;
+                }
+                let references = matrix.entry((from.clone(), to)).or_default();
+                references.invocations += counts.invocations;
+                references.fields += counts.fields;
+                references.types += counts.types;
+            }
+        }
+        let mut elements = scanned;
+        elements.extend(matrix.keys().map(|(_, to)| to.clone()));
+        Ok(Self {
+            elements: elements.into_iter().collect(),
+            dependencies: matrix
+                .into_iter()
+                .map(|((from, to), references)| Dependency { from, to, references })
+                .collect(),
+        })
+    }
+
+    pub fn to_csv(&self) -> String {
+        let index: BTreeMap<&str, usize> = self
+            .elements
+            .iter()
+            .enumerate()
+            .map(|(index, element)| (element.as_str(), index))
+            .collect();
+        let mut rows = vec![vec![0; self.elements.len()]; self.elements.len()];
+        for dependency in &self.dependencies {
+            rows[index[dependency.from.as_str()]][index[dependency.to.as_str()]] = dependency.references.total();
+        }
+        let mut header = vec![String::new()];
+        header.extend(self.elements.iter().map(|element| csv_field(element)));
+        let mut output = vec![header.join(",")];
+        for (element, row) in self.elements.iter().zip(rows) {
+            let mut line = vec![csv_field(element)];
+            line.extend(row.iter().map(|count| count.to_string()));
+            output.push(line.join(","));
+        }
+        output.join("\n")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
+
+/// Dotted name of the class, or of its package, `(default)` for the unnamed one.
+pub(crate) fn element(class_name: &str, level: Level) -> String {
+    let class_name = class_name.replace('/', ".");
+    match level {
+        Level::Class => class_name,
+        Level::Package => class_name
+            .rsplit_once('.')
+            .map_or_else(|| "(default)".to_string(), |(package, _)| package.to_string()),
+    }
+}
+
+/// Internal name of the class of a type an instruction refers to, or of the elements of an array type,
+/// unless they are primitives.
+fn class_of(class: &ObjectArrayType) -> Option<String> {
+    match class {
+        // Array classes, e.g. `[Ljava/lang/String;`
+        ObjectArrayType::BinaryName(class_name) if class_name.starts_with('[') => class_name
+            .trim_start_matches('[')
+            .strip_prefix('L')
+            .and_then(|name| name.strip_suffix(';'))
+            .map(str::to_string),
+        ObjectArrayType::BinaryName(class_name) => Some(class_name.to_string()),
+        ObjectArrayType::ArrayType(descriptor) => match &descriptor.field_type {
+            FieldType::Object(class_name) => Some(
+                class_name
+                    .segments
+                    .iter()
+                    .map(|segment| segment.name.as_ref())
+                    .collect::<Vec<_>>()
+                    .join("/"),
+            ),
+            _ => None,
+        },
+    }
+}
+
+/// Every class reference of the class, once per instruction.
+pub(crate) fn references(class_file: &ClassFile) -> Vec<Reference> {
+    let mut references: Vec<Reference> = class_file
+        .super_class
+        .iter()
+        .chain(&class_file.interfaces)
+        .map(|class| Reference {
+            kind: Kind::Type,
+            class: class.to_string(),
+            member: None,
+            method: None,
+            line_number: None,
+        })
+        .collect();
+    for method in &class_file.methods {
+        for attr in &method.attributes {
+            let AttributeData::Code(code) = &attr.data else {
+                continue;
+            };
+            let Some(bytecode) = &code.bytecode else {
+                continue;
+            };
+            let line_number_table = code.attributes.iter().find_map(|attr| match &attr.data {
+                AttributeData::LineNumberTable(table) => Some(table),
+                _ => None,
+            });
+            for (offset, opcode) in &bytecode.opcodes {
+                let (kind, class, member) = match opcode {
+                    Opcode::Invokespecial(member_ref)
+                    | Opcode::Invokestatic(member_ref)
+                    | Opcode::Invokevirtual(member_ref)
+                    | Opcode::Invokeinterface(member_ref, _) => (
+                        Kind::Invocation,
+                        member_ref.class_name.to_string(),
+                        Some(member_ref.name_and_type.name.to_string()),
+                    ),
+                    Opcode::Getfield(member_ref)
+                    | Opcode::Putfield(member_ref)
+                    | Opcode::Getstatic(member_ref)
+                    | Opcode::Putstatic(member_ref) => (
+                        Kind::Field,
+                        member_ref.class_name.to_string(),
+                        Some(member_ref.name_and_type.name.to_string()),
+                    ),
+                    Opcode::New(class_name)
+                    | Opcode::Ldc(Loadable::ClassInfo(class_name))
+                    | Opcode::LdcW(Loadable::ClassInfo(class_name)) => {
+                        match class_of(&ObjectArrayType::BinaryName(class_name.clone())) {
+                            Some(class) => (Kind::Type, class, None),
+                            None => continue,
+                        }
+                    }
+                    Opcode::Anewarray(class)
+                    | Opcode::Checkcast(class)
+                    | Opcode::Instanceof(class)
+                    | Opcode::Multianewarray(class, _) => match class_of(class) {
+                        Some(class) => (Kind::Type, class, None),
+                        None => continue,
+                    },
+                    _ => continue,
+                };
+                let line_number = line_number_table.and_then(|table| {
+                    let index = table.partition_point(|entry| usize::from(entry.start_pc) <= *offset);
+                    index.checked_sub(1).map(|index| table[index].line_number)
+                });
+                references.push(Reference {
+                    kind,
+                    class,
+                    member,
+                    method: Some(method.name.to_string()),
+                    line_number,
+                });
+            }
+        }
+    }
+    references
+}
@@ -0,0 +1,201 @@
+//! javap-style disassembly of the instructions around a call site, for `--explain`, with the constant
+//! pool entries the instructions refer to resolved in place.
+
+use cafebabe::{
+    bytecode::Opcode,
+    constant_pool::{LiteralConstant, Loadable, MemberKind, MemberRef, ObjectArrayType, ReferenceKind},
+    descriptors::{FieldDescriptor, FieldType, MethodDescriptor, ReturnDescriptor},
+};
+
+/// Instructions shown before and after the call.
+pub const RADIUS: usize = 5;
+
+/// The instructions within `radius` of `opcodes[index]`, one line each, the one at `index` marked with `>`.
+pub fn window(opcodes: &[(usize, Opcode)], index: usize, radius: usize) -> Vec<String> {
+    let start = index.saturating_sub(radius);
+    let end = (index + radius + 1).min(opcodes.len());
+    opcodes[start..end]
+        .iter()
+        .enumerate()
+        .map(|(position, (offset, opcode))| {
+            let marker = if start + position == index { '>' } else { ' ' };
+            format!("{} {:>5}: {}", marker, offset, instruction(*offset, opcode))
+        })
+        .collect()
+}
+
+/// One instruction, e.g. `invokevirtual Method java/lang/String.toString:()Ljava/lang/String;`.
+pub fn instruction(offset: usize, opcode: &Opcode) -> String {
+    let name = mnemonic(opcode);
+    let operand = match opcode {
+        Opcode::Invokespecial(member_ref) | Opcode::Invokestatic(member_ref) | Opcode::Invokevirtual(member_ref) => {
+            format!("Method {}", member(member_ref))
+        }
+        Opcode::Invokeinterface(member_ref, _) => format!("InterfaceMethod {}", member(member_ref)),
+        Opcode::Getfield(member_ref)
+        | Opcode::Getstatic(member_ref)
+        | Opcode::Putfield(member_ref)
+        | Opcode::Putstatic(member_ref) => format!("Field {}", member(member_ref)),
+        Opcode::Invokedynamic(invoke_dynamic) => format!(
+            "InvokeDynamic #{}:{}:{}",
+            invoke_dynamic.attr_index, invoke_dynamic.name_and_type.name, invoke_dynamic.name_and_type.descriptor
+        ),
+        Opcode::Ldc(loadable) | Opcode::LdcW(loadable) | Opcode::Ldc2W(loadable) => constant(loadable),
+        Opcode::New(class_name) => format!("class {}", class_name),
+        Opcode::Anewarray(class) | Opcode::Checkcast(class) | Opcode::Instanceof(class) => {
+            format!("class {}", object_type(class))
+        }
+        Opcode::Multianewarray(class, dimensions) => format!("class {}, {}", object_type(class), dimensions),
+        Opcode::Newarray(element) => format!("{:?}", element).to_ascii_lowercase(),
+        Opcode::Goto(jump)
+        | Opcode::Jsr(jump)
+        | Opcode::IfAcmpeq(jump)
+        | Opcode::IfAcmpne(jump)
+        | Opcode::IfIcmpeq(jump)
+        | Opcode::IfIcmpge(jump)
+        | Opcode::IfIcmpgt(jump)
+        | Opcode::IfIcmple(jump)
+        | Opcode::IfIcmplt(jump)
+        | Opcode::IfIcmpne(jump)
+        | Opcode::Ifeq(jump)
+        | Opcode::Ifge(jump)
+        | Opcode::Ifgt(jump)
+        | Opcode::Ifle(jump)
+        | Opcode::Iflt(jump)
+        | Opcode::Ifne(jump)
+        | Opcode::Ifnonnull(jump)
+        | Opcode::Ifnull(jump) => (offset as i64 + i64::from(*jump)).to_string(),
+        Opcode::Lookupswitch(table) => format!(
+            "{{ {} cases, default: {} }}",
+            table.match_offsets.len(),
+            offset as i64 + i64::from(table.default)
+        ),
+        Opcode::Tableswitch(table) => format!(
+            "{{ {} to {}, default: {} }}",
+            table.low,
+            table.high,
+            offset as i64 + i64::from(table.default)
+        ),
+        // Local variable indexes and immediate values, e.g. `Iinc(1, 2)`
+        _ => {
+            let debug = format!("{:?}", opcode);
+            debug
+                .split_once('(')
+                .and_then(|(_, operands)| operands.strip_suffix(')'))
+                .unwrap_or_default()
+                .to_string()
+        }
+    };
+    if operand.is_empty() {
+        name
+    } else {
+        format!("{} {}", name, operand)
+    }
+}
+
+/// The javap mnemonic of an opcode, e.g. `iconst_1` for `Iconst1` and `if_icmpeq` for `IfIcmpeq`.
+fn mnemonic(opcode: &Opcode) -> String {
+    let debug = format!("{:?}", opcode);
+    let variant = debug
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .next()
+        .unwrap_or_default();
+    let mut name = String::new();
+    for (index, c) in variant.char_indices() {
+        if index > 0 && c.is_ascii_uppercase() {
+            name.push('_');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    if name.ends_with(|c: char| c.is_ascii_digit()) && name[..name.len() - 1].ends_with("const") {
+        name.insert(name.len() - 1, '_');
+    }
+    name
+}
+
+fn member(member_ref: &MemberRef) -> String {
+    format!(
+        "{}.{}:{}",
+        member_ref.class_name, member_ref.name_and_type.name, member_ref.name_and_type.descriptor
+    )
+}
+
+fn object_type(class: &ObjectArrayType) -> String {
+    match class {
+        ObjectArrayType::BinaryName(class_name) => class_name.to_string(),
+        ObjectArrayType::ArrayType(descriptor) => field_descriptor(descriptor),
+    }
+}
+
+fn constant(loadable: &Loadable) -> String {
+    match loadable {
+        Loadable::LiteralConstant(LiteralConstant::Integer(value)) => format!("int {}", value),
+        Loadable::LiteralConstant(LiteralConstant::Float(value)) => format!("float {}f", value),
+        Loadable::LiteralConstant(LiteralConstant::Long(value)) => format!("long {}l", value),
+        Loadable::LiteralConstant(LiteralConstant::Double(value)) => format!("double {}d", value),
+        Loadable::LiteralConstant(LiteralConstant::String(value)) => format!("String {:?}", value),
+        Loadable::LiteralConstant(LiteralConstant::StringBytes(bytes)) => format!("String ({} bytes)", bytes.len()),
+        Loadable::ClassInfo(class_name) => format!("class {}", class_name),
+        Loadable::MethodHandle(handle) => {
+            let kind = match handle.kind {
+                ReferenceKind::GetField => "REF_getField",
+                ReferenceKind::GetStatic => "REF_getStatic",
+                ReferenceKind::PutField => "REF_putField",
+                ReferenceKind::PutStatic => "REF_putStatic",
+                ReferenceKind::InvokeVirtual => "REF_invokeVirtual",
+                ReferenceKind::InvokeStatic => "REF_invokeStatic",
+                ReferenceKind::InvokeSpecial => "REF_invokeSpecial",
+                ReferenceKind::NewInvokeSpecial => "REF_newInvokeSpecial",
+                ReferenceKind::InvokeInterface => "REF_invokeInterface",
+            };
+            let member_kind = match handle.member_kind {
+                MemberKind::Field => "Field",
+                MemberKind::Method => "Method",
+                MemberKind::InterfaceMethod => "InterfaceMethod",
+            };
+            format!(
+                "MethodHandle {} {} {}.{}:{}",
+                kind, member_kind, handle.class_name, handle.member_ref.name, handle.member_ref.descriptor
+            )
+        }
+        Loadable::MethodType(descriptor) => format!("MethodType {}", descriptor),
+        Loadable::Dynamic(dynamic) => format!(
+            "Dynamic #{}:{}:{}",
+            dynamic.attr_index, dynamic.name_and_type.name, dynamic.name_and_type.descriptor
+        ),
+    }
+}
+
+/// A field descriptor as it appears in a class file, e.g. `[Ljava/lang/String;`.
+pub fn field_descriptor(descriptor: &FieldDescriptor) -> String {
+    let element = match &descriptor.field_type {
+        FieldType::Byte => "B".to_string(),
+        FieldType::Char => "C".to_string(),
+        FieldType::Double => "D".to_string(),
+        FieldType::Float => "F".to_string(),
+        FieldType::Integer => "I".to_string(),
+        FieldType::Long => "J".to_string(),
+        FieldType::Short => "S".to_string(),
+        FieldType::Boolean => "Z".to_string(),
+        FieldType::Object(class_name) => format!(
+            "L{};",
+            class_name
+                .segments
+                .iter()
+                .map(|segment| segment.name.as_ref())
+                .collect::<Vec<_>>()
+                .join("/")
+        ),
+    };
+    format!("{}{}", "[".repeat(descriptor.dimensions.into()), element)
+}
+
+/// A method descriptor as it appears in a class file, e.g. `(I)Ljava/lang/String;`.
+pub fn method_descriptor(descriptor: &MethodDescriptor) -> String {
+    let parameters: String = descriptor.parameters.iter().map(field_descriptor).collect();
+    let return_type = match &descriptor.return_type {
+        ReturnDescriptor::Return(return_type) => field_descriptor(return_type),
+        ReturnDescriptor::Void => "V".to_string(),
+    };
+    format!("({}){}", parameters, return_type)
+}
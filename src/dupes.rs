@@ -0,0 +1,182 @@
+//! `jmf dupes`: clusters of methods with identical bodies across the scanned classes, such as copy-pasted
+//! utility methods to consolidate.
+//!
+//! Bodies are compared by their instructions as cafebabe decodes them, with the constant pool entries they
+//! refer to resolved to their values, so that the same code compares equal whichever constant pool indices
+//! its class gave it. Line numbers and local variable names do not count; exception handlers do.
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use anyhow::Result;
+use cafebabe::{attributes::AttributeData, ClassFile, MethodAccessFlags};
+use serde::Serialize;
+
+use crate::{
+    color,
+    disasm::method_descriptor,
+    duplicates::sha256_hex,
+    scan::{ScanArgs, Scanner},
+};
+
+#[derive(clap::Args, Debug)]
+pub struct DupesArgs {
+    /// Smallest number of instructions of the bodies to compare, below which getters, delegating methods and
+    /// default constructors would make most clusters
+    #[arg(long = "min-instructions", value_name = "N", default_value_t = 10)]
+    pub min_instructions: usize,
+
+    #[command(flatten)]
+    pub scan: ScanArgs,
+
+    #[arg(short = 'f', long = "format", value_enum, default_value_t = crate::ReportFormatter::Txt)]
+    pub format: crate::ReportFormatter,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DupeMethod {
+    pub class_name: String,
+    pub method_name: String,
+    pub descriptor: String,
+    /// First line of the method, from its `LineNumberTable`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_number: Option<u16>,
+    /// Jar or directory the class was found in
+    pub origin: String,
+}
+
+impl std::fmt::Display for DupeMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}#{}{}",
+            self.class_name,
+            self.method_name,
+            color::dim(&self.descriptor)
+        )?;
+        if let Some(line_number) = self.line_number {
+            write!(f, " (L{})", line_number)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DupeCluster {
+    /// SHA-256 of the normalized body
+    pub sha256: String,
+    pub instructions: usize,
+    pub methods: Vec<DupeMethod>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Dupes {
+    pub clusters: Vec<DupeCluster>,
+}
+
+impl Dupes {
+    /// The clusters of at least two methods, those duplicating the most instructions first.
+    pub fn run(args: DupesArgs) -> Result<Self> {
+        let scanner = Scanner::new(args.scan)?;
+        let bodies =
+            scanner.map_classes(|source, class_file| bodies(class_file, &source.artifact(), args.min_instructions))?;
+        let mut by_hash: BTreeMap<String, (usize, Vec<DupeMethod>)> = BTreeMap::new();
+        for (sha256, instructions, method) in bodies.into_iter().flatten() {
+            by_hash
+                .entry(sha256)
+                .or_insert_with(|| (instructions, Vec::new()))
+                .1
+                .push(method);
+        }
+        let mut clusters: Vec<DupeCluster> = by_hash
+            .into_iter()
+            .filter(|(_, (_, methods))| methods.len() > 1)
+            .map(|(sha256, (instructions, mut methods))| {
+                methods.sort_by(|a, b| {
+                    (&a.class_name, &a.method_name, &a.descriptor).cmp(&(&b.class_name, &b.method_name, &b.descriptor))
+                });
+                DupeCluster {
+                    sha256,
+                    instructions,
+                    methods,
+                }
+            })
+            .collect();
+        clusters.sort_by_key(|cluster| std::cmp::Reverse(cluster.instructions * (cluster.methods.len() - 1)));
+        Ok(Self { clusters })
+    }
+
+    pub fn to_text(&self) -> String {
+        if self.clusters.is_empty() {
+            return "No results".to_string();
+        }
+        let mut output = Vec::new();
+        for cluster in &self.clusters {
+            output.push(color::bold(format!(
+                "{} methods with the same {} instructions ({})",
+                cluster.methods.len(),
+                cluster.instructions,
+                &cluster.sha256[..12]
+            )));
+            output.extend(cluster.methods.iter().map(|method| format!(" - {}", method)));
+        }
+        output.join("\n")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
+
+/// The normalized body hash, instruction count and location of each method of the class with a body of at
+/// least `min_instructions`, leaving out those the compiler generates.
+fn bodies(class_file: &ClassFile, origin: &str, min_instructions: usize) -> Vec<(String, usize, DupeMethod)> {
+    let mut found = Vec::new();
+    for method in &class_file.methods {
+        if method
+            .access_flags
+            .intersects(MethodAccessFlags::SYNTHETIC | MethodAccessFlags::BRIDGE)
+        {
+            continue;
+        }
+        let Some(code) = method.attributes.iter().find_map(|attr| match &attr.data {
+            AttributeData::Code(code) => Some(code),
+            _ => None,
+        }) else {
+            continue;
+        };
+        let Some(bytecode) = &code.bytecode else {
+            continue;
+        };
+        if bytecode.opcodes.len() < min_instructions {
+            continue;
+        }
+        // Branches are relative and handlers cover offsets within the method, so neither depends on the class
+        let mut normalized = String::new();
+        for (_, opcode) in &bytecode.opcodes {
+            let _ = writeln!(normalized, "{:?}", opcode);
+        }
+        for entry in &code.exception_table {
+            let _ = writeln!(
+                normalized,
+                "catch {:?} {}..{} -> {}",
+                entry.catch_type, entry.start_pc, entry.end_pc, entry.handler_pc
+            );
+        }
+        let line_number = code.attributes.iter().find_map(|attr| match &attr.data {
+            AttributeData::LineNumberTable(table) => table.iter().map(|entry| entry.line_number).min(),
+            _ => None,
+        });
+        found.push((
+            sha256_hex(normalized.as_bytes()),
+            bytecode.opcodes.len(),
+            DupeMethod {
+                class_name: class_file.this_class.replace('/', "."),
+                method_name: method.name.to_string(),
+                descriptor: method_descriptor(&method.descriptor),
+                line_number,
+                origin: origin.to_string(),
+            },
+        ));
+    }
+    found
+}
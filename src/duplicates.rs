@@ -0,0 +1,102 @@
+//! Classes that appear in more than one scan root with differing contents, for `jmf duplicates`.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use cafebabe::parse_class;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::scan::ClassSource;
+
+#[derive(Debug, Serialize)]
+pub struct DuplicateEntry {
+    pub origin: String,
+    pub sha256: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DuplicateClass {
+    pub class_name: String,
+    pub entries: Vec<DuplicateEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Duplicates {
+    pub classes: Vec<DuplicateClass>,
+}
+
+impl Duplicates {
+    /// Groups the class files by name and keeps those found in several roots whose contents differ.
+    /// Identical copies are harmless and not reported.
+    pub fn new(sources: &[ClassSource]) -> Result<Self> {
+        let hashes: Vec<(String, DuplicateEntry)> = sources
+            .par_iter()
+            .filter(|source| !is_metadata(&source.name))
+            .map(|source| {
+                let data = source.read()?;
+                // Classes are keyed by their declared name; the entry path is only a fallback
+                let class_name = match parse_class(&data) {
+                    Ok(class_file) => class_file.this_class.to_string(),
+                    Err(_) => source.name.trim_end_matches(".class").to_string(),
+                };
+                Ok((
+                    class_name.replace('/', "."),
+                    DuplicateEntry {
+                        origin: source.origin.display().to_string(),
+                        sha256: sha256_hex(&data),
+                    },
+                ))
+            })
+            .collect::<Result<_>>()?;
+
+        let mut by_class: BTreeMap<String, Vec<DuplicateEntry>> = BTreeMap::new();
+        for (class_name, entry) in hashes {
+            by_class.entry(class_name).or_default().push(entry);
+        }
+
+        let classes = by_class
+            .into_iter()
+            .filter(|(_, entries)| entries.iter().any(|entry| entry.sha256 != entries[0].sha256))
+            .map(|(class_name, mut entries)| {
+                entries.sort_by(|a, b| a.origin.cmp(&b.origin));
+                DuplicateClass { class_name, entries }
+            })
+            .collect();
+        Ok(Self { classes })
+    }
+
+    pub fn to_text(&self) -> String {
+        if self.classes.is_empty() {
+            return "No results".to_string();
+        }
+        let mut output = Vec::new();
+        for class in &self.classes {
+            output.push(class.class_name.clone());
+            output.extend(
+                class
+                    .entries
+                    .iter()
+                    .map(|entry| format!(" - {} ({})", entry.origin, &entry.sha256[..12])),
+            );
+        }
+        output.join("\n")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
+
+/// Entries that legitimately appear in many jars, such as `module-info.class` or multi-release versions.
+fn is_metadata(name: &str) -> bool {
+    name.starts_with("META-INF/") || name.ends_with("module-info.class") || name.ends_with("package-info.class")
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
@@ -0,0 +1,72 @@
+//! Caller → callee edges of the call graph, for `jmf graph`.
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::graph::{CallGraph, MethodKey};
+
+#[derive(Debug, Serialize)]
+pub struct Edge {
+    pub caller: String,
+    pub callee: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Edges {
+    pub edges: Vec<Edge>,
+}
+
+impl Edges {
+    /// Every distinct call between methods, sorted by caller and callee. With `internal`, only calls
+    /// into methods declared by scanned classes are kept.
+    pub fn new(graph: &CallGraph, internal: bool) -> Self {
+        let declared: HashSet<MethodKey> = graph.declared_methods().into_iter().collect();
+        let mut edges: Vec<Edge> = graph
+            .edges()
+            .iter()
+            .flat_map(|(caller, callees)| {
+                callees
+                    .iter()
+                    .filter(|callee| !internal || declared.contains(*callee))
+                    .map(move |callee| Edge {
+                        caller: dotted(caller),
+                        callee: dotted(callee),
+                    })
+            })
+            .collect();
+        edges.sort_by(|a, b| (&a.caller, &a.callee).cmp(&(&b.caller, &b.callee)));
+        Self { edges }
+    }
+
+    pub fn to_text(&self) -> String {
+        if self.edges.is_empty() {
+            return "No results".to_string();
+        }
+        self.edges
+            .iter()
+            .map(|edge| format!("{} -> {}", edge.caller, edge.callee))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Graphviz digraph, e.g. for `dot -Tsvg`.
+    pub fn to_dot(&self) -> String {
+        let mut output = vec!["digraph calls {".to_string()];
+        output.extend(
+            self.edges
+                .iter()
+                .map(|edge| format!("  \"{}\" -> \"{}\";", edge.caller, edge.callee)),
+        );
+        output.push("}".to_string());
+        output.join("\n")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
+
+fn dotted((class_name, method_name): &MethodKey) -> String {
+    format!("{}#{}", class_name.replace('/', "."), method_name)
+}
@@ -0,0 +1,241 @@
+//! Conditions on the element values of an annotation, in the syntax of queries:
+//!
+//! ```text
+//! value ~ "/admin/.*" and not method == "GET"
+//! ```
+//!
+//! An element is compared with `==` (equal) or `~` (a regular expression matching the whole value), and an
+//! array element holds when any of its values does. Strings, numbers and booleans compare as Java writes
+//! them, without quotes, enum constants by their name and class literals by their dotted class name.
+//! Conditions combine with `and`, `or`, `not` and parentheses.
+
+use anyhow::{bail, Context, Result};
+use cafebabe::{
+    attributes::{Annotation, AnnotationElementValue},
+    descriptors::FieldType,
+};
+use regex::Regex;
+
+use crate::{
+    graph::annotation_name,
+    query::{describe, tokenize, Token},
+};
+
+#[derive(Debug, Clone)]
+pub struct ElementFilter {
+    source: String,
+    expr: Expr,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Equals(String, String),
+    Matches(String, Regex),
+}
+
+impl ElementFilter {
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut parser = Parser {
+            input,
+            tokens: tokenize(input, "condition")?,
+            position: 0,
+        };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.tokens.get(parser.position) {
+            bail!("Invalid condition '{}': unexpected {}", input, token);
+        }
+        Ok(Self {
+            source: input.trim().to_string(),
+            expr,
+        })
+    }
+
+    /// Whether the conditions hold for the elements of the annotation; elements it leaves to their
+    /// defaults are not in the class file, so no condition on them holds.
+    pub fn matches(&self, annotation: &Annotation) -> bool {
+        self.expr.eval(annotation)
+    }
+}
+
+impl std::fmt::Display for ElementFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl Expr {
+    fn eval(&self, annotation: &Annotation) -> bool {
+        let values = |name: &str| {
+            annotation
+                .elements
+                .iter()
+                .filter(|element| element.name == name)
+                .flat_map(|element| match &element.value {
+                    AnnotationElementValue::ArrayValue(values) => values.iter().map(value_text).collect(),
+                    value => vec![value_text(value)],
+                })
+                .collect::<Vec<_>>()
+        };
+        match self {
+            Expr::And(left, right) => left.eval(annotation) && right.eval(annotation),
+            Expr::Or(left, right) => left.eval(annotation) || right.eval(annotation),
+            Expr::Not(expr) => !expr.eval(annotation),
+            Expr::Equals(name, expected) => values(name).iter().any(|value| value == expected),
+            Expr::Matches(name, regex) => values(name).iter().any(|value| regex.is_match(value)),
+        }
+    }
+}
+
+/// An element value as conditions compare it.
+fn value_text(value: &AnnotationElementValue) -> String {
+    match value {
+        AnnotationElementValue::StringConstant(value) => value.to_string(),
+        AnnotationElementValue::EnumConstant { const_name, .. } => const_name.to_string(),
+        AnnotationElementValue::ClassLiteral { class_name } => class_literal(class_name),
+        value => display(value),
+    }
+}
+
+/// An element value as Java source writes it, e.g. `"/admin"`, `RequestMethod.GET` or `{1, 2}`.
+pub fn display(value: &AnnotationElementValue) -> String {
+    match value {
+        AnnotationElementValue::ByteConstant(value)
+        | AnnotationElementValue::ShortConstant(value)
+        | AnnotationElementValue::IntConstant(value) => value.to_string(),
+        AnnotationElementValue::LongConstant(value) => format!("{}L", value),
+        AnnotationElementValue::FloatConstant(value) => format!("{}f", value),
+        AnnotationElementValue::DoubleConstant(value) => value.to_string(),
+        AnnotationElementValue::BooleanConstant(value) => (*value != 0).to_string(),
+        AnnotationElementValue::CharConstant(value) => {
+            format!(
+                "'{}'",
+                char::from_u32(*value as u32).unwrap_or(char::REPLACEMENT_CHARACTER)
+            )
+        }
+        AnnotationElementValue::StringConstant(value) => format!("{:?}", value),
+        AnnotationElementValue::EnumConstant { type_name, const_name } => match &type_name.field_type {
+            FieldType::Object(class_name) => format!(
+                "{}.{}",
+                class_name.segments.last().map_or("", |segment| segment.name.as_ref()),
+                const_name
+            ),
+            _ => const_name.to_string(),
+        },
+        AnnotationElementValue::ClassLiteral { class_name } => format!("{}.class", class_literal(class_name)),
+        AnnotationElementValue::AnnotationValue(annotation) => {
+            let elements: Vec<String> = annotation
+                .elements
+                .iter()
+                .map(|element| format!("{} = {}", element.name, display(&element.value)))
+                .collect();
+            format!(
+                "@{}({})",
+                annotation_name(annotation).unwrap_or_default(),
+                elements.join(", ")
+            )
+        }
+        AnnotationElementValue::ArrayValue(values) => {
+            format!("{{{}}}", values.iter().map(display).collect::<Vec<_>>().join(", "))
+        }
+    }
+}
+
+/// Dotted class name of the descriptor of a class literal, e.g. `java.lang.String` for `Ljava/lang/String;`.
+fn class_literal(descriptor: &str) -> String {
+    descriptor
+        .strip_prefix('L')
+        .and_then(|name| name.strip_suffix(';'))
+        .unwrap_or(descriptor)
+        .replace('/', ".")
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.tokens.get(self.position) == Some(&Token::Ident(keyword.to_string())) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.eat_keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_not()?;
+        while self.eat_keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr> {
+        if self.eat_keyword("not") {
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        let name = match self.next() {
+            Some(Token::Open) => {
+                let expr = self.parse_or()?;
+                return match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    found => bail!(
+                        "Invalid condition '{}': expected ')', found {}",
+                        self.input,
+                        describe(found)
+                    ),
+                };
+            }
+            Some(Token::Ident(name)) => name,
+            found => bail!(
+                "Invalid condition '{}': expected an element name, found {}",
+                self.input,
+                describe(found)
+            ),
+        };
+        let equals = match self.next() {
+            Some(Token::Equals) => true,
+            Some(Token::Tilde) => false,
+            found => bail!(
+                "Invalid condition '{}': expected '==' or '~', found {}",
+                self.input,
+                describe(found)
+            ),
+        };
+        let value = match self.next() {
+            Some(Token::Str(value)) => value,
+            found => bail!(
+                "Invalid condition '{}': expected a string, found {}",
+                self.input,
+                describe(found)
+            ),
+        };
+        if equals {
+            return Ok(Expr::Equals(name, value));
+        }
+        Regex::new(&value).with_context(|| format!("Invalid condition '{}': bad regular expression", self.input))?;
+        // Anchored, so that the value matches as a whole
+        let regex = Regex::new(&format!("^(?:{})$", value))?;
+        Ok(Expr::Matches(name, regex))
+    }
+}
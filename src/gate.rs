@@ -0,0 +1,294 @@
+//! `jmf gate`: a policy check for CI in one command, which diffs the calls against a baseline result,
+//! writes a Markdown summary to post as a pull request comment and exits with the result of the policy on
+//! the calls the baseline does not have.
+
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use crate::{
+    analysis::FoundCall, notify::NotifyArgs, otlp::OtlpArgs, plural, policy::Severity, scan::ScanArgs, Args,
+    FilterArgs, MethodFinder, PolicyResult, RuleResult, INTERRUPTED_EXIT_CODE,
+};
+
+/// Violations listed in a summary; the others are only counted, to stay within the size of a comment.
+const MAX_LISTED_VIOLATIONS: usize = 50;
+
+/// First line of the summary, for bots to find the comment to update on the next push.
+const MARKER: &str = "<!-- jmf gate -->";
+
+#[derive(clap::Args, Debug)]
+pub struct GateArgs {
+    /// Policy file with the rules to evaluate
+    pub policy: PathBuf,
+
+    /// JSON result of an earlier `jmf check -f json` or `jmf gate --result`, whose calls are tolerated
+    #[arg(long = "baseline", value_name = "PATH")]
+    pub baseline: Option<PathBuf>,
+
+    /// File the Markdown summary is written to, instead of being printed
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    /// Also write the JSON result of the policy, e.g. to be the baseline of later runs
+    #[arg(long = "result", value_name = "PATH")]
+    pub result: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub filters: FilterArgs,
+
+    /// Directory the caller source files are found in, for `// jmf:ignore` comments (repeatable)
+    #[arg(long = "source-root", default_value = "src/main/java")]
+    pub source_roots: Vec<PathBuf>,
+
+    #[command(flatten)]
+    pub scan: ScanArgs,
+
+    #[command(flatten)]
+    pub notify: NotifyArgs,
+
+    #[command(flatten)]
+    pub otlp: OtlpArgs,
+
+    /// Fail when warning-severity rules have new matches too, not only error-severity ones
+    #[arg(long = "warnings-as-errors")]
+    pub warnings_as_errors: bool,
+}
+
+/// The unsuppressed calls of a baseline result, by rule id and fingerprint.
+struct Baseline {
+    calls: HashSet<(String, String)>,
+}
+
+impl Baseline {
+    fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let result: Value =
+            serde_json::from_str(&text).with_context(|| format!("Invalid JSON in {}", path.display()))?;
+        let calls = result["rules"]
+            .as_array()
+            .map(|rules| {
+                rules
+                    .iter()
+                    .flat_map(|rule| {
+                        let id = rule["id"].as_str().unwrap_or_default().to_string();
+                        rule["calls"]
+                            .as_array()
+                            .into_iter()
+                            .flatten()
+                            .filter(|call| call["suppressed"].is_null())
+                            .filter_map(move |call| Some((id.clone(), call["fingerprint"].as_str()?.to_string())))
+                    })
+                    .collect()
+            })
+            .with_context(|| format!("Not a jmf check JSON result: {}", path.display()))?;
+        Ok(Self { calls })
+    }
+}
+
+/// The calls of a rule, against the baseline.
+struct RuleGate<'a> {
+    rule: &'a RuleResult<'a>,
+    calls: usize,
+    new: Vec<&'a FoundCall>,
+    fixed: usize,
+    suppressed: usize,
+}
+
+struct Gate<'a> {
+    policy: String,
+    baseline: bool,
+    rules: Vec<RuleGate<'a>>,
+    passed: bool,
+}
+
+impl<'a> Gate<'a> {
+    /// Without a baseline, every call is new.
+    fn new(policy: &Path, result: &'a PolicyResult, baseline: Option<&Baseline>, warnings_as_errors: bool) -> Self {
+        let rules: Vec<RuleGate> = result
+            .rules
+            .iter()
+            .map(|rule| {
+                let failing: Vec<&FoundCall> = rule.failing_calls().map(|call| call.call).collect();
+                let current: HashSet<(String, String)> = failing
+                    .iter()
+                    .map(|call| (rule.id.clone(), call.fingerprint.clone()))
+                    .collect();
+                let new = failing
+                    .iter()
+                    .copied()
+                    .filter(|call| {
+                        baseline.is_none_or(|baseline| {
+                            !baseline.calls.contains(&(rule.id.clone(), call.fingerprint.clone()))
+                        })
+                    })
+                    .collect();
+                let fixed = baseline.map_or(0, |baseline| {
+                    baseline
+                        .calls
+                        .iter()
+                        .filter(|key| key.0 == rule.id && !current.contains(*key))
+                        .count()
+                });
+                RuleGate {
+                    rule,
+                    calls: failing.len(),
+                    new,
+                    fixed,
+                    suppressed: rule.calls.len() - failing.len(),
+                }
+            })
+            .collect();
+        let fails = |severity: Severity| match severity {
+            Severity::Error => true,
+            Severity::Warn => warnings_as_errors,
+            Severity::Info => false,
+        };
+        let passed = !rules
+            .iter()
+            .any(|rule| fails(rule.rule.severity) && !rule.new.is_empty());
+        Self {
+            policy: policy.display().to_string(),
+            baseline: baseline.is_some(),
+            rules,
+            passed,
+        }
+    }
+
+    fn to_markdown(&self) -> String {
+        let new: usize = self.rules.iter().map(|rule| rule.new.len()).sum();
+        let fixed: usize = self.rules.iter().map(|rule| rule.fixed).sum();
+        let suppressed: usize = self.rules.iter().map(|rule| rule.suppressed).sum();
+        let mut output = vec![
+            MARKER.to_string(),
+            format!("### jmf gate: {}", if self.passed { "passed" } else { "failed" }),
+            String::new(),
+        ];
+        let mut totals = vec![format!(
+            "**{}**",
+            plural(new, if self.baseline { "new violation" } else { "violation" })
+        )];
+        if self.baseline {
+            totals.push(format!("{} fixed", fixed));
+        }
+        totals.push(format!("{} suppressed", suppressed));
+        output.push(format!(
+            "`{}`{}: {}.",
+            self.policy,
+            if self.baseline { " against the baseline" } else { "" },
+            totals.join(", ")
+        ));
+        output.push(String::new());
+        if self.baseline {
+            output.push("| Rule | Severity | Calls | New | Fixed | Suppressed |".to_string());
+            output.push("| ---- | -------- | ----: | --: | ----: | ---------: |".to_string());
+        } else {
+            output.push("| Rule | Severity | Calls | Suppressed |".to_string());
+            output.push("| ---- | -------- | ----: | ---------: |".to_string());
+        }
+        for rule in &self.rules {
+            let mut cells = vec![
+                format!("`{}`", rule.rule.target),
+                rule.rule.severity.to_string(),
+                rule.calls.to_string(),
+            ];
+            if self.baseline {
+                cells.push(rule.new.len().to_string());
+                cells.push(rule.fixed.to_string());
+            }
+            cells.push(rule.suppressed.to_string());
+            output.push(format!("| {} |", cells.join(" | ")));
+        }
+        if new > 0 {
+            output.push(String::new());
+            output.push(
+                if self.baseline {
+                    "#### New violations"
+                } else {
+                    "#### Violations"
+                }
+                .to_string(),
+            );
+            output.push(String::new());
+            let violations = self
+                .rules
+                .iter()
+                .flat_map(|rule| rule.new.iter().map(move |call| (rule.rule, *call)));
+            for (rule, call) in violations.take(MAX_LISTED_VIOLATIONS) {
+                let mut line = format!(
+                    "- **{}** `{}` in `{}#{}` (L{})",
+                    rule.severity,
+                    rule.target,
+                    call.class_name.replace('/', "."),
+                    call.method_name,
+                    call.line_number
+                );
+                if let Some(message) = &rule.message {
+                    line.push_str(&format!(": {}", message));
+                }
+                output.push(line);
+            }
+            if new > MAX_LISTED_VIOLATIONS {
+                output.push(format!("- …and {} more", new - MAX_LISTED_VIOLATIONS));
+            }
+        }
+        output.join("\n")
+    }
+}
+
+/// Runs the policy, then writes the summary and the result, notifies and exports as `jmf check` would.
+pub fn run(args: GateArgs) -> Result<ExitCode> {
+    let baseline = args.baseline.as_deref().map(Baseline::load).transpose()?;
+    let (policy, output, result_path, warnings_as_errors) = (
+        args.policy.clone(),
+        args.output.clone(),
+        args.result.clone(),
+        args.warnings_as_errors,
+    );
+    let finder = MethodFinder::new(Args {
+        policy: Some(args.policy),
+        filters: args.filters,
+        source_roots: args.source_roots,
+        notify: args.notify,
+        otlp: args.otlp,
+        warnings_as_errors,
+        ..Args::defaults(args.scan)
+    })?;
+    let started = SystemTime::now();
+    let (results, summary) = finder.scan_folder()?;
+    let result = PolicyResult {
+        summary: summary.clone(),
+        ..PolicyResult::new(&finder.analyzer.rules, &results)
+    };
+    if let Some(path) = &result_path {
+        fs::write(path, result.to_json()).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    let gate = Gate::new(&policy, &result, baseline.as_ref(), warnings_as_errors);
+    let markdown = gate.to_markdown();
+    match &output {
+        Some(path) => {
+            fs::write(path, markdown + "\n").with_context(|| format!("Failed to write {}", path.display()))?
+        }
+        None => writeln!(io::stdout().lock(), "{}", markdown)?,
+    }
+    finder.args.notify.notify(&finder.target(), &results)?;
+    finder
+        .args
+        .otlp
+        .export(&finder.analyzer.rules, &results, &summary, started);
+    Ok(if summary.interrupted {
+        ExitCode::from(INTERRUPTED_EXIT_CODE)
+    } else if gate.passed {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
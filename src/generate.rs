@@ -0,0 +1,168 @@
+//! `jmf policy generate`: a policy file denying what a library marks as not for use, its deprecated methods
+//! or its internal packages, to be checked into the repositories that depend on it.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::{
+    api::{self, Api, Class},
+    policy::Severity,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct GenerateArgs {
+    /// Jar, directory or any other scan root of the library
+    #[arg(long = "from", value_name = "PATH")]
+    pub from: PathBuf,
+
+    /// What the rules deny (comma-separated)
+    #[arg(long = "select", value_enum, value_delimiter = ',', required = true)]
+    pub select: Vec<Selection>,
+
+    /// Severity of the generated rules
+    #[arg(long = "severity", value_enum, default_value_t = Severity::Error)]
+    pub severity: Severity,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, ValueEnum)]
+pub enum Selection {
+    /// Public and protected methods marked `@Deprecated`, or of deprecated classes
+    #[value(name = "deprecated")]
+    Deprecated,
+    /// Packages with an `internal` segment, or that the library's module does not export to everyone
+    #[value(name = "internal")]
+    Internal,
+}
+
+/// A `[[rule]]` of the generated policy file, with the keys policy files take.
+#[derive(Debug, Serialize)]
+struct GeneratedRule {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    class: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    descriptor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package: Option<String>,
+    severity: Severity,
+    message: String,
+}
+
+#[derive(Debug, Serialize)]
+struct PolicyFile {
+    rule: Vec<GeneratedRule>,
+}
+
+/// The policy file, as TOML.
+pub fn generate(args: &GenerateArgs) -> Result<String> {
+    let api = Api::read(std::slice::from_ref(&args.from), true)?;
+    let library = args.from.display().to_string();
+    // Messages name the artifact, as the developers who hit the rules know it
+    let artifact = args
+        .from
+        .file_name()
+        .map_or_else(|| library.clone(), |name| name.to_string_lossy().to_string());
+    let mut rules = Vec::new();
+    if args.select.contains(&Selection::Deprecated) {
+        for class in &api.classes {
+            rules.extend(deprecated_rules(class, args.severity, &artifact));
+        }
+    }
+    if args.select.contains(&Selection::Internal) {
+        for (package, module) in internal_packages(&api) {
+            rules.push(GeneratedRule {
+                id: format!("internal:{}", package),
+                class: None,
+                method: None,
+                descriptor: None,
+                message: match module {
+                    Some(module) => format!("Not exported by module {} of {}", module, artifact),
+                    None => format!("Internal to {}", artifact),
+                },
+                package: Some(package),
+                severity: args.severity,
+            });
+        }
+    }
+    let selected: Vec<String> = args
+        .select
+        .iter()
+        .filter_map(|selection| selection.to_possible_value())
+        .map(|value| value.get_name().to_string())
+        .collect();
+    let header = format!(
+        "# Generated by `jmf policy generate --from {} --select {}`: {} rules\n\n",
+        library,
+        selected.join(","),
+        rules.len()
+    );
+    let policy = toml::to_string(&PolicyFile { rule: rules }).context("Failed to write the policy")?;
+    Ok(header + &policy)
+}
+
+/// A rule per deprecated method name, restricted to the deprecated overloads when others are not.
+fn deprecated_rules(class: &Class, severity: Severity, artifact: &str) -> Vec<GeneratedRule> {
+    let deprecated = |method: &api::Member| class.deprecated || method.deprecated;
+    let mut names: Vec<&str> = Vec::new();
+    for method in class.methods.iter().filter(|method| deprecated(method)) {
+        if !names.contains(&method.name.as_str()) {
+            names.push(&method.name);
+        }
+    }
+    let mut rules = Vec::new();
+    for name in names {
+        let overloads: Vec<&api::Member> = class.methods.iter().filter(|method| method.name == name).collect();
+        let all_deprecated = overloads.iter().all(|method| deprecated(method));
+        for method in overloads.into_iter().filter(|method| deprecated(method)) {
+            let descriptor = (!all_deprecated).then(|| method.descriptor.clone());
+            let target = format!("{}#{}{}", class.name, name, descriptor.as_deref().unwrap_or_default());
+            rules.push(GeneratedRule {
+                id: format!("deprecated:{}", target),
+                class: Some(class.name.clone()),
+                method: Some(name.to_string()),
+                descriptor,
+                package: None,
+                severity,
+                message: format!("Deprecated in {}", artifact),
+            });
+            if all_deprecated {
+                break;
+            }
+        }
+    }
+    rules
+}
+
+/// Packages of public classes that have an `internal` segment or, when the library declares a module, that
+/// it does not export to every module, which comes with them, in name order.
+fn internal_packages(api: &Api) -> BTreeMap<String, Option<String>> {
+    let exported: BTreeSet<&str> = api
+        .modules
+        .iter()
+        .flat_map(|module| &module.exports)
+        .filter(|exports| exports.to.is_empty())
+        .map(|exports| exports.package.as_str())
+        .collect();
+    let module = api.modules.first().map(|module| &module.name);
+    let mut packages = BTreeMap::new();
+    for package in api
+        .classes
+        .iter()
+        .filter_map(|class| class.name.rsplit_once('.').map(|(package, _)| package))
+    {
+        if package.split('.').any(|segment| segment == "internal") {
+            packages.insert(package.to_string(), None);
+        } else if module.is_some() && !exported.contains(package) {
+            packages.insert(package.to_string(), module.cloned());
+        }
+    }
+    packages
+}
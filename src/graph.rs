@@ -0,0 +1,325 @@
+//! Call graph over the scanned classes, used to tell call sites reachable from entry points apart
+//! from dead code.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use cafebabe::{
+    attributes::{Annotation, AttributeData, AttributeInfo},
+    bytecode::Opcode,
+    constant_pool::BootstrapArgument,
+    descriptors::FieldType,
+    ClassFile,
+};
+
+use crate::disasm;
+
+/// A method identified by its internal class name and method name, which its overloads share.
+pub type MethodKey = (String, String);
+
+/// A node of the call graph: a method identified by its internal class name, method name and descriptor.
+pub type MethodId = (String, String, String);
+
+/// An entry point specification: `@Annotation` (annotated methods, or every method of an
+/// annotated class), `Class#method`, or a bare method name such as `main`.
+#[derive(Debug, Clone)]
+pub enum EntryPoint {
+    Annotation(String),
+    Method { class: String, method: String },
+    MethodName(String),
+}
+
+impl EntryPoint {
+    pub fn parse(spec: &str) -> Self {
+        if let Some(annotation) = spec.strip_prefix('@') {
+            EntryPoint::Annotation(annotation.replace('/', "."))
+        } else if let Some((class, method)) = spec.split_once('#') {
+            EntryPoint::Method {
+                class: class.replace('.', "/"),
+                method: method.to_string(),
+            }
+        } else {
+            EntryPoint::MethodName(spec.to_string())
+        }
+    }
+
+    fn matches(&self, class: &ClassNode, class_name: &str, method: &MethodNode) -> bool {
+        match self {
+            EntryPoint::Annotation(annotation) => {
+                let matches = |candidate: &String| {
+                    candidate == annotation || candidate.rsplit('.').next() == Some(annotation.as_str())
+                };
+                class.annotations.iter().any(matches) || method.annotations.iter().any(matches)
+            }
+            EntryPoint::Method { class, method: name } => class == class_name && *name == method.name,
+            EntryPoint::MethodName(name) => *name == method.name,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ClassNode {
+    supertypes: Vec<String>,
+    annotations: Vec<String>,
+    methods: Vec<MethodNode>,
+}
+
+#[derive(Debug)]
+struct MethodNode {
+    name: String,
+    descriptor: String,
+    annotations: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct CallGraph {
+    classes: HashMap<String, ClassNode>,
+    calls: HashMap<MethodId, HashSet<MethodId>>,
+}
+
+impl CallGraph {
+    pub fn add_class(&mut self, class_file: &ClassFile) {
+        let class_name = class_file.this_class.to_string();
+        let bootstrap_methods = class_file.attributes.iter().find_map(|attr| match &attr.data {
+            AttributeData::BootstrapMethods(methods) => Some(methods),
+            _ => None,
+        });
+
+        let mut node = ClassNode {
+            supertypes: class_file
+                .super_class
+                .iter()
+                .chain(class_file.interfaces.iter())
+                .map(|name| name.to_string())
+                .collect(),
+            annotations: annotations(&class_file.attributes),
+            methods: Vec::new(),
+        };
+
+        for method in &class_file.methods {
+            let descriptor = disasm::method_descriptor(&method.descriptor);
+            node.methods.push(MethodNode {
+                name: method.name.to_string(),
+                descriptor: descriptor.clone(),
+                annotations: annotations(&method.attributes),
+            });
+
+            let Some(bytecode) = method.attributes.iter().find_map(|attr| match &attr.data {
+                AttributeData::Code(code) => code.bytecode.as_ref(),
+                _ => None,
+            }) else {
+                continue;
+            };
+
+            let callees = self
+                .calls
+                .entry((class_name.clone(), method.name.to_string(), descriptor))
+                .or_default();
+            for (_, opcode) in &bytecode.opcodes {
+                match opcode {
+                    Opcode::Invokespecial(member_ref)
+                    | Opcode::Invokestatic(member_ref)
+                    | Opcode::Invokevirtual(member_ref)
+                    | Opcode::Invokeinterface(member_ref, _) => {
+                        callees.insert((
+                            member_ref.class_name.to_string(),
+                            member_ref.name_and_type.name.to_string(),
+                            member_ref.name_and_type.descriptor.to_string(),
+                        ));
+                    }
+                    // Lambdas and method references point at their implementation method through
+                    // the bootstrap arguments of the call site.
+                    Opcode::Invokedynamic(invoke_dynamic) => {
+                        let arguments = bootstrap_methods
+                            .and_then(|methods| methods.get(usize::from(invoke_dynamic.attr_index)))
+                            .map(|entry| entry.arguments.as_slice())
+                            .unwrap_or_default();
+                        for argument in arguments {
+                            if let BootstrapArgument::MethodHandle(handle) = argument {
+                                callees.insert((
+                                    handle.class_name.to_string(),
+                                    handle.member_ref.name.to_string(),
+                                    handle.member_ref.descriptor.to_string(),
+                                ));
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        self.classes.insert(class_name, node);
+    }
+
+    pub fn merge(mut self, other: CallGraph) -> CallGraph {
+        self.classes.extend(other.classes);
+        for (caller, callees) in other.calls {
+            self.calls.entry(caller).or_default().extend(callees);
+        }
+        self
+    }
+
+    /// Every method declared by a scanned class.
+    pub fn declared_methods(&self) -> Vec<MethodKey> {
+        let mut methods: Vec<MethodKey> = self
+            .classes
+            .iter()
+            .flat_map(|(class_name, class)| {
+                class
+                    .methods
+                    .iter()
+                    .map(move |method| (class_name.clone(), method.name.clone()))
+            })
+            .collect();
+        methods.sort();
+        methods.dedup();
+        methods
+    }
+
+    /// Every caller with the distinct methods it invokes, the overloads of each method merged.
+    pub fn edges(&self) -> HashMap<MethodKey, HashSet<MethodKey>> {
+        let mut edges: HashMap<MethodKey, HashSet<MethodKey>> = HashMap::new();
+        for ((class_name, method_name, _), callees) in &self.calls {
+            edges
+                .entry((class_name.clone(), method_name.clone()))
+                .or_default()
+                .extend(
+                    callees
+                        .iter()
+                        .map(|(class_name, method_name, _)| (class_name.clone(), method_name.clone())),
+                );
+        }
+        edges
+    }
+
+    /// Methods of the scanned classes matching any of the entry point specifications, every overload of
+    /// a method named by them.
+    pub fn entry_points(&self, specs: &[EntryPoint]) -> Vec<MethodId> {
+        let mut entry_points: Vec<MethodId> = self
+            .classes
+            .iter()
+            .flat_map(|(class_name, class)| {
+                class
+                    .methods
+                    .iter()
+                    .filter(|method| specs.iter().any(|spec| spec.matches(class, class_name, method)))
+                    .map(|method| (class_name.clone(), method.name.clone(), method.descriptor.clone()))
+            })
+            .collect();
+        entry_points.sort();
+        entry_points
+    }
+
+    /// Breadth-first search from the entry points. Every reachable method maps to the method it was
+    /// first reached from, or `None` for the entry points themselves.
+    pub fn reachable(&self, entry_points: &[MethodId]) -> HashMap<MethodId, Option<MethodId>> {
+        let subtypes = self.subtypes();
+        let mut parents: HashMap<MethodId, Option<MethodId>> = HashMap::new();
+        let mut queue: VecDeque<MethodId> = VecDeque::new();
+        for entry_point in entry_points {
+            if parents.insert(entry_point.clone(), None).is_none() {
+                queue.push_back(entry_point.clone());
+            }
+        }
+
+        while let Some(current) = queue.pop_front() {
+            let Some(callees) = self.calls.get(&current) else {
+                continue;
+            };
+            let mut callees: Vec<&MethodId> = callees.iter().collect();
+            callees.sort();
+            for callee in callees {
+                // A call may dispatch to any override declared by a scanned subtype.
+                let overrides = self.overrides(&subtypes, callee);
+                for target in std::iter::once(callee.clone()).chain(overrides) {
+                    if !parents.contains_key(&target) {
+                        parents.insert(target.clone(), Some(current.clone()));
+                        queue.push_back(target);
+                    }
+                }
+            }
+        }
+        parents
+    }
+
+    fn subtypes(&self) -> HashMap<&str, Vec<&str>> {
+        let mut subtypes: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (class_name, class) in &self.classes {
+            for supertype in &class.supertypes {
+                subtypes
+                    .entry(supertype.as_str())
+                    .or_default()
+                    .push(class_name.as_str());
+            }
+        }
+        subtypes
+    }
+
+    fn overrides(
+        &self,
+        subtypes: &HashMap<&str, Vec<&str>>,
+        (class_name, method_name, descriptor): &MethodId,
+    ) -> Vec<MethodId> {
+        let mut overrides = Vec::new();
+        let mut seen: HashSet<&str> = HashSet::new();
+        let mut pending: Vec<&str> = subtypes.get(class_name.as_str()).cloned().unwrap_or_default();
+        while let Some(subtype) = pending.pop() {
+            if !seen.insert(subtype) {
+                continue;
+            }
+            if let Some(class) = self.classes.get(subtype) {
+                if class
+                    .methods
+                    .iter()
+                    .any(|method| method.name == *method_name && method.descriptor == *descriptor)
+                {
+                    overrides.push((subtype.to_string(), method_name.clone(), descriptor.clone()));
+                }
+            }
+            pending.extend(subtypes.get(subtype).into_iter().flatten());
+        }
+        overrides
+    }
+}
+
+/// Walks the parents recorded by [`CallGraph::reachable`] back from `method` to its entry point,
+/// returning the chain in call order.
+pub fn path_to(parents: &HashMap<MethodId, Option<MethodId>>, method: &MethodId) -> Vec<MethodId> {
+    let mut path = vec![method.clone()];
+    while let Some(Some(parent)) = parents.get(path.last().unwrap()) {
+        if path.contains(parent) {
+            break;
+        }
+        path.push(parent.clone());
+    }
+    path.reverse();
+    path
+}
+
+/// Dotted names of the visible and invisible annotations among `attributes`.
+pub fn annotations(attributes: &[AttributeInfo]) -> Vec<String> {
+    attributes
+        .iter()
+        .flat_map(|attr| match &attr.data {
+            AttributeData::RuntimeVisibleAnnotations(annotations)
+            | AttributeData::RuntimeInvisibleAnnotations(annotations) => annotations.as_slice(),
+            _ => &[],
+        })
+        .filter_map(annotation_name)
+        .collect()
+}
+
+/// Dotted name of the type of an annotation.
+pub fn annotation_name(annotation: &Annotation) -> Option<String> {
+    match &annotation.type_descriptor.field_type {
+        FieldType::Object(class_name) => Some(
+            class_name
+                .segments
+                .iter()
+                .map(|segment| segment.name.as_ref())
+                .collect::<Vec<_>>()
+                .join("."),
+        ),
+        _ => None,
+    }
+}
@@ -0,0 +1,101 @@
+//! Heuristic detection of dynamic Groovy calls, for `--groovy`. Groovy compiles a method call to a call
+//! site looked up at run time, so the bytecode names the invoked method but not its class:
+//!
+//! - with call-site caching, the method is fetched from a `CallSite[]` whose names
+//!   `$createCallSiteArray_1` stores, as `callSites[3].call(receiver)`;
+//! - with `invokedynamic`, the method name is the first string argument of the `IndyInterface` bootstrap.
+//!
+//! Such calls are attributed to any target method of the same name.
+
+use cafebabe::{
+    attributes::{AttributeData, BootstrapMethodEntry},
+    bytecode::Opcode,
+    constant_pool::{BootstrapArgument, LiteralConstant, Loadable},
+    ClassFile,
+};
+
+/// Method names of the call-site array of a class compiled by Groovy, by call-site index; empty for other
+/// classes.
+pub fn call_site_names(class_file: &ClassFile) -> Vec<String> {
+    let mut names = Vec::new();
+    let Some(bytecode) = class_file
+        .methods
+        .iter()
+        .find(|method| method.name == "$createCallSiteArray_1")
+        .and_then(|method| {
+            method.attributes.iter().find_map(|attr| match &attr.data {
+                AttributeData::Code(code) => code.bytecode.as_ref(),
+                _ => None,
+            })
+        })
+    else {
+        return names;
+    };
+    // `names[index] = "name"`, as `aload_0, <index>, ldc "name", aastore`
+    for window in bytecode.opcodes.windows(3) {
+        let (Some(index), Opcode::Ldc(Loadable::LiteralConstant(LiteralConstant::String(name))), Opcode::Aastore) =
+            (int_constant(&window[0].1), &window[1].1, &window[2].1)
+        else {
+            continue;
+        };
+        if names.len() <= index {
+            names.resize(index + 1, String::new());
+        }
+        names[index] = name.to_string();
+    }
+    names
+}
+
+/// Offsets and method names of the dynamic calls among `opcodes`: the loads of a call site from the
+/// call-site array, and the `invokedynamic` instructions bootstrapped by Groovy.
+pub fn dynamic_calls(
+    opcodes: &[(usize, Opcode)],
+    call_site_names: &[String],
+    bootstrap_methods: &[BootstrapMethodEntry],
+) -> Vec<(usize, String)> {
+    let uses_call_sites = opcodes.iter().any(|(_, opcode)| {
+        matches!(opcode, Opcode::Invokestatic(member_ref) if member_ref.name_and_type.name == "$getCallSiteArray")
+    });
+    let mut calls = Vec::new();
+    for (position, (offset, opcode)) in opcodes.iter().enumerate() {
+        match opcode {
+            Opcode::Aaload if uses_call_sites && position > 0 => {
+                let name = int_constant(&opcodes[position - 1].1).and_then(|index| call_site_names.get(index));
+                if let Some(name) = name.filter(|name| !name.is_empty()) {
+                    calls.push((*offset, name.clone()));
+                }
+            }
+            Opcode::Invokedynamic(invoke_dynamic) => {
+                let Some(entry) = bootstrap_methods.get(usize::from(invoke_dynamic.attr_index)) else {
+                    continue;
+                };
+                if !entry.method.class_name.ends_with("/IndyInterface") {
+                    continue;
+                }
+                let name = entry.arguments.iter().find_map(|argument| match argument {
+                    BootstrapArgument::LiteralConstant(LiteralConstant::String(name)) => Some(name.to_string()),
+                    _ => None,
+                });
+                if let Some(name) = name {
+                    calls.push((*offset, name));
+                }
+            }
+            _ => {}
+        }
+    }
+    calls
+}
+
+fn int_constant(opcode: &Opcode) -> Option<usize> {
+    match opcode {
+        Opcode::Iconst0 => Some(0),
+        Opcode::Iconst1 => Some(1),
+        Opcode::Iconst2 => Some(2),
+        Opcode::Iconst3 => Some(3),
+        Opcode::Iconst4 => Some(4),
+        Opcode::Iconst5 => Some(5),
+        Opcode::Bipush(value) => usize::try_from(*value).ok(),
+        Opcode::Sipush(value) => usize::try_from(*value).ok(),
+        _ => None,
+    }
+}
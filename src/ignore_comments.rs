@@ -0,0 +1,54 @@
+//! `// jmf:ignore` comments: call sites acknowledged in the caller's source file under the `--source-root`
+//! directories, on the line of the call or alone on the line before it, are reported as suppressed.
+
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use crate::analysis::FoundCall;
+
+/// How suppressions by comment are reported.
+const IGNORE_COMMENT: &str = "// jmf:ignore";
+
+/// Marks as suppressed the calls whose source line, or the comment line before it, has a `jmf:ignore`
+/// comment.
+/// Calls without a readable source file are left as they are.
+pub fn apply(calls: &mut [FoundCall], source_roots: &[PathBuf]) {
+    let mut files: HashMap<String, Option<Vec<String>>> = HashMap::new();
+    for call in calls.iter_mut().filter(|call| call.suppressed.is_none()) {
+        let source_path = call.source_path();
+        let lines = files.entry(source_path).or_insert_with_key(|source_path| {
+            source_roots
+                .iter()
+                .map(|source_root| source_root.join(source_path))
+                .find_map(|file| fs::read_to_string(file).ok())
+                .map(|text| text.lines().map(str::to_string).collect())
+        });
+        let Some(lines) = lines else {
+            continue;
+        };
+        let index = usize::from(call.line_number).saturating_sub(1);
+        let on_line = lines.get(index).is_some_and(|line| has_ignore_comment(line));
+        // A comment on the line before only counts when the line holds nothing else, so that it does not
+        // also cover the calls of the line after another call
+        let before = index
+            .checked_sub(1)
+            .and_then(|index| lines.get(index))
+            .is_some_and(|line| is_comment(line) && has_ignore_comment(line));
+        let ignored = on_line || before;
+        if ignored {
+            call.suppressed = Some(IGNORE_COMMENT.to_string());
+        }
+    }
+}
+
+/// Whether a line has a `// jmf:ignore` or `/* jmf:ignore */` comment, with any spacing.
+fn has_ignore_comment(line: &str) -> bool {
+    ["//", "/*"].iter().any(|opening| {
+        line.match_indices(opening)
+            .any(|(index, _)| line[index + opening.len()..].trim_start().starts_with("jmf:ignore"))
+    })
+}
+
+fn is_comment(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("//") || line.starts_with("/*")
+}
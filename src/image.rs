@@ -0,0 +1,149 @@
+//! Container images as scan roots: `docker://REFERENCE`, saved with the `docker` CLI (pulled first when it
+//! is not present locally), and `docker-archive:PATH`, a tar written by `docker save`. The layers are
+//! applied in order, whiteouts included, and the class files and archives of the resulting file system
+//! are scanned.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::File,
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use memmap2::Mmap;
+
+use crate::{
+    analysis::entry_capacity,
+    tar::{self, EntryKind},
+};
+
+const DOCKER: &str = "docker://";
+const DOCKER_ARCHIVE: &str = "docker-archive:";
+
+/// Whether a scan root names a container image.
+pub fn is_image(root: &Path) -> bool {
+    let root = root.to_string_lossy();
+    root.starts_with(DOCKER) || root.starts_with(DOCKER_ARCHIVE)
+}
+
+/// The files of an image whose path `wanted` accepts, by path.
+pub fn files(root: &Path, wanted: impl Fn(&str) -> bool) -> Result<BTreeMap<String, Vec<u8>>> {
+    let root = root.to_string_lossy();
+    let (path, _saved) = match root.strip_prefix(DOCKER) {
+        Some(reference) => {
+            let saved = save(reference)?;
+            (saved.path().to_path_buf(), Some(saved))
+        }
+        None => (PathBuf::from(root.strip_prefix(DOCKER_ARCHIVE).unwrap_or(&root)), None),
+    };
+    let file = File::open(&path).with_context(|| format!("Failed to open image archive {}", path.display()))?;
+    // SAFETY: the archive is read-only input, as the jars of the scan roots
+    let archive =
+        unsafe { Mmap::map(&file) }.with_context(|| format!("Failed to map image archive {}", path.display()))?;
+    apply_layers(&archive, wanted).with_context(|| format!("Failed to read image archive {}", path.display()))
+}
+
+/// Saves an image with `docker save`, pulling it first when it is not present locally.
+fn save(reference: &str) -> Result<tempfile::NamedTempFile> {
+    // Would be taken for an option of the docker CLI
+    anyhow::ensure!(!reference.starts_with('-'), "Invalid image reference {}", reference);
+    let present = Command::new("docker")
+        .args(["image", "inspect", reference])
+        .output()
+        .context("Failed to run docker, which docker:// scan roots need")?
+        .status
+        .success();
+    if !present {
+        let status = Command::new("docker")
+            .args(["pull", "--quiet", reference])
+            .status()
+            .context("Failed to run docker pull")?;
+        anyhow::ensure!(status.success(), "docker pull {} failed: {}", reference, status);
+    }
+    let saved = tempfile::Builder::new()
+        .prefix("jmf-image-")
+        .suffix(".tar")
+        .tempfile()?;
+    let output = Command::new("docker")
+        .args(["save", "--output"])
+        .arg(saved.path())
+        .arg(reference)
+        .output()
+        .context("Failed to run docker save")?;
+    anyhow::ensure!(
+        output.status.success(),
+        "docker save {} failed: {}",
+        reference,
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(saved)
+}
+
+/// Applies the layers listed by the `manifest.json` of a `docker save` archive.
+fn apply_layers(archive: &[u8], wanted: impl Fn(&str) -> bool) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut entries = BTreeMap::new();
+    let mut manifest = None;
+    tar::for_each(Cursor::new(archive), |entry| {
+        if entry.name == "manifest.json" {
+            let mut json = Vec::new();
+            entry.data.read_to_end(&mut json)?;
+            manifest = Some(serde_json::from_slice::<serde_json::Value>(&json).context("Invalid manifest.json")?);
+        }
+        entries.insert(entry.name, (entry.offset as usize, entry.size as usize));
+        Ok(())
+    })?;
+    let manifest = manifest.context("No manifest.json, not an archive written by docker save")?;
+    let layers = manifest[0]["Layers"].as_array().context("No layers in manifest.json")?;
+
+    let mut files = BTreeMap::new();
+    for layer in layers {
+        let layer = layer.as_str().context("Invalid layer in manifest.json")?;
+        let (offset, size) = *entries
+            .get(layer)
+            .with_context(|| format!("Layer {} not found", layer))?;
+        let data = offset
+            .checked_add(size)
+            .and_then(|end| archive.get(offset..end))
+            .with_context(|| format!("Layer {} is truncated", layer))?;
+        let reader: Box<dyn Read> = if data.starts_with(&[0x1f, 0x8b]) {
+            Box::new(GzDecoder::new(data))
+        } else {
+            Box::new(data)
+        };
+        // The files of this layer, which its own whiteouts leave in place
+        let mut added = HashSet::new();
+        tar::for_each(reader, |entry| {
+            let (directory, file_name) = entry.name.rsplit_once('/').unwrap_or(("", &entry.name));
+            // Whiteouts delete what lower layers added
+            if file_name == ".wh..wh..opq" {
+                files.retain(|path: &String, _| added.contains(path) || !is_below(path, directory));
+            } else if let Some(deleted) = file_name.strip_prefix(".wh.") {
+                let deleted = if directory.is_empty() {
+                    deleted.to_string()
+                } else {
+                    format!("{}/{}", directory, deleted)
+                };
+                files
+                    .retain(|path: &String, _| added.contains(path) || (*path != deleted && !is_below(path, &deleted)));
+            } else if entry.kind == EntryKind::File && wanted(&entry.name) {
+                let mut contents = Vec::with_capacity(entry_capacity(entry.size));
+                entry.data.read_to_end(&mut contents)?;
+                added.insert(entry.name.clone());
+                files.insert(entry.name, contents);
+            } else {
+                added.remove(&entry.name);
+                files.remove(&entry.name);
+            }
+            Ok(())
+        })
+        .with_context(|| format!("Failed to read layer {}", layer))?;
+    }
+    Ok(files)
+}
+
+fn is_below(path: &str, directory: &str) -> bool {
+    directory.is_empty() || path.strip_prefix(directory).is_some_and(|rest| rest.starts_with('/'))
+}
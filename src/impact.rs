@@ -0,0 +1,229 @@
+//! `jmf impact`: the public API a library upgrade removes or changes, diffed between the old and the new
+//! version, and the calls of the scanned application that the upgrade would break.
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use cafebabe::MethodAccessFlags;
+use serde::Serialize;
+
+use crate::{
+    analysis::FoundCall,
+    api::{self, Api, Member},
+    color,
+    policy::{Rule, Severity},
+    scan::{ScanArgs, ScanSummary},
+    Args, MethodFinder,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct ImpactArgs {
+    /// Current version of the library: a jar, directory or any other scan root
+    #[arg(long = "old", value_name = "PATH")]
+    pub old: PathBuf,
+
+    /// Version of the library to upgrade to
+    #[arg(long = "new", value_name = "PATH")]
+    pub new: PathBuf,
+
+    #[command(flatten)]
+    pub scan: ScanArgs,
+
+    #[arg(short = 'f', long = "format", value_enum, default_value_t = crate::ReportFormatter::Txt)]
+    pub format: crate::ReportFormatter,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeKind {
+    /// The method, or its class, is gone
+    Removed,
+    /// The method only exists with other parameter or return types
+    SignatureChanged,
+    /// The method became static or stopped being static, which breaks its invocations
+    StaticChanged,
+    /// The method is no longer public or protected
+    VisibilityReduced,
+}
+
+impl ChangeKind {
+    fn label(self) -> &'static str {
+        match self {
+            ChangeKind::Removed => "removed",
+            ChangeKind::SignatureChanged => "signature changed",
+            ChangeKind::StaticChanged => "static changed",
+            ChangeKind::VisibilityReduced => "visibility reduced",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiChange {
+    pub class: String,
+    pub method: String,
+    /// Descriptor in the old version, which the calls were compiled against
+    pub descriptor: String,
+    pub kind: ChangeKind,
+    /// What the new version has instead, e.g. `now greet(Ljava/lang/String;Z)Ljava/lang/String;`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub calls: Vec<FoundCall>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Impact {
+    pub old: String,
+    pub new: String,
+    pub changes: Vec<ApiChange>,
+    #[serde(flatten)]
+    pub summary: ScanSummary,
+}
+
+impl Impact {
+    pub fn run(args: ImpactArgs) -> Result<Self> {
+        let old = Api::load(&args.old)?;
+        let new = Api::load(&args.new)?;
+        let mut changes = diff(&old, &new);
+        let rules: Vec<Rule> = changes
+            .iter()
+            .map(|change| Rule {
+                descriptor: Some(change.descriptor.clone()),
+                severity: Severity::Error,
+                ..Rule::method(&change.class, &change.method)
+            })
+            .collect();
+        let (calls, summary) = if rules.is_empty() {
+            (Vec::new(), ScanSummary::default())
+        } else {
+            MethodFinder::with_rules(Args::defaults(args.scan), rules)?.scan_folder()?
+        };
+        for call in calls {
+            changes[call.rule].calls.push(call);
+        }
+        Ok(Self {
+            old: args.old.display().to_string(),
+            new: args.new.display().to_string(),
+            changes,
+            summary,
+        })
+    }
+
+    pub fn broken_calls(&self) -> usize {
+        self.changes.iter().map(|change| change.calls.len()).sum()
+    }
+
+    /// The changes that break calls first, then those nothing calls.
+    pub fn to_text(&self) -> String {
+        let breaking = self.changes.iter().filter(|change| !change.calls.is_empty()).count();
+        let mut output = vec![color::bold(format!(
+            "Upgrading {} to {}: {} API changes, {} breaking {} calls",
+            self.old,
+            self.new,
+            self.changes.len(),
+            breaking,
+            self.broken_calls()
+        ))];
+        let (called, uncalled): (Vec<&ApiChange>, Vec<&ApiChange>) =
+            self.changes.iter().partition(|change| !change.calls.is_empty());
+        for change in called.iter().chain(&uncalled) {
+            let mut heading = format!(
+                "[{}] {}#{}{}",
+                change.kind.label(),
+                change.class,
+                change.method,
+                change.descriptor
+            );
+            if let Some(detail) = &change.detail {
+                heading.push_str(&format!(", {}", detail));
+            }
+            output.push(heading);
+            if change.calls.is_empty() {
+                output.push(color::dim(" - not called"));
+            }
+            output.extend(change.calls.iter().map(|call| format!(" - {}", call)));
+        }
+        if !self.summary.is_empty() {
+            output.push(self.summary.to_text());
+        }
+        output.join("\n")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
+
+/// The method of the new version a call compiled against the old one resolves to, looked up through the
+/// superclasses the new version declares, as the JVM resolves it.
+fn resolve<'a>(api: &'a Api, class: &str, method: &Member) -> Option<&'a Member> {
+    let mut class = api.class(class);
+    while let Some(api_class) = class {
+        if let Some(member) = api_class.method(&method.name, &method.descriptor) {
+            return Some(member);
+        }
+        if method.name == "<init>" {
+            return None;
+        }
+        class = api_class.super_class.as_deref().and_then(|name| api.class(name));
+    }
+    None
+}
+
+/// The exported methods of the public classes of `old` that `new` removes or changes, in class order.
+fn diff(old: &Api, new: &Api) -> Vec<ApiChange> {
+    let is_static =
+        |method: &Member| MethodAccessFlags::from_bits_truncate(method.flags).contains(MethodAccessFlags::STATIC);
+    let mut changes = Vec::new();
+    for class in old.classes.iter().filter(|class| class.is_public()) {
+        let new_class = new.class(&class.name).filter(|class| class.is_public());
+        for method in class.methods.iter().filter(|method| api::is_exported(method.flags)) {
+            let (kind, detail) = match new_class {
+                None => (
+                    ChangeKind::Removed,
+                    Some(if new.class(&class.name).is_some() {
+                        "its class is no longer public".to_string()
+                    } else {
+                        "with its class".to_string()
+                    }),
+                ),
+                Some(new_class) => match resolve(new, &class.name, method) {
+                    Some(new_method) if !api::is_exported(new_method.flags) => (ChangeKind::VisibilityReduced, None),
+                    Some(new_method) if is_static(new_method) != is_static(method) => {
+                        let now = if is_static(new_method) {
+                            "now static"
+                        } else {
+                            "no longer static"
+                        };
+                        (ChangeKind::StaticChanged, Some(now.to_string()))
+                    }
+                    Some(_) => continue,
+                    None => {
+                        let overloads: Vec<String> = new_class
+                            .methods
+                            .iter()
+                            .filter(|overload| overload.name == method.name && api::is_exported(overload.flags))
+                            .map(|overload| format!("{}{}", overload.name, overload.descriptor))
+                            .collect();
+                        if overloads.is_empty() {
+                            (ChangeKind::Removed, None)
+                        } else {
+                            (
+                                ChangeKind::SignatureChanged,
+                                Some(format!("now {}", overloads.join(", "))),
+                            )
+                        }
+                    }
+                },
+            };
+            changes.push(ApiChange {
+                class: class.name.clone(),
+                method: method.name.clone(),
+                descriptor: method.descriptor.clone(),
+                kind,
+                detail,
+                calls: Vec::new(),
+            });
+        }
+    }
+    changes
+}
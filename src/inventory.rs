@@ -0,0 +1,124 @@
+//! Inventory of the external APIs a codebase calls, for `jmf inventory`.
+
+use std::collections::{HashMap, HashSet};
+
+use cafebabe::{attributes::AttributeData, bytecode::Opcode, ClassFile};
+use serde::Serialize;
+
+use crate::metrics::csv_field;
+
+#[derive(Debug, Serialize)]
+pub struct ExternalApi {
+    pub class_name: String,
+    pub method_name: String,
+    pub calls: usize,
+}
+
+/// Invocations found in a single class, before classes declared in the scan set are excluded.
+#[derive(Debug, Default)]
+pub struct ClassInvocations {
+    class_name: String,
+    calls: HashMap<(String, String), usize>,
+}
+
+impl ClassInvocations {
+    pub fn new(class_file: &ClassFile) -> Self {
+        let mut calls: HashMap<(String, String), usize> = HashMap::new();
+        for method in &class_file.methods {
+            let Some(bytecode) = method.attributes.iter().find_map(|attr| match &attr.data {
+                AttributeData::Code(code) => code.bytecode.as_ref(),
+                _ => None,
+            }) else {
+                continue;
+            };
+            for (_, opcode) in &bytecode.opcodes {
+                if let Opcode::Invokespecial(member_ref)
+                | Opcode::Invokestatic(member_ref)
+                | Opcode::Invokevirtual(member_ref)
+                | Opcode::Invokeinterface(member_ref, _) = opcode
+                {
+                    let key = (
+                        member_ref.class_name.to_string(),
+                        member_ref.name_and_type.name.to_string(),
+                    );
+                    *calls.entry(key).or_default() += 1;
+                }
+            }
+        }
+        Self {
+            class_name: class_file.this_class.to_string(),
+            calls,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Inventory {
+    pub apis: Vec<ExternalApi>,
+}
+
+impl Inventory {
+    /// Keeps calls into classes outside the scan set whose name starts with one of `prefixes`
+    /// (all external calls when `prefixes` is empty), most called first.
+    pub fn new(classes: Vec<ClassInvocations>, prefixes: &[String]) -> Self {
+        let declared: HashSet<String> = classes.iter().map(|class| class.class_name.clone()).collect();
+        let prefixes: Vec<String> = prefixes.iter().map(|prefix| prefix.replace('.', "/")).collect();
+
+        let mut totals: HashMap<(String, String), usize> = HashMap::new();
+        for class in classes {
+            for ((class_name, method_name), count) in class.calls {
+                if declared.contains(&class_name) {
+                    continue;
+                }
+                if !prefixes.is_empty() && !prefixes.iter().any(|prefix| class_name.starts_with(prefix.as_str())) {
+                    continue;
+                }
+                *totals.entry((class_name, method_name)).or_default() += count;
+            }
+        }
+
+        let mut apis: Vec<ExternalApi> = totals
+            .into_iter()
+            .map(|((class_name, method_name), calls)| ExternalApi {
+                class_name: class_name.replace('/', "."),
+                method_name,
+                calls,
+            })
+            .collect();
+        apis.sort_by(|a, b| {
+            b.calls
+                .cmp(&a.calls)
+                .then_with(|| a.class_name.cmp(&b.class_name))
+                .then_with(|| a.method_name.cmp(&b.method_name))
+        });
+        Self { apis }
+    }
+
+    pub fn to_text(&self) -> String {
+        if self.apis.is_empty() {
+            return "No results".to_string();
+        }
+        self.apis
+            .iter()
+            .map(|api| format!("{}#{} ({})", api.class_name, api.method_name, api.calls))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut output = vec!["class_name,method_name,calls".to_string()];
+        output.extend(self.apis.iter().map(|api| {
+            format!(
+                "{},{},{}",
+                csv_field(&api.class_name),
+                csv_field(&api.method_name),
+                api.calls
+            )
+        }));
+        output.join("\n")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
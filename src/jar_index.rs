@@ -0,0 +1,188 @@
+//! `--jar-index`: a persistent index holding, for each archive scan root, a bloom filter of the classes and
+//! packages its class files reference. A scan whose targets are all absent from an archive's filter skips
+//! the archive without opening it; filters are built the first time an archive is scanned.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::{Context, Result};
+
+use crate::duplicates::sha256_hex;
+
+/// Header of filter files, followed by the format version.
+const MAGIC: &[u8] = b"JMFBLOOM";
+const VERSION: u8 = 1;
+/// Bits per inserted name, for about 1% false positives with `HASHES` hashes.
+const BITS_PER_NAME: usize = 10;
+const HASHES: u32 = 7;
+
+pub struct BloomFilter {
+    bits: Vec<u8>,
+    hashes: u32,
+}
+
+impl BloomFilter {
+    pub fn new(names: usize) -> Self {
+        Self {
+            bits: vec![0; (names * BITS_PER_NAME).div_ceil(8).max(8)],
+            hashes: HASHES,
+        }
+    }
+
+    pub fn insert(&mut self, name: &str) {
+        for bit in self.bit_indices(name) {
+            self.bits[bit / 8] |= 1 << (bit % 8);
+        }
+    }
+
+    /// Whether the name may have been inserted; `false` is certain.
+    pub fn may_contain(&self, name: &str) -> bool {
+        self.bit_indices(name)
+            .all(|bit| self.bits[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    /// Double hashing over a stable hash, so that filters stay valid across builds.
+    fn bit_indices(&self, name: &str) -> impl Iterator<Item = usize> {
+        let hash = fnv1a(name.as_bytes());
+        let (first, second) = (hash as u32 as u64, (hash >> 32) | 1);
+        let len = self.bits.len() as u64 * 8;
+        (0..u64::from(self.hashes)).map(move |index| (first.wrapping_add(index.wrapping_mul(second)) % len) as usize)
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.hashes.to_le_bytes());
+        bytes.extend_from_slice(&self.bits);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let rest = bytes.strip_prefix(MAGIC)?.strip_prefix(&[VERSION])?;
+        let (hashes, bits) = rest.split_first_chunk::<4>()?;
+        let hashes = u32::from_le_bytes(*hashes);
+        (hashes > 0 && !bits.is_empty()).then(|| Self {
+            bits: bits.to_vec(),
+            hashes,
+        })
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// The filters of the archives scanned with the same listing options, in a directory.
+pub struct JarIndex {
+    directory: PathBuf,
+    /// Options deciding which class files an archive lists, which a filter must cover
+    options: String,
+}
+
+impl JarIndex {
+    pub fn new(directory: &Path, options: String) -> Self {
+        Self {
+            directory: directory.to_path_buf(),
+            options,
+        }
+    }
+
+    /// Filters are keyed by the archive's path, size and modification time, so that a rebuilt archive
+    /// gets a new filter.
+    fn filter_path(&self, archive: &Path) -> Result<PathBuf> {
+        let metadata = fs::metadata(archive).with_context(|| format!("Failed to read {}", archive.display()))?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        let absolute = archive.canonicalize().unwrap_or_else(|_| archive.to_path_buf());
+        let key = format!(
+            "{}\0{}\0{}\0{}",
+            absolute.display(),
+            metadata.len(),
+            modified.as_nanos(),
+            self.options
+        );
+        Ok(self
+            .directory
+            .join(format!("{}.bloom", &sha256_hex(key.as_bytes())[..32])))
+    }
+
+    pub fn load(&self, archive: &Path) -> Result<Option<BloomFilter>> {
+        let path = self.filter_path(archive)?;
+        match fs::read(&path) {
+            Ok(bytes) => Ok(BloomFilter::from_bytes(&bytes)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    pub fn store(&self, archive: &Path, filter: &BloomFilter) -> Result<()> {
+        let path = self.filter_path(archive)?;
+        fs::create_dir_all(&self.directory)
+            .with_context(|| format!("Failed to create {}", self.directory.display()))?;
+        fs::write(&path, filter.to_bytes()).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// The classes a class file references through its constant pool, which every call, method handle and
+/// bootstrap argument goes through, as internal names. Malformed constant pools yield what was read.
+pub fn referenced_classes(class_data: &[u8]) -> Vec<String> {
+    let Some(count) = read_u16(class_data, 8) else {
+        return Vec::new();
+    };
+    let mut utf8 = vec![None; usize::from(count)];
+    let mut class_indices = Vec::new();
+    let mut offset = 10;
+    let mut index = 1;
+    while index < usize::from(count) {
+        let Some(&tag) = class_data.get(offset) else {
+            break;
+        };
+        let size = match tag {
+            1 => {
+                let Some(len) = read_u16(class_data, offset + 1) else {
+                    break;
+                };
+                let start = offset + 3;
+                utf8[index] = class_data.get(start..start + usize::from(len));
+                2 + usize::from(len)
+            }
+            7 => {
+                class_indices.extend(read_u16(class_data, offset + 1));
+                2
+            }
+            8 | 16 | 19 | 20 => 2,
+            15 => 3,
+            3 | 4 | 9 | 10 | 11 | 12 | 17 | 18 => 4,
+            5 | 6 => 8,
+            _ => break,
+        };
+        offset += 1 + size;
+        // Long and double constants take two entries
+        index += if matches!(tag, 5 | 6) { 2 } else { 1 };
+    }
+    class_indices
+        .into_iter()
+        .filter_map(|name_index| utf8.get(usize::from(name_index)).copied().flatten())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        // Arrays of classes name their element type
+        .map(|name| {
+            name.trim_start_matches('[')
+                .trim_start_matches('L')
+                .trim_end_matches(';')
+                .to_string()
+        })
+        .collect()
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    bytes
+        .get(offset..offset + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+}
@@ -0,0 +1,31 @@
+//! The class file analysis behind `jmf`, usable without the command-line front end.
+//!
+//! With default features off, the crate has no dependency on threads, the file system walker or
+//! dynamic loading, and builds for `wasm32-wasip1`:
+//!
+//! ```text
+//! cargo build --lib --no-default-features --target wasm32-wasip1
+//! ```
+
+pub mod analysis;
+pub mod color;
+pub mod context;
+pub mod disasm;
+pub mod elements;
+pub mod graph;
+pub mod groovy;
+pub mod members;
+pub mod modules;
+pub mod page;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod pattern;
+pub mod plugin;
+pub mod policy;
+pub mod profile;
+pub mod query;
+pub mod relocation;
+pub mod scala;
+#[cfg(feature = "async")]
+pub mod stream;
+pub mod versions;
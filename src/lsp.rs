@@ -0,0 +1,394 @@
+//! A minimal language server for `jmf lsp`, answering `textDocument/references` from the compiled classes
+//! of the scan roots, so editors can find usages in deployed artifacts that source indexing never sees.
+//!
+//! References are resolved for the methods and constructors of the top-level class of a document, known
+//! from its `package` declaration and file name. A call site links to its source file under a
+//! `--source-root` when the file is there, and to its class file otherwise.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, Write},
+    path::{self, Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use cafebabe::{attributes::AttributeData, bytecode::Opcode, ClassFile};
+use serde_json::{json, Value};
+
+use crate::{
+    analysis::{is_archive, FoundCall},
+    context::CallContext,
+    graph::MethodKey,
+    scan::{self, ClassSource, ScanArgs, Scanner},
+};
+
+#[derive(clap::Args, Debug)]
+pub struct LspArgs {
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    /// Source directory to link call sites to, relative to the workspace root (repeatable)
+    #[arg(long = "source-root", default_value = "src/main/java")]
+    source_roots: Vec<PathBuf>,
+}
+
+impl LspArgs {
+    pub fn scan(&self) -> &ScanArgs {
+        &self.scan
+    }
+
+    pub fn scan_mut(&mut self) -> &mut ScanArgs {
+        &mut self.scan
+    }
+}
+
+/// Largest message body read, in bytes.
+const MAX_MESSAGE: usize = 16 * 1024 * 1024;
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const REQUEST_FAILED: i64 = -32803;
+
+/// A call site in the index.
+struct Site {
+    call: FoundCall,
+    /// URI of the caller's class file, for call sites without sources
+    class_uri: String,
+}
+
+struct Server {
+    scanner: Scanner,
+    source_roots: Vec<PathBuf>,
+    /// Text of the open documents by URI
+    documents: HashMap<String, String>,
+    /// Call sites by invoked method, built on the first request after start or a change of watched files
+    index: Option<HashMap<MethodKey, Vec<Site>>>,
+}
+
+pub fn run(args: LspArgs) -> Result<ExitCode> {
+    // Standard output carries the protocol
+    scan::init_stderr_logging(&args.scan)?;
+    let mut server = Server {
+        scanner: Scanner::new(args.scan)?,
+        source_roots: args.source_roots,
+        documents: HashMap::new(),
+        index: None,
+    };
+    let mut input = io::stdin().lock();
+    let mut output = io::stdout().lock();
+    let mut shut_down = false;
+    while let Some(message) = read_message(&mut input)? {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let Some(id) = message.get("id") else {
+            match method {
+                "exit" => {
+                    return Ok(if shut_down {
+                        ExitCode::SUCCESS
+                    } else {
+                        ExitCode::FAILURE
+                    })
+                }
+                _ => server.notify(method, params),
+            }
+            continue;
+        };
+        let result = match method {
+            "initialize" => Ok(server.initialize(params)),
+            "shutdown" => {
+                shut_down = true;
+                Ok(Value::Null)
+            }
+            "textDocument/references" => server
+                .references(params)
+                .map_err(|e| (REQUEST_FAILED, format!("{:#}", e))),
+            _ => Err((METHOD_NOT_FOUND, format!("Unsupported method {}", method))),
+        };
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => {
+                json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+            }
+        };
+        write_message(&mut output, &response)?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Reads a message framed by a `Content-Length` header, or `None` at the end of the input.
+fn read_message(input: &mut impl BufRead) -> Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = Some(value.trim().parse::<usize>().context("Invalid Content-Length")?);
+        }
+    }
+    let length = length.context("Message without Content-Length")?;
+    anyhow::ensure!(
+        length <= MAX_MESSAGE,
+        "Content-Length {} exceeds {} bytes",
+        length,
+        MAX_MESSAGE
+    );
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body).context("Invalid message")?))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()?;
+    Ok(())
+}
+
+impl Server {
+    fn initialize(&mut self, params: &Value) -> Value {
+        let root = params["rootUri"]
+            .as_str()
+            .and_then(uri_to_path)
+            .or_else(|| params["rootPath"].as_str().map(PathBuf::from));
+        if let Some(root) = root {
+            self.source_roots = self
+                .source_roots
+                .iter()
+                .map(|source_root| root.join(source_root))
+                .collect();
+        }
+        json!({
+            "capabilities": { "referencesProvider": true, "textDocumentSync": 1 },
+            "serverInfo": { "name": "jmf", "version": env!("CARGO_PKG_VERSION") },
+        })
+    }
+
+    fn notify(&mut self, method: &str, params: &Value) {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri, text.to_string());
+            }
+            // Full synchronization: the last change holds the whole text
+            "textDocument/didChange" => {
+                if let Some(text) = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                {
+                    self.documents.insert(uri, text.to_string());
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+            }
+            // Rebuilt artifacts are scanned again on the next request
+            "workspace/didChangeWatchedFiles" => {
+                self.index = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn references(&mut self, params: &Value) -> Result<Value> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .context("Missing textDocument.uri")?;
+        let line = params["position"]["line"].as_u64().context("Missing position.line")? as usize;
+        let character = params["position"]["character"].as_u64().unwrap_or_default() as usize;
+        let text = match self.documents.get(uri) {
+            Some(text) => text.clone(),
+            None => {
+                let path = uri_to_path(uri).with_context(|| format!("Unsupported URI {}", uri))?;
+                fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?
+            }
+        };
+        let Some(key) = symbol_at(uri, &text, line, character) else {
+            return Ok(Value::Null);
+        };
+        if self.index.is_none() {
+            self.index = Some(self.build_index()?);
+        }
+        let sites = self.index.as_ref().and_then(|index| index.get(&key));
+        let mut locations: Vec<(String, u16)> = sites
+            .into_iter()
+            .flatten()
+            .map(|site| (self.site_uri(site), site.call.line_number))
+            .collect();
+        locations.sort();
+        locations.dedup();
+        Ok(Value::Array(
+            locations
+                .into_iter()
+                .map(|(uri, line)| {
+                    let position = json!({ "line": line.saturating_sub(1), "character": 0 });
+                    json!({ "uri": uri, "range": { "start": position, "end": position } })
+                })
+                .collect(),
+        ))
+    }
+
+    fn build_index(&self) -> Result<HashMap<MethodKey, Vec<Site>>> {
+        let mut index: HashMap<MethodKey, Vec<Site>> = HashMap::new();
+        for sites in self.scanner.map_classes(class_sites)? {
+            for (key, site) in sites {
+                index.entry(key).or_default().push(site);
+            }
+        }
+        Ok(index)
+    }
+
+    fn site_uri(&self, site: &Site) -> String {
+        let source_path = site.call.source_path();
+        self.source_roots
+            .iter()
+            .map(|source_root| source_root.join(&source_path))
+            .find(|path| path.is_file())
+            .map_or_else(|| site.class_uri.clone(), |path| path_to_uri(&path))
+    }
+}
+
+/// Every invocation in a class, by invoked method.
+fn class_sites(source: &ClassSource, class_file: &ClassFile) -> Vec<(MethodKey, Site)> {
+    let class_uri = if is_archive(&source.origin) {
+        format!("jar:{}!/{}", path_to_uri(&source.origin), source.name)
+    } else {
+        path_to_uri(&source.origin.join(&source.name))
+    };
+    let source_file: Option<Arc<str>> = class_file.attributes.iter().find_map(|attr| match &attr.data {
+        AttributeData::SourceFile(source_file) => Some(Arc::from(source_file.as_ref())),
+        _ => None,
+    });
+    let mut sites = Vec::new();
+    for method in &class_file.methods {
+        let Some(code) = method.attributes.iter().find_map(|attr| match &attr.data {
+            AttributeData::Code(code) => Some(code),
+            _ => None,
+        }) else {
+            continue;
+        };
+        let (Some(bytecode), Some(line_number_table)) = (
+            &code.bytecode,
+            code.attributes.iter().find_map(|attr| match &attr.data {
+                AttributeData::LineNumberTable(lnt) => Some(lnt),
+                _ => None,
+            }),
+        ) else {
+            continue;
+        };
+        for (offset, opcode) in &bytecode.opcodes {
+            let (Opcode::Invokespecial(member_ref)
+            | Opcode::Invokestatic(member_ref)
+            | Opcode::Invokevirtual(member_ref)
+            | Opcode::Invokeinterface(member_ref, _)) = opcode
+            else {
+                continue;
+            };
+            let index = line_number_table.partition_point(|entry| entry.start_pc <= *offset as u16);
+            if index == 0 {
+                continue;
+            }
+            let key = (
+                member_ref.class_name.to_string(),
+                member_ref.name_and_type.name.to_string(),
+            );
+            let call = FoundCall {
+                source_file: source_file.clone(),
+                ..FoundCall::new(
+                    class_file.this_class.to_string(),
+                    method.name.to_string(),
+                    line_number_table[index - 1].line_number,
+                    CallContext::default(),
+                )
+            };
+            sites.push((
+                key,
+                Site {
+                    call,
+                    class_uri: class_uri.clone(),
+                },
+            ));
+        }
+    }
+    sites
+}
+
+/// The method, or the constructor for the class name itself, at a position in a Java document.
+fn symbol_at(uri: &str, text: &str, line: usize, character: usize) -> Option<MethodKey> {
+    let line = text.lines().nth(line)?;
+    // Positions count UTF-16 code units
+    let mut units = 0;
+    let cursor = line
+        .char_indices()
+        .find(|(_, c)| {
+            units += c.len_utf16();
+            units > character
+        })
+        .map_or(line.len(), |(index, _)| index);
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    let start = line[..cursor].rfind(|c| !is_ident(c)).map_or(0, |index| index + 1);
+    let end = line[cursor..]
+        .find(|c| !is_ident(c))
+        .map_or(line.len(), |index| cursor + index);
+    let word = &line[start..end];
+    if word.is_empty() {
+        return None;
+    }
+
+    let simple_name = uri.rsplit('/').next()?.strip_suffix(".java")?;
+    let package = text
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("package "))
+        .map(|package| package.trim_end_matches(';').trim().replace('.', "/"));
+    let class_name = match package {
+        Some(package) if !package.is_empty() => format!("{}/{}", package, simple_name),
+        _ => simple_name.to_string(),
+    };
+    let method = if word == simple_name { "<init>" } else { word };
+    Some((class_name, method.to_string()))
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let path = path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut uri = "file://".to_string();
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~$".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut index = 0;
+    while index < encoded.len() {
+        let decoded = (encoded[index] == b'%')
+            .then(|| std::str::from_utf8(encoded.get(index + 1..index + 3)?).ok())
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match decoded {
+            Some(byte) => {
+                bytes.push(byte);
+                index += 3;
+            }
+            None => {
+                bytes.push(encoded[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
@@ -1,89 +1,685 @@
 use std::{
+    collections::{HashMap, HashSet},
     fs,
+    io::{self, IsTerminal, Write},
     path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::SystemTime,
 };
 
 use anyhow::{Context, Result};
-use cafebabe::{attributes::AttributeData, bytecode::Opcode, parse_class};
-use clap::{Parser, ValueEnum};
-use log::{debug, error, LevelFilter};
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use cafebabe::parse_class;
+use clap::{Parser, Subcommand, ValueEnum};
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use serde::Serialize;
-use simple_logger::SimpleLogger;
-use walkdir::WalkDir;
+use tracing::{debug_span, error};
+
+use java_method_finder::{
+    analysis, color, context, disasm, elements, graph, members, modules, pattern, plugin, policy, profile, query,
+    relocation, versions,
+};
+
+use crate::{
+    analysis::{Analyzer, Deadline, FoundCall, Visibility},
+    duplicates::{sha256_hex, Duplicates},
+    edges::Edges,
+    elements::ElementFilter,
+    graph::EntryPoint,
+    inventory::{ClassInvocations, Inventory},
+    metrics::Metrics,
+    modules::{ModuleIndex, ModuleInfo, Modules},
+    notify::NotifyArgs,
+    otlp::OtlpArgs,
+    owners::{CodeOwners, OwnerResult},
+    pattern::Pattern,
+    plugin::{Plugin, PluginMatcher},
+    policy::{Rule, Severity},
+    profile::Phase,
+    query::Query,
+    relocation::Relocation,
+    resolve::Resolution,
+    result_cache::{CachedCall, Entry, Outcome, ResultCache},
+    scan::{ClassSource, ErrorKind, OriginSummary, ScanArgs, ScanError, ScanSummary, Scanner},
+    scan_manifest::ScanManifest,
+    stats::{Hotspots, Stats},
+    trend::Trend,
+    versions::Versions,
+};
+
+mod aliases;
+mod allocations;
+mod annotated;
+mod api;
+mod bench;
+mod blame;
+mod changes;
+mod config;
+mod cron;
+mod curl;
+mod cycles;
+mod deps;
+mod dupes;
+mod duplicates;
+mod edges;
+mod gate;
+mod generate;
+mod ignore_comments;
+mod image;
+mod impact;
+mod inventory;
+mod jar_index;
+mod lsp;
+mod manifest;
+mod metrics;
+mod notify;
+mod otlp;
+mod owners;
+#[cfg(feature = "parquet")]
+mod parquet;
+mod remote;
+mod resolve;
+mod result_cache;
+mod sarif;
+mod scan;
+mod scan_manifest;
+mod serve;
+mod stats;
+mod tar;
+mod tree;
+mod trend;
+mod verify;
 
 #[derive(Parser, Debug)]
-#[command(name = "jmf", about = "Java Method Finder", long_about = None)]
+#[command(
+    name = "jmf",
+    about = "Java Method Finder",
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    args: Args,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Find calls to a method, a pattern or a package (the default when no subcommand is given)
+    Find(Args),
+    /// Evaluate the rules of a policy file in a single scan
+    Check(CheckArgs),
+    /// Run a policy as a CI gate: a Markdown summary of the calls against a baseline, for a pull request
+    /// comment, and the exit code of the policy on the new ones
+    Gate(gate::GateArgs),
+    /// Find calls matching a query over call and caller conditions
+    Query(QueryArgs),
+    /// Run queries saved in jmf.toml by name
+    Run(RunArgs),
+    /// Print the caller -> callee edges of the call graph
+    Graph(GraphArgs),
+    /// Separate the found calls into those reachable from entry points and those in dead code
+    Reachable(ReachableArgs),
+    /// Compute fan-in/fan-out per method and per class from the call graph
+    Metrics(MetricsArgs),
+    /// Export the dependencies between the scanned packages or classes as a matrix
+    Deps(deps::DepsArgs),
+    /// Report the dependency cycles between the scanned packages or classes, with the references making them
+    Cycles(cycles::CyclesArgs),
+    /// List every distinct external class#method the scanned classes invoke, with call counts
+    #[command(visible_alias = "index")]
+    Inventory(InventoryArgs),
+    /// Report classes found in more than one scan root with differing contents
+    Duplicates(DuplicatesArgs),
+    /// Report clusters of methods with identical bodies across the scanned classes
+    Dupes(dupes::DupesArgs),
+    /// List how many scanned classes target each class file version
+    Versions(VersionsArgs),
+    /// List the JPMS modules declared in the scan set, with their requires, exports and opens
+    Modules(ModulesArgs),
+    /// Answer editor "find references" requests from the compiled classes, as a language server on stdio
+    Lsp(lsp::LspArgs),
+    /// Chart the calls per target over time from a directory of saved JSON results
+    Trend(trend::TrendArgs),
+    /// Run the scheduled scans of jmf.toml as a service, persisting their results and answering a JSON API
+    Serve(serve::ServeArgs),
+    /// Run a scan several times and report its throughput, parse and match times and allocations
+    Bench(bench::BenchArgs),
+    /// List the classes of a jar or directory with their fields and methods, descriptors and access flags
+    Api(api::ApiArgs),
+    /// Diff the public API of two versions of a library and find the calls the upgrade would break
+    Impact(impact::ImpactArgs),
+    /// Write policy files from what a library marks as not for use
+    Policy(PolicyArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct PolicyArgs {
+    #[command(subcommand)]
+    command: PolicyCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum PolicyCommand {
+    /// Print a policy file denying the deprecated methods or the internal packages of a library
+    Generate(generate::GenerateArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct ReachableArgs {
+    #[command(flatten)]
+    args: Args,
+
+    #[arg(short = 'e', long = "entrypoints", value_delimiter = ',', default_value = "main")]
+    entrypoints: Vec<String>,
+
+    /// Print a shortest call chain from an entry point to each reachable caller
+    #[arg(long = "why")]
+    why: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct CheckArgs {
+    /// Policy file with the rules to evaluate
+    policy: PathBuf,
+
+    #[command(flatten)]
+    filters: FilterArgs,
+
+    /// Directory the caller source files are found in, for `// jmf:ignore` comments (repeatable)
+    #[arg(long = "source-root", default_value = "src/main/java")]
+    source_roots: Vec<PathBuf>,
+
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    #[command(flatten)]
+    notify: NotifyArgs,
+
+    #[command(flatten)]
+    otlp: OtlpArgs,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    /// Fail when warning-severity rules have matches too, not only error-severity ones
+    #[arg(long = "warnings-as-errors")]
+    warnings_as_errors: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct QueryArgs {
+    /// Query, e.g. `calls("com.lib.Api#run") and not caller(annotated("Deprecated"))`
+    query: String,
+
+    #[command(flatten)]
+    filters: FilterArgs,
+
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    #[command(flatten)]
+    notify: NotifyArgs,
+
+    #[command(flatten)]
+    otlp: OtlpArgs,
+
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct RunArgs {
+    /// Names of the saved queries to run
+    #[arg(required_unless_present = "all", conflicts_with = "all")]
+    names: Vec<String>,
+
+    /// Run every saved query
+    #[arg(long = "all")]
+    all: bool,
+
+    /// Configuration file with the saved queries
+    #[arg(long = "config", default_value = "jmf.toml")]
+    config: PathBuf,
+
+    #[command(flatten)]
+    filters: FilterArgs,
+
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    #[command(flatten)]
+    notify: NotifyArgs,
+
+    #[command(flatten)]
+    otlp: OtlpArgs,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    /// Fail when warning-severity rules have matches too, not only error-severity ones
+    #[arg(long = "warnings-as-errors")]
+    warnings_as_errors: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct GraphArgs {
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    /// Only keep calls into methods declared by scanned classes
+    #[arg(long = "internal")]
+    internal: bool,
+
+    #[arg(short = 'f', long = "format", value_enum, default_value_t = GraphFormatter::Txt)]
+    format: GraphFormatter,
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum GraphFormatter {
+    #[value(name = "txt")]
+    Txt,
+    #[value(name = "dot")]
+    Dot,
+    #[value(name = "json")]
+    Json,
+    /// A Parquet table of the edges, with the `parquet` feature
+    #[value(name = "parquet")]
+    Parquet,
+}
+
+#[derive(clap::Args, Debug)]
+struct MetricsArgs {
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    #[arg(short = 'f', long = "format", value_enum, default_value_t = MetricsFormatter::Csv)]
+    format: MetricsFormatter,
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum MetricsFormatter {
+    #[value(name = "csv")]
+    Csv,
+    #[value(name = "json")]
+    Json,
+}
+
+#[derive(clap::Args, Debug)]
+struct InventoryArgs {
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    /// Only list classes starting with this prefix (repeatable)
+    #[arg(long = "external-prefix")]
+    external_prefixes: Vec<String>,
+
+    #[arg(short = 'f', long = "format", value_enum, default_value_t = InventoryFormatter::Txt)]
+    format: InventoryFormatter,
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum InventoryFormatter {
+    #[value(name = "txt")]
+    Txt,
+    #[value(name = "csv")]
+    Csv,
+    #[value(name = "json")]
+    Json,
+}
+
+#[derive(clap::Args, Debug)]
+struct DuplicatesArgs {
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    /// Exit with code 1 when duplicate classes are found
+    #[arg(long = "fail")]
+    fail: bool,
+
+    #[arg(short = 'f', long = "format", value_enum, default_value_t = ReportFormatter::Txt)]
+    format: ReportFormatter,
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum ReportFormatter {
+    #[value(name = "txt")]
+    Txt,
+    #[value(name = "json")]
+    Json,
+}
+
+#[derive(clap::Args, Debug)]
+struct VersionsArgs {
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    #[arg(short = 'f', long = "format", value_enum, default_value_t = ReportFormatter::Txt)]
+    format: ReportFormatter,
+}
+
+#[derive(clap::Args, Debug)]
+struct ModulesArgs {
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    #[arg(short = 'f', long = "format", value_enum, default_value_t = ReportFormatter::Txt)]
+    format: ReportFormatter,
+}
+
+#[derive(clap::Args, Debug)]
 struct Args {
-    #[arg(short = 'c', long = "class")]
-    target_class: String,
+    /// Alias of the `--alias-file` to search for, instead of `-c` and `-m`
+    #[arg(
+        value_name = "ALIAS",
+        requires = "alias_file",
+        conflicts_with_all = ["target_class", "target_method", "package", "pattern", "policy", "query"]
+    )]
+    alias: Option<String>,
+
+    /// TOML file mapping aliases to targets, shared by a team auditing the same APIs
+    #[arg(long = "alias-file", value_name = "PATH")]
+    alias_file: Option<PathBuf>,
+
+    #[arg(
+        short = 'c',
+        long = "class",
+        required_unless_present_any = ["alias", "pattern", "policy", "query", "plugins"]
+    )]
+    target_class: Option<String>,
+
+    #[arg(
+        short = 'm',
+        long = "method",
+        required_unless_present_any = ["alias", "pattern", "policy", "package", "query", "plugins", "kind"]
+    )]
+    target_method: Option<String>,
 
-    #[arg(short = 'm', long = "method")]
-    target_method: String,
+    /// Treat `-c` as a package and find calls into any of its classes, grouped by callee
+    #[arg(long = "package", requires = "target_class", conflicts_with = "target_method")]
+    package: bool,
 
-    #[arg(short = 's', long = "scan", default_value = "./target/classes")]
-    scan_folder: String,
+    #[arg(short = 'p', long = "pattern", conflicts_with_all = ["target_class", "target_method"])]
+    pattern: Option<String>,
 
-    #[arg(short = 'f', long = "format", value_enum, default_value_t = Formatter::Txt)]
+    /// Policy file with rules to evaluate in a single scan
+    #[arg(long = "policy", conflicts_with_all = ["target_class", "target_method", "pattern"])]
+    policy: Option<PathBuf>,
+
+    /// Query combining call and caller conditions, e.g. `calls("com.lib.Api#run") and caller(package ~ "com.app.*")`
+    #[arg(
+        short = 'q',
+        long = "query",
+        conflicts_with_all = ["target_class", "target_method", "pattern", "policy"]
+    )]
+    query: Option<String>,
+
+    #[command(flatten)]
+    filters: FilterArgs,
+
+    /// Dynamic library, or `.wasm` module with the wasm-plugins feature, adding a matcher or an output
+    /// format (repeatable)
+    #[arg(long = "plugin")]
+    plugins: Vec<PathBuf>,
+
+    /// Print the declarations of the target method in the scanned classes before the calls
+    #[arg(long = "resolve", requires = "target_method")]
+    resolve: bool,
+
+    /// Print a disassembly of the instructions around each call
+    #[arg(long = "explain")]
+    explain: bool,
+
+    /// Also report dynamic Groovy calls to a method of the target's name
+    #[arg(long = "groovy", requires = "target_method")]
+    groovy: bool,
+
+    /// Directory the caller source files are found in, relative to their package (repeatable)
+    #[arg(long = "source-root", default_value = "src/main/java")]
+    source_roots: Vec<PathBuf>,
+
+    /// Annotate each call with the commit and author that last changed its line, from `git blame` on the
+    /// caller source file under `--source-root`
+    #[arg(long = "blame")]
+    blame: bool,
+
+    /// CODEOWNERS file assigning the caller source files under `--source-root` to teams; calls are marked
+    /// with their owners and grouped by them unless `--group-by` says otherwise
+    #[arg(long = "owners", value_name = "CODEOWNERS")]
+    owners: Option<PathBuf>,
+
+    /// Label of the `--format badge` badge
+    #[arg(long = "badge-label", default_value = DEFAULT_BADGE_LABEL)]
+    badge_label: String,
+
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    #[command(flatten)]
+    notify: NotifyArgs,
+
+    #[command(flatten)]
+    otlp: OtlpArgs,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    /// Fail when warning-severity rules have matches too, not only error-severity ones
+    #[arg(long = "warnings-as-errors")]
+    warnings_as_errors: bool,
+
+    /// Search only the Nth overload of `-m` declared in the scanned classes, from 1 in descriptor order;
+    /// without N, the overloads are listed and one is read from stdin
+    #[arg(
+        long = "select-overload",
+        value_name = "N",
+        num_args = 0..=1,
+        requires = "target_method",
+        conflicts_with = "package"
+    )]
+    select_overload: Option<Option<usize>>,
+
+    /// Preset review of the calls of `-c` and `-m`: `thread-safety` reports, as warnings, those outside
+    /// `synchronized` code that no `--lock-call` precedes in their method
+    #[arg(
+        long = "preset",
+        value_enum,
+        requires = "target_method",
+        conflicts_with_all = ["package", "pattern", "policy", "query", "select_overload"]
+    )]
+    preset: Option<Preset>,
+
+    /// Lock-acquiring method guarding the calls of `--preset thread-safety`, as a `class#method` glob
+    /// (repeatable)
+    #[arg(
+        long = "lock-call",
+        value_name = "CLASS#METHOD",
+        requires = "preset",
+        default_values = ["java.util.concurrent.locks.*#lock*", "java.util.concurrent.locks.*#tryLock"]
+    )]
+    lock_calls: Vec<String>,
+
+    /// What `-c` names: the class of a method to find the calls of, or an annotation to find the uses of
+    #[arg(long = "kind", value_enum, default_value_t = TargetKind::Call)]
+    kind: TargetKind,
+
+    /// Conditions on the element values of the `--kind annotation`, e.g. `value ~ "/admin/.*"`
+    #[arg(long = "where", value_name = "CONDITIONS")]
+    filter: Option<String>,
+}
+
+/// Which classes match and which of their calls are reported, shared by the commands that search.
+#[derive(clap::Args, Debug, Default)]
+struct FilterArgs {
+    /// Also match classes relocated by shading, as `original->relocated` package prefixes (repeatable)
+    #[arg(long = "relocation", value_parser = Relocation::parse)]
+    relocations: Vec<Relocation>,
+
+    /// Report the calls of callers annotated with this annotation, by dotted or simple name, as suppressed
+    /// rather than failing; a `value` naming rule ids restricts it to those rules (repeatable)
+    #[arg(long = "suppress-annotation", value_name = "ANNOTATION")]
+    suppress_annotations: Vec<String>,
+
+    /// Only report calls from caller methods of these visibilities, comma-separated; a public method of a
+    /// package-private class is `package`
+    #[arg(long = "caller-visibility", value_enum, value_delimiter = ',')]
+    caller_visibilities: Vec<Visibility>,
+}
+
+/// How the calls are reported, shared by the commands that search.
+#[derive(clap::Args, Debug)]
+struct OutputArgs {
+    /// Output format; repeated with `--output`, one scan writes a report in each
+    #[arg(short = 'f', long = "format", value_enum, default_value = "txt")]
+    formats: Vec<Formatter>,
+
+    /// File the report of the matching `--format` is written to, the first `--output` receiving the first
+    /// format; a format without one is printed
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    outputs: Vec<PathBuf>,
+
+    /// Format of the report being printed, one of `formats`
+    #[arg(skip = Formatter::Txt)]
     format: Formatter,
 
-    #[arg(short = 'v', long = "verbose")]
-    verbose: bool,
+    /// Print aggregated counts instead of the individual calls
+    #[arg(long = "stats")]
+    stats: bool,
+
+    /// Print the N caller classes and methods with the most calls instead of the individual calls
+    #[arg(long = "top", value_name = "N", conflicts_with = "stats")]
+    top: Option<usize>,
+
+    /// Group the calls by the scan root or jar they were found in
+    #[arg(long = "group-by", value_enum, conflicts_with_all = ["stats", "top"])]
+    group_by: Option<GroupBy>,
+
+    /// Print one NUL-terminated `class<TAB>method<TAB>line` record per call, for `xargs -0`
+    #[arg(long = "print0", conflicts_with_all = ["formats", "outputs", "stats", "top", "group_by"])]
+    print0: bool,
 }
 
-#[derive(Debug, Copy, Clone, ValueEnum)]
+impl Default for OutputArgs {
+    fn default() -> Self {
+        Self {
+            formats: vec![Formatter::Txt],
+            outputs: Vec::new(),
+            format: Formatter::Txt,
+            stats: false,
+            top: None,
+            group_by: None,
+            print0: false,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, ValueEnum)]
 enum Formatter {
     #[value(name = "txt")]
     Txt,
     #[value(name = "json")]
     Json,
+    #[value(name = "sarif")]
+    Sarif,
+    #[value(name = "tree")]
+    Tree,
+    /// Printed by the formatter of a `--plugin`
+    #[value(name = "plugin")]
+    Plugin,
+    /// shields.io endpoint JSON with the number of calls, for a badge generated by CI
+    #[value(name = "badge")]
+    Badge,
+    /// A Parquet table of the calls, with the `parquet` feature
+    #[value(name = "parquet")]
+    Parquet,
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum Preset {
+    /// Calls outside `synchronized` code and not preceded by a lock acquisition, for a manual review
+    #[value(name = "thread-safety")]
+    ThreadSafety,
 }
 
-#[derive(Debug, Serialize, Clone)]
-struct FoundCall {
-    class_name: String,
-    method_name: String,
-    line_number: u16,
+#[derive(Debug, Copy, Clone, PartialEq, ValueEnum)]
+enum TargetKind {
+    #[value(name = "call")]
+    Call,
+    #[value(name = "annotation")]
+    Annotation,
 }
 
-impl FoundCall {
-    pub fn new(class_name: String, method_name: String, line_number: u16) -> Self {
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum GroupBy {
+    /// The scan root or jar, labeled by its `--root-alias` or jar name
+    #[value(name = "origin")]
+    Origin,
+    /// The owning team from `--owners`
+    #[value(name = "owner")]
+    Owner,
+}
+
+/// A shields.io endpoint badge counting the calls, colored from green for none to red for a hundred or
+/// more.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Badge {
+    schema_version: u8,
+    label: String,
+    message: String,
+    color: &'static str,
+}
+
+impl Badge {
+    pub fn new(label: &str, count: usize) -> Self {
+        let color = match count {
+            0 => "brightgreen",
+            1..=9 => "yellow",
+            10..=99 => "orange",
+            _ => "red",
+        };
         Self {
-            class_name,
-            method_name,
-            line_number,
+            schema_version: 1,
+            label: label.to_string(),
+            message: count.to_string(),
+            color,
         }
     }
-}
 
-impl std::fmt::Display for FoundCall {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}#{} (L{})",
-            self.class_name.replace('/', "."),
-            self.method_name,
-            self.line_number
-        )
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self).unwrap()
     }
 }
 
 #[derive(Debug, Serialize)]
-struct SearchResult {
+struct SearchResult<'a> {
     target: String,
-    calls: Vec<FoundCall>,
+    /// Declarations of the target from `--resolve`
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    resolved: &'a [Resolution],
+    calls: &'a [FoundCall],
+    #[serde(flatten)]
+    summary: ScanSummary,
 }
 
-impl SearchResult {
-    pub fn new(target_class: &str, target_method: &str, calls: Vec<FoundCall>) -> Self {
+impl<'a> SearchResult<'a> {
+    pub fn new(target: String, calls: &'a [FoundCall]) -> Self {
         Self {
-            target: format!("{}#{}", target_class, target_method),
+            target,
+            resolved: &[],
             calls,
+            summary: ScanSummary::default(),
         }
     }
 
     pub fn to_text(&self) -> String {
-        let mut output = vec![self.target.clone()];
+        let mut output = vec![color::bold(&self.target)];
         if self.calls.is_empty() {
             output.push("No results".to_string());
         } else {
@@ -97,181 +693,1906 @@ impl SearchResult {
     }
 }
 
-struct MethodFinder {
-    args: Args,
+#[derive(Debug, Serialize)]
+struct CalleeGroup<'a> {
+    target: String,
+    calls: Vec<&'a FoundCall>,
 }
 
-fn init_logger(verbose: bool) {
-    SimpleLogger::new()
-        .with_level(if verbose { LevelFilter::Debug } else { LevelFilter::Info })
-        .without_timestamps()
-        .with_module_level("simple_logger", LevelFilter::Error)
-        .init()
-        .unwrap();
+/// Calls into a package, grouped by the invoked class and method.
+#[derive(Debug, Serialize)]
+struct PackageResult<'a> {
+    target: String,
+    targets: Vec<CalleeGroup<'a>>,
+    #[serde(flatten)]
+    summary: ScanSummary,
 }
 
-impl MethodFinder {
-    fn new(args: Args) -> Self {
-        init_logger(args.verbose);
-        MethodFinder { args }
-    }
-
-    fn log_debug(&self, message: &str) {
-        if self.args.verbose {
-            debug!("{}", message);
+impl<'a> PackageResult<'a> {
+    pub fn new(target: String, calls: &'a [FoundCall]) -> Self {
+        let mut targets: Vec<CalleeGroup> = Vec::new();
+        for call in calls {
+            let callee = call.callee.clone().unwrap_or_default();
+            match targets.iter_mut().find(|group| group.target == callee) {
+                Some(group) => group.calls.push(call),
+                None => targets.push(CalleeGroup {
+                    target: callee,
+                    calls: vec![call],
+                }),
+            }
+        }
+        targets.sort_by(|a, b| a.target.cmp(&b.target));
+        Self {
+            target,
+            targets,
+            summary: ScanSummary::default(),
         }
     }
 
-    fn scan_folder(&self) -> Result<Vec<FoundCall>> {
-        let scan_path = PathBuf::from(&self.args.scan_folder);
-        if !scan_path.exists() {
-            return Err(anyhow::anyhow!("Scan folder does not exist: {}", scan_path.display()));
+    pub fn to_text(&self) -> String {
+        let mut output = vec![color::bold(&self.target)];
+        if self.targets.is_empty() {
+            output.push("No results".to_string());
         }
-        if !scan_path.is_dir() {
-            return Err(anyhow::anyhow!("Scan path is not a directory: {}", scan_path.display()));
+        for group in &self.targets {
+            output.push(color::bold(&group.target));
+            output.extend(group.calls.iter().map(|call| format!(" - {}", call)));
         }
-        self.log_debug(&format!("Start scanning folder: {}", scan_path.display()));
+        output.join("\n")
+    }
 
-        let class_files: Vec<_> = WalkDir::new(&scan_path)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file() && e.path().extension().map_or(false, |ext| ext == "class"))
-            .map(|e| e.path().to_owned())
-            .collect();
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
 
-        let results: Vec<FoundCall> = class_files
-            .par_iter()
-            .filter_map(|path| {
-                self.log_debug(&format!("Analyzing class file: {}", path.display()));
-                match self.analyze_class(path) {
-                    Ok(found_calls) => {
-                        if !found_calls.is_empty() {
-                            Some(found_calls)
-                        } else {
-                            None
-                        }
-                    }
-                    Err(e) => {
-                        error!("Error analyzing {}: {:#}", path.display(), e);
-                        None
-                    }
-                }
+#[derive(Debug, Serialize)]
+struct OriginGroup<'a> {
+    origin: String,
+    count: usize,
+    calls: Vec<&'a FoundCall>,
+}
+
+/// Calls grouped by the scan root or jar they were found in, the busiest first.
+#[derive(Debug, Serialize)]
+struct OriginResult<'a> {
+    target: String,
+    origins: Vec<OriginGroup<'a>>,
+    #[serde(flatten)]
+    summary: ScanSummary,
+}
+
+impl<'a> OriginResult<'a> {
+    /// Groups `calls` under `origins`, so that scan roots without calls are listed too.
+    pub fn new(target: String, origins: Vec<String>, calls: &'a [FoundCall]) -> Self {
+        let mut origins: Vec<OriginGroup> = origins
+            .into_iter()
+            .map(|origin| OriginGroup {
+                origin,
+                count: 0,
+                calls: Vec::new(),
             })
-            .flatten()
             .collect();
+        for call in calls {
+            let origin = call.origin.clone().unwrap_or_default();
+            match origins.iter_mut().find(|group| group.origin == origin) {
+                Some(group) => group.calls.push(call),
+                None => origins.push(OriginGroup {
+                    origin,
+                    count: 0,
+                    calls: vec![call],
+                }),
+            }
+        }
+        for group in &mut origins {
+            group.count = group.calls.len();
+        }
+        origins.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.origin.cmp(&b.origin)));
+        Self {
+            target,
+            origins,
+            summary: ScanSummary::default(),
+        }
+    }
 
-        Ok(results)
+    pub fn to_text(&self) -> String {
+        let mut output = vec![color::bold(&self.target)];
+        for group in &self.origins {
+            let hits = if group.count == 1 { "hit" } else { "hits" };
+            output.push(color::bold(format!("{}: {} {}", group.origin, group.count, hits)));
+            // The heading already names the origin
+            output.extend(group.calls.iter().map(|call| {
+                format!(
+                    " - {}",
+                    FoundCall {
+                        origin: None,
+                        ..(*call).clone()
+                    }
+                )
+            }));
+        }
+        output.join("\n")
     }
 
-    fn analyze_class(&self, path: &Path) -> Result<Vec<FoundCall>> {
-        let mut found_calls = Vec::new();
-        let class_data = fs::read(path).with_context(|| format!("Failed to read class file {}", path.display()))?;
-        let class_file =
-            parse_class(&class_data).with_context(|| format!("Failed to parse class file {}", path.display()))?;
-        let target_class = self.args.target_class.replace('.', "/");
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
 
-        let class_name = class_file.this_class;
+#[derive(Debug, Serialize)]
+struct ReachableCall<'a> {
+    #[serde(flatten)]
+    call: &'a FoundCall,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    path: &'a [String],
+}
 
-        // Skip if this is the target class
-        if class_name == target_class {
-            return Ok(found_calls);
-        }
+/// The reachable calls, apart from the paths to them so that they can be reported as calls.
+#[derive(Debug)]
+struct ReachableCalls {
+    calls: Vec<FoundCall>,
+    paths: Vec<Vec<String>>,
+}
 
-        self.log_debug(&format!("Visiting class: {}", class_name));
+impl ReachableCalls {
+    fn iter(&self) -> impl Iterator<Item = ReachableCall<'_>> {
+        self.calls
+            .iter()
+            .zip(&self.paths)
+            .map(|(call, path)| ReachableCall { call, path })
+    }
+}
 
-        for method in &class_file.methods {
-            let method_name = &method.name;
+impl Serialize for ReachableCalls {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
 
-            let code_attr = method
-                .attributes
-                .iter()
-                .find_map(|attr| {
-                    if let AttributeData::Code(code) = &attr.data {
-                        Some(code)
-                    } else {
-                        None
-                    }
-                })
-                .with_context(|| format!("Code attribute not found in method {}#{}", class_name, method_name))?;
+#[derive(Debug, Serialize)]
+struct ReachabilityResult {
+    target: String,
+    reachable: ReachableCalls,
+    unreachable: Vec<FoundCall>,
+    #[serde(flatten)]
+    summary: ScanSummary,
+}
 
-            let line_number_table = code_attr
-                .attributes
-                .iter()
-                .find_map(|attr| {
-                    if let AttributeData::LineNumberTable(lnt) = &attr.data {
-                        Some(lnt)
-                    } else {
-                        None
-                    }
-                })
-                .with_context(|| format!("LineNumberTable not found in method {}#{}", class_name, method_name))?;
-
-            if let Some(bytecode) = &code_attr.bytecode {
-                self.log_debug(&format!("Visiting method: {}#{}", class_name, method_name));
-
-                for opcode in &bytecode.opcodes {
-                    if let Opcode::Invokespecial(member_ref)
-                    | Opcode::Invokestatic(member_ref)
-                    | Opcode::Invokevirtual(member_ref) = &opcode.1
-                    {
-                        let offset = &opcode.0;
-
-                        let index = line_number_table.partition_point(|entry| entry.start_pc <= *offset as u16);
-
-                        if index > 0
-                            && member_ref.class_name == target_class
-                            && member_ref.name_and_type.name == self.args.target_method
-                        {
-                            let line_number = line_number_table[index - 1].line_number;
-                            let found_call =
-                                FoundCall::new(class_name.to_string(), method_name.to_string(), line_number);
-                            found_calls.push(found_call.clone());
-                            self.log_debug(&format!("Found method call: {}", found_call));
-                        }
-                    }
-                }
-            } else {
-                anyhow::bail!("No bytecode found in method {}#{}", class_name, method_name);
+impl ReachabilityResult {
+    pub fn to_text(&self) -> String {
+        let mut output = vec![color::bold(&self.target), "Reachable:".to_string()];
+        if self.reachable.calls.is_empty() {
+            output.push("No results".to_string());
+        }
+        for reachable in self.reachable.iter() {
+            output.push(format!(" - {}", reachable.call));
+            if !reachable.path.is_empty() {
+                output.push(format!("   via {}", reachable.path.join(" -> ")));
             }
         }
+        output.push("Unreachable:".to_string());
+        if self.unreachable.is_empty() {
+            output.push("No results".to_string());
+        }
+        output.extend(self.unreachable.iter().map(|call| format!(" - {}", call)));
+        output.join("\n")
+    }
 
-        Ok(found_calls)
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
     }
+}
 
-    fn print_results(&self, results: &[FoundCall]) {
-        let search_result = SearchResult::new(
-            &self.args.target_class,
-            &self.args.target_method,
-            results
-                .iter()
-                .map(|r| FoundCall::new(r.class_name.clone(), r.method_name.clone(), r.line_number))
-                .collect(),
-        );
-        if results.is_empty() {
-            println!("{}#{}", self.args.target_class, self.args.target_method);
-            println!("No results");
-        } else {
-            match self.args.format {
-                Formatter::Txt => {
-                    println!("{}", search_result.to_text());
-                }
-                Formatter::Json => {
-                    println!("{}", search_result.to_json());
-                }
-            }
+#[derive(Debug, Serialize)]
+struct RuleResult<'a> {
+    id: String,
+    target: String,
+    severity: Severity,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    help_url: Option<String>,
+    calls: Vec<RuleCall<'a>>,
+}
+
+impl<'a> RuleResult<'a> {
+    /// The calls that are not suppressed.
+    fn failing_calls(&self) -> impl Iterator<Item = &RuleCall<'a>> {
+        self.calls.iter().filter(|call| call.call.suppressed.is_none())
+    }
+
+    fn heading(&self) -> String {
+        let severity = paint_severity(self.severity, self.severity);
+        let heading = match &self.message {
+            Some(message) => format!("[{}] {}: {}", severity, color::bold(&self.target), message),
+            None => format!("[{}] {}", severity, color::bold(&self.target)),
+        };
+        match &self.help_url {
+            Some(help_url) => format!("{} (see {})", heading, help_url),
+            None => heading,
         }
     }
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
-    let finder = MethodFinder::new(args);
+fn paint_severity(severity: Severity, text: impl std::fmt::Display) -> String {
+    match severity {
+        Severity::Error => color::red(text),
+        Severity::Warn => color::yellow(text),
+        Severity::Info => color::dim(text),
+    }
+}
+
+/// A count and its noun, as `1 class` or `3 classes`.
+fn plural(count: usize, noun: &str) -> String {
+    match (count, noun.ends_with('s')) {
+        (1, _) => format!("{} {}", count, noun),
+        (_, true) => format!("{} {}es", count, noun),
+        (_, false) => format!("{} {}s", count, noun),
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct RuleCall<'a> {
+    #[serde(flatten)]
+    call: &'a FoundCall,
+    /// Invoked method, for rules spanning many callees
+    #[serde(skip_serializing_if = "Option::is_none")]
+    callee: Option<String>,
+}
 
-    match finder.scan_folder() {
-        Ok(results) => {
-            finder.print_results(&results);
-            Ok(())
+impl std::fmt::Display for RuleCall<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.call)?;
+        if let Some(callee) = &self.callee {
+            write!(f, " -> {}", callee)?;
         }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct PolicyResult<'a> {
+    rules: Vec<RuleResult<'a>>,
+    #[serde(flatten)]
+    summary: ScanSummary,
+}
+
+impl<'a> PolicyResult<'a> {
+    pub fn new(rules: &[Rule], calls: &'a [FoundCall]) -> Self {
+        Self {
+            rules: rules
+                .iter()
+                .enumerate()
+                .map(|(index, rule)| RuleResult {
+                    id: rule.id(),
+                    target: rule.target(),
+                    severity: rule.severity,
+                    message: rule.message.clone(),
+                    help_url: rule.help_url.clone(),
+                    calls: calls
+                        .iter()
+                        .filter(|call| call.rule == index)
+                        .map(|call| RuleCall {
+                            call,
+                            callee: if rule.per_edge() { call.callee.clone() } else { None },
+                        })
+                        .collect(),
+                })
+                .collect(),
+            summary: ScanSummary::default(),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut output = Vec::new();
+        for rule in &self.rules {
+            output.push(rule.heading());
+            if rule.calls.is_empty() {
+                output.push("No results".to_string());
+            } else {
+                output.extend(rule.calls.iter().map(|call| format!(" - {}", call)));
+            }
+        }
+        output.join("\n")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+
+    pub fn to_tree(&self) -> String {
+        let mut output = Vec::new();
+        for rule in &self.rules {
+            output.push(rule.heading());
+            output.push(tree::to_tree(rule.calls.iter().map(|call| call.call)));
+        }
+        output.join("\n")
+    }
+
+    /// Calls per severity across the caller classes, as `3 errors, 12 warnings across 9 classes`, followed
+    /// by the calls of each rule.
+    pub fn summary_text(&self) -> String {
+        let count = |severity: Severity| {
+            self.rules
+                .iter()
+                .filter(|rule| rule.severity == severity)
+                .map(|rule| rule.failing_calls().count())
+                .sum::<usize>()
+        };
+        let classes = |calls: &mut dyn Iterator<Item = &RuleCall<'a>>| {
+            calls.map(|call| &call.call.class_name).collect::<HashSet<_>>().len()
+        };
+        // Severities without calls are not colored, so that the others stand out
+        let counted = |severity: Severity, noun: &str| match count(severity) {
+            0 => plural(0, noun),
+            calls => paint_severity(severity, plural(calls, noun)),
+        };
+        let mut counts = vec![counted(Severity::Error, "error"), counted(Severity::Warn, "warning")];
+        if count(Severity::Info) > 0 {
+            counts.push(counted(Severity::Info, "info"));
+        }
+        let mut output = vec![format!(
+            "{} across {}",
+            counts.join(", "),
+            plural(
+                classes(&mut self.rules.iter().flat_map(|rule| rule.failing_calls())),
+                "class"
+            )
+        )];
+        output.extend(self.rules.iter().map(|rule| {
+            let suppressed = rule.calls.len() - rule.failing_calls().count();
+            format!(
+                " - [{}] {}: {} in {}{}",
+                paint_severity(rule.severity, rule.severity),
+                rule.target,
+                plural(rule.failing_calls().count(), "call"),
+                plural(classes(&mut rule.failing_calls()), "class"),
+                match suppressed {
+                    0 => String::new(),
+                    _ => format!(", {} suppressed", suppressed),
+                }
+            )
+        }));
+        output.join("\n")
+    }
+
+    /// The run fails when any error-severity rule has matches, or any warning-severity one with
+    /// `--warnings-as-errors`.
+    pub fn exit_code(&self, warnings_as_errors: bool) -> ExitCode {
+        let fails = |severity: Severity| match severity {
+            Severity::Error => true,
+            Severity::Warn => warnings_as_errors,
+            Severity::Info => false,
+        };
+        if self
+            .rules
+            .iter()
+            .any(|rule| fails(rule.severity) && rule.failing_calls().next().is_some())
+        {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+/// Counts allocations for `jmf bench`.
+#[global_allocator]
+static ALLOCATOR: allocations::CountingAllocator = allocations::CountingAllocator;
+
+const DEFAULT_BADGE_LABEL: &str = "legacy API calls";
+
+/// Exit code of a scan stopped by Ctrl-C, as for a process killed by SIGINT.
+const INTERRUPTED_EXIT_CODE: u8 = 130;
+
+/// What the analysis of a class file gives.
+type ClassOutcome = Result<Vec<FoundCall>, ScanError>;
+
+/// Class files with the outcome of each.
+type Outcomes<'a> = Vec<(&'a ClassSource, ClassOutcome)>;
+
+struct MethodFinder {
+    args: Args,
+    analyzer: Analyzer,
+    /// Whether each rule is reported with its severity, as for policy files
+    policy: bool,
+    /// Plugin printing the results for `--format plugin`
+    formatter: Option<Arc<dyn Plugin>>,
+    scanner: Scanner,
+    /// Compiler-generated members of the target classes, by dotted class name and method name, with the
+    /// scan root of the class
+    generated_members: Mutex<HashMap<(String, String), (PathBuf, String)>>,
+    /// Locations of the archive entries named `.class` that are not class files, for the `--result-cache`
+    not_class_files: Mutex<HashSet<String>>,
+    /// Rules of the `--owners` file
+    owners: Option<CodeOwners>,
+}
+
+impl MethodFinder {
+    fn new(mut args: Args) -> Result<Self> {
+        let alias = match (&args.alias, &args.alias_file) {
+            (Some(alias), Some(alias_file)) => Some(aliases::resolve(alias_file, alias)?),
+            _ => None,
+        };
+        // Package aliases are reported as `--package` is
+        if let Some(rule) = &alias {
+            args.package = matches!(rule.target, policy::Target::Package(_));
+        }
+        let rules = match (&args.policy, &args.pattern, &args.query, &args.target_class) {
+            _ if alias.is_some() => alias.into_iter().collect(),
+            (Some(policy), _, _, _) => policy::load(policy)?,
+            (None, Some(pattern), _, _) => vec![Rule::pattern(Pattern::parse(pattern)?)],
+            (None, None, Some(query), _) => vec![Rule::query(Query::parse(query)?)],
+            (None, None, None, Some(target_class)) if args.preset.is_some() => vec![preset_rule(
+                args.preset.unwrap(),
+                target_class,
+                args.target_method.as_deref().unwrap_or_default(),
+                &args.lock_calls,
+            )?],
+            (None, None, None, Some(target_class)) if args.package => vec![Rule::package(target_class)],
+            (None, None, None, Some(target_class)) => vec![Rule::method(
+                target_class,
+                args.target_method.as_deref().unwrap_or_default(),
+            )],
+            // Only plugins search
+            (None, None, None, None) => Vec::new(),
+        };
+        // Presets flag their calls as policy rules do
+        let policy = args.policy.is_some() || args.preset.is_some();
+        Self::build(args, rules, policy)
+    }
+
+    /// Evaluates the given rules the way policy files are, e.g. saved queries.
+    fn with_rules(args: Args, rules: Vec<Rule>) -> Result<Self> {
+        Self::build(args, rules, true)
+    }
+
+    fn build(mut args: Args, mut rules: Vec<Rule>, policy: bool) -> Result<Self> {
+        let mut scanner = Scanner::new(args.scan.clone())?;
+        if let Some(selection) = args.select_overload {
+            select_overload(&scanner, &args.scan, &mut rules, selection)?;
+        }
+        args.otlp.check()?;
+        let owners = args.owners.as_deref().map(CodeOwners::load).transpose()?;
+        if owners.is_some()
+            && args.output.group_by.is_none()
+            && !args.output.stats
+            && args.output.top.is_none()
+            && !args.output.print0
+        {
+            args.output.group_by = Some(GroupBy::Owner);
+        }
+        anyhow::ensure!(
+            owners.is_some() || !matches!(args.output.group_by, Some(GroupBy::Owner)),
+            "--group-by owner needs --owners"
+        );
+        let plugins = args
+            .plugins
+            .iter()
+            .map(|path| plugin::load(path))
+            .collect::<Result<Vec<_>>>()?;
+        rules.extend(
+            plugins
+                .iter()
+                .filter(|plugin| plugin.has_matcher())
+                .map(|plugin| Rule::plugin(PluginMatcher(plugin.clone()))),
+        );
+        // Archives can only be skipped for targets that the calls to them name, rather than relocated
+        // classes or dynamic Groovy calls
+        if !rules.is_empty() && args.filters.relocations.is_empty() && !args.groovy {
+            let references: Option<Vec<String>> = rules
+                .iter()
+                .map(|rule| match &rule.target {
+                    policy::Target::Method { class, .. } => Some(class.replace('.', "/")),
+                    policy::Target::Package(package) => Some(package.replace('.', "/")),
+                    _ => None,
+                })
+                .collect();
+            if let Some(references) = references {
+                scanner.skip_archives_without(references);
+            }
+        }
+        let formatter = plugins.into_iter().find(|plugin| plugin.has_formatter());
+        if let Some(output) = args.output.outputs.get(args.output.formats.len()) {
+            anyhow::bail!("--output {} has no --format", output.display());
+        }
+        anyhow::ensure!(
+            args.output.formats.len() <= args.output.outputs.len() + 1,
+            "Only one --format can be printed; give the others an --output"
+        );
+        args.output.format = args.output.formats.first().copied().unwrap_or(Formatter::Txt);
+        if args.output.formats.contains(&Formatter::Parquet) {
+            ensure_parquet(args.output.formats.len() > args.output.outputs.len())?;
+        }
+        if args.output.formats.contains(&Formatter::Plugin) && formatter.is_none() {
+            anyhow::bail!("--format plugin needs a --plugin with a formatter");
+        }
+        let mut analyzer = Analyzer::new(rules);
+        analyzer.relocations = args.filters.relocations.clone();
+        analyzer.file_timeout = args.scan.file_timeout;
+        analyzer.explain = args.explain;
+        analyzer.groovy = args.groovy;
+        analyzer.suppress_annotations = args.filters.suppress_annotations.clone();
+        analyzer.caller_visibilities = args.filters.caller_visibilities.clone();
+        Ok(MethodFinder {
+            args,
+            analyzer,
+            policy,
+            formatter,
+            scanner,
+            generated_members: Mutex::new(HashMap::new()),
+            not_class_files: Mutex::new(HashSet::new()),
+            owners,
+        })
+    }
+
+    fn target(&self) -> String {
+        self.analyzer
+            .rules
+            .iter()
+            .map(Rule::target)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn log_debug(&self, message: &str) {
+        self.scanner.log_debug(message);
+    }
+
+    /// Found calls over all class files, along with the class files that could not be analyzed.
+    fn scan_folder(&self) -> Result<(Vec<FoundCall>, ScanSummary)> {
+        let class_files = self.scanner.class_files()?;
+        let mut modules = ModuleIndex::default();
+        for (source, module) in read_modules(&class_files) {
+            modules.insert(&source.artifact(), &source.name, module);
+        }
+        if !modules.is_empty() {
+            for source in &class_files {
+                modules.insert_class(&source.artifact(), &source.name);
+            }
+        }
+        let cache = self.result_cache();
+        // Entries of the archives in the cache, and the keys of the others
+        let mut cached: HashMap<&Path, Entry> = HashMap::new();
+        let mut uncached: HashMap<&Path, String> = HashMap::new();
+        if let Some(cache) = &cache {
+            for (archive, _) in class_files.iter().filter_map(ClassSource::archive_entry) {
+                if cached.contains_key(archive) || uncached.contains_key(archive) {
+                    continue;
+                }
+                let key = cache.key(archive)?;
+                match cache.load(&key) {
+                    Some(entry) => {
+                        self.log_debug(&format!("Using cached results of archive: {}", archive.display()));
+                        let mut generated_members = self.generated_members.lock().unwrap();
+                        for (class, method, note) in &entry.generated_members {
+                            generated_members
+                                .insert((class.clone(), method.clone()), (archive.to_path_buf(), note.clone()));
+                        }
+                        cached.insert(archive, entry);
+                    }
+                    None => {
+                        uncached.insert(archive, key);
+                    }
+                }
+            }
+        }
+        let copies = self.identical_copies(&class_files);
+        let failed = AtomicBool::new(false);
+        let analyzed: Vec<(usize, ClassOutcome)> = class_files
+            .par_iter()
+            .enumerate()
+            .filter(|(index, _)| !copies.contains_key(index))
+            .filter(|_| !scan::interrupted() && !failed.load(Ordering::Relaxed))
+            .map(|(index, source)| {
+                let cached = source
+                    .archive_entry()
+                    .and_then(|(archive, name)| cached.get(archive)?.classes.get(&name));
+                if let Some(outcome) = cached {
+                    return (index, self.cached_outcome(source, outcome));
+                }
+                self.log_debug(&format!("Analyzing class file: {}", source.location()));
+                let outcome = self.analyze_class(source).inspect_err(|e| {
+                    error!("Error analyzing {}: {}", source.location(), e.message);
+                    if self.args.scan.fail_fast {
+                        failed.store(true, Ordering::Relaxed);
+                    }
+                });
+                (index, outcome)
+            })
+            .collect();
+        let (mut outcomes, copy_positions) = self.with_copies(&class_files, analyzed, &copies);
+        if let Some((_, Err(error))) = outcomes
+            .iter()
+            .find(|(_, outcome)| outcome.is_err() && self.args.scan.fail_fast)
+        {
+            anyhow::bail!(
+                "Stopped at a class file that could not be analyzed (--fail-fast): {}",
+                error
+            );
+        }
+
+        if let Some(cache) = &cache {
+            self.store_results(cache, &class_files, &outcomes, &uncached)?;
+        }
+
+        let by_origin = self.origin_summaries(&outcomes);
+        let classes = outcomes.len();
+        // The calls of a class are reported once, with the origins of its copies
+        for &(copy, original) in &copy_positions {
+            let label = self.args.scan.source_label(outcomes[copy].0);
+            if let Ok(calls) = &mut outcomes[original].1 {
+                for call in calls {
+                    if call.origin.as_ref() != Some(&label) && !call.also_in.contains(&label) {
+                        call.also_in.push(label.clone());
+                    }
+                }
+            }
+        }
+        let copy_positions: HashSet<usize> = copy_positions.into_iter().map(|(copy, _)| copy).collect();
+        if let Some(path) = &self.args.scan.emit_manifest {
+            let sources: Vec<(&ClassSource, Option<ErrorKind>)> = outcomes
+                .iter()
+                .map(|(source, outcome)| (*source, outcome.as_ref().err().map(|error| error.kind)))
+                .collect();
+            ScanManifest::new(&sources)?.write(path)?;
+        }
+        let mut results = Vec::new();
+        let mut errors = Vec::new();
+        for (position, (source, outcome)) in outcomes.into_iter().enumerate() {
+            if copy_positions.contains(&position) {
+                continue;
+            }
+            match outcome {
+                Ok(mut found_calls) => {
+                    if !modules.is_empty() {
+                        let module = modules.module_of(&source.artifact(), &source.name);
+                        for call in &mut found_calls {
+                            call.module = module.map(str::to_string);
+                            call.illegal_access = call
+                                .callee
+                                .as_deref()
+                                .and_then(|callee| callee.split_once('#'))
+                                .and_then(|(class, _)| modules.illegal_access(module, class));
+                        }
+                    }
+                    results.extend(found_calls)
+                }
+                Err(error) => errors.push(error),
+            }
+        }
+        let generated_members = self.generated_members.lock().unwrap();
+        for call in &mut results {
+            if let policy::Target::Method { class, method } = &self.analyzer.rules[call.rule].target {
+                call.generated = generated_members
+                    .get(&(class.clone(), method.clone()))
+                    .map(|(_, note)| note.clone());
+            }
+        }
+        ignore_comments::apply(&mut results, &self.args.source_roots);
+        if self.args.blame {
+            blame::annotate(&mut results, &self.args.source_roots)?;
+        }
+        if let Some(owners) = &self.owners {
+            owners.assign(&mut results, &self.args.source_roots);
+        }
+        Ok((
+            results,
+            ScanSummary {
+                errors,
+                skipped: self.scanner.skipped(),
+                not_class_files: self.scanner.not_class_files(),
+                interrupted: scan::interrupted(),
+                by_origin,
+                classes,
+                identical: copy_positions.len(),
+            },
+        ))
+    }
+
+    /// The class files identical to one under another scan root, such as those of `target/classes` and of
+    /// the jar assembled from them, by index, with the index of the first copy, which is the one analyzed.
+    /// Only the class files whose path is found under several scan roots are read to be compared.
+    fn identical_copies(&self, class_files: &[ClassSource]) -> HashMap<usize, usize> {
+        let mut by_name: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (index, source) in class_files.iter().enumerate() {
+            by_name.entry(&source.name).or_default().push(index);
+        }
+        let mut candidates: Vec<usize> = by_name
+            .into_values()
+            .filter(|indices| {
+                indices
+                    .iter()
+                    .any(|&index| class_files[index].origin != class_files[indices[0]].origin)
+            })
+            .flatten()
+            .collect();
+        candidates.sort_unstable();
+        let hashes: Vec<(usize, Option<String>)> = candidates
+            .par_iter()
+            .map(|&index| (index, class_files[index].read().ok().map(|data| sha256_hex(&data))))
+            .collect();
+        let mut first: HashMap<(&str, String), usize> = HashMap::new();
+        let mut copies = HashMap::new();
+        for (index, hash) in hashes {
+            let Some(hash) = hash else {
+                continue;
+            };
+            let source = &class_files[index];
+            match first.get(&(source.name.as_str(), hash.clone())) {
+                Some(&original) if class_files[original].origin != source.origin => {
+                    copies.insert(index, original);
+                }
+                Some(_) => {}
+                None => {
+                    first.insert((&source.name, hash), index);
+                }
+            }
+        }
+        if !copies.is_empty() {
+            self.log_debug(&format!(
+                "Analyzing once {} class files identical to ones under other scan roots",
+                copies.len()
+            ));
+        }
+        copies
+    }
+
+    /// The outcomes of the class files in scan order, the copies of analyzed ones taking theirs, with the
+    /// positions of the copies and of their originals among them.
+    fn with_copies<'a>(
+        &self,
+        class_files: &'a [ClassSource],
+        analyzed: Vec<(usize, ClassOutcome)>,
+        copies: &HashMap<usize, usize>,
+    ) -> (Outcomes<'a>, Vec<(usize, usize)>) {
+        let mut analyzed = analyzed.into_iter().peekable();
+        let mut positions: HashMap<usize, usize> = HashMap::new();
+        let mut outcomes: Outcomes = Vec::new();
+        let mut copy_positions = Vec::new();
+        for (index, source) in class_files.iter().enumerate() {
+            if let Some(original) = copies.get(&index) {
+                // An original that the scan stopped before leaves its copies out too
+                let Some(&position) = positions.get(original) else {
+                    continue;
+                };
+                let outcome = match &outcomes[position].1 {
+                    Ok(calls) => {
+                        let mut calls = calls.clone();
+                        self.label_origin(source, &mut calls);
+                        Ok(calls)
+                    }
+                    Err(error) => {
+                        let mut error = error.clone();
+                        error.label(source, &self.args.scan);
+                        Err(error)
+                    }
+                };
+                copy_positions.push((outcomes.len(), position));
+                outcomes.push((source, outcome));
+            } else if let Some((_, outcome)) = analyzed.next_if(|(analyzed, _)| *analyzed == index) {
+                positions.insert(index, outcomes.len());
+                outcomes.push((source, outcome));
+            }
+        }
+        (outcomes, copy_positions)
+    }
+
+    /// Stores the entries of the archives that were not in the `--result-cache`, unless the scan stopped
+    /// before analyzing all their class files or a class file timed out, which another run may not.
+    fn store_results(
+        &self,
+        cache: &ResultCache,
+        class_files: &[ClassSource],
+        outcomes: &[(&ClassSource, Result<Vec<FoundCall>, ScanError>)],
+        uncached: &HashMap<&Path, String>,
+    ) -> Result<()> {
+        let mut entries: HashMap<&Path, Entry> = HashMap::new();
+        let not_class_files = self.not_class_files.lock().unwrap();
+        for (source, outcome) in outcomes {
+            let Some((archive, name)) = source.archive_entry() else {
+                continue;
+            };
+            if !uncached.contains_key(archive) {
+                continue;
+            }
+            let outcome = match outcome {
+                Ok(_) if not_class_files.contains(&source.location()) => Outcome::NotClassFile,
+                Ok(calls) => Outcome::Calls(calls.iter().map(CachedCall::from).collect()),
+                Err(error) => Outcome::Error(error.clone()),
+            };
+            entries.entry(archive).or_default().classes.insert(name, outcome);
+        }
+        let generated_members = self.generated_members.lock().unwrap();
+        for (archive, mut entry) in entries {
+            let sources = class_files
+                .iter()
+                .filter(|source| source.archive_entry().is_some_and(|(root, _)| root == archive))
+                .count();
+            let timed_out = entry
+                .classes
+                .values()
+                .any(|outcome| matches!(outcome, Outcome::Error(error) if error.kind == ErrorKind::Timeout));
+            if entry.classes.len() < sources || timed_out {
+                continue;
+            }
+            entry.generated_members = generated_members
+                .iter()
+                .filter(|(_, (origin, _))| origin == archive)
+                .map(|((class, method), (_, note))| (class.clone(), method.clone(), note.clone()))
+                .collect();
+            cache.store(&uncached[archive], &entry)?;
+            self.log_debug(&format!("Cached results of archive: {}", archive.display()));
+        }
+        Ok(())
+    }
+
+    /// Classes, matches and errors per scan root or jar, in scan order, when there are several.
+    fn origin_summaries(&self, outcomes: &[(&ClassSource, Result<Vec<FoundCall>, ScanError>)]) -> Vec<OriginSummary> {
+        let mut summaries: Vec<OriginSummary> = Vec::new();
+        let mut indices: HashMap<String, usize> = HashMap::new();
+        for (source, outcome) in outcomes {
+            let origin = self.args.scan.source_label(source);
+            let index = *indices.entry(origin.clone()).or_insert_with(|| {
+                summaries.push(OriginSummary::new(origin));
+                summaries.len() - 1
+            });
+            let summary = &mut summaries[index];
+            summary.classes += 1;
+            match outcome {
+                Ok(calls) => summary.matches += calls.len(),
+                Err(_) => summary.errors += 1,
+            }
+        }
+        if summaries.len() < 2 {
+            summaries.clear();
+        }
+        summaries
+    }
+
+    fn find_reachable(&self, entrypoints: &[String], why: bool) -> Result<ReachabilityResult> {
+        let (found_calls, summary) = self.scan_folder()?;
+        let graph = self.scanner.call_graph()?;
+        let specs: Vec<EntryPoint> = entrypoints.iter().map(|spec| EntryPoint::parse(spec)).collect();
+        let entry_points = graph.entry_points(&specs);
+        self.log_debug(&format!("Resolved {} entry point methods", entry_points.len()));
+        let reachable = graph.reachable(&entry_points);
+
+        // Overloads are told apart by their descriptors
+        let caller = |call: &FoundCall| {
+            (
+                call.class_name.to_string(),
+                call.method_name.to_string(),
+                call.method_descriptor.to_string(),
+            )
+        };
+        let (reachable_calls, unreachable): (Vec<_>, Vec<_>) = found_calls
+            .into_iter()
+            .partition(|call| reachable.contains_key(&caller(call)));
+        let paths = reachable_calls
+            .iter()
+            .map(|call| {
+                if why {
+                    graph::path_to(&reachable, &caller(call))
+                        .iter()
+                        .map(|(class_name, method_name, _)| format!("{}#{}", class_name.replace('/', "."), method_name))
+                        .collect()
+                } else {
+                    Vec::new()
+                }
+            })
+            .collect();
+        let reachable = ReachableCalls {
+            calls: reachable_calls,
+            paths,
+        };
+        Ok(ReachabilityResult {
+            target: self.target(),
+            reachable,
+            unreachable,
+            summary,
+        })
+    }
+
+    fn print_reachable(&self, out: &mut dyn Write, result: &ReachabilityResult) -> Result<ExitCode> {
+        match self.args.output.format {
+            Formatter::Txt | Formatter::Tree => {
+                writeln!(out, "{}", result.to_text())?;
+                if !result.summary.is_empty() {
+                    writeln!(out, "{}", result.summary.to_text())?;
+                }
+            }
+            Formatter::Json => writeln!(out, "{}", result.to_json())?,
+            Formatter::Badge => writeln!(
+                out,
+                "{}",
+                Badge::new(&self.args.badge_label, result.reachable.calls.len()).to_json()
+            )?,
+            Formatter::Parquet => {
+                #[cfg(feature = "parquet")]
+                out.write_all(&parquet::Table::calls(&self.analyzer.rules, &result.reachable.calls).to_parquet())?;
+            }
+            Formatter::Sarif => writeln!(
+                out,
+                "{}",
+                sarif::to_sarif(&self.analyzer.rules, &result.reachable.calls, &result.summary)
+            )?,
+            Formatter::Plugin => return self.print_with_plugin(out, result),
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+
+    /// Renders a report per `--format`, with `args.output.format` set to each in turn, into its `--output` file or
+    /// onto standard output. The exit code is that of the first report that does not succeed, if any.
+    fn write_reports(&mut self, render: impl Fn(&Self, &mut dyn Write) -> Result<ExitCode>) -> Result<ExitCode> {
+        let mut exit_code = ExitCode::SUCCESS;
+        for (index, format) in self.args.output.formats.clone().into_iter().enumerate() {
+            self.args.output.format = format;
+            let report_exit_code = match self.args.output.outputs.get(index).cloned() {
+                Some(path) => {
+                    let mut report = Vec::new();
+                    let report_exit_code = color::plain(|| render(self, &mut report))?;
+                    fs::write(&path, report).with_context(|| format!("Failed to write {}", path.display()))?;
+                    report_exit_code
+                }
+                None => render(self, &mut io::stdout().lock())?,
+            };
+            if exit_code == ExitCode::SUCCESS {
+                exit_code = report_exit_code;
+            }
+        }
+        Ok(exit_code)
+    }
+
+    /// Prints `result` through the formatter of the `--format plugin` plugin.
+    fn print_with_plugin(&self, out: &mut dyn Write, result: impl Serialize) -> Result<ExitCode> {
+        let Some(formatter) = &self.formatter else {
+            return Ok(ExitCode::FAILURE);
+        };
+        let output = serde_json::to_value(result)
+            .map_err(anyhow::Error::from)
+            .and_then(|result| formatter.format(&result));
+        match output {
+            Ok(output) => {
+                writeln!(out, "{}", output)?;
+                Ok(ExitCode::SUCCESS)
+            }
+            Err(e) => {
+                error!("{:#}", e);
+                Ok(ExitCode::FAILURE)
+            }
+        }
+    }
+
+    fn analyze_class(&self, source: &ClassSource) -> Result<Vec<FoundCall>, ScanError> {
+        let _span = debug_span!("class", location = %source.location()).entered();
+        let deadline = Deadline::new(self.args.scan.file_timeout);
+        let class_data = source
+            .read()
+            .map_err(|e| ScanError::new(source, &e, None, &self.args.scan))?;
+        // The version from the header tells unsupported class files from malformed ones
+        let class_version = scan::header_version(&class_data);
+        self.analyze_class_data(source, &class_data, &deadline)
+            .map_err(|e| ScanError::new(source, &e, class_version, &self.args.scan))
+    }
+
+    fn analyze_class_data(
+        &self,
+        source: &ClassSource,
+        class_data: &[u8],
+        deadline: &Deadline,
+    ) -> Result<Vec<FoundCall>> {
+        if !self.scanner.is_class_file(class_data, source) {
+            if self.args.scan.result_cache.is_some() && source.archive_entry().is_some() {
+                self.not_class_files.lock().unwrap().insert(source.location());
+            }
+            return Ok(Vec::new());
+        }
+        deadline.check()?;
+        let (class_file, lenient_error) = self
+            .scanner
+            .parse_leniently(class_data)
+            // The location is reported with the error, as `--paths` displays it
+            .context("Failed to parse class file")?;
+        deadline.check()?;
+        let _timer = profile::start(Phase::Match);
+        if !self.scanner.accepts(&class_file) {
+            self.log_debug(&format!(
+                "Skipping class file version {} or from an unchanged source file: {}",
+                class_file.major_version,
+                source.location()
+            ));
+            return Ok(Vec::new());
+        }
+        let class_name = class_file.this_class.replace('/', ".");
+        if self
+            .analyzer
+            .rules
+            .iter()
+            .any(|rule| matches!(&rule.target, policy::Target::Method { class, .. } if *class == class_name))
+        {
+            let members = members::generated_members(&class_file);
+            let mut generated_members = self.generated_members.lock().unwrap();
+            for (method, note) in members {
+                generated_members.insert((class_name.clone(), method), (source.origin.clone(), note));
+            }
+        }
+        // Parsed without bytecode under `--lenient`, the class has no calls to search
+        if let Some(e) = lenient_error {
+            return Err(
+                anyhow::Error::new(e).context("Failed to parse class file, read without its bytecode under --lenient")
+            );
+        }
+        let mut calls = self.analyzer.analyze(&class_file, deadline)?;
+        self.label_origin(source, &mut calls);
+        Ok(calls)
+    }
+
+    fn label_origin(&self, source: &ClassSource, calls: &mut [FoundCall]) {
+        // Bundles and the jars of manifest class paths are labeled even when scanned from a single root
+        if self.args.scan.scan_roots.len() > 1
+            || matches!(self.args.output.group_by, Some(GroupBy::Origin))
+            || source.bundle.is_some()
+            || self.args.scan.follow_manifest_classpath
+        {
+            let origin = self.args.scan.source_label(source);
+            for call in calls {
+                call.origin = Some(origin.clone());
+            }
+        }
+    }
+
+    /// The `--result-cache` for the rules and the options changing what analysis finds. Plugins may match
+    /// differently without any of them changing, and `--changed-since` depends on the working tree, so
+    /// neither is cached.
+    fn result_cache(&self) -> Option<ResultCache> {
+        let directory = self.args.scan.result_cache.as_deref()?;
+        let scan = &self.args.scan;
+        let analyzer = &self.analyzer;
+        if scan.changed_since.is_some()
+            || analyzer
+                .rules
+                .iter()
+                .any(|rule| matches!(rule.target, policy::Target::Plugin(_)))
+        {
+            return None;
+        }
+        let query = format!(
+            "{:?}\0{:?}\0{:?}\0{}\0{}\0{:?}\0{:?}\0{:?}\0{:?}\0{}",
+            analyzer.rules,
+            analyzer.relocations,
+            analyzer.file_timeout,
+            analyzer.explain,
+            analyzer.groovy,
+            analyzer.suppress_annotations,
+            analyzer.caller_visibilities,
+            scan.min_class_version,
+            scan.max_class_version,
+            scan.listing_options()
+        );
+        Some(ResultCache::new(directory, &query))
+    }
+
+    /// The outcome of a class file stored in the `--result-cache`, labeled for this scan.
+    fn cached_outcome(&self, source: &ClassSource, outcome: &Outcome) -> Result<Vec<FoundCall>, ScanError> {
+        match outcome {
+            Outcome::Calls(calls) => {
+                let mut calls: Vec<FoundCall> = calls.iter().map(CachedCall::to_call).collect();
+                self.label_origin(source, &mut calls);
+                Ok(calls)
+            }
+            Outcome::Error(error) => {
+                let mut error = error.clone();
+                error.label(source, &self.args.scan);
+                Err(error)
+            }
+            Outcome::NotClassFile => {
+                self.scanner.skip_not_class_file(source);
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    fn print_stats(&self, out: &mut dyn Write, results: &[FoundCall], summary: &ScanSummary) -> Result<()> {
+        let (key, stats, text) = match self.args.output.top {
+            Some(top) => {
+                let hotspots = Hotspots::new(results, top);
+                ("top", serde_json::json!(hotspots), hotspots.to_text(&self.target()))
+            }
+            None => {
+                let stats = Stats::new(results);
+                ("stats", serde_json::json!(stats), stats.to_text(&self.target()))
+            }
+        };
+        match self.args.output.format {
+            Formatter::Txt | Formatter::Tree => writeln!(out, "{}", text)?,
+            Formatter::Badge | Formatter::Parquet => unreachable!("print_found prints badges and Parquet"),
+            Formatter::Json => {
+                let mut output = serde_json::json!({ "target": self.target(), key: stats });
+                if let (Some(output), serde_json::Value::Object(summary)) =
+                    (output.as_object_mut(), serde_json::json!(summary))
+                {
+                    output.extend(summary);
+                }
+                writeln!(out, "{}", serde_json::to_string_pretty(&output).unwrap())?;
+            }
+            Formatter::Sarif => {
+                let mut log = sarif::log(&self.analyzer.rules, results, summary);
+                log["runs"][0]["properties"] = serde_json::json!({ key: stats });
+                writeln!(out, "{}", serde_json::to_string_pretty(&log).unwrap())?;
+            }
+            Formatter::Plugin => {
+                self.print_with_plugin(out, serde_json::json!({ "target": self.target(), key: stats }))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn print_results(
+        &self,
+        out: &mut dyn Write,
+        results: &[FoundCall],
+        summary: &ScanSummary,
+        resolutions: &[Resolution],
+    ) -> Result<ExitCode> {
+        if self.args.output.print0 {
+            print0(results);
+            // Standard output only holds records, so the summary goes to standard error
+            if !summary.is_empty() {
+                eprintln!("{}", summary.to_text());
+            }
+            return Ok(if summary.interrupted {
+                ExitCode::from(INTERRUPTED_EXIT_CODE)
+            } else if self.policy {
+                PolicyResult::new(&self.analyzer.rules, results).exit_code(self.args.warnings_as_errors)
+            } else {
+                ExitCode::SUCCESS
+            });
+        }
+        if matches!(self.args.output.format, Formatter::Txt | Formatter::Tree) {
+            for resolution in resolutions {
+                writeln!(out, "{}\n", resolution.to_text())?;
+            }
+        }
+        let exit_code = self.print_found(out, results, summary, resolutions)?;
+        if matches!(self.args.output.format, Formatter::Txt | Formatter::Tree) {
+            if !summary.by_origin.is_empty() {
+                writeln!(out, "\n{}", summary.origin_table())?;
+            }
+            if !summary.is_empty() {
+                writeln!(out, "{}", summary.to_text())?;
+            }
+        }
+        if summary.interrupted {
+            return Ok(ExitCode::from(INTERRUPTED_EXIT_CODE));
+        }
+        Ok(exit_code)
+    }
+
+    fn print_found(
+        &self,
+        out: &mut dyn Write,
+        results: &[FoundCall],
+        summary: &ScanSummary,
+        resolutions: &[Resolution],
+    ) -> Result<ExitCode> {
+        if matches!(self.args.output.format, Formatter::Badge | Formatter::Parquet) {
+            if matches!(self.args.output.format, Formatter::Badge) {
+                writeln!(out, "{}", Badge::new(&self.args.badge_label, results.len()).to_json())?;
+            }
+            #[cfg(feature = "parquet")]
+            if matches!(self.args.output.format, Formatter::Parquet) {
+                out.write_all(&parquet::Table::calls(&self.analyzer.rules, results).to_parquet())?;
+            }
+            return Ok(if self.policy {
+                PolicyResult::new(&self.analyzer.rules, results).exit_code(self.args.warnings_as_errors)
+            } else {
+                ExitCode::SUCCESS
+            });
+        }
+        if self.args.output.stats || self.args.output.top.is_some() {
+            self.print_stats(out, results, summary)?;
+            return Ok(if self.policy {
+                PolicyResult::new(&self.analyzer.rules, results).exit_code(self.args.warnings_as_errors)
+            } else {
+                ExitCode::SUCCESS
+            });
+        }
+        if matches!(self.args.output.group_by, Some(GroupBy::Owner))
+            && !matches!(self.args.output.format, Formatter::Sarif)
+        {
+            let mut owner_result = OwnerResult::new(self.target(), results);
+            owner_result.summary = summary.clone();
+            match self.args.output.format {
+                Formatter::Json => writeln!(out, "{}", owner_result.to_json())?,
+                Formatter::Plugin => {
+                    if self.print_with_plugin(out, &owner_result)? == ExitCode::FAILURE {
+                        return Ok(ExitCode::FAILURE);
+                    }
+                }
+                _ => writeln!(out, "{}", owner_result.to_text())?,
+            }
+            return Ok(if self.policy {
+                PolicyResult::new(&self.analyzer.rules, results).exit_code(self.args.warnings_as_errors)
+            } else {
+                ExitCode::SUCCESS
+            });
+        }
+        if self.args.output.group_by.is_some() && !matches!(self.args.output.format, Formatter::Sarif) {
+            let origins = self
+                .args
+                .scan
+                .scan_roots
+                .iter()
+                .map(|root| self.args.scan.origin_label(root));
+            let origin_result = OriginResult {
+                summary: summary.clone(),
+                ..OriginResult::new(self.target(), origins.collect(), results)
+            };
+            match self.args.output.format {
+                Formatter::Json => writeln!(out, "{}", origin_result.to_json())?,
+                Formatter::Plugin => {
+                    if self.print_with_plugin(out, &origin_result)? == ExitCode::FAILURE {
+                        return Ok(ExitCode::FAILURE);
+                    }
+                }
+                _ => writeln!(out, "{}", origin_result.to_text())?,
+            }
+            return Ok(if self.policy {
+                PolicyResult::new(&self.analyzer.rules, results).exit_code(self.args.warnings_as_errors)
+            } else {
+                ExitCode::SUCCESS
+            });
+        }
+        if self.policy {
+            let policy_result = PolicyResult {
+                summary: summary.clone(),
+                ..PolicyResult::new(&self.analyzer.rules, results)
+            };
+            match self.args.output.format {
+                Formatter::Txt => writeln!(out, "{}", policy_result.to_text())?,
+                Formatter::Json => writeln!(out, "{}", policy_result.to_json())?,
+                Formatter::Sarif => writeln!(out, "{}", sarif::to_sarif(&self.analyzer.rules, results, summary))?,
+                Formatter::Tree => writeln!(out, "{}", policy_result.to_tree())?,
+                Formatter::Badge | Formatter::Parquet => unreachable!("print_found prints badges and Parquet"),
+                Formatter::Plugin => {
+                    if self.print_with_plugin(out, &policy_result)? == ExitCode::FAILURE {
+                        return Ok(ExitCode::FAILURE);
+                    }
+                }
+            }
+            // Policy files end with a summary; the saved queries of `jmf run` do not
+            if self.args.policy.is_some() && matches!(self.args.output.format, Formatter::Txt | Formatter::Tree) {
+                writeln!(out, "\n{}", policy_result.summary_text())?;
+            }
+            return Ok(policy_result.exit_code(self.args.warnings_as_errors));
+        }
+        if self.args.package && !matches!(self.args.output.format, Formatter::Sarif) {
+            let package_result = PackageResult {
+                summary: summary.clone(),
+                ..PackageResult::new(self.target(), results)
+            };
+            match self.args.output.format {
+                Formatter::Json => writeln!(out, "{}", package_result.to_json())?,
+                Formatter::Plugin => return self.print_with_plugin(out, &package_result),
+                Formatter::Tree => writeln!(out, "{}\n{}", color::bold(self.target()), tree::to_tree(results))?,
+                _ => writeln!(out, "{}", package_result.to_text())?,
+            }
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        let mut search_result = SearchResult::new(self.target(), results);
+        search_result.summary = summary.clone();
+        search_result.resolved = resolutions;
+        if results.is_empty()
+            && summary.is_empty()
+            && resolutions.is_empty()
+            && !matches!(self.args.output.format, Formatter::Sarif | Formatter::Plugin)
+        {
+            writeln!(out, "{}", color::bold(self.target()))?;
+            writeln!(out, "No results")?;
+        } else {
+            match self.args.output.format {
+                Formatter::Txt => {
+                    writeln!(out, "{}", search_result.to_text())?;
+                }
+                Formatter::Json => {
+                    writeln!(out, "{}", search_result.to_json())?;
+                }
+                Formatter::Sarif => {
+                    writeln!(out, "{}", sarif::to_sarif(&self.analyzer.rules, results, summary))?;
+                }
+                Formatter::Tree => {
+                    writeln!(out, "{}\n{}", color::bold(self.target()), tree::to_tree(results))?;
+                }
+                Formatter::Badge | Formatter::Parquet => unreachable!("print_found prints badges and Parquet"),
+                Formatter::Plugin => return self.print_with_plugin(out, &search_result),
+            }
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+fn print0(results: &[FoundCall]) {
+    let mut stdout = io::stdout().lock();
+    for call in results {
+        let record = format!(
+            "{}\t{}\t{}\0",
+            call.class_name.replace('/', "."),
+            call.method_name,
+            call.line_number
+        );
+        if stdout.write_all(record.as_bytes()).is_err() {
+            // The reading end of the pipe is gone
+            return;
+        }
+    }
+}
+
+fn run_graph(args: GraphArgs) -> Result<()> {
+    let scanner = Scanner::new(args.scan)?;
+    let edges = Edges::new(&scanner.call_graph()?, args.internal);
+    let _timer = profile::start(Phase::Output);
+    match args.format {
+        GraphFormatter::Txt => println!("{}", edges.to_text()),
+        GraphFormatter::Dot => println!("{}", edges.to_dot()),
+        GraphFormatter::Json => println!("{}", edges.to_json()),
+        GraphFormatter::Parquet => {
+            ensure_parquet(true)?;
+            #[cfg(feature = "parquet")]
+            io::stdout().write_all(&parquet::Table::edges(&edges).to_parquet())?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks that jmf can write Parquet, and where: not onto a terminal when the file is `printed`.
+fn ensure_parquet(printed: bool) -> Result<()> {
+    anyhow::ensure!(
+        cfg!(feature = "parquet"),
+        "--format parquet needs jmf built with the parquet feature"
+    );
+    anyhow::ensure!(
+        !printed || !io::stdout().is_terminal(),
+        "--format parquet writes a binary file; give it an --output or redirect it"
+    );
+    Ok(())
+}
+
+fn run_metrics(args: MetricsArgs) -> Result<()> {
+    let scanner = Scanner::new(args.scan)?;
+    let metrics = Metrics::new(&scanner.call_graph()?);
+    let _timer = profile::start(Phase::Output);
+    match args.format {
+        MetricsFormatter::Csv => println!("{}", metrics.to_csv()),
+        MetricsFormatter::Json => println!("{}", metrics.to_json()),
+    }
+    Ok(())
+}
+
+fn run_inventory(args: InventoryArgs) -> Result<()> {
+    let scanner = Scanner::new(args.scan)?;
+    let classes = scanner.map_classes(|_, class_file| ClassInvocations::new(class_file))?;
+    let inventory = Inventory::new(classes, &args.external_prefixes);
+    let _timer = profile::start(Phase::Output);
+    match args.format {
+        InventoryFormatter::Txt => println!("{}", inventory.to_text()),
+        InventoryFormatter::Csv => println!("{}", inventory.to_csv()),
+        InventoryFormatter::Json => println!("{}", inventory.to_json()),
+    }
+    Ok(())
+}
+
+fn run_duplicates(args: DuplicatesArgs) -> Result<ExitCode> {
+    let scanner = Scanner::new(args.scan)?;
+    let duplicates = Duplicates::new(&scanner.class_files()?)?;
+    let _timer = profile::start(Phase::Output);
+    match args.format {
+        ReportFormatter::Txt => println!("{}", duplicates.to_text()),
+        ReportFormatter::Json => println!("{}", duplicates.to_json()),
+    }
+    if args.fail && !duplicates.classes.is_empty() {
+        Ok(ExitCode::FAILURE)
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+fn run_versions(args: VersionsArgs) -> Result<()> {
+    let scanner = Scanner::new(args.scan)?;
+    let versions = Versions::new(&scanner.map_classes(|_, class_file| class_file.major_version)?);
+    let _timer = profile::start(Phase::Output);
+    match args.format {
+        ReportFormatter::Txt => println!("{}", versions.to_text()),
+        ReportFormatter::Json => println!("{}", versions.to_json()),
+    }
+    Ok(())
+}
+
+fn run_modules(args: ModulesArgs) -> Result<()> {
+    let scanner = Scanner::new(args.scan)?;
+    let class_files = scanner.class_files()?;
+    let modules = Modules::new(
+        read_modules(&class_files)
+            .into_iter()
+            .map(|(_, module)| module)
+            .collect(),
+    );
+    let _timer = profile::start(Phase::Output);
+    match args.format {
+        ReportFormatter::Txt => println!("{}", modules.to_text()),
+        ReportFormatter::Json => println!("{}", modules.to_json()),
+    }
+    Ok(())
+}
+
+/// The modules the `module-info.class` files among the class files declare; unreadable ones are logged.
+fn read_modules(class_files: &[ClassSource]) -> Vec<(&ClassSource, ModuleInfo)> {
+    class_files
+        .iter()
+        .filter(|source| modules::is_module_info(&source.name))
+        .filter_map(|source| {
+            let read = || -> Result<Option<ModuleInfo>> {
+                let data = source.read()?;
+                let class_file = parse_class(&data).context("Failed to parse module descriptor")?;
+                Ok(ModuleInfo::from_class(&class_file, source.artifact()))
+            };
+            match read() {
+                Ok(module) => module.map(|module| (source, module)),
+                Err(e) => {
+                    error!("Error reading {}: {:#}", source.location(), e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Restricts the method rule to one overload of its target, the `selection`th or else the one picked on
+/// stdin; a method with a single descriptor is left as it is.
+fn select_overload(
+    scanner: &Scanner,
+    scan_args: &ScanArgs,
+    rules: &mut [Rule],
+    selection: Option<usize>,
+) -> Result<()> {
+    let Some(resolution) = resolve::resolve(scanner, scan_args, rules)?.into_iter().next() else {
+        return Ok(());
+    };
+    let mut overloads: Vec<&resolve::ResolvedMethod> = Vec::new();
+    for method in &resolution.methods {
+        if overloads
+            .iter()
+            .all(|overload| overload.descriptor != method.descriptor)
+        {
+            overloads.push(method);
+        }
+    }
+    if overloads.len() < 2 {
+        return Ok(());
+    }
+    let index = match selection {
+        Some(index) => index,
+        None => {
+            eprintln!("{} has {} overloads:", resolution.target, overloads.len());
+            for (index, overload) in overloads.iter().enumerate() {
+                eprintln!("  {}) {}  {}", index + 1, overload.declaration, overload.descriptor);
+            }
+            eprint!("Overload to search [1-{}]: ", overloads.len());
+            io::stderr().flush()?;
+            let mut line = String::new();
+            io::stdin().read_line(&mut line)?;
+            line.trim()
+                .parse()
+                .with_context(|| format!("Invalid overload: {}", line.trim()))?
+        }
+    };
+    anyhow::ensure!(
+        (1..=overloads.len()).contains(&index),
+        "{} has {} overloads, not {}",
+        resolution.target,
+        overloads.len(),
+        index
+    );
+    if let Some(rule) = rules
+        .iter_mut()
+        .find(|rule| matches!(rule.target, policy::Target::Method { .. }))
+    {
+        rule.descriptor = Some(overloads[index - 1].descriptor.clone());
+    }
+    Ok(())
+}
+
+impl Args {
+    /// Arguments searching `scan` with every option at its default and no target, which the commands that
+    /// take their rules from elsewhere fill in.
+    fn defaults(scan: ScanArgs) -> Self {
+        Args {
+            alias: None,
+            alias_file: None,
+            target_class: None,
+            target_method: None,
+            package: false,
+            pattern: None,
+            policy: None,
+            query: None,
+            filters: FilterArgs::default(),
+            plugins: Vec::new(),
+            resolve: false,
+            explain: false,
+            groovy: false,
+            source_roots: Vec::new(),
+            blame: false,
+            owners: None,
+            badge_label: DEFAULT_BADGE_LABEL.to_string(),
+            scan,
+            notify: NotifyArgs::default(),
+            otlp: OtlpArgs::default(),
+            output: OutputArgs::default(),
+            warnings_as_errors: false,
+            select_overload: None,
+            preset: None,
+            lock_calls: Vec::new(),
+            kind: TargetKind::Call,
+            filter: None,
+        }
+    }
+}
+
+impl From<CheckArgs> for Args {
+    fn from(check_args: CheckArgs) -> Self {
+        Args {
+            policy: Some(check_args.policy),
+            filters: check_args.filters,
+            source_roots: check_args.source_roots,
+            notify: check_args.notify,
+            otlp: check_args.otlp,
+            output: check_args.output,
+            warnings_as_errors: check_args.warnings_as_errors,
+            ..Args::defaults(check_args.scan)
+        }
+    }
+}
+
+impl From<QueryArgs> for Args {
+    fn from(query_args: QueryArgs) -> Self {
+        Args {
+            query: Some(query_args.query),
+            filters: query_args.filters,
+            notify: query_args.notify,
+            otlp: query_args.otlp,
+            output: query_args.output,
+            ..Args::defaults(query_args.scan)
+        }
+    }
+}
+
+fn run_saved(run_args: RunArgs) -> Result<ExitCode> {
+    let queries = config::load_queries(&run_args.config)?;
+    let rules = config::select(queries, &run_args.names, run_args.all, &run_args.config)?;
+    let args = Args {
+        filters: run_args.filters,
+        notify: run_args.notify,
+        otlp: run_args.otlp,
+        output: run_args.output,
+        warnings_as_errors: run_args.warnings_as_errors,
+        ..Args::defaults(run_args.scan)
+    };
+    search(MethodFinder::with_rules(args, rules)?)
+}
+
+/// The rule of a `--preset`, a query over the calls of the target that reports those to review.
+fn preset_rule(preset: Preset, target_class: &str, target_method: &str, lock_calls: &[String]) -> Result<Rule> {
+    match preset {
+        Preset::ThreadSafety => {
+            let mut query = format!("calls(\"{}#{}\") and not synchronized", target_class, target_method);
+            for lock_call in lock_calls {
+                query.push_str(&format!(" and not guarded(\"{}\")", lock_call));
+            }
+            Ok(Rule {
+                id: Some("thread-safety".to_string()),
+                severity: Severity::Warn,
+                message: Some("not synchronized nor after a lock call; review for thread safety".to_string()),
+                ..Rule::query(Query::parse(&query)?)
+            })
+        }
+    }
+}
+
+/// `jmf find`, which `--kind annotation` turns into a search for the uses of an annotation.
+fn find(args: Args) -> Result<ExitCode> {
+    if args.kind == TargetKind::Call {
+        // `--kind` has a default, so clap cannot tie `--where` to its value
+        anyhow::ensure!(
+            args.filter.is_none(),
+            "--where filters the uses of an annotation, so it needs --kind annotation"
+        );
+        anyhow::ensure!(
+            args.target_method.is_some() || args.target_class.is_none() || args.package,
+            "-m is required to find the calls of -c, unless --kind annotation searches for an annotation"
+        );
+        return search(MethodFinder::new(args)?);
+    }
+    let annotation = match &args.target_class {
+        Some(annotation)
+            if args.target_method.is_none()
+                && !args.package
+                && args.pattern.is_none()
+                && args.policy.is_none()
+                && args.query.is_none()
+                && args.plugins.is_empty() =>
+        {
+            annotation
+        }
+        _ => anyhow::bail!("--kind annotation searches for the annotation -c names, alone"),
+    };
+    let filter = args.filter.as_deref().map(ElementFilter::parse).transpose()?;
+    let format = match (&args.output.formats[..], &args.output.outputs[..]) {
+        ([format @ (Formatter::Txt | Formatter::Json)], []) => *format,
+        _ => anyhow::bail!("--kind annotation prints a single txt or json --format"),
+    };
+    let search = annotated::AnnotationSearch::run(args.scan, annotation, filter)?;
+    match format {
+        Formatter::Json => println!("{}", search.to_json()),
+        _ => println!("{}", search.to_text()),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn search(mut finder: MethodFinder) -> Result<ExitCode> {
+    let started = SystemTime::now();
+    let (results, summary) = finder.scan_folder()?;
+    finder.log_debug(&format!("Analyzed {} class files", summary.classes));
+    let resolutions = if finder.args.resolve {
+        resolve::resolve(&finder.scanner, &finder.args.scan, &finder.analyzer.rules)?
+    } else {
+        Vec::new()
+    };
+    let exit_code = profile::time(Phase::Output, || {
+        finder.write_reports(|finder, out| finder.print_results(out, &results, &summary, &resolutions))
+    })?;
+    finder.args.notify.notify(&finder.target(), &results)?;
+    finder
+        .args
+        .otlp
+        .export(&finder.analyzer.rules, &results, &summary, started);
+    Ok(exit_code)
+}
+
+impl Cli {
+    /// Whether the command polls `scan::interrupted`, reporting what it found so far on Ctrl-C; the
+    /// others keep the default handling, which stops them at once.
+    fn reports_interrupts(&self) -> bool {
+        matches!(
+            self.command,
+            None | Some(Command::Find(_))
+                | Some(Command::Check(_))
+                | Some(Command::Gate(_))
+                | Some(Command::Query(_))
+                | Some(Command::Run(_))
+                | Some(Command::Reachable(_))
+                | Some(Command::Bench(_))
+                | Some(Command::Serve(_))
+        )
+    }
+
+    /// Scan arguments of the command, which `jmf trend`, `jmf serve` and `jmf api` do without.
+    fn scan_args(&self) -> Option<&ScanArgs> {
+        Some(match &self.command {
+            Some(Command::Find(args)) => &args.scan,
+            Some(Command::Check(check_args)) => &check_args.scan,
+            Some(Command::Gate(gate_args)) => &gate_args.scan,
+            Some(Command::Query(query_args)) => &query_args.scan,
+            Some(Command::Run(run_args)) => &run_args.scan,
+            Some(Command::Graph(graph_args)) => &graph_args.scan,
+            Some(Command::Reachable(reachable_args)) => &reachable_args.args.scan,
+            Some(Command::Metrics(metrics_args)) => &metrics_args.scan,
+            Some(Command::Deps(deps_args)) => &deps_args.scan,
+            Some(Command::Cycles(cycles_args)) => &cycles_args.scan,
+            Some(Command::Inventory(inventory_args)) => &inventory_args.scan,
+            Some(Command::Duplicates(duplicates_args)) => &duplicates_args.scan,
+            Some(Command::Dupes(dupes_args)) => &dupes_args.scan,
+            Some(Command::Versions(versions_args)) => &versions_args.scan,
+            Some(Command::Modules(modules_args)) => &modules_args.scan,
+            Some(Command::Lsp(lsp_args)) => lsp_args.scan(),
+            Some(Command::Bench(bench_args)) => &bench_args.scan,
+            Some(Command::Impact(impact_args)) => &impact_args.scan,
+            Some(Command::Api(_)) | Some(Command::Policy(_)) | Some(Command::Trend(_)) | Some(Command::Serve(_)) => {
+                return None
+            }
+            None => &self.args.scan,
+        })
+    }
+
+    fn scan_args_mut(&mut self) -> Option<&mut ScanArgs> {
+        Some(match &mut self.command {
+            Some(Command::Find(args)) => &mut args.scan,
+            Some(Command::Check(check_args)) => &mut check_args.scan,
+            Some(Command::Gate(gate_args)) => &mut gate_args.scan,
+            Some(Command::Query(query_args)) => &mut query_args.scan,
+            Some(Command::Run(run_args)) => &mut run_args.scan,
+            Some(Command::Graph(graph_args)) => &mut graph_args.scan,
+            Some(Command::Reachable(reachable_args)) => &mut reachable_args.args.scan,
+            Some(Command::Metrics(metrics_args)) => &mut metrics_args.scan,
+            Some(Command::Deps(deps_args)) => &mut deps_args.scan,
+            Some(Command::Cycles(cycles_args)) => &mut cycles_args.scan,
+            Some(Command::Inventory(inventory_args)) => &mut inventory_args.scan,
+            Some(Command::Duplicates(duplicates_args)) => &mut duplicates_args.scan,
+            Some(Command::Dupes(dupes_args)) => &mut dupes_args.scan,
+            Some(Command::Versions(versions_args)) => &mut versions_args.scan,
+            Some(Command::Modules(modules_args)) => &mut modules_args.scan,
+            Some(Command::Lsp(lsp_args)) => lsp_args.scan_mut(),
+            Some(Command::Bench(bench_args)) => &mut bench_args.scan,
+            Some(Command::Impact(impact_args)) => &mut impact_args.scan,
+            Some(Command::Api(_)) | Some(Command::Policy(_)) | Some(Command::Trend(_)) | Some(Command::Serve(_)) => {
+                return None
+            }
+            None => &mut self.args.scan,
+        })
+    }
+}
+
+fn run(mut cli: Cli) -> Result<ExitCode> {
+    if cli.reports_interrupts() {
+        scan::install_interrupt_handler()?;
+    }
+    // The language server speaks its protocol on standard input
+    let lsp = matches!(cli.command, Some(Command::Lsp(_)));
+    if let Some(scan_args) = cli.scan_args_mut().filter(|_| !lsp) {
+        scan_args.read_stdin_roots()?;
+    }
+    match cli.scan_args() {
+        Some(scan_args) => color::init(scan_args.color),
+        None => {
+            if let Some(Command::Trend(trend_args)) = &cli.command {
+                color::init(trend_args.color);
+            }
+        }
+    }
+    if cli.scan_args().is_some_and(|scan_args| scan_args.deterministic) {
+        // A single worker runs parallel iterators in order
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build_global()
+            .context("Failed to start the single-threaded scan of --deterministic")?;
+    }
+    if let Some(scan_args) = cli.scan_args().filter(|scan_args| scan_args.dry_run) {
+        let scanner = Scanner::new(scan_args.clone())?;
+        println!("{}", scanner.dry_run()?);
+        return Ok(ExitCode::SUCCESS);
+    }
+    let exit_code = match cli.command {
+        Some(Command::Find(args)) => find(args)?,
+        Some(Command::Check(check_args)) => search(MethodFinder::new(check_args.into())?)?,
+        Some(Command::Gate(gate_args)) => gate::run(gate_args)?,
+        Some(Command::Query(query_args)) => search(MethodFinder::new(query_args.into())?)?,
+        Some(Command::Run(run_args)) => run_saved(run_args)?,
+        Some(Command::Graph(graph_args)) => {
+            run_graph(graph_args)?;
+            ExitCode::SUCCESS
+        }
+        Some(Command::Reachable(reachable_args)) => {
+            let mut finder = MethodFinder::new(reachable_args.args)?;
+            let started = SystemTime::now();
+            let result = finder.find_reachable(&reachable_args.entrypoints, reachable_args.why)?;
+            profile::time(Phase::Output, || {
+                finder.write_reports(|finder, out| finder.print_reachable(out, &result))
+            })?;
+            let reachable = &result.reachable.calls;
+            finder.args.notify.notify(&result.target, reachable)?;
+            finder
+                .args
+                .otlp
+                .export(&finder.analyzer.rules, reachable, &result.summary, started);
+            if result.summary.interrupted {
+                ExitCode::from(INTERRUPTED_EXIT_CODE)
+            } else {
+                ExitCode::SUCCESS
+            }
+        }
+        Some(Command::Metrics(metrics_args)) => {
+            run_metrics(metrics_args)?;
+            ExitCode::SUCCESS
+        }
+        Some(Command::Inventory(inventory_args)) => {
+            run_inventory(inventory_args)?;
+            ExitCode::SUCCESS
+        }
+        Some(Command::Cycles(cycles_args)) => {
+            let format = cycles_args.format;
+            let cycles = cycles::Cycles::run(cycles_args)?;
+            match format {
+                ReportFormatter::Txt => println!("{}", cycles.to_text()),
+                ReportFormatter::Json => println!("{}", cycles.to_json()),
+            }
+            ExitCode::SUCCESS
+        }
+        Some(Command::Deps(deps_args)) => {
+            let format = deps_args.format;
+            let deps = deps::Deps::run(deps_args)?;
+            match format {
+                deps::DepsFormatter::Csv => println!("{}", deps.to_csv()),
+                deps::DepsFormatter::Json => println!("{}", deps.to_json()),
+            }
+            ExitCode::SUCCESS
+        }
+        Some(Command::Duplicates(duplicates_args)) => run_duplicates(duplicates_args)?,
+        Some(Command::Dupes(dupes_args)) => {
+            let format = dupes_args.format;
+            let dupes = dupes::Dupes::run(dupes_args)?;
+            match format {
+                ReportFormatter::Txt => println!("{}", dupes.to_text()),
+                ReportFormatter::Json => println!("{}", dupes.to_json()),
+            }
+            ExitCode::SUCCESS
+        }
+        Some(Command::Versions(versions_args)) => {
+            run_versions(versions_args)?;
+            ExitCode::SUCCESS
+        }
+        Some(Command::Modules(modules_args)) => {
+            run_modules(modules_args)?;
+            ExitCode::SUCCESS
+        }
+        Some(Command::Lsp(lsp_args)) => lsp::run(lsp_args)?,
+        Some(Command::Serve(serve_args)) => serve::run(serve_args)?,
+        Some(Command::Bench(bench_args)) => {
+            let format = bench_args.format;
+            let bench = bench::Bench::run(bench_args)?;
+            match format {
+                ReportFormatter::Txt => println!("{}", bench.to_text()),
+                ReportFormatter::Json => println!("{}", bench.to_json()),
+            }
+            ExitCode::SUCCESS
+        }
+        Some(Command::Api(api_args)) => {
+            let api = api::Api::read(&api_args.roots, api_args.public_only)?;
+            match api_args.format {
+                ReportFormatter::Txt => println!("{}", api.to_text()),
+                ReportFormatter::Json => println!("{}", api.to_json()),
+            }
+            ExitCode::SUCCESS
+        }
+        Some(Command::Impact(impact_args)) => {
+            let format = impact_args.format;
+            let impact = impact::Impact::run(impact_args)?;
+            match format {
+                ReportFormatter::Txt => println!("{}", impact.to_text()),
+                ReportFormatter::Json => println!("{}", impact.to_json()),
+            }
+            // Calls the upgrade breaks fail the run, as error rules do
+            if impact.broken_calls() > 0 {
+                ExitCode::FAILURE
+            } else {
+                ExitCode::SUCCESS
+            }
+        }
+        Some(Command::Policy(PolicyArgs {
+            command: PolicyCommand::Generate(generate_args),
+        })) => {
+            print!("{}", generate::generate(&generate_args)?);
+            ExitCode::SUCCESS
+        }
+        Some(Command::Trend(trend_args)) => {
+            let trend = Trend::load(&trend_args.history)?;
+            match trend_args.format {
+                ReportFormatter::Txt => println!("{}", trend.to_text()),
+                ReportFormatter::Json => println!("{}", trend.to_json()),
+            }
+            ExitCode::SUCCESS
+        }
+        None => find(cli.args)?,
+    };
+    if profile::enabled() {
+        eprintln!("{}", profile::report());
+    }
+    Ok(exit_code)
+}
+
+fn main() -> Result<ExitCode> {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(exit_code) => Ok(exit_code),
         Err(e) => {
             eprintln!("Error: {:#}", e);
             Err(e)
@@ -0,0 +1,92 @@
+//! `META-INF/MANIFEST.MF` of jars, for the headers that add classes to a scan such as `Bundle-ClassPath`
+//! and `Class-Path`.
+
+/// Main attributes of a manifest, in order.
+#[derive(Debug, Default)]
+pub struct Manifest {
+    headers: Vec<(String, String)>,
+}
+
+impl Manifest {
+    /// Parses the main section; lines starting with a space continue the previous header.
+    pub fn parse(text: &str) -> Self {
+        let mut headers: Vec<(String, String)> = Vec::new();
+        for line in text.lines() {
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() {
+                break;
+            }
+            if let Some(continuation) = line.strip_prefix(' ') {
+                if let Some((_, value)) = headers.last_mut() {
+                    value.push_str(continuation);
+                }
+            } else if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+            }
+        }
+        Self { headers }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// `Bundle-SymbolicName` without its directives, e.g. `com.example.core` for
+    /// `com.example.core;singleton:=true`.
+    pub fn bundle_symbolic_name(&self) -> Option<&str> {
+        self.get("Bundle-SymbolicName")
+            .and_then(|value| value.split(';').next())
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+    }
+
+    /// Paths of `Bundle-ClassPath` within the bundle, `.` standing for the bundle itself.
+    pub fn bundle_class_path(&self) -> Vec<String> {
+        self.get("Bundle-ClassPath")
+            .map(|value| clauses(value).map(str::to_string).collect())
+            .unwrap_or_default()
+    }
+
+    /// Relative URLs of `Class-Path`, separated by spaces, with their `%20` escapes decoded.
+    pub fn class_path(&self) -> Vec<String> {
+        self.get("Class-Path")
+            .map(|value| value.split_whitespace().map(decode_url_path).collect())
+            .unwrap_or_default()
+    }
+}
+
+pub fn decode_url_path(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 3)
+            .filter(|_| bytes[index] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Paths of a comma-separated OSGi header, without their parameters: `lib/a.jar;x=1, lib/b.jar` lists
+/// `lib/a.jar` and `lib/b.jar`.
+fn clauses(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .filter_map(|clause| clause.split(';').next())
+        .map(|path| path.trim().trim_start_matches('/'))
+        .filter(|path| !path.is_empty())
+}
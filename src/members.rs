@@ -0,0 +1,54 @@
+//! Members the compiler generates for enums and records, so that calls to `values()` or to a record
+//! accessor can be told apart from calls to hand-written methods.
+
+use cafebabe::{attributes::AttributeData, bytecode::Opcode, ClassFile, MethodInfo};
+
+use crate::disasm::method_descriptor;
+
+/// The compiler-generated methods a class declares, as `(method name, note)`, e.g.
+/// `("values", "enum values()")`.
+pub fn generated_members(class_file: &ClassFile) -> Vec<(String, String)> {
+    let mut members = Vec::new();
+    if class_file.super_class.as_deref() == Some("java/lang/Enum") {
+        let value_of = format!("(Ljava/lang/String;)L{};", class_file.this_class);
+        for method in &class_file.methods {
+            match method.name.as_ref() {
+                "values" if method.descriptor.parameters.is_empty() => {
+                    members.push(("values".to_string(), "enum values()".to_string()));
+                }
+                "valueOf" if method_descriptor(&method.descriptor) == value_of => {
+                    members.push(("valueOf".to_string(), "enum valueOf(String)".to_string()));
+                }
+                _ => {}
+            }
+        }
+    }
+    let components = class_file.attributes.iter().find_map(|attr| match &attr.data {
+        AttributeData::Record(components) => Some(components),
+        _ => None,
+    });
+    if let Some(components) = components {
+        for method in &class_file.methods {
+            let name = method.name.as_ref();
+            if components.iter().any(|component| component.name == name) && method.descriptor.parameters.is_empty() {
+                members.push((name.to_string(), format!("accessor of record component {}", name)));
+            } else if matches!(name, "toString" | "hashCode" | "equals") && bootstraps_itself(method) {
+                members.push((name.to_string(), format!("record {}()", name)));
+            }
+        }
+    }
+    members
+}
+
+/// Whether the method body hands off to an `invokedynamic` of its own name, the way javac implements the
+/// `toString`, `hashCode` and `equals` of records through `ObjectMethods`.
+fn bootstraps_itself(method: &MethodInfo) -> bool {
+    method.attributes.iter().any(|attr| match &attr.data {
+        AttributeData::Code(code) => code.bytecode.as_ref().is_some_and(|bytecode| {
+            bytecode.opcodes.iter().any(|(_, opcode)| {
+                matches!(opcode, Opcode::Invokedynamic(invoke_dynamic) if invoke_dynamic.name_and_type.name == method.name)
+            })
+        }),
+        _ => false,
+    })
+}
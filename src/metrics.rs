@@ -0,0 +1,107 @@
+//! Fan-in/fan-out metrics over the call graph, for `jmf metrics`.
+
+use std::collections::{BTreeMap, HashSet};
+
+use serde::Serialize;
+
+use crate::graph::{CallGraph, MethodKey};
+
+/// Distinct callers and callees of a node.
+type Neighbours<T> = (HashSet<T>, HashSet<T>);
+
+#[derive(Debug, Serialize)]
+pub struct Metric {
+    pub name: String,
+    /// Distinct scanned callers
+    pub fan_in: usize,
+    /// Distinct callees, including those outside the scan set
+    pub fan_out: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Metrics {
+    pub methods: Vec<Metric>,
+    pub classes: Vec<Metric>,
+}
+
+impl Metrics {
+    pub fn new(graph: &CallGraph) -> Self {
+        let mut methods: BTreeMap<MethodKey, Neighbours<MethodKey>> = graph
+            .declared_methods()
+            .into_iter()
+            .map(|method| (method, Default::default()))
+            .collect();
+        let mut classes: BTreeMap<String, Neighbours<String>> = methods
+            .keys()
+            .map(|(class_name, _)| (class_name.clone(), Default::default()))
+            .collect();
+
+        for (caller, callees) in &graph.edges() {
+            for callee in callees {
+                if let Some((_, fan_out)) = methods.get_mut(caller) {
+                    fan_out.insert(callee.clone());
+                }
+                if let Some((fan_in, _)) = methods.get_mut(callee) {
+                    fan_in.insert(caller.clone());
+                }
+                if caller.0 == callee.0 {
+                    continue;
+                }
+                if let Some((_, fan_out)) = classes.get_mut(&caller.0) {
+                    fan_out.insert(callee.0.clone());
+                }
+                if let Some((fan_in, _)) = classes.get_mut(&callee.0) {
+                    fan_in.insert(caller.0.clone());
+                }
+            }
+        }
+
+        Self {
+            methods: methods
+                .into_iter()
+                .map(|((class_name, method_name), (fan_in, fan_out))| Metric {
+                    name: format!("{}#{}", class_name.replace('/', "."), method_name),
+                    fan_in: fan_in.len(),
+                    fan_out: fan_out.len(),
+                })
+                .collect(),
+            classes: classes
+                .into_iter()
+                .map(|(class_name, (fan_in, fan_out))| Metric {
+                    name: class_name.replace('/', "."),
+                    fan_in: fan_in.len(),
+                    fan_out: fan_out.len(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut output = vec!["kind,name,fan_in,fan_out".to_string()];
+        for (kind, metrics) in [("class", &self.classes), ("method", &self.methods)] {
+            output.extend(metrics.iter().map(|metric| {
+                format!(
+                    "{},{},{},{}",
+                    kind,
+                    csv_field(&metric.name),
+                    metric.fan_in,
+                    metric.fan_out
+                )
+            }));
+        }
+        output.join("\n")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
+
+/// Quotes a CSV field when it contains a separator, quote or line break.
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,218 @@
+//! JPMS modules declared by `module-info.class` files, for `jmf modules` and the module each call is made
+//! from.
+
+use std::collections::HashMap;
+
+use cafebabe::{
+    attributes::{AttributeData, ModuleRequiresFlags},
+    ClassFile,
+};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Requires {
+    pub module: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub transitive: bool,
+    #[serde(rename = "static", skip_serializing_if = "std::ops::Not::not")]
+    pub static_phase: bool,
+}
+
+/// An `exports` or `opens` directive: a package, to every module or only to the listed ones.
+#[derive(Debug, Clone, Serialize)]
+pub struct PackageAccess {
+    pub package: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub to: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModuleInfo {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Jar or directory the module is declared in
+    pub artifact: String,
+    pub requires: Vec<Requires>,
+    pub exports: Vec<PackageAccess>,
+    pub opens: Vec<PackageAccess>,
+}
+
+impl ModuleInfo {
+    /// The module a `module-info` class file declares, if it has a `Module` attribute.
+    pub fn from_class(class_file: &ClassFile, artifact: impl Into<String>) -> Option<Self> {
+        let module = class_file.attributes.iter().find_map(|attr| match &attr.data {
+            AttributeData::Module(module) => Some(module),
+            _ => None,
+        })?;
+        let access = |package: &str, to: &[std::borrow::Cow<str>]| PackageAccess {
+            package: package.replace('/', "."),
+            to: to.iter().map(|module| module.to_string()).collect(),
+        };
+        Some(Self {
+            name: module.name.to_string(),
+            version: module.version.as_ref().map(|version| version.to_string()),
+            artifact: artifact.into(),
+            requires: module
+                .requires
+                .iter()
+                .map(|requires| Requires {
+                    module: requires.name.to_string(),
+                    transitive: requires.flags.contains(ModuleRequiresFlags::TRANSITIVE),
+                    static_phase: requires.flags.contains(ModuleRequiresFlags::STATIC_PHASE),
+                })
+                .collect(),
+            exports: module
+                .exports
+                .iter()
+                .map(|exports| access(&exports.package_name, &exports.exports_to))
+                .collect(),
+            opens: module
+                .opens
+                .iter()
+                .map(|opens| access(&opens.package_name, &opens.opens_to))
+                .collect(),
+        })
+    }
+}
+
+/// Whether a class file path, relative to its root, names a module descriptor, including those of
+/// multi-release jars.
+pub fn is_module_info(name: &str) -> bool {
+    name == "module-info.class" || name.ends_with("/module-info.class")
+}
+
+/// A class file path relative to its root without the `META-INF/versions/N/` of multi-release jars.
+fn unversioned(name: &str) -> &str {
+    name.strip_prefix("META-INF/versions/")
+        .and_then(|rest| rest.split_once('/'))
+        .map_or(name, |(_, name)| name)
+}
+
+/// The modules of a scan set, by the jar or directory and the directory within it they are declared in,
+/// and the packages of their classes.
+#[derive(Debug, Default)]
+pub struct ModuleIndex {
+    /// Artifact, directory of the descriptor within it and module
+    modules: Vec<(String, String, ModuleInfo)>,
+    /// Module of each package with classes in a module
+    packages: HashMap<String, usize>,
+}
+
+impl ModuleIndex {
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    /// Adds the module declared by the descriptor at `name`, relative to the root of `artifact`.
+    pub fn insert(&mut self, artifact: &str, name: &str, module: ModuleInfo) {
+        let directory = unversioned(name).trim_end_matches("module-info.class");
+        self.modules.push((artifact.to_string(), directory.to_string(), module));
+    }
+
+    /// Records the package of the class file at `name` in `artifact` as one of its module's.
+    pub fn insert_class(&mut self, artifact: &str, name: &str) {
+        let Some(index) = self.module_index(artifact, name) else {
+            return;
+        };
+        let name = unversioned(name);
+        if let Some((package, _)) = name.rsplit_once('/') {
+            self.packages.entry(package.replace('/', ".")).or_insert(index);
+        }
+    }
+
+    /// The module of the class file at `name` in `artifact`: that of the nearest descriptor above it.
+    pub fn module_of(&self, artifact: &str, name: &str) -> Option<&str> {
+        self.module_index(artifact, name)
+            .map(|index| self.modules[index].2.name.as_str())
+    }
+
+    fn module_index(&self, artifact: &str, name: &str) -> Option<usize> {
+        let name = unversioned(name);
+        self.modules
+            .iter()
+            .enumerate()
+            .filter(|(_, (module_artifact, directory, _))| {
+                module_artifact == artifact && name.starts_with(directory.as_str())
+            })
+            .max_by_key(|(_, (_, directory, _))| directory.len())
+            .map(|(index, _)| index)
+    }
+
+    /// Why a call from a class of `caller_module`, or of the unnamed module, into `callee_class`, as
+    /// `com.example.Foo`, fails with an illegal access: its package is in another module that does not
+    /// export it, or exports it only to other modules.
+    pub fn illegal_access(&self, caller_module: Option<&str>, callee_class: &str) -> Option<String> {
+        let (package, _) = callee_class.rsplit_once('.')?;
+        let module = &self.modules[*self.packages.get(package)?].2;
+        if caller_module == Some(module.name.as_str()) {
+            return None;
+        }
+        match module.exports.iter().find(|exports| exports.package == package) {
+            None => Some(format!("{} does not export {}", module.name, package)),
+            Some(exports) if exports.to.is_empty() => None,
+            Some(exports) if caller_module.is_some_and(|caller| exports.to.iter().any(|to| to == caller)) => None,
+            Some(exports) => Some(format!(
+                "{} exports {} only to {}",
+                module.name,
+                package,
+                exports.to.join(", ")
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Modules {
+    pub modules: Vec<ModuleInfo>,
+}
+
+impl Modules {
+    /// The modules, by name.
+    pub fn new(mut modules: Vec<ModuleInfo>) -> Self {
+        modules.sort_by(|a, b| (&a.name, &a.artifact).cmp(&(&b.name, &b.artifact)));
+        Self { modules }
+    }
+
+    pub fn to_text(&self) -> String {
+        if self.modules.is_empty() {
+            return "No results".to_string();
+        }
+        let mut output = Vec::new();
+        for module in &self.modules {
+            let version = module.version.as_ref().map(|version| format!("@{}", version));
+            output.push(format!(
+                "{}{} ({})",
+                module.name,
+                version.unwrap_or_default(),
+                module.artifact
+            ));
+            for requires in &module.requires {
+                let modifiers = [(requires.transitive, "transitive "), (requires.static_phase, "static ")]
+                    .iter()
+                    .filter(|(applies, _)| *applies)
+                    .map(|(_, modifier)| *modifier)
+                    .collect::<String>();
+                output.push(format!(" - requires {}{}", modifiers, requires.module));
+            }
+            for (directive, accesses) in [("exports", &module.exports), ("opens", &module.opens)] {
+                for access in accesses {
+                    match access.to.is_empty() {
+                        true => output.push(format!(" - {} {}", directive, access.package)),
+                        false => output.push(format!(
+                            " - {} {} to {}",
+                            directive,
+                            access.package,
+                            access.to.join(", ")
+                        )),
+                    }
+                }
+            }
+        }
+        output.join("\n")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
@@ -0,0 +1,115 @@
+//! `--notify-webhook`: a summary of the found calls POSTed with `curl` when there are more than a threshold,
+//! so that scheduled scans alert the owning channel.
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde_json::json;
+use tracing::debug;
+
+use crate::{analysis::FoundCall, curl};
+
+/// Calls listed in a notification; the others are only counted.
+const MAX_LISTED_CALLS: usize = 20;
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default)]
+pub enum PayloadFormat {
+    /// `{"target", "count", "threshold", "calls"}`
+    #[default]
+    Json,
+    /// A Slack incoming webhook message
+    Slack,
+}
+
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct NotifyArgs {
+    /// URL to POST a summary of the calls to when there are more than `--notify-threshold`
+    #[arg(long = "notify-webhook", value_name = "URL")]
+    pub webhook: Option<String>,
+
+    /// Number of calls tolerated before notifying
+    #[arg(
+        long = "notify-threshold",
+        value_name = "N",
+        default_value_t = 0,
+        requires = "webhook"
+    )]
+    pub threshold: usize,
+
+    /// Payload of the notification
+    #[arg(long = "notify-format", value_enum, default_value_t = PayloadFormat::Json, requires = "webhook")]
+    pub payload: PayloadFormat,
+}
+
+impl NotifyArgs {
+    /// POSTs the summary when a webhook is set and the calls exceed the threshold.
+    pub fn notify(&self, target: &str, calls: &[FoundCall]) -> Result<()> {
+        let Some(webhook) = &self.webhook else {
+            return Ok(());
+        };
+        if calls.len() <= self.threshold {
+            debug!("Not notifying: {} calls, threshold {}", calls.len(), self.threshold);
+            return Ok(());
+        }
+        post(webhook, &self.body(target, calls))
+    }
+
+    fn body(&self, target: &str, calls: &[FoundCall]) -> String {
+        let listed: Vec<String> = calls.iter().take(MAX_LISTED_CALLS).map(line).collect();
+        let unlisted = calls.len() - listed.len();
+        match self.payload {
+            PayloadFormat::Json => json!({
+                "target": target,
+                "count": calls.len(),
+                "threshold": self.threshold,
+                "calls": listed,
+            }),
+            PayloadFormat::Slack => {
+                let hits = if calls.len() == 1 { "call" } else { "calls" };
+                let mut text = format!(
+                    "*jmf*: {} {} to `{}` (threshold {})",
+                    calls.len(),
+                    hits,
+                    target,
+                    self.threshold
+                );
+                for call in &listed {
+                    text.push_str(&format!("\n• `{}`", call));
+                }
+                if unlisted > 0 {
+                    text.push_str(&format!("\n…and {} more", unlisted));
+                }
+                json!({ "text": text })
+            }
+        }
+        .to_string()
+    }
+}
+
+/// A call without colors or markers, as `com.example.Foo#bar (L12)`.
+fn line(call: &FoundCall) -> String {
+    format!(
+        "{}#{} (L{})",
+        call.class_name.replace('/', "."),
+        call.method_name,
+        call.line_number
+    )
+}
+
+fn post(url: &str, payload: &str) -> Result<()> {
+    let output = curl::post_json(url, &[], payload, "--notify-webhook")?;
+    anyhow::ensure!(
+        output.status.success(),
+        "Failed to notify {}: {}",
+        redact(url),
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(())
+}
+
+/// The scheme and host of a webhook URL, whose path is its secret.
+fn redact(url: &str) -> String {
+    match url.split_once("://") {
+        Some((scheme, rest)) => format!("{}://{}/…", scheme, rest.split('/').next().unwrap_or_default()),
+        None => "the webhook".to_string(),
+    }
+}
@@ -0,0 +1,241 @@
+//! `--otlp-endpoint`: the metrics of a scan and a span covering it, exported with the `otlp` feature to an
+//! OpenTelemetry collector over OTLP/HTTP with JSON bodies, POSTed with `curl` like `--notify-webhook`, so
+//! that scheduled scans feed the same observability stack as the rest of a CI fleet.
+//!
+//! The endpoint and the service defaults follow the OpenTelemetry SDK environment variables:
+//! `OTEL_EXPORTER_OTLP_ENDPOINT`, `OTEL_EXPORTER_OTLP_HEADERS`, `OTEL_SERVICE_NAME` and
+//! `OTEL_RESOURCE_ATTRIBUTES`.
+
+use std::time::SystemTime;
+
+use anyhow::Result;
+
+use crate::{analysis::FoundCall, policy::Rule, scan::ScanSummary};
+
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct OtlpArgs {
+    /// OTLP/HTTP endpoint to export the metrics and span of the scan to, e.g. `http://localhost:4318`, which
+    /// `OTEL_EXPORTER_OTLP_ENDPOINT` otherwise gives (with the `otlp` feature)
+    #[arg(long = "otlp-endpoint", value_name = "URL")]
+    pub endpoint: Option<String>,
+}
+
+impl OtlpArgs {
+    /// Fails early when an endpoint is given to a jmf that cannot export to it.
+    pub fn check(&self) -> Result<()> {
+        anyhow::ensure!(
+            cfg!(feature = "otlp") || self.endpoint.is_none(),
+            "--otlp-endpoint needs jmf built with the otlp feature"
+        );
+        Ok(())
+    }
+
+    /// Exports the scan that started at `started` and found `calls` for `rules`, when there is an endpoint.
+    /// A collector that cannot be reached is logged rather than failing the scan.
+    #[cfg(feature = "otlp")]
+    pub fn export(&self, rules: &[Rule], calls: &[FoundCall], summary: &ScanSummary, started: SystemTime) {
+        let Some(endpoint) = self.endpoint.clone().or_else(|| env("OTEL_EXPORTER_OTLP_ENDPOINT")) else {
+            return;
+        };
+        let scan = export::Scan::new(rules, calls, summary, started);
+        let endpoint = endpoint.trim_end_matches('/');
+        for (path, payload) in [("v1/metrics", scan.metrics()), ("v1/traces", scan.traces())] {
+            let url = format!("{}/{}", endpoint, path);
+            if let Err(e) = export::post(&url, &payload.to_string()) {
+                tracing::error!("{:#}", e);
+            }
+        }
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    pub fn export(&self, _rules: &[Rule], _calls: &[FoundCall], _summary: &ScanSummary, _started: SystemTime) {}
+}
+
+/// A set, non-empty environment variable.
+#[cfg(feature = "otlp")]
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+
+#[cfg(feature = "otlp")]
+mod export {
+    use std::time::{Duration, SystemTime};
+
+    use anyhow::Result;
+    use serde_json::{json, Value};
+
+    use super::env;
+    use crate::{analysis::FoundCall, curl, duplicates::sha256_hex, policy::Rule, scan::ScanSummary};
+
+    /// What the metrics and the span tell of a scan.
+    pub struct Scan {
+        started: SystemTime,
+        ended: SystemTime,
+        classes: usize,
+        errors: usize,
+        /// Calls per rule, by id or target, including the rules without any
+        matches: Vec<(String, usize)>,
+        interrupted: bool,
+    }
+
+    impl Scan {
+        pub fn new(rules: &[Rule], calls: &[FoundCall], summary: &ScanSummary, started: SystemTime) -> Self {
+            let mut matches: Vec<(String, usize)> = rules
+                .iter()
+                .map(|rule| (rule.id.clone().unwrap_or_else(|| rule.target()), 0))
+                .collect();
+            for call in calls {
+                if let Some((_, count)) = matches.get_mut(call.rule) {
+                    *count += 1;
+                }
+            }
+            Self {
+                started,
+                ended: SystemTime::now(),
+                classes: summary.classes,
+                errors: summary.errors.len(),
+                matches,
+                interrupted: summary.interrupted,
+            }
+        }
+
+        /// An `ExportMetricsServiceRequest`: the duration of the scan, the classes and errors of the scan
+        /// and the calls per rule, each a delta over the scan.
+        pub fn metrics(&self) -> Value {
+            let duration = self.ended.duration_since(self.started).unwrap_or_default();
+            let point = |value: usize, attributes: Value| {
+                json!({
+                    "startTimeUnixNano": unix_nanos(self.started),
+                    "timeUnixNano": unix_nanos(self.ended),
+                    "asInt": value.to_string(),
+                    "attributes": attributes,
+                })
+            };
+            let sum = |name: &str, unit: &str, description: &str, points: Vec<Value>| {
+                json!({
+                    "name": name,
+                    "unit": unit,
+                    "description": description,
+                    // DELTA, as each scan counts its own
+                    "sum": { "aggregationTemporality": 1, "isMonotonic": true, "dataPoints": points },
+                })
+            };
+            let metrics = vec![
+                json!({
+                    "name": "jmf.scan.duration",
+                    "unit": "s",
+                    "description": "Duration of the scan",
+                    "gauge": { "dataPoints": [{
+                        "timeUnixNano": unix_nanos(self.ended),
+                        "asDouble": duration.as_secs_f64(),
+                    }] },
+                }),
+                sum(
+                    "jmf.scan.classes",
+                    "{class}",
+                    "Class files scanned",
+                    vec![point(self.classes, json!([]))],
+                ),
+                sum(
+                    "jmf.scan.errors",
+                    "{class}",
+                    "Class files that could not be analyzed",
+                    vec![point(self.errors, json!([]))],
+                ),
+                sum(
+                    "jmf.matches",
+                    "{call}",
+                    "Calls found per rule",
+                    self.matches
+                        .iter()
+                        .map(|(rule, count)| point(*count, json!([attribute("jmf.rule", rule)])))
+                        .collect(),
+                ),
+            ];
+            json!({ "resourceMetrics": [{
+                "resource": resource(),
+                "scopeMetrics": [{ "scope": scope(), "metrics": metrics }],
+            }] })
+        }
+
+        /// An `ExportTraceServiceRequest` of a single span from the start to the end of the scan.
+        pub fn traces(&self) -> Value {
+            let ids = sha256_hex(format!("{:?} {}", self.started, std::process::id()).as_bytes());
+            let calls: usize = self.matches.iter().map(|(_, count)| count).sum();
+            let span = json!({
+                "traceId": &ids[..32],
+                "spanId": &ids[32..48],
+                "name": "jmf scan",
+                // INTERNAL
+                "kind": 1,
+                "startTimeUnixNano": unix_nanos(self.started),
+                "endTimeUnixNano": unix_nanos(self.ended),
+                "attributes": [
+                    attribute("jmf.classes", &self.classes.to_string()),
+                    attribute("jmf.errors", &self.errors.to_string()),
+                    attribute("jmf.matches", &calls.to_string()),
+                ],
+                // ERROR when interrupted, UNSET otherwise
+                "status": { "code": if self.interrupted { 2 } else { 0 } },
+            });
+            json!({ "resourceSpans": [{
+                "resource": resource(),
+                "scopeSpans": [{ "scope": scope(), "spans": [span] }],
+            }] })
+        }
+    }
+
+    fn attribute(key: &str, value: &str) -> Value {
+        json!({ "key": key, "value": { "stringValue": value } })
+    }
+
+    /// `service.name` and the `OTEL_RESOURCE_ATTRIBUTES`, e.g. `deployment.environment=ci`.
+    fn resource() -> Value {
+        let mut attributes = vec![attribute(
+            "service.name",
+            &env("OTEL_SERVICE_NAME").unwrap_or_else(|| "jmf".to_string()),
+        )];
+        attributes.extend(
+            pairs("OTEL_RESOURCE_ATTRIBUTES")
+                .iter()
+                .filter(|(key, _)| key != "service.name")
+                .map(|(key, value)| attribute(key, value)),
+        );
+        json!({ "attributes": attributes })
+    }
+
+    fn scope() -> Value {
+        json!({ "name": "jmf", "version": env!("CARGO_PKG_VERSION") })
+    }
+
+    /// The `key=value` pairs of a comma-separated environment variable, such as `OTEL_EXPORTER_OTLP_HEADERS`.
+    fn pairs(name: &str) -> Vec<(String, String)> {
+        env(name)
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect()
+    }
+
+    /// Nanoseconds since the epoch, as a string like the JSON encoding of OTLP wants 64-bit integers.
+    fn unix_nanos(time: SystemTime) -> String {
+        time.duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO)
+            .as_nanos()
+            .to_string()
+    }
+
+    /// POSTs to the endpoint with the `OTEL_EXPORTER_OTLP_HEADERS`, usually API keys, which are kept off
+    /// the command line of curl.
+    pub fn post(url: &str, payload: &str) -> Result<()> {
+        let output = curl::post_json(url, &pairs("OTEL_EXPORTER_OTLP_HEADERS"), payload, "--otlp-endpoint")?;
+        anyhow::ensure!(
+            output.status.success(),
+            "Failed to export to {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+//! `--owners`: the teams a `CODEOWNERS` file assigns the caller source files to, and the report of calls
+//! grouped by them.
+
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::Serialize;
+
+use crate::{analysis::FoundCall, color, scan::ScanSummary};
+
+/// Heading of the calls whose source file no rule assigns to an owner.
+const UNOWNED: &str = "(unowned)";
+
+struct OwnerRule {
+    patterns: GlobSet,
+    owners: Vec<String>,
+}
+
+pub struct CodeOwners {
+    /// Directory the patterns are relative to: the repository root holding the file or its `.github` or
+    /// `docs` directory
+    root: PathBuf,
+    rules: Vec<OwnerRule>,
+}
+
+impl CodeOwners {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut rules = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split_once('#').map_or(line, |(rule, _)| rule).trim();
+            let mut words = line.split_whitespace();
+            let Some(pattern) = words.next() else {
+                continue;
+            };
+            let patterns = globs(pattern)
+                .with_context(|| format!("Invalid pattern on line {} of {}", index + 1, path.display()))?;
+            rules.push(OwnerRule {
+                patterns,
+                owners: words.map(str::to_string).collect(),
+            });
+        }
+        let directory = path.parent().unwrap_or(Path::new(""));
+        let root = match directory.file_name() {
+            Some(name) if name == ".github" || name == "docs" => directory.parent().unwrap_or(Path::new("")),
+            _ => directory,
+        };
+        Ok(Self {
+            root: root.to_path_buf(),
+            rules,
+        })
+    }
+
+    /// Owners of a path relative to the repository root; the last matching rule wins, as on GitHub.
+    pub fn owners(&self, path: &str) -> Vec<String> {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.patterns.is_match(path))
+            .map(|rule| rule.owners.clone())
+            .unwrap_or_default()
+    }
+
+    /// Sets the owners of each call from the path of its source file under the first source root
+    /// containing it, or else the first source root.
+    pub fn assign(&self, calls: &mut [FoundCall], source_roots: &[PathBuf]) {
+        for call in calls {
+            let source_path = call.source_path();
+            let Some(file) = source_roots
+                .iter()
+                .map(|source_root| source_root.join(&source_path))
+                .find(|file| file.is_file())
+                .or_else(|| source_roots.first().map(|source_root| source_root.join(&source_path)))
+            else {
+                continue;
+            };
+            call.owners = self.owners(&self.relative(&file));
+        }
+    }
+
+    /// A path relative to the repository root, with `/` separators.
+    fn relative(&self, file: &Path) -> String {
+        let current_dir = std::env::current_dir().unwrap_or_default();
+        let absolute = |path: &Path| normalize(&current_dir.join(path));
+        let file = absolute(file);
+        let relative = file.strip_prefix(absolute(&self.root)).unwrap_or(&file);
+        relative
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// The globs of a gitignore-style pattern: anchored when it starts with or contains a `/`, matching at any
+/// depth otherwise, and matching the files below the directories it matches.
+fn globs(pattern: &str) -> Result<GlobSet> {
+    let directory = pattern.ends_with('/');
+    let pattern = pattern.trim_end_matches('/');
+    let anchored = pattern.contains('/');
+    let pattern = pattern.trim_start_matches('/');
+    let pattern = if anchored {
+        pattern.to_string()
+    } else {
+        format!("**/{}", pattern)
+    };
+    // As in gitignore, `*` does not match a `/`
+    let glob = |pattern: &str| GlobBuilder::new(pattern).literal_separator(true).build();
+    let mut builder = GlobSetBuilder::new();
+    if !directory {
+        builder.add(glob(&pattern)?);
+    }
+    builder.add(glob(&format!("{}/**", pattern))?);
+    Ok(builder.build()?)
+}
+
+#[derive(Debug, Serialize)]
+struct OwnerGroup<'a> {
+    owner: String,
+    count: usize,
+    calls: Vec<&'a FoundCall>,
+}
+
+/// Calls grouped by the owners of their source files, the busiest first; a call with several owners is
+/// listed under each.
+#[derive(Debug, Serialize)]
+pub struct OwnerResult<'a> {
+    target: String,
+    owners: Vec<OwnerGroup<'a>>,
+    #[serde(flatten)]
+    pub summary: ScanSummary,
+}
+
+impl<'a> OwnerResult<'a> {
+    pub fn new(target: String, calls: &'a [FoundCall]) -> Self {
+        let mut owners: Vec<OwnerGroup> = Vec::new();
+        for call in calls {
+            let call_owners = if call.owners.is_empty() {
+                vec![UNOWNED.to_string()]
+            } else {
+                call.owners.clone()
+            };
+            for owner in call_owners {
+                match owners.iter_mut().find(|group| group.owner == owner) {
+                    Some(group) => group.calls.push(call),
+                    None => owners.push(OwnerGroup {
+                        owner,
+                        count: 0,
+                        calls: vec![call],
+                    }),
+                }
+            }
+        }
+        for group in &mut owners {
+            group.count = group.calls.len();
+        }
+        owners.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.owner.cmp(&b.owner)));
+        Self {
+            target,
+            owners,
+            summary: ScanSummary::default(),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut output = vec![color::bold(&self.target)];
+        for group in &self.owners {
+            let hits = if group.count == 1 { "hit" } else { "hits" };
+            output.push(color::bold(format!("{}: {} {}", group.owner, group.count, hits)));
+            // The heading already names the owner
+            output.extend(group.calls.iter().map(|call| {
+                format!(
+                    " - {}",
+                    FoundCall {
+                        owners: Vec::new(),
+                        ..(*call).clone()
+                    }
+                )
+            }));
+        }
+        output.join("\n")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
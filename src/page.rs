@@ -0,0 +1,51 @@
+//! Offset/limit pagination and filters over found calls, for paging through large query responses.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{analysis::FoundCall, policy::glob_match};
+
+/// Which calls of a response to return, e.g. `{"offset": 100, "limit": 50, "class": "com.app.**"}`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PageRequest {
+    pub offset: usize,
+    /// Every call from `offset` on when unset
+    pub limit: Option<usize>,
+    /// Glob on the dotted caller class, as in policy files
+    pub class: Option<String>,
+    /// Glob on the caller method
+    pub method: Option<String>,
+}
+
+/// One page of calls, along with the number of calls passing the filters.
+#[derive(Debug, Serialize)]
+pub struct Page<'a> {
+    pub total: usize,
+    pub offset: usize,
+    pub calls: Vec<&'a FoundCall>,
+}
+
+impl PageRequest {
+    fn accepts(&self, call: &FoundCall) -> bool {
+        self.class
+            .as_ref()
+            .is_none_or(|class| glob_match(class.as_bytes(), call.class_name.replace('/', ".").as_bytes()))
+            && self
+                .method
+                .as_ref()
+                .is_none_or(|method| glob_match(method.as_bytes(), call.method_name.as_bytes()))
+    }
+
+    pub fn apply<'a>(&self, calls: &'a [FoundCall]) -> Page<'a> {
+        let filtered: Vec<&FoundCall> = calls.iter().filter(|call| self.accepts(call)).collect();
+        Page {
+            total: filtered.len(),
+            offset: self.offset,
+            calls: filtered
+                .into_iter()
+                .skip(self.offset)
+                .take(self.limit.unwrap_or(usize::MAX))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Calls as an iterator fed by a pool of rayon workers, for synchronous embedders with large result sets.
+
+use std::{path::PathBuf, sync::mpsc, thread};
+
+use anyhow::{anyhow, Context, Result};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::analysis::{scanned_files, Analyzer, FoundCall};
+
+/// Calls waiting for the consumer before the workers pause.
+const BUFFER: usize = 256;
+
+impl Analyzer {
+    /// `scan` with the class files and jars spread over a rayon thread pool of its own, yielding each call
+    /// as soon as its file is analyzed, so results don't have to fit in memory at once. Calls arrive in no
+    /// particular order. The workers pause while the consumer falls behind, which leaves the global pool
+    /// free for the consumer's own rayon work, and the pool is shut down once the iterator is dropped.
+    /// A class file that cannot be analyzed is yielded as a [`ClassError`](crate::analysis::ClassError)
+    /// and the iteration goes on; any other failure ends it with an error.
+    pub fn iter_calls(self, path: PathBuf) -> impl Iterator<Item = Result<FoundCall>> {
+        let (sender, receiver) = mpsc::sync_channel(BUFFER);
+        thread::spawn(move || {
+            // The pool's threads stop when it is dropped, with this thread
+            let pool = rayon::ThreadPoolBuilder::new()
+                .thread_name(|index| format!("jmf-iter-calls-{}", index))
+                .build()
+                .context("Failed to start the threads of iter_calls");
+            let result = pool.and_then(|pool| {
+                let files = scanned_files(&path)?;
+                pool.install(|| {
+                    files.par_iter().try_for_each_with(sender.clone(), |sender, file| {
+                        let errors = self.scan_file(file, &mut |call| {
+                            sender.send(Ok(call)).map_err(|_| anyhow!("The iterator was dropped"))
+                        })?;
+                        for error in errors {
+                            sender
+                                .send(Err(error.into()))
+                                .map_err(|_| anyhow!("The iterator was dropped"))?;
+                        }
+                        Ok(())
+                    })
+                })
+            });
+            if let Err(e) = result {
+                // Nobody is left to tell when the iterator was dropped
+                let _ = sender.send(Err(e));
+            }
+        });
+        receiver.into_iter()
+    }
+}
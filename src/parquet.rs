@@ -0,0 +1,379 @@
+//! `--format parquet`: found calls and call graph edges as Parquet files, for analytics pipelines to load
+//! into a warehouse.
+//!
+//! The files are written without a Parquet library: a single row group of one uncompressed, PLAIN-encoded
+//! data page per column, which every reader supports, with string, 32-bit and 64-bit integer columns,
+//! optional or not. The metadata is encoded with the Thrift compact protocol, as the format specifies.
+
+use std::sync::Arc;
+
+use java_method_finder::{analysis::FoundCall, policy::Rule};
+
+use crate::edges::Edges;
+
+/// A column of values of one type, `None` for nulls in the optional ones.
+pub enum Column {
+    String(Vec<String>),
+    OptionalString(Vec<Option<String>>),
+    Int32(Vec<i32>),
+    Int64(Vec<i64>),
+}
+
+impl Column {
+    fn len(&self) -> usize {
+        match self {
+            Column::String(values) => values.len(),
+            Column::OptionalString(values) => values.len(),
+            Column::Int32(values) => values.len(),
+            Column::Int64(values) => values.len(),
+        }
+    }
+}
+
+/// Named columns of the same length.
+pub struct Table {
+    columns: Vec<(&'static str, Column)>,
+}
+
+impl Table {
+    /// The found calls, a row per call with the rule it matched.
+    pub fn calls(rules: &[Rule], calls: &[FoundCall]) -> Self {
+        let rule = |call: &FoundCall| rules.get(call.rule);
+        let text = |value: &Arc<str>| value.to_string();
+        Self {
+            columns: vec![
+                (
+                    "rule",
+                    Column::OptionalString(
+                        calls
+                            .iter()
+                            .map(|call| rule(call).and_then(|rule| rule.id.clone()))
+                            .collect(),
+                    ),
+                ),
+                (
+                    "target",
+                    Column::String(
+                        calls
+                            .iter()
+                            .map(|call| rule(call).map(Rule::target).unwrap_or_default())
+                            .collect(),
+                    ),
+                ),
+                (
+                    "severity",
+                    Column::String(
+                        calls
+                            .iter()
+                            .map(|call| rule(call).map(|rule| rule.severity.to_string()).unwrap_or_default())
+                            .collect(),
+                    ),
+                ),
+                (
+                    "callee",
+                    Column::OptionalString(calls.iter().map(|call| call.callee.clone()).collect()),
+                ),
+                (
+                    "class_name",
+                    Column::String(calls.iter().map(|call| call.class_name.replace('/', ".")).collect()),
+                ),
+                (
+                    "method_name",
+                    Column::String(calls.iter().map(|call| text(&call.method_name)).collect()),
+                ),
+                (
+                    "line_number",
+                    Column::Int32(calls.iter().map(|call| i32::from(call.line_number)).collect()),
+                ),
+                (
+                    "offset",
+                    Column::Int64(calls.iter().map(|call| call.offset as i64).collect()),
+                ),
+                (
+                    "fingerprint",
+                    Column::String(calls.iter().map(|call| call.fingerprint.clone()).collect()),
+                ),
+                (
+                    "java_release",
+                    Column::String(calls.iter().map(|call| text(&call.java_release)).collect()),
+                ),
+                (
+                    "module",
+                    Column::OptionalString(calls.iter().map(|call| call.module.clone()).collect()),
+                ),
+                (
+                    "origin",
+                    Column::OptionalString(calls.iter().map(|call| call.origin.clone()).collect()),
+                ),
+                (
+                    "source_file",
+                    Column::OptionalString(calls.iter().map(|call| call.source_file.as_ref().map(text)).collect()),
+                ),
+            ],
+        }
+    }
+
+    /// The call graph edges, a row per distinct caller and callee.
+    pub fn edges(edges: &Edges) -> Self {
+        Self {
+            columns: vec![
+                (
+                    "caller",
+                    Column::String(edges.edges.iter().map(|edge| edge.caller.clone()).collect()),
+                ),
+                (
+                    "callee",
+                    Column::String(edges.edges.iter().map(|edge| edge.callee.clone()).collect()),
+                ),
+            ],
+        }
+    }
+
+    /// The table as a Parquet file.
+    pub fn to_parquet(&self) -> Vec<u8> {
+        let rows = self.columns.first().map_or(0, |(_, column)| column.len());
+        let mut file = MAGIC.to_vec();
+        let mut chunks = Vec::new();
+        for (_, column) in &self.columns {
+            let offset = file.len();
+            let page = page(column);
+            let mut header = Compact::default();
+            header.i32(1, 0); // DATA_PAGE
+            header.i32(2, page.len() as i32);
+            header.i32(3, page.len() as i32);
+            header.begin_struct(5);
+            header.i32(1, rows as i32);
+            header.i32(2, PLAIN);
+            header.i32(3, RLE);
+            header.i32(4, RLE);
+            header.end_struct();
+            header.stop();
+            file.extend(&header.bytes);
+            file.extend(&page);
+            chunks.push((offset, file.len() - offset));
+        }
+
+        let mut metadata = Compact::default();
+        metadata.i32(1, 1);
+        metadata.begin_list(2, STRUCT, self.columns.len() + 1);
+        metadata.begin_element();
+        metadata.binary(4, b"schema");
+        metadata.i32(5, self.columns.len() as i32);
+        metadata.end_element();
+        for (name, column) in &self.columns {
+            metadata.begin_element();
+            metadata.i32(1, physical_type(column));
+            metadata.i32(3, i32::from(matches!(column, Column::OptionalString(_))));
+            metadata.binary(4, name.as_bytes());
+            if matches!(column, Column::String(_) | Column::OptionalString(_)) {
+                metadata.i32(6, 0); // UTF8
+                metadata.begin_struct(10);
+                metadata.begin_struct(1); // STRING
+                metadata.end_struct();
+                metadata.end_struct();
+            }
+            metadata.end_element();
+        }
+        metadata.i64(3, rows as i64);
+        metadata.begin_list(4, STRUCT, 1);
+        metadata.begin_element();
+        metadata.begin_list(1, STRUCT, self.columns.len());
+        for ((name, column), (offset, size)) in self.columns.iter().zip(&chunks) {
+            metadata.begin_element();
+            metadata.i64(2, *offset as i64);
+            metadata.begin_struct(3);
+            metadata.i32(1, physical_type(column));
+            metadata.begin_list(2, I32, 2);
+            metadata.list_i32(PLAIN);
+            metadata.list_i32(RLE);
+            metadata.begin_list(3, BINARY, 1);
+            metadata.list_binary(name.as_bytes());
+            metadata.i32(4, 0); // UNCOMPRESSED
+            metadata.i64(5, rows as i64);
+            metadata.i64(6, *size as i64);
+            metadata.i64(7, *size as i64);
+            metadata.i64(9, *offset as i64);
+            metadata.end_struct();
+            metadata.end_element();
+        }
+        metadata.i64(2, chunks.iter().map(|(_, size)| *size as i64).sum());
+        metadata.i64(3, rows as i64);
+        metadata.end_element();
+        metadata.binary(6, concat!("jmf version ", env!("CARGO_PKG_VERSION")).as_bytes());
+        metadata.stop();
+
+        file.extend(&metadata.bytes);
+        file.extend((metadata.bytes.len() as u32).to_le_bytes());
+        file.extend(MAGIC);
+        file
+    }
+}
+
+const MAGIC: &[u8] = b"PAR1";
+
+// Encodings
+const PLAIN: i32 = 0;
+const RLE: i32 = 3;
+
+// Thrift compact protocol types
+const I32: u8 = 5;
+const I64: u8 = 6;
+const BINARY: u8 = 8;
+const STRUCT: u8 = 12;
+
+fn physical_type(column: &Column) -> i32 {
+    match column {
+        Column::Int32(_) => 1,
+        Column::Int64(_) => 2,
+        Column::String(_) | Column::OptionalString(_) => 6, // BYTE_ARRAY
+    }
+}
+
+/// The definition levels of an optional column, then its PLAIN-encoded non-null values.
+fn page(column: &Column) -> Vec<u8> {
+    let mut page = Vec::new();
+    match column {
+        Column::String(values) => {
+            for value in values {
+                page.extend((value.len() as u32).to_le_bytes());
+                page.extend(value.as_bytes());
+            }
+        }
+        Column::OptionalString(values) => {
+            let levels = definition_levels(values.iter().map(Option::is_some));
+            page.extend((levels.len() as u32).to_le_bytes());
+            page.extend(levels);
+            for value in values.iter().flatten() {
+                page.extend((value.len() as u32).to_le_bytes());
+                page.extend(value.as_bytes());
+            }
+        }
+        Column::Int32(values) => {
+            for value in values {
+                page.extend(value.to_le_bytes());
+            }
+        }
+        Column::Int64(values) => {
+            for value in values {
+                page.extend(value.to_le_bytes());
+            }
+        }
+    }
+    page
+}
+
+/// Definition levels of bit width 1 in the RLE/bit-packing hybrid encoding, as runs of repeated values.
+fn definition_levels(defined: impl Iterator<Item = bool>) -> Vec<u8> {
+    let mut levels = Vec::new();
+    let mut run: Option<(bool, u64)> = None;
+    let flush = |levels: &mut Vec<u8>, (value, length): (bool, u64)| {
+        varint(levels, length << 1);
+        levels.push(u8::from(value));
+    };
+    for value in defined {
+        run = match run {
+            Some((current, length)) if current == value => Some((current, length + 1)),
+            Some(previous) => {
+                flush(&mut levels, previous);
+                Some((value, 1))
+            }
+            None => Some((value, 1)),
+        };
+    }
+    if let Some(last) = run {
+        flush(&mut levels, last);
+    }
+    levels
+}
+
+fn varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// A Thrift compact protocol encoder, for the structs of the Parquet metadata.
+#[derive(Default)]
+struct Compact {
+    bytes: Vec<u8>,
+    /// Id of the last field of each struct being written, which field headers are relative to
+    last_ids: Vec<i16>,
+    last_id: i16,
+}
+
+impl Compact {
+    fn field(&mut self, id: i16, kind: u8) {
+        let delta = id - self.last_id;
+        if (1..=15).contains(&delta) {
+            self.bytes.push(((delta as u8) << 4) | kind);
+        } else {
+            self.bytes.push(kind);
+            varint(&mut self.bytes, zigzag(i64::from(id)));
+        }
+        self.last_id = id;
+    }
+
+    fn i32(&mut self, id: i16, value: i32) {
+        self.field(id, I32);
+        varint(&mut self.bytes, zigzag(i64::from(value)));
+    }
+
+    fn i64(&mut self, id: i16, value: i64) {
+        self.field(id, I64);
+        varint(&mut self.bytes, zigzag(value));
+    }
+
+    fn binary(&mut self, id: i16, value: &[u8]) {
+        self.field(id, BINARY);
+        self.list_binary(value);
+    }
+
+    fn begin_struct(&mut self, id: i16) {
+        self.field(id, STRUCT);
+        self.begin_element();
+    }
+
+    fn end_struct(&mut self) {
+        self.end_element();
+    }
+
+    fn begin_list(&mut self, id: i16, kind: u8, size: usize) {
+        self.field(id, 9);
+        if size < 15 {
+            self.bytes.push(((size as u8) << 4) | kind);
+        } else {
+            self.bytes.push(0xf0 | kind);
+            varint(&mut self.bytes, size as u64);
+        }
+    }
+
+    /// Starts a struct nested in a field or a list.
+    fn begin_element(&mut self) {
+        self.last_ids.push(self.last_id);
+        self.last_id = 0;
+    }
+
+    fn end_element(&mut self) {
+        self.stop();
+        self.last_id = self.last_ids.pop().unwrap_or_default();
+    }
+
+    fn list_i32(&mut self, value: i32) {
+        varint(&mut self.bytes, zigzag(i64::from(value)));
+    }
+
+    fn list_binary(&mut self, value: &[u8]) {
+        varint(&mut self.bytes, value.len() as u64);
+        self.bytes.extend(value);
+    }
+
+    fn stop(&mut self) {
+        self.bytes.push(0);
+    }
+}
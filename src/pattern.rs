@@ -0,0 +1,182 @@
+//! A small pattern language over the opcode stream of a method.
+//!
+//! A pattern is a sequence of steps joined by `then` (the next step must occur later in the same
+//! method) or `!then` (the next step must not occur later in the same method):
+//!
+//! ```text
+//! call java.util.concurrent.locks.Lock#lock !then call java.util.concurrent.locks.Lock#unlock
+//! new java.io.FileInputStream !then call *#close
+//! ```
+//!
+//! A step is `call <class>#<method>`, `new <class>` or a bare opcode mnemonic such as
+//! `monitorenter`. Class and method names may contain `*` wildcards.
+
+use anyhow::{bail, Result};
+use cafebabe::bytecode::Opcode;
+
+#[derive(Debug, Clone)]
+enum Step {
+    Call { class: String, method: String },
+    New { class: String },
+    Op(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Link {
+    Then,
+    NotThen,
+}
+
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    first: Step,
+    rest: Vec<(Link, Step)>,
+}
+
+impl Pattern {
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut tokens = input.split_whitespace();
+        let first = parse_step(&mut tokens, input)?;
+        let mut rest = Vec::new();
+        while let Some(token) = tokens.next() {
+            let link = match token {
+                "then" => Link::Then,
+                "!then" => Link::NotThen,
+                other => bail!(
+                    "Invalid pattern '{}': expected 'then' or '!then', found '{}'",
+                    input,
+                    other
+                ),
+            };
+            rest.push((link, parse_step(&mut tokens, input)?));
+        }
+        Ok(Self { first, rest })
+    }
+
+    /// Returns the offsets of every occurrence of the first step for which the rest of the
+    /// pattern holds.
+    pub fn find_matches(&self, opcodes: &[(usize, Opcode)]) -> Vec<usize> {
+        opcodes
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, opcode))| self.first.matches(opcode))
+            .filter(|(start, _)| self.rest_holds(opcodes, *start))
+            .map(|(_, (offset, _))| *offset)
+            .collect()
+    }
+
+    fn rest_holds(&self, opcodes: &[(usize, Opcode)], start: usize) -> bool {
+        let mut position = start;
+        for (link, step) in &self.rest {
+            let next = opcodes[position + 1..]
+                .iter()
+                .position(|(_, opcode)| step.matches(opcode));
+            match (link, next) {
+                (Link::Then, Some(found)) => position += found + 1,
+                (Link::Then, None) | (Link::NotThen, Some(_)) => return false,
+                (Link::NotThen, None) => {}
+            }
+        }
+        true
+    }
+}
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.first)?;
+        for (link, step) in &self.rest {
+            let link = match link {
+                Link::Then => "then",
+                Link::NotThen => "!then",
+            };
+            write!(f, " {} {}", link, step)?;
+        }
+        Ok(())
+    }
+}
+
+impl Step {
+    fn matches(&self, opcode: &Opcode) -> bool {
+        match (self, opcode) {
+            (
+                Step::Call { class, method },
+                Opcode::Invokespecial(member_ref)
+                | Opcode::Invokestatic(member_ref)
+                | Opcode::Invokevirtual(member_ref)
+                | Opcode::Invokeinterface(member_ref, _),
+            ) => {
+                wildcard_match(class, &member_ref.class_name) && wildcard_match(method, &member_ref.name_and_type.name)
+            }
+            (Step::New { class }, Opcode::New(class_name)) => wildcard_match(class, class_name),
+            (Step::Op(name), opcode) => *name == opcode_name(opcode),
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Step::Call { class, method } => write!(f, "call {}#{}", class.replace('/', "."), method),
+            Step::New { class } => write!(f, "new {}", class.replace('/', ".")),
+            Step::Op(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+fn parse_step<'a>(tokens: &mut impl Iterator<Item = &'a str>, input: &str) -> Result<Step> {
+    let Some(keyword) = tokens.next() else {
+        bail!("Invalid pattern '{}': expected a step", input);
+    };
+    match keyword {
+        "call" => {
+            let Some((class, method)) = tokens.next().and_then(|target| target.split_once('#')) else {
+                bail!("Invalid pattern '{}': 'call' expects <class>#<method>", input);
+            };
+            Ok(Step::Call {
+                class: class.replace('.', "/"),
+                method: method.to_string(),
+            })
+        }
+        "new" => {
+            let Some(class) = tokens.next() else {
+                bail!("Invalid pattern '{}': 'new' expects <class>", input);
+            };
+            Ok(Step::New {
+                class: class.replace('.', "/"),
+            })
+        }
+        op if op.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => Ok(Step::Op(op.replace('_', ""))),
+        other => bail!("Invalid pattern '{}': unknown step '{}'", input, other),
+    }
+}
+
+/// Lowercase mnemonic of an opcode without underscores, e.g. `monitorenter` or `ifacmpeq`.
+pub fn opcode_name(opcode: &Opcode) -> String {
+    let debug = format!("{:?}", opcode);
+    debug
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+/// Matches `text` against `pattern`, where `*` matches any (possibly empty) sequence.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || !text[first.len()..].ends_with(last) {
+        return false;
+    }
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
@@ -0,0 +1,359 @@
+//! Plugins adding custom matchers and output formats, loaded from dynamic libraries with `--plugin`.
+//!
+//! A plugin library exports C functions exchanging NUL-terminated UTF-8 JSON:
+//!
+//! ```c
+//! const char *jmf_plugin_name(void);
+//! // {"class", "method", "annotations", "opcodes": [{"offset", "opcode", "class"?, "name"?, "descriptor"?}]}
+//! // in, a JSON array of matched offsets out
+//! char *jmf_plugin_match(const char *method);
+//! // The search result as printed by `-f json` in, the text to print out
+//! char *jmf_plugin_format(const char *result);
+//! // Releases strings returned by the two functions above
+//! void jmf_plugin_free(char *string);
+//! ```
+//!
+//! `jmf_plugin_match` and `jmf_plugin_format` are both optional. `jmf_plugin_match` is called from
+//! several threads at once. Loading libraries needs the `dylib` feature; library users can also
+//! implement [`Plugin`] directly.
+//!
+//! A `.wasm` plugin is a WebAssembly module exporting the same functions, with 32-bit pointers into its
+//! exported `memory`, and `jmf_plugin_alloc(size)` returning a buffer for the strings passed in, which
+//! are released with `jmf_plugin_free` too. It runs sandboxed in the wasmi interpreter, one call at a
+//! time, and needs the `wasm-plugins` feature.
+
+#[cfg(feature = "dylib")]
+use std::ffi::{c_char, CStr, CString};
+#[cfg(any(feature = "dylib", feature = "wasm-plugins"))]
+use std::path::Path;
+#[cfg(feature = "wasm-plugins")]
+use std::sync::Mutex;
+use std::{fmt, sync::Arc};
+
+use anyhow::{bail, Result};
+use cafebabe::bytecode::Opcode;
+use serde_json::{json, Value};
+
+use crate::{pattern::opcode_name, policy::Caller};
+#[cfg(any(feature = "dylib", feature = "wasm-plugins"))]
+use anyhow::Context;
+#[cfg(feature = "dylib")]
+use libloading::Library;
+#[cfg(feature = "wasm-plugins")]
+use wasmi::{Engine, Linker, Memory, Module, Store, TypedFunc};
+
+/// A custom check or output format.
+pub trait Plugin: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Offsets within `opcodes` of the method of `caller` to report.
+    fn find_matches(&self, _caller: &Caller, _opcodes: &[(usize, Opcode)]) -> Result<Vec<usize>> {
+        Ok(Vec::new())
+    }
+
+    fn has_matcher(&self) -> bool {
+        false
+    }
+
+    fn has_formatter(&self) -> bool {
+        false
+    }
+
+    /// Text output for the JSON search result.
+    fn format(&self, _result: &Value) -> Result<String> {
+        bail!("Plugin {} has no formatter", self.name())
+    }
+}
+
+/// A plugin as a rule target.
+#[derive(Clone)]
+pub struct PluginMatcher(pub Arc<dyn Plugin>);
+
+impl fmt::Debug for PluginMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PluginMatcher({})", self.0.name())
+    }
+}
+
+#[cfg(feature = "dylib")]
+type NameFn = unsafe extern "C" fn() -> *const c_char;
+#[cfg(feature = "dylib")]
+type TransformFn = unsafe extern "C" fn(*const c_char) -> *mut c_char;
+#[cfg(feature = "dylib")]
+type FreeFn = unsafe extern "C" fn(*mut c_char);
+
+#[cfg(feature = "dylib")]
+struct DylibPlugin {
+    name: String,
+    match_fn: Option<TransformFn>,
+    format_fn: Option<TransformFn>,
+    free_fn: FreeFn,
+    /// Keeps the functions above loaded
+    _library: Library,
+}
+
+/// Loads a dynamic library, or a WebAssembly module when the file name ends in `.wasm`.
+#[cfg(feature = "dylib")]
+pub fn load(path: &Path) -> Result<Arc<dyn Plugin>> {
+    if path.extension().is_some_and(|ext| ext == "wasm") {
+        #[cfg(feature = "wasm-plugins")]
+        return load_wasm(path);
+        #[cfg(not(feature = "wasm-plugins"))]
+        bail!(
+            "Plugin {} is a WebAssembly module, which needs jmf built with the wasm-plugins feature",
+            path.display()
+        );
+    }
+    // SAFETY: loading a plugin runs its initializers; plugins are trusted like the jmf binary itself
+    let library = unsafe { Library::new(path) }.with_context(|| format!("Failed to load plugin {}", path.display()))?;
+    // SAFETY: the symbols are declared with the signatures documented above
+    unsafe {
+        let name_fn = *library
+            .get::<NameFn>(b"jmf_plugin_name\0")
+            .with_context(|| format!("Plugin {} does not export jmf_plugin_name", path.display()))?;
+        let free_fn = *library
+            .get::<FreeFn>(b"jmf_plugin_free\0")
+            .with_context(|| format!("Plugin {} does not export jmf_plugin_free", path.display()))?;
+        let match_fn = library
+            .get::<TransformFn>(b"jmf_plugin_match\0")
+            .ok()
+            .map(|symbol| *symbol);
+        let format_fn = library
+            .get::<TransformFn>(b"jmf_plugin_format\0")
+            .ok()
+            .map(|symbol| *symbol);
+        let name = CStr::from_ptr(name_fn()).to_string_lossy().into_owned();
+        Ok(Arc::new(DylibPlugin {
+            name,
+            match_fn,
+            format_fn,
+            free_fn,
+            _library: library,
+        }))
+    }
+}
+
+#[cfg(feature = "dylib")]
+impl DylibPlugin {
+    fn call(&self, function: TransformFn, input: &Value) -> Result<String> {
+        let input = CString::new(input.to_string())?;
+        // SAFETY: the plugin returns a NUL-terminated string that stays valid until it is freed
+        unsafe {
+            let output = function(input.as_ptr());
+            if output.is_null() {
+                bail!("Plugin {} returned no result", self.name);
+            }
+            let text = CStr::from_ptr(output).to_string_lossy().into_owned();
+            (self.free_fn)(output);
+            Ok(text)
+        }
+    }
+}
+
+#[cfg(feature = "dylib")]
+impl Plugin for DylibPlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn find_matches(&self, caller: &Caller, opcodes: &[(usize, Opcode)]) -> Result<Vec<usize>> {
+        let Some(match_fn) = self.match_fn else {
+            return Ok(Vec::new());
+        };
+        let output = self.call(match_fn, &method_json(caller, opcodes))?;
+        serde_json::from_str(&output)
+            .with_context(|| format!("Plugin {} returned invalid offsets: {}", self.name, output))
+    }
+
+    fn has_matcher(&self) -> bool {
+        self.match_fn.is_some()
+    }
+
+    fn has_formatter(&self) -> bool {
+        self.format_fn.is_some()
+    }
+
+    fn format(&self, result: &Value) -> Result<String> {
+        match self.format_fn {
+            Some(format_fn) => self.call(format_fn, result),
+            None => bail!("Plugin {} has no formatter", self.name),
+        }
+    }
+}
+
+#[cfg(feature = "wasm-plugins")]
+struct WasmPlugin {
+    name: String,
+    has_matcher: bool,
+    has_formatter: bool,
+    /// wasmi stores are not `Sync`, so calls from several threads take turns
+    instance: Mutex<WasmInstance>,
+}
+
+#[cfg(feature = "wasm-plugins")]
+struct WasmInstance {
+    store: Store<()>,
+    memory: Memory,
+    alloc_fn: TypedFunc<u32, u32>,
+    free_fn: TypedFunc<u32, ()>,
+    match_fn: Option<TypedFunc<u32, u32>>,
+    format_fn: Option<TypedFunc<u32, u32>>,
+}
+
+/// Loads a WebAssembly module exporting the plugin functions.
+#[cfg(feature = "wasm-plugins")]
+pub fn load_wasm(path: &Path) -> Result<Arc<dyn Plugin>> {
+    let wasm = std::fs::read(path).with_context(|| format!("Failed to read plugin {}", path.display()))?;
+    let engine = Engine::default();
+    let module = Module::new(&engine, wasm).with_context(|| format!("Failed to load plugin {}", path.display()))?;
+    let mut store = Store::new(&engine, ());
+    // Plugins get no host functions
+    let instance = Linker::<()>::new(&engine)
+        .instantiate_and_start(&mut store, &module)
+        .with_context(|| format!("Failed to instantiate plugin {}", path.display()))?;
+    let export = |name: &str| format!("Plugin {} does not export {}", path.display(), name);
+    let memory = instance
+        .get_memory(&store, "memory")
+        .with_context(|| export("memory"))?;
+    let name_fn = instance
+        .get_typed_func::<(), u32>(&store, "jmf_plugin_name")
+        .with_context(|| export("jmf_plugin_name"))?;
+    let alloc_fn = instance
+        .get_typed_func::<u32, u32>(&store, "jmf_plugin_alloc")
+        .with_context(|| export("jmf_plugin_alloc"))?;
+    let free_fn = instance
+        .get_typed_func::<u32, ()>(&store, "jmf_plugin_free")
+        .with_context(|| export("jmf_plugin_free"))?;
+    let match_fn = instance.get_typed_func::<u32, u32>(&store, "jmf_plugin_match").ok();
+    let format_fn = instance.get_typed_func::<u32, u32>(&store, "jmf_plugin_format").ok();
+    let name_ptr = name_fn.call(&mut store, ())?;
+    let name = read_wasm_string(&store, memory, name_ptr)?;
+    Ok(Arc::new(WasmPlugin {
+        name,
+        has_matcher: match_fn.is_some(),
+        has_formatter: format_fn.is_some(),
+        instance: Mutex::new(WasmInstance {
+            store,
+            memory,
+            alloc_fn,
+            free_fn,
+            match_fn,
+            format_fn,
+        }),
+    }))
+}
+
+/// The NUL-terminated string at `ptr` in the memory of a module.
+#[cfg(feature = "wasm-plugins")]
+fn read_wasm_string(store: &Store<()>, memory: Memory, ptr: u32) -> Result<String> {
+    let data = memory
+        .data(store)
+        .get(ptr as usize..)
+        .context("String out of the plugin memory")?;
+    let end = data
+        .iter()
+        .position(|&byte| byte == 0)
+        .context("Unterminated string in the plugin memory")?;
+    Ok(String::from_utf8_lossy(&data[..end]).into_owned())
+}
+
+#[cfg(feature = "wasm-plugins")]
+impl WasmPlugin {
+    /// Calls the function `export` picks with `input`, or gives `None` when the module does not export it.
+    fn call(&self, export: fn(&WasmInstance) -> Option<TypedFunc<u32, u32>>, input: &Value) -> Result<Option<String>> {
+        let mut instance = self.instance.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Some(function) = export(&instance) else {
+            return Ok(None);
+        };
+        let WasmInstance {
+            store,
+            memory,
+            alloc_fn,
+            free_fn,
+            ..
+        } = &mut *instance;
+        let mut input = input.to_string().into_bytes();
+        input.push(0);
+        let size = u32::try_from(input.len()).context("Input too large for a WebAssembly plugin")?;
+        let input_ptr = alloc_fn.call(&mut *store, size)?;
+        memory
+            .write(&mut *store, input_ptr as usize, &input)
+            .map_err(|e| anyhow::anyhow!("Failed to pass the input to plugin {}: {}", self.name, e))?;
+        let output_ptr = function.call(&mut *store, input_ptr)?;
+        free_fn.call(&mut *store, input_ptr)?;
+        if output_ptr == 0 {
+            bail!("Plugin {} returned no result", self.name);
+        }
+        let text = read_wasm_string(store, *memory, output_ptr)?;
+        free_fn.call(&mut *store, output_ptr)?;
+        Ok(Some(text))
+    }
+}
+
+#[cfg(feature = "wasm-plugins")]
+impl Plugin for WasmPlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn find_matches(&self, caller: &Caller, opcodes: &[(usize, Opcode)]) -> Result<Vec<usize>> {
+        if !self.has_matcher {
+            return Ok(Vec::new());
+        }
+        let Some(output) = self.call(|instance| instance.match_fn, &method_json(caller, opcodes))? else {
+            return Ok(Vec::new());
+        };
+        serde_json::from_str(&output)
+            .with_context(|| format!("Plugin {} returned invalid offsets: {}", self.name, output))
+    }
+
+    fn has_matcher(&self) -> bool {
+        self.has_matcher
+    }
+
+    fn has_formatter(&self) -> bool {
+        self.has_formatter
+    }
+
+    fn format(&self, result: &Value) -> Result<String> {
+        match self.call(|instance| instance.format_fn, result)? {
+            Some(text) => Ok(text),
+            None => bail!("Plugin {} has no formatter", self.name),
+        }
+    }
+}
+
+/// A method as passed to `jmf_plugin_match`.
+#[cfg(any(feature = "dylib", feature = "wasm-plugins"))]
+fn method_json(caller: &Caller, opcodes: &[(usize, Opcode)]) -> Value {
+    json!({
+        "class": caller.class_name.replace('/', "."),
+        "method": caller.method_name,
+        "annotations": caller.annotations,
+        "opcodes": opcodes.iter().map(|(offset, opcode)| opcode_json(*offset, opcode)).collect::<Vec<_>>(),
+    })
+}
+
+/// An opcode as passed to `jmf_plugin_match`.
+pub fn opcode_json(offset: usize, opcode: &Opcode) -> Value {
+    let mut value = json!({ "offset": offset, "opcode": opcode_name(opcode) });
+    match opcode {
+        Opcode::Invokespecial(member_ref)
+        | Opcode::Invokestatic(member_ref)
+        | Opcode::Invokevirtual(member_ref)
+        | Opcode::Invokeinterface(member_ref, _)
+        | Opcode::Getfield(member_ref)
+        | Opcode::Getstatic(member_ref)
+        | Opcode::Putfield(member_ref)
+        | Opcode::Putstatic(member_ref) => {
+            value["class"] = json!(member_ref.class_name.replace('/', "."));
+            value["name"] = json!(member_ref.name_and_type.name);
+            value["descriptor"] = json!(member_ref.name_and_type.descriptor);
+        }
+        Opcode::New(class_name) => {
+            value["class"] = json!(class_name.replace('/', "."));
+        }
+        _ => {}
+    }
+    value
+}
@@ -0,0 +1,493 @@
+//! Policy files: a set of rules, each a search target carrying a severity and a message.
+//!
+//! ```toml
+//! [[rule]]
+//! id = "legacy-api"
+//! class = "com.example.Legacy"
+//! method = "oldApi"
+//! severity = "error"
+//! message = "use Foo.newApi() instead"
+//! help_url = "https://wiki.example.com/migrations/new-api"
+//!
+//! [[rule]]
+//! from = "com.app.web.**"
+//! to = "com.app.persistence.**"
+//! message = "the web layer must go through services"
+//! ```
+
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use cafebabe::{
+    attributes::BootstrapMethodEntry,
+    bytecode::Opcode,
+    constant_pool::{BootstrapArgument, Loadable, MethodHandle},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{pattern::Pattern, plugin::PluginMatcher, query::Query};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warn,
+    #[default]
+    Error,
+}
+
+impl Severity {
+    pub fn sarif_level(self) -> &'static str {
+        match self {
+            Severity::Info => "note",
+            Severity::Warn => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Severity::Info => "info",
+            Severity::Warn => "warn",
+            Severity::Error => "error",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Target {
+    Method {
+        class: String,
+        method: String,
+    },
+    /// Any method of any class directly in the package
+    Package(String),
+    /// Calls from classes matching `from` into classes matching `to`, as globs over dotted
+    /// class names where `*` stays within a package segment and `**` spans segments
+    Access {
+        from: String,
+        to: String,
+    },
+    Pattern(Pattern),
+    Query(Query),
+    Plugin(PluginMatcher),
+}
+
+/// The method containing the call sites a rule is matched against.
+pub struct Caller<'a> {
+    /// Internal class name
+    pub class_name: &'a str,
+    pub method_name: &'a str,
+    /// Dotted annotations of the method and of its class
+    pub annotations: &'a [String],
+    /// Bootstrap methods of the class, whose arguments hold the method handles of method references
+    pub bootstrap_methods: &'a [BootstrapMethodEntry<'a>],
+    /// Offsets and method names of the dynamic Groovy calls of the method, from `--groovy`
+    pub dynamic_calls: &'a [(usize, String)],
+    /// Whether the method is `synchronized`, holding the monitor of its object or class throughout
+    pub synchronized: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub id: Option<String>,
+    pub target: Target,
+    pub severity: Severity,
+    pub message: Option<String>,
+    /// Documentation of the rule, such as its migration guide
+    pub help_url: Option<String>,
+    /// Descriptor of the one overload a method rule matches, e.g. `(Ljava/lang/String;)V`
+    pub descriptor: Option<String>,
+}
+
+/// A rule as written in a policy file, or a saved query in `jmf.toml`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleEntry {
+    id: Option<String>,
+    class: Option<String>,
+    method: Option<String>,
+    package: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    pattern: Option<String>,
+    query: Option<String>,
+    #[serde(default)]
+    severity: Severity,
+    message: Option<String>,
+    help_url: Option<String>,
+    descriptor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    #[serde(default)]
+    rule: Vec<RuleEntry>,
+}
+
+impl Rule {
+    pub fn method(class: &str, method: &str) -> Self {
+        Self {
+            id: None,
+            target: Target::Method {
+                class: class.to_string(),
+                method: method.to_string(),
+            },
+            severity: Severity::default(),
+            message: None,
+            help_url: None,
+            descriptor: None,
+        }
+    }
+
+    pub fn package(package: &str) -> Self {
+        Self {
+            id: None,
+            target: Target::Package(package.to_string()),
+            severity: Severity::default(),
+            message: None,
+            help_url: None,
+            descriptor: None,
+        }
+    }
+
+    pub fn pattern(pattern: Pattern) -> Self {
+        Self {
+            id: None,
+            target: Target::Pattern(pattern),
+            severity: Severity::default(),
+            message: None,
+            help_url: None,
+            descriptor: None,
+        }
+    }
+
+    pub fn plugin(plugin: PluginMatcher) -> Self {
+        Self {
+            id: None,
+            target: Target::Plugin(plugin),
+            severity: Severity::default(),
+            message: None,
+            help_url: None,
+            descriptor: None,
+        }
+    }
+
+    pub fn query(query: Query) -> Self {
+        Self {
+            id: None,
+            target: Target::Query(query),
+            severity: Severity::default(),
+            message: None,
+            help_url: None,
+            descriptor: None,
+        }
+    }
+
+    /// A target as given on the command line: `<class>#<method>`, optionally followed by the descriptor
+    /// of one overload, or `<package>.*` for every class of a package.
+    pub fn parse(target: &str) -> Result<Self> {
+        match (target.split_once('#'), target.strip_suffix(".*")) {
+            (Some((class, method)), _) if !class.is_empty() && !method.is_empty() => match method.find('(') {
+                Some(start) if start > 0 => Ok(Self {
+                    descriptor: Some(method[start..].to_string()),
+                    ..Self::method(class, &method[..start])
+                }),
+                _ => Ok(Self::method(class, method)),
+            },
+            (None, Some(package)) if !package.is_empty() => Ok(Self::package(package)),
+            _ => bail!("Invalid target '{}', expected <class>#<method> or <package>.*", target),
+        }
+    }
+
+    /// Human readable target, e.g. `com.example.Legacy#oldApi`.
+    pub fn target(&self) -> String {
+        match &self.target {
+            Target::Method { class, method } => match &self.descriptor {
+                Some(descriptor) => format!("{}#{}{}", class, method, descriptor),
+                None => format!("{}#{}", class, method),
+            },
+            Target::Package(package) => format!("{}.*", package),
+            Target::Access { from, to } => format!("{} -> {}", from, to),
+            Target::Pattern(pattern) => pattern.to_string(),
+            Target::Query(query) => query.to_string(),
+            Target::Plugin(plugin) => format!("plugin:{}", plugin.0.name()),
+        }
+    }
+
+    /// Whether the rule matches many callees, so each violation should name the invoked method.
+    pub fn per_edge(&self) -> bool {
+        matches!(
+            self.target,
+            Target::Package(_) | Target::Access { .. } | Target::Query(_) | Target::Plugin(_)
+        )
+    }
+
+    /// Stable identifier used by SARIF; defaults to the target.
+    pub fn id(&self) -> String {
+        self.id.clone().unwrap_or_else(|| self.target())
+    }
+
+    /// Offsets within `opcodes` of the method of `caller` that this rule matches.
+    pub fn find_matches(&self, caller: &Caller, opcodes: &[(usize, Opcode)]) -> Result<Vec<usize>> {
+        let rules = std::slice::from_ref(self);
+        let matches = RuleIndex::new(rules).find_matches(rules, caller, opcodes)?;
+        Ok(matches.into_iter().map(|(_, offset)| offset).collect())
+    }
+}
+
+/// The rules of a scan indexed by what their call sites invoke, so that a single pass over the opcodes
+/// of a method matches every method, package and access rule at once, each invocation being looked up
+/// rather than compared with each rule. Patterns, queries and plugins match sequences of opcodes or
+/// whole methods, and see the same opcodes in turn.
+pub struct RuleIndex {
+    /// Method rules by internal class name and method name
+    methods: HashMap<String, HashMap<String, Vec<usize>>>,
+    /// Method rules by method name, for dynamic Groovy calls
+    method_names: HashMap<String, Vec<usize>>,
+    /// Package rules by internal package name
+    packages: HashMap<String, Vec<usize>>,
+    access: Vec<usize>,
+    sequences: Vec<usize>,
+}
+
+impl RuleIndex {
+    pub fn new(rules: &[Rule]) -> Self {
+        let mut index = Self {
+            methods: HashMap::new(),
+            method_names: HashMap::new(),
+            packages: HashMap::new(),
+            access: Vec::new(),
+            sequences: Vec::new(),
+        };
+        for (rule, entry) in rules.iter().enumerate() {
+            match &entry.target {
+                Target::Method { class, method } => {
+                    index
+                        .methods
+                        .entry(class.replace('.', "/"))
+                        .or_default()
+                        .entry(method.clone())
+                        .or_default()
+                        .push(rule);
+                    index.method_names.entry(method.clone()).or_default().push(rule);
+                }
+                Target::Package(package) => index.packages.entry(package.replace('.', "/")).or_default().push(rule),
+                Target::Access { .. } => index.access.push(rule),
+                Target::Pattern(_) | Target::Query(_) | Target::Plugin(_) => index.sequences.push(rule),
+            }
+        }
+        index
+    }
+
+    /// Rules, among the `rules` indexed, and offsets within `opcodes` of the method of `caller` that they
+    /// match, by rule and offset.
+    pub fn find_matches(
+        &self,
+        rules: &[Rule],
+        caller: &Caller,
+        opcodes: &[(usize, Opcode)],
+    ) -> Result<Vec<(usize, usize)>> {
+        let class_name = caller.class_name;
+        let caller_package = package_of(class_name);
+        let dotted_class_name = class_name.replace('/', ".");
+        let access: Vec<(usize, &str)> = self
+            .access
+            .iter()
+            .filter_map(|&rule| match &rules[rule].target {
+                Target::Access { from, to } if glob_match(from.as_bytes(), dotted_class_name.as_bytes()) => {
+                    Some((rule, to.as_str()))
+                }
+                _ => None,
+            })
+            .collect();
+        // Calls from within the target class itself are not reported
+        let methods = |class: &str, name: &str, descriptor: &str| -> Vec<usize> {
+            if class == class_name {
+                return Vec::new();
+            }
+            self.methods
+                .get(class)
+                .and_then(|methods| methods.get(name))
+                .map_or(Vec::new(), |indexed| {
+                    indexed
+                        .iter()
+                        .copied()
+                        .filter(|&rule| {
+                            rules[rule]
+                                .descriptor
+                                .as_deref()
+                                .is_none_or(|overload| overload == descriptor)
+                        })
+                        .collect()
+                })
+        };
+        let handle_rules = |handle: &MethodHandle| {
+            methods(
+                &handle.class_name,
+                &handle.member_ref.name,
+                &handle.member_ref.descriptor,
+            )
+        };
+        // Handles method references and dynamic constants are bootstrapped with
+        let bootstrap_rules = |attr_index: u16| -> Vec<usize> {
+            caller
+                .bootstrap_methods
+                .get(usize::from(attr_index))
+                .into_iter()
+                .flat_map(|entry| &entry.arguments)
+                .flat_map(|argument| match argument {
+                    BootstrapArgument::MethodHandle(handle) => handle_rules(handle),
+                    _ => Vec::new(),
+                })
+                .collect()
+        };
+
+        let mut matches = Vec::new();
+        for (offset, opcode) in opcodes {
+            let (member_ref, method_call) = match opcode {
+                Opcode::Invokespecial(member_ref)
+                | Opcode::Invokestatic(member_ref)
+                | Opcode::Invokevirtual(member_ref) => (member_ref, true),
+                Opcode::Invokeinterface(member_ref, _) => (member_ref, true),
+                // Handle references: handles loaded as constants, and those of invokedynamic bootstraps
+                Opcode::Ldc(loadable) | Opcode::LdcW(loadable) | Opcode::Ldc2W(loadable) => {
+                    let matched = match loadable {
+                        Loadable::MethodHandle(handle) => handle_rules(handle),
+                        Loadable::Dynamic(dynamic) => bootstrap_rules(dynamic.attr_index),
+                        _ => Vec::new(),
+                    };
+                    matches.extend(matched.into_iter().map(|rule| (rule, *offset)));
+                    continue;
+                }
+                Opcode::Invokedynamic(invoke_dynamic) => {
+                    let matched = bootstrap_rules(invoke_dynamic.attr_index);
+                    matches.extend(matched.into_iter().map(|rule| (rule, *offset)));
+                    continue;
+                }
+                _ => continue,
+            };
+            let callee_class = &member_ref.class_name;
+            if method_call {
+                let matched = methods(
+                    callee_class,
+                    &member_ref.name_and_type.name,
+                    &member_ref.name_and_type.descriptor,
+                );
+                matches.extend(matched.into_iter().map(|rule| (rule, *offset)));
+            }
+            let callee_package = package_of(callee_class);
+            // Calls from within the target package itself are not reported
+            if callee_package != caller_package {
+                if let Some(matched) = self.packages.get(callee_package) {
+                    matches.extend(matched.iter().map(|&rule| (rule, *offset)));
+                }
+            }
+            if !access.is_empty() {
+                let dotted_callee = callee_class.replace('/', ".");
+                matches.extend(
+                    access
+                        .iter()
+                        .filter(|(_, to)| glob_match(to.as_bytes(), dotted_callee.as_bytes()))
+                        .map(|&(rule, _)| (rule, *offset)),
+                );
+            }
+        }
+        // Dynamic calls name the method only
+        for (offset, name) in caller.dynamic_calls {
+            let indexed = self.method_names.get(name.as_str()).map_or(&[][..], Vec::as_slice);
+            matches.extend(
+                indexed
+                    .iter()
+                    .filter(|&&rule| {
+                        matches!(&rules[rule].target, Target::Method { class, .. } if class.replace('.', "/") != class_name)
+                    })
+                    .map(|&rule| (rule, *offset)),
+            );
+        }
+        for &rule in &self.sequences {
+            let offsets = match &rules[rule].target {
+                Target::Pattern(pattern) => pattern.find_matches(opcodes),
+                Target::Query(query) => query.find_matches(caller, opcodes),
+                Target::Plugin(plugin) => plugin.0.find_matches(caller, opcodes)?,
+                _ => Vec::new(),
+            };
+            matches.extend(offsets.into_iter().map(|offset| (rule, offset)));
+        }
+        matches.sort_unstable();
+        matches.dedup();
+        Ok(matches)
+    }
+}
+
+pub fn load(path: &Path) -> Result<Vec<Rule>> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read policy file {}", path.display()))?;
+    let policy: PolicyFile =
+        toml::from_str(&content).with_context(|| format!("Failed to parse policy file {}", path.display()))?;
+
+    policy
+        .rule
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| entry.into_rule(|| format!("Rule {} in policy file {}", index + 1, path.display())))
+        .collect()
+}
+
+impl RuleEntry {
+    /// `describe` names the entry in error messages, e.g. `Rule 2 in policy file policy.toml`.
+    pub fn into_rule(self, describe: impl Fn() -> String) -> Result<Rule> {
+        let target = match (
+            self.class,
+            self.method,
+            self.package,
+            self.from,
+            self.to,
+            self.pattern,
+            self.query,
+        ) {
+            (Some(class), Some(method), None, None, None, None, None) => Target::Method { class, method },
+            (None, None, Some(package), None, None, None, None) => Target::Package(package),
+            (None, None, None, Some(from), Some(to), None, None) => Target::Access { from, to },
+            (None, None, None, None, None, Some(pattern), None) => Target::Pattern(Pattern::parse(&pattern)?),
+            (None, None, None, None, None, None, Some(query)) => Target::Query(Query::parse(&query)?),
+            _ => bail!(
+                "{} needs either class and method, package, from and to, pattern, or query",
+                describe()
+            ),
+        };
+        if self.descriptor.is_some() && !matches!(target, Target::Method { .. }) {
+            bail!("{} has a descriptor, which only method rules take", describe());
+        }
+        Ok(Rule {
+            id: self.id,
+            target,
+            severity: self.severity,
+            message: self.message,
+            help_url: self.help_url,
+            descriptor: self.descriptor,
+        })
+    }
+}
+
+/// Internal package name of an internal class name, e.g. `com/example` for `com/example/Foo`.
+fn package_of(class_name: &str) -> &str {
+    class_name.rsplit_once('/').map_or("", |(package, _)| package)
+}
+
+/// Matches a dotted class name against a glob where `*` matches within a segment and `**` across segments.
+pub fn glob_match(glob: &[u8], text: &[u8]) -> bool {
+    match glob {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => (0..=text.len()).any(|index| glob_match(rest, &text[index..])),
+        [b'*', rest @ ..] => {
+            let segment_end = text.iter().position(|&c| c == b'.').unwrap_or(text.len());
+            (0..=segment_end).any(|index| glob_match(rest, &text[index..]))
+        }
+        [c, rest @ ..] => text.first() == Some(c) && glob_match(rest, &text[1..]),
+    }
+}
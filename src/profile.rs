@@ -0,0 +1,88 @@
+//! Timing breakdown of a scan, for `--profile`.
+
+use std::{
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Phase {
+    Walk,
+    Parse,
+    Match,
+    Output,
+}
+
+const PHASES: [(Phase, &str); 4] = [
+    (Phase::Walk, "walk"),
+    (Phase::Parse, "parse"),
+    (Phase::Match, "match"),
+    (Phase::Output, "output"),
+];
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static NANOS: [AtomicU64; 4] = [const { AtomicU64::new(0) }; 4];
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn disable() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Adds the time until it is dropped to its phase.
+pub struct Timer {
+    phase: Phase,
+    start: Option<Instant>,
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            NANOS[self.phase as usize].fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Starts timing `phase` when profiling is enabled.
+pub fn start(phase: Phase) -> Timer {
+    Timer {
+        phase,
+        start: enabled().then(Instant::now),
+    }
+}
+
+/// Runs `f`, adding its duration to `phase` when profiling is enabled.
+pub fn time<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    let _timer = start(phase);
+    f()
+}
+
+/// Time spent in `phase` so far, across threads.
+pub fn elapsed(phase: Phase) -> Duration {
+    Duration::from_nanos(NANOS[phase as usize].load(Ordering::Relaxed))
+}
+
+/// Clears the times of every phase, e.g. between the runs of `jmf bench`.
+pub fn reset() {
+    for nanos in &NANOS {
+        nanos.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Time spent per phase. Parse and match run on several threads, so their times add up across threads
+/// and can exceed the wall-clock time.
+pub fn report() -> String {
+    let mut output = vec!["Profile:".to_string()];
+    output.extend(
+        PHASES
+            .iter()
+            .map(|(phase, name)| format!(" - {}: {:.3?}", name, elapsed(*phase))),
+    );
+    output.join("\n")
+}
@@ -0,0 +1,361 @@
+//! A small query language combining call and caller conditions, evaluated at every call site:
+//!
+//! ```text
+//! calls("com.lib.Api#run") and caller(package ~ "com.app.web.*") and not caller(annotated("Deprecated"))
+//! ```
+//!
+//! `calls("<class>#<method>")` matches the invoked method; the method may be left out to match any
+//! method of the class. `caller(...)` holds conditions on the calling method: `package`, `class` and
+//! `method` compared with `==` (equal) or `~` (glob, where `*` stays within a package segment and `**`
+//! spans segments), and `annotated("<annotation>")` on the method or its class. Conditions combine
+//! with `and`, `or`, `not` and parentheses, both inside and outside `caller(...)`.
+//!
+//! Two conditions look at the call site within its method: `synchronized` holds in a `synchronized` method
+//! or block, and `guarded("<class>#<method>")` when the method calls a lock-acquiring method, given as in
+//! `calls(...)`, before it.
+
+use anyhow::{bail, Result};
+use cafebabe::{bytecode::Opcode, constant_pool::MemberRef};
+
+use crate::{
+    context,
+    policy::{glob_match, Caller},
+};
+
+#[derive(Debug, Clone)]
+pub struct Query {
+    source: String,
+    expr: Expr,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Equals,
+    Matches,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    /// Dotted class glob and method glob of the invoked method
+    Calls {
+        class: String,
+        method: String,
+    },
+    Package(Op, String),
+    Class(Op, String),
+    Method(Op, String),
+    Annotated(String),
+    Synchronized,
+    /// Dotted class glob and method glob of a method called before the call site
+    Guarded {
+        class: String,
+        method: String,
+    },
+}
+
+/// An invocation with the instructions of its method, which `synchronized` and `guarded(...)` look back over.
+struct CallSite<'a> {
+    callee: &'a MemberRef<'a>,
+    opcodes: &'a [(usize, Opcode<'a>)],
+    index: usize,
+}
+
+fn invoked<'a>(opcode: &'a Opcode<'a>) -> Option<&'a MemberRef<'a>> {
+    match opcode {
+        Opcode::Invokespecial(member_ref)
+        | Opcode::Invokestatic(member_ref)
+        | Opcode::Invokevirtual(member_ref)
+        | Opcode::Invokeinterface(member_ref, _) => Some(member_ref),
+        _ => None,
+    }
+}
+
+fn member_match(class: &str, method: &str, member_ref: &MemberRef) -> bool {
+    glob_match(class.as_bytes(), member_ref.class_name.replace('/', ".").as_bytes())
+        && glob_match(method.as_bytes(), member_ref.name_and_type.name.as_bytes())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token {
+    Ident(String),
+    Str(String),
+    Open,
+    Close,
+    Equals,
+    Tilde,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Ident(ident) => write!(f, "'{}'", ident),
+            Token::Str(value) => write!(f, "\"{}\"", value),
+            Token::Open => write!(f, "'('"),
+            Token::Close => write!(f, "')'"),
+            Token::Equals => write!(f, "'=='"),
+            Token::Tilde => write!(f, "'~'"),
+        }
+    }
+}
+
+/// A token, or the end of the query, for error messages.
+pub(crate) fn describe(token: Option<Token>) -> String {
+    token.map_or_else(|| "the end of the query".to_string(), |token| token.to_string())
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut parser = Parser {
+            input,
+            tokens: tokenize(input, "query")?,
+            position: 0,
+        };
+        let expr = parser.parse_or(false)?;
+        if let Some(token) = parser.peek() {
+            bail!("Invalid query '{}': unexpected {}", input, token);
+        }
+        Ok(Self {
+            source: input.trim().to_string(),
+            expr,
+        })
+    }
+
+    /// Offsets of the invocations within `opcodes` for which the query holds.
+    pub fn find_matches(&self, caller: &Caller, opcodes: &[(usize, Opcode)]) -> Vec<usize> {
+        opcodes
+            .iter()
+            .enumerate()
+            .filter(|(index, (_, opcode))| {
+                invoked(opcode).is_some_and(|callee| {
+                    self.expr.eval(
+                        caller,
+                        &CallSite {
+                            callee,
+                            opcodes,
+                            index: *index,
+                        },
+                    )
+                })
+            })
+            .map(|(_, (offset, _))| *offset)
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Query {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl Expr {
+    fn eval(&self, caller: &Caller, site: &CallSite) -> bool {
+        let caller_class = || caller.class_name.replace('/', ".");
+        match self {
+            Expr::And(left, right) => left.eval(caller, site) && right.eval(caller, site),
+            Expr::Or(left, right) => left.eval(caller, site) || right.eval(caller, site),
+            Expr::Not(expr) => !expr.eval(caller, site),
+            Expr::Calls { class, method } => member_match(class, method, site.callee),
+            Expr::Synchronized => {
+                caller.synchronized || context::monitor_depth(site.opcodes, site.opcodes[site.index].0) > 0
+            }
+            Expr::Guarded { class, method } => site.opcodes[..site.index]
+                .iter()
+                .filter_map(|(_, opcode)| invoked(opcode))
+                .any(|member_ref| member_match(class, method, member_ref)),
+            Expr::Package(op, value) => {
+                let class_name = caller_class();
+                compare(
+                    *op,
+                    value,
+                    class_name.rsplit_once('.').map_or("", |(package, _)| package),
+                )
+            }
+            Expr::Class(op, value) => compare(*op, value, &caller_class()),
+            Expr::Method(op, value) => compare(*op, value, caller.method_name),
+            Expr::Annotated(annotation) => caller
+                .annotations
+                .iter()
+                .any(|candidate| candidate == annotation || candidate.rsplit('.').next() == Some(annotation.as_str())),
+        }
+    }
+}
+
+fn compare(op: Op, value: &str, text: &str) -> bool {
+    match op {
+        Op::Equals => value == text,
+        Op::Matches => glob_match(value.as_bytes(), text.as_bytes()),
+    }
+}
+
+/// Tokens of a query, or of another expression in its syntax, which errors call `what`.
+pub(crate) fn tokenize(input: &str, what: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '~' => tokens.push(Token::Tilde),
+            '=' if chars.next_if_eq(&'=').is_some() => tokens.push(Token::Equals),
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => value.extend(chars.next()),
+                        Some(c) => value.push(c),
+                        None => bail!("Invalid {} '{}': unterminated string", what, input),
+                    }
+                }
+                tokens.push(Token::Str(value));
+            }
+            c if c.is_ascii_alphabetic() => {
+                let mut ident = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    ident.push(c);
+                }
+                tokens.push(Token::Ident(ident));
+            }
+            other => bail!("Invalid {} '{}': unexpected '{}'", what, input, other),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek() == Some(&Token::Ident(keyword.to_string())) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: Token, what: &str) -> Result<()> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            found => bail!(
+                "Invalid query '{}': expected {}, found {}",
+                self.input,
+                what,
+                describe(found)
+            ),
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        match self.next() {
+            Some(Token::Str(value)) => Ok(value),
+            found => bail!(
+                "Invalid query '{}': expected a string, found {}",
+                self.input,
+                describe(found)
+            ),
+        }
+    }
+
+    /// `in_caller` switches from call conditions to the caller conditions allowed inside `caller(...)`.
+    fn parse_or(&mut self, in_caller: bool) -> Result<Expr> {
+        let mut expr = self.parse_and(in_caller)?;
+        while self.eat_keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and(in_caller)?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self, in_caller: bool) -> Result<Expr> {
+        let mut expr = self.parse_not(in_caller)?;
+        while self.eat_keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not(in_caller)?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self, in_caller: bool) -> Result<Expr> {
+        if self.eat_keyword("not") {
+            return Ok(Expr::Not(Box::new(self.parse_not(in_caller)?)));
+        }
+        if self.peek() == Some(&Token::Open) {
+            self.position += 1;
+            let expr = self.parse_or(in_caller)?;
+            self.expect(Token::Close, "')'")?;
+            return Ok(expr);
+        }
+        match (self.next(), in_caller) {
+            (Some(Token::Ident(name)), false) if name == "calls" || name == "guarded" => {
+                self.expect(Token::Open, "'('")?;
+                let target = self.string()?;
+                self.expect(Token::Close, "')'")?;
+                let (class, method) = target.split_once('#').unwrap_or((&target, "*"));
+                let (class, method) = (class.to_string(), method.to_string());
+                Ok(if name == "calls" {
+                    Expr::Calls { class, method }
+                } else {
+                    Expr::Guarded { class, method }
+                })
+            }
+            (Some(Token::Ident(name)), false) if name == "synchronized" => Ok(Expr::Synchronized),
+            (Some(Token::Ident(name)), false) if name == "caller" => {
+                self.expect(Token::Open, "'('")?;
+                let expr = self.parse_or(true)?;
+                self.expect(Token::Close, "')'")?;
+                Ok(expr)
+            }
+            (Some(Token::Ident(name)), true) if name == "annotated" => {
+                self.expect(Token::Open, "'('")?;
+                let annotation = self.string()?;
+                self.expect(Token::Close, "')'")?;
+                Ok(Expr::Annotated(annotation))
+            }
+            (Some(Token::Ident(name)), true) if matches!(name.as_str(), "package" | "class" | "method") => {
+                let op = match self.next() {
+                    Some(Token::Equals) => Op::Equals,
+                    Some(Token::Tilde) => Op::Matches,
+                    found => bail!(
+                        "Invalid query '{}': expected '==' or '~', found {}",
+                        self.input,
+                        describe(found)
+                    ),
+                };
+                let value = self.string()?;
+                Ok(match name.as_str() {
+                    "package" => Expr::Package(op, value),
+                    "class" => Expr::Class(op, value),
+                    _ => Expr::Method(op, value),
+                })
+            }
+            (found, false) => bail!(
+                "Invalid query '{}': expected calls(...), caller(...), synchronized or guarded(...), found {}",
+                self.input,
+                describe(found)
+            ),
+            (found, true) => bail!(
+                "Invalid query '{}': expected package, class, method or annotated(...), found {}",
+                self.input,
+                describe(found)
+            ),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Shading/relocation rules, so that searches for `com.thirdparty` also match classes relocated to
+//! `com.app.shaded.thirdparty` by a shading plugin.
+
+use std::borrow::Cow;
+
+use cafebabe::bytecode::Opcode;
+
+#[derive(Debug, Clone)]
+pub struct Relocation {
+    /// Original package prefix, in internal form
+    original: String,
+    /// Relocated package prefix, in internal form
+    relocated: String,
+}
+
+impl Relocation {
+    /// Parses `original->relocated`, e.g. `com.thirdparty->com.app.shaded.thirdparty`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        match spec.split_once("->") {
+            Some((original, relocated)) if !original.trim().is_empty() && !relocated.trim().is_empty() => Ok(Self {
+                original: original.trim().replace('.', "/"),
+                relocated: relocated.trim().replace('.', "/"),
+            }),
+            _ => Err(format!("expected <original>-><relocated>, got '{}'", spec)),
+        }
+    }
+
+    /// Original name of a relocated internal class name, or `None` when it is not under the relocated prefix.
+    fn original_name(&self, class_name: &str) -> Option<String> {
+        let rest = class_name.strip_prefix(&self.relocated)?;
+        if rest.is_empty() || rest.starts_with('/') {
+            Some(format!("{}{}", self.original, rest))
+        } else {
+            None
+        }
+    }
+}
+
+/// Original name of `class_name` under the first matching relocation.
+pub fn original_name(relocations: &[Relocation], class_name: &str) -> Option<String> {
+    relocations
+        .iter()
+        .find_map(|relocation| relocation.original_name(class_name))
+}
+
+/// Copy of `opcodes` with relocated class names of invocations and instantiations mapped back to their
+/// original names, so that rules written against the original names match either form.
+pub fn unrelocate<'a>(relocations: &[Relocation], opcodes: &[(usize, Opcode<'a>)]) -> Vec<(usize, Opcode<'a>)> {
+    opcodes
+        .iter()
+        .map(|(offset, opcode)| {
+            let mut opcode = opcode.clone();
+            match &mut opcode {
+                Opcode::Invokespecial(member_ref)
+                | Opcode::Invokestatic(member_ref)
+                | Opcode::Invokevirtual(member_ref)
+                | Opcode::Invokeinterface(member_ref, _) => {
+                    if let Some(original) = original_name(relocations, &member_ref.class_name) {
+                        member_ref.class_name = Cow::Owned(original);
+                    }
+                }
+                Opcode::New(class_name) => {
+                    if let Some(original) = original_name(relocations, class_name) {
+                        *class_name = Cow::Owned(original);
+                    }
+                }
+                _ => {}
+            }
+            (*offset, opcode)
+        })
+        .collect()
+}
+
+/// Dotted relocated class at an invocation or instantiation, when it was found in its relocated form.
+pub fn relocated_class(relocations: &[Relocation], opcode: &Opcode) -> Option<String> {
+    let class_name = match opcode {
+        Opcode::Invokespecial(member_ref)
+        | Opcode::Invokestatic(member_ref)
+        | Opcode::Invokevirtual(member_ref)
+        | Opcode::Invokeinterface(member_ref, _) => &member_ref.class_name,
+        Opcode::New(class_name) => class_name,
+        _ => return None,
+    };
+    original_name(relocations, class_name).map(|_| class_name.replace('/', "."))
+}
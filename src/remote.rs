@@ -0,0 +1,289 @@
+//! Remote scan roots, copied into the per-user cache directory:
+//!
+//! - `http://` and `https://` URLs of jars and archives, downloaded with `curl` and verified against the
+//!   checksum files published next to them, as Maven repositories do with `app.jar.sha256`;
+//! - with the `object-store` feature, `s3://BUCKET/KEY` and `gs://BUCKET/OBJECT`, copied with the `aws`
+//!   and `gcloud` CLIs so that their standard credential chains apply, and verified against the
+//!   `KEY.sha512` or `KEY.sha256` objects next to them the same way.
+
+use std::{
+    env,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use sha2::{Digest, Sha256, Sha512};
+use tracing::debug;
+
+use crate::duplicates::sha256_hex;
+
+/// How downloads are checked against the checksum files published with them.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChecksumMode {
+    /// Verify the `.sha512` or `.sha256` file when one is published
+    #[default]
+    Auto,
+    /// Fail when neither is published
+    Require,
+    /// Do not look for checksum files
+    Off,
+}
+
+/// Whether a scan root is a URL.
+pub fn is_remote(root: &Path) -> bool {
+    let root = root.to_string_lossy();
+    is_http(&root) || is_object_store(&root)
+}
+
+fn is_http(url: &str) -> bool {
+    url.starts_with("https://") || url.starts_with("http://")
+}
+
+fn is_object_store(url: &str) -> bool {
+    url.starts_with("s3://") || url.starts_with("gs://")
+}
+
+/// The local copy of a remote scan root, downloaded unless the cache has it already, and verified.
+///
+/// HTTP downloads are revalidated with the server on every use, object store copies are checked against
+/// their published checksum and copied again when it no longer matches.
+pub fn fetch(url: &str, checksum: ChecksumMode) -> Result<PathBuf> {
+    let path = cache_path(url)?;
+    if is_http(url) || !path.is_file() {
+        download(url, &path)?;
+    } else if verify(url, &path, checksum).is_ok() {
+        debug!("Using cached copy of {}: {}", url, path.display());
+        return Ok(path);
+    } else {
+        debug!("Cached copy of {} failed verification, copying it again", url);
+        download(url, &path)?;
+    }
+    if let Err(e) = verify(url, &path, checksum) {
+        // A corrupt download must not be reused
+        let _ = fs::remove_file(&path);
+        return Err(e);
+    }
+    Ok(path)
+}
+
+/// Copies a remote scan root to `path`, through a partial file so that a failed download is never cached.
+fn download(url: &str, path: &Path) -> Result<()> {
+    let partial = path.with_file_name(format!(
+        "{}.part",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    let (program, mut command) = download_command(url, &partial, path)?;
+    let output = command
+        .output()
+        .with_context(|| format!("Failed to run {}, which {} scan roots need", program, scheme(url)))?;
+    if !output.status.success() {
+        let _ = fs::remove_file(&partial);
+        anyhow::bail!(
+            "Failed to download {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    if is_http(url) && String::from_utf8_lossy(&output.stdout).trim() == "304" {
+        debug!("Cached download of {} is up to date: {}", url, path.display());
+        let _ = fs::remove_file(&partial);
+        return Ok(());
+    }
+    fs::rename(&partial, path).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The program, and its command, copying a remote scan root to `partial`; HTTP downloads only transfer
+/// the file when it changed since the cached copy at `path`, and print the status code.
+fn download_command(url: &str, partial: &Path, path: &Path) -> Result<(&'static str, Command)> {
+    if !is_http(url) {
+        return object_store_command(url, partial);
+    }
+    let mut command = Command::new("curl");
+    command.args([
+        "--fail",
+        "--location",
+        "--silent",
+        "--show-error",
+        "--remote-time",
+        "--write-out",
+        "%{http_code}",
+    ]);
+    if path.is_file() {
+        command.arg("--time-cond").arg(path);
+    }
+    command.arg("--output").arg(partial).arg(url);
+    Ok(("curl", command))
+}
+
+#[cfg(feature = "object-store")]
+fn object_store_command(url: &str, path: &Path) -> Result<(&'static str, Command)> {
+    let program = if url.starts_with("s3://") { "aws" } else { "gcloud" };
+    let mut command = Command::new(program);
+    if program == "aws" {
+        command.args(["s3", "cp", "--only-show-errors", url]).arg(path);
+    } else {
+        command.args(["storage", "cp", "--quiet", url]).arg(path);
+    }
+    Ok((program, command))
+}
+
+#[cfg(not(feature = "object-store"))]
+fn object_store_command(url: &str, _path: &Path) -> Result<(&'static str, Command)> {
+    anyhow::bail!(
+        "{} scan roots need jmf built with the object-store feature: {}",
+        scheme(url),
+        url
+    )
+}
+
+fn scheme(url: &str) -> &str {
+    url.split_once("://").map_or(url, |(scheme, _)| scheme)
+}
+
+/// Downloads are cached by URL, keeping the file name for the archive kind and for messages.
+fn cache_path(url: &str) -> Result<PathBuf> {
+    let file_name = url
+        .split(['?', '#'])
+        .next()
+        .and_then(|path| path.rsplit('/').next())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("download");
+    let directory = cache_dir()?.join(&sha256_hex(url.as_bytes())[..16]);
+    create_private_dir(&directory)?;
+    Ok(directory.join(file_name))
+}
+
+/// The per-user cache of downloads, `$XDG_CACHE_HOME/jmf/downloads` or `~/.cache/jmf/downloads`.
+fn cache_dir() -> Result<PathBuf> {
+    let non_empty = |name: &str| env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    let base = non_empty("XDG_CACHE_HOME")
+        .or_else(|| non_empty("HOME").map(|home| home.join(".cache")))
+        .or_else(|| non_empty("LOCALAPPDATA"))
+        .context("Neither XDG_CACHE_HOME nor HOME is set, which the cache of URL scan roots needs")?;
+    Ok(base.join("jmf").join("downloads"))
+}
+
+/// Creates `directory` and its missing parents readable by the current user only, as an existing one
+/// is made too, so that no other user can plant or read a cached download.
+fn create_private_dir(directory: &Path) -> Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder
+        .create(directory)
+        .with_context(|| format!("Failed to create {}", directory.display()))?;
+    #[cfg(unix)]
+    for directory in [directory, directory.parent().unwrap_or(directory)] {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::set_permissions(directory, fs::Permissions::from_mode(0o700))
+            .with_context(|| format!("Failed to restrict {}", directory.display()))?;
+    }
+    Ok(())
+}
+
+fn verify(url: &str, path: &Path, checksum: ChecksumMode) -> Result<()> {
+    if checksum == ChecksumMode::Off {
+        return Ok(());
+    }
+    for algorithm in ["sha512", "sha256"] {
+        let Some(published) = published_checksum(&format!("{}.{}", url, algorithm), path, algorithm)? else {
+            continue;
+        };
+        let actual = file_digest(path, algorithm)?;
+        anyhow::ensure!(
+            published.eq_ignore_ascii_case(&actual),
+            "Checksum mismatch for {}: {} is {}, the download's is {}",
+            url,
+            algorithm,
+            published,
+            actual
+        );
+        debug!("Verified {} of {}", algorithm, url);
+        return Ok(());
+    }
+    anyhow::ensure!(
+        checksum != ChecksumMode::Require,
+        "No .sha512 or .sha256 checksum is published for {}",
+        url
+    );
+    debug!("No checksum published for {}", url);
+    Ok(())
+}
+
+/// The hex digest of a checksum file, whose first word it is, or `None` when it is not published: the
+/// server answers 404, or the object store has no such object. Any other failure fails the verification.
+fn published_checksum(url: &str, path: &Path, algorithm: &str) -> Result<Option<String>> {
+    let text = if is_http(url) {
+        let output = Command::new("curl")
+            .args([
+                "--location",
+                "--silent",
+                "--show-error",
+                "--write-out",
+                "\n%{http_code}",
+                url,
+            ])
+            .output()
+            .context("Failed to run curl")?;
+        anyhow::ensure!(
+            output.status.success(),
+            "Failed to fetch {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        let (body, status) = stdout.rsplit_once('\n').unwrap_or_default();
+        match status.trim() {
+            "404" => return Ok(None),
+            status if status.starts_with('2') => body.to_string(),
+            status => anyhow::bail!("Failed to fetch {}: HTTP status {}", url, status),
+        }
+    } else {
+        let file = path.with_file_name(format!(
+            "{}.{}",
+            path.file_name().unwrap_or_default().to_string_lossy(),
+            algorithm
+        ));
+        let (program, mut command) = object_store_command(url, &file)?;
+        let output = command
+            .output()
+            .with_context(|| format!("Failed to run {}, which {} scan roots need", program, scheme(url)))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if OBJECT_NOT_FOUND.iter().any(|message| stderr.contains(message)) {
+                return Ok(None);
+            }
+            anyhow::bail!("Failed to download {}: {}", url, stderr.trim());
+        }
+        let text = fs::read_to_string(&file).with_context(|| format!("Failed to read {}", file.display()));
+        let _ = fs::remove_file(&file);
+        text?
+    };
+    Ok(text.split_whitespace().next().map(str::to_string))
+}
+
+/// How the `aws` and `gcloud` CLIs report a missing object.
+const OBJECT_NOT_FOUND: [&str; 3] = ["(404)", "NoSuchKey", "matched no objects"];
+
+pub fn file_digest(path: &Path, algorithm: &str) -> Result<String> {
+    let mut file = File::open(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let digest = match algorithm {
+        "sha512" => {
+            let mut hasher = Sha512::new();
+            io::copy(&mut file, &mut hasher)?;
+            hasher.finalize().to_vec()
+        }
+        _ => {
+            let mut hasher = Sha256::new();
+            io::copy(&mut file, &mut hasher)?;
+            hasher.finalize().to_vec()
+        }
+    };
+    Ok(digest.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
@@ -0,0 +1,216 @@
+//! Resolution of method targets against the scanned classes, for `--resolve`: the declarations a target
+//! stands for, so that an audit of an overloaded method covers the overloads it is meant to.
+
+use anyhow::Result;
+use cafebabe::{
+    attributes::AttributeData,
+    descriptors::{FieldDescriptor, FieldType, ReturnDescriptor},
+    ClassFile, MethodAccessFlags, MethodInfo,
+};
+use serde::Serialize;
+
+use crate::{
+    disasm::method_descriptor,
+    members::generated_members,
+    policy::{Rule, Target},
+    scan::{ClassSource, ScanArgs, Scanner},
+};
+
+/// A declaration of a target method in the scanned classes.
+#[derive(Debug, Serialize)]
+pub struct ResolvedMethod {
+    /// Java declaration, e.g. `public static java.lang.String valueOf(int)`
+    pub declaration: String,
+    pub descriptor: String,
+    /// Generic signature, when the method has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    pub access_flags: Vec<&'static str>,
+    /// What the compiler generated the method for, e.g. `enum values()`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generated: Option<String>,
+    /// Scan root or jar declaring the method
+    pub origin: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Resolution {
+    pub target: String,
+    pub methods: Vec<ResolvedMethod>,
+}
+
+impl Resolution {
+    pub fn to_text(&self) -> String {
+        if self.methods.is_empty() {
+            return format!("{} is not declared in the scanned classes", self.target);
+        }
+        let mut output = vec![format!("{} resolves to:", self.target)];
+        for method in &self.methods {
+            output.push(format!(" - {} [origin: {}]", method.declaration, method.origin));
+            output.push(format!("   descriptor: {}", method.descriptor));
+            if let Some(signature) = &method.signature {
+                output.push(format!("   signature: {}", signature));
+            }
+            output.push(format!("   flags: {}", method.access_flags.join(", ")));
+            if let Some(generated) = &method.generated {
+                output.push(format!("   generated: {}", generated));
+            }
+        }
+        output.join("\n")
+    }
+}
+
+/// Declarations of the method targets among `rules`, in rule order; other targets are not resolved.
+pub fn resolve(scanner: &Scanner, scan_args: &ScanArgs, rules: &[Rule]) -> Result<Vec<Resolution>> {
+    let targets: Vec<(&str, &str)> = rules
+        .iter()
+        .filter_map(|rule| match &rule.target {
+            Target::Method { class, method } => Some((class.as_str(), method.as_str())),
+            _ => None,
+        })
+        .collect();
+    if targets.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut declarations: Vec<(usize, String, ResolvedMethod)> = scanner
+        .map_classes(|source, class_file| declared_targets(&targets, scan_args, source, class_file))?
+        .into_iter()
+        .flatten()
+        .collect();
+    // Classes are parsed in parallel
+    declarations.sort_by(|a, b| (a.0, &a.1, &a.2.origin).cmp(&(b.0, &b.1, &b.2.origin)));
+    let mut resolutions: Vec<Resolution> = targets
+        .iter()
+        .map(|(class, method)| Resolution {
+            target: format!("{}#{}", class, method),
+            methods: Vec::new(),
+        })
+        .collect();
+    for (index, _, method) in declarations {
+        resolutions[index].methods.push(method);
+    }
+    Ok(resolutions)
+}
+
+/// Methods of a class matching a target, with the index of the target and the descriptor to order by.
+fn declared_targets(
+    targets: &[(&str, &str)],
+    scan_args: &ScanArgs,
+    source: &ClassSource,
+    class_file: &ClassFile,
+) -> Vec<(usize, String, ResolvedMethod)> {
+    let class_name = class_file.this_class.replace('/', ".");
+    let generated = generated_members(class_file);
+    let mut declarations = Vec::new();
+    for (index, (class, method_name)) in targets.iter().enumerate() {
+        if *class != class_name {
+            continue;
+        }
+        for method in class_file.methods.iter().filter(|method| method.name == *method_name) {
+            let descriptor = method_descriptor(&method.descriptor);
+            declarations.push((
+                index,
+                descriptor.clone(),
+                ResolvedMethod {
+                    declaration: declaration(&class_name, method),
+                    descriptor,
+                    signature: method.attributes.iter().find_map(|attr| match &attr.data {
+                        AttributeData::Signature(signature) => Some(signature.to_string()),
+                        _ => None,
+                    }),
+                    access_flags: access_flags(method.access_flags),
+                    generated: generated
+                        .iter()
+                        .find(|(name, _)| *name == method.name)
+                        .map(|(_, note)| note.clone()),
+                    origin: scan_args.source_label(source),
+                },
+            ));
+        }
+    }
+    declarations
+}
+
+const ACCESS_FLAGS: [(MethodAccessFlags, &str); 12] = [
+    (MethodAccessFlags::PUBLIC, "ACC_PUBLIC"),
+    (MethodAccessFlags::PRIVATE, "ACC_PRIVATE"),
+    (MethodAccessFlags::PROTECTED, "ACC_PROTECTED"),
+    (MethodAccessFlags::STATIC, "ACC_STATIC"),
+    (MethodAccessFlags::FINAL, "ACC_FINAL"),
+    (MethodAccessFlags::SYNCHRONIZED, "ACC_SYNCHRONIZED"),
+    (MethodAccessFlags::BRIDGE, "ACC_BRIDGE"),
+    (MethodAccessFlags::VARARGS, "ACC_VARARGS"),
+    (MethodAccessFlags::NATIVE, "ACC_NATIVE"),
+    (MethodAccessFlags::ABSTRACT, "ACC_ABSTRACT"),
+    (MethodAccessFlags::STRICT, "ACC_STRICT"),
+    (MethodAccessFlags::SYNTHETIC, "ACC_SYNTHETIC"),
+];
+
+pub fn access_flags(flags: MethodAccessFlags) -> Vec<&'static str> {
+    ACCESS_FLAGS
+        .iter()
+        .filter(|(flag, _)| flags.contains(*flag))
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+/// The method as javap declares it, e.g. `public static void main(java.lang.String...)`.
+pub fn declaration(class_name: &str, method: &MethodInfo) -> String {
+    const MODIFIERS: [(MethodAccessFlags, &str); 9] = [
+        (MethodAccessFlags::PUBLIC, "public"),
+        (MethodAccessFlags::PRIVATE, "private"),
+        (MethodAccessFlags::PROTECTED, "protected"),
+        (MethodAccessFlags::STATIC, "static"),
+        (MethodAccessFlags::FINAL, "final"),
+        (MethodAccessFlags::SYNCHRONIZED, "synchronized"),
+        (MethodAccessFlags::NATIVE, "native"),
+        (MethodAccessFlags::ABSTRACT, "abstract"),
+        (MethodAccessFlags::STRICT, "strictfp"),
+    ];
+    let mut words: Vec<String> = MODIFIERS
+        .iter()
+        .filter(|(flag, _)| method.access_flags.contains(*flag))
+        .map(|(_, modifier)| modifier.to_string())
+        .collect();
+    let mut parameters: Vec<String> = method.descriptor.parameters.iter().map(java_type).collect();
+    if method.access_flags.contains(MethodAccessFlags::VARARGS) {
+        if let Some(last) = parameters.last_mut() {
+            if let Some(element) = last.strip_suffix("[]") {
+                *last = format!("{}...", element);
+            }
+        }
+    }
+    let parameters = parameters.join(", ");
+    match (method.name.as_ref(), &method.descriptor.return_type) {
+        ("<init>", _) => words.push(format!("{}({})", class_name, parameters)),
+        ("<clinit>", _) => words.push("{}".to_string()),
+        (name, return_type) => {
+            words.push(match return_type {
+                ReturnDescriptor::Return(return_type) => java_type(return_type),
+                ReturnDescriptor::Void => "void".to_string(),
+            });
+            words.push(format!("{}({})", name, parameters));
+        }
+    }
+    words.join(" ")
+}
+
+pub fn java_type(descriptor: &FieldDescriptor) -> String {
+    let element = match &descriptor.field_type {
+        FieldType::Byte => "byte".to_string(),
+        FieldType::Char => "char".to_string(),
+        FieldType::Double => "double".to_string(),
+        FieldType::Float => "float".to_string(),
+        FieldType::Integer => "int".to_string(),
+        FieldType::Long => "long".to_string(),
+        FieldType::Short => "short".to_string(),
+        FieldType::Boolean => "boolean".to_string(),
+        FieldType::Object(class_name) => class_name
+            .segments
+            .iter()
+            .map(|segment| segment.name.as_ref())
+            .collect::<Vec<_>>()
+            .join("."),
+    };
+    format!("{}{}", element, "[]".repeat(descriptor.dimensions.into()))
+}
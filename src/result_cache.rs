@@ -0,0 +1,116 @@
+//! `--result-cache`: the outcome of every class file of an archive scan root, stored in a directory under a
+//! key hashing the archive's contents and the query, so that searching unchanged release jars again, as a
+//! nightly job does, reads the calls back rather than analyzing the classes.
+//!
+//! Entries hold what the analysis of each class file gives, without the labels that depend on how the
+//! archive is scanned, such as its origin, which are set again when an entry is read.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{analysis::FoundCall, duplicates::sha256_hex, remote, scan::ScanError};
+
+/// Bumped when entries change shape, so that older ones are never read.
+const VERSION: u32 = 3;
+
+/// The entries of the archives searched for the same query, in a directory.
+pub struct ResultCache {
+    directory: PathBuf,
+    /// Hash of everything besides the archive that decides the outcome of its class files
+    query: String,
+}
+
+/// A call with the fields that reports leave out, which later stages need.
+#[derive(Serialize, Deserialize)]
+pub struct CachedCall {
+    pub call: FoundCall,
+    pub rule: usize,
+    pub source_file: Option<Arc<str>>,
+    pub callee: Option<String>,
+    pub method_descriptor: Arc<str>,
+}
+
+impl From<&FoundCall> for CachedCall {
+    fn from(call: &FoundCall) -> Self {
+        Self {
+            call: FoundCall {
+                origin: None,
+                also_in: Vec::new(),
+                ..call.clone()
+            },
+            rule: call.rule,
+            source_file: call.source_file.clone(),
+            callee: call.callee.clone(),
+            method_descriptor: call.method_descriptor.clone(),
+        }
+    }
+}
+
+impl CachedCall {
+    pub fn to_call(&self) -> FoundCall {
+        FoundCall {
+            rule: self.rule,
+            source_file: self.source_file.clone(),
+            callee: self.callee.clone(),
+            method_descriptor: self.method_descriptor.clone(),
+            ..self.call.clone()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum Outcome {
+    Calls(Vec<CachedCall>),
+    Error(ScanError),
+    /// Named `.class` without being a class file, counted in the summary
+    NotClassFile,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Entry {
+    /// Outcome of each class file, by its path in the archive
+    pub classes: HashMap<String, Outcome>,
+    /// Notes on the compiler-generated members of the classes, by dotted class name and method name
+    pub generated_members: Vec<(String, String, String)>,
+}
+
+impl ResultCache {
+    /// `query` describes the rules and the options that change what the analysis of a class file gives.
+    pub fn new(directory: &Path, query: &str) -> Self {
+        Self {
+            directory: directory.to_path_buf(),
+            query: sha256_hex(format!("{}\0{}\0{}", VERSION, env!("CARGO_PKG_VERSION"), query).as_bytes()),
+        }
+    }
+
+    /// The key of the entry of an archive for the query, hashing the archive as it is read rather than
+    /// holding it in memory.
+    pub fn key(&self, archive: &Path) -> Result<String> {
+        let digest = remote::file_digest(archive, "sha256")?;
+        Ok(sha256_hex(format!("{}\0{}", digest, self.query).as_bytes()))
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.directory.join(format!("{}.json", &key[..32]))
+    }
+
+    /// The entry stored under the key, if any; an unreadable one is none.
+    pub fn load(&self, key: &str) -> Option<Entry> {
+        let bytes = fs::read(self.entry_path(key)).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    pub fn store(&self, key: &str, entry: &Entry) -> Result<()> {
+        let path = self.entry_path(key);
+        fs::create_dir_all(&self.directory)
+            .with_context(|| format!("Failed to create {}", self.directory.display()))?;
+        fs::write(&path, serde_json::to_vec(entry)?).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
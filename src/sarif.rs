@@ -0,0 +1,122 @@
+//! SARIF 2.1.0 output, as consumed by code scanning dashboards.
+
+use serde_json::{json, Value};
+
+use crate::{policy::Rule, scan::ScanSummary, FoundCall};
+
+pub fn to_sarif(rules: &[Rule], calls: &[FoundCall], summary: &ScanSummary) -> String {
+    serde_json::to_string_pretty(&log(rules, calls, summary)).unwrap()
+}
+
+pub fn log(rules: &[Rule], calls: &[FoundCall], summary: &ScanSummary) -> Value {
+    let rule_descriptors: Vec<Value> = rules
+        .iter()
+        .map(|rule| {
+            let mut descriptor = json!({
+                "id": rule.id(),
+                "shortDescription": { "text": rule.target() },
+                "defaultConfiguration": { "level": rule.severity.sarif_level() },
+            });
+            if let Some(message) = &rule.message {
+                descriptor["fullDescription"] = json!({ "text": message });
+            }
+            if let Some(help_url) = &rule.help_url {
+                descriptor["helpUri"] = json!(help_url);
+            }
+            descriptor
+        })
+        .collect();
+
+    let results: Vec<Value> = calls
+        .iter()
+        .map(|call| {
+            let rule = &rules[call.rule];
+            let caller = format!("{}#{}", call.class_name.replace('/', "."), call.method_name);
+            let callee = match &call.callee {
+                Some(callee) if rule.per_edge() => callee.clone(),
+                _ => rule.target(),
+            };
+            let text = match &rule.message {
+                Some(message) => format!("Call to {} from {}: {}", callee, caller, message),
+                None => format!("Call to {} from {}", callee, caller),
+            };
+            let mut result = json!({
+                "ruleId": rule.id(),
+                "ruleIndex": call.rule,
+                "level": rule.severity.sarif_level(),
+                "message": { "text": text },
+                "partialFingerprints": { "jmfCallSite/v1": call.fingerprint },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": call.source_path() },
+                        "region": { "startLine": call.line_number },
+                    },
+                    "logicalLocations": [{ "fullyQualifiedName": caller, "kind": "function" }],
+                }],
+            });
+            // The enclosing method, for viewers that show the context of a result
+            if let Some(lines) = call.method_lines {
+                result["locations"][0]["physicalLocation"]["contextRegion"] =
+                    json!({ "startLine": lines.first, "endLine": lines.last });
+            }
+            if let Some(origin) = &call.origin {
+                result["properties"] = json!({ "origin": origin });
+            }
+            if let Some(suppressed) = &call.suppressed {
+                result["suppressions"] = json!([{
+                    "kind": "inSource",
+                    "justification": format!("Suppressed by {}", suppressed),
+                }]);
+            }
+            result
+        })
+        .collect();
+
+    // Class files that could not be analyzed are tool notifications rather than results
+    let mut notifications: Vec<Value> = summary
+        .errors
+        .iter()
+        .map(|error| {
+            let mut notification = json!({
+                "level": "error",
+                "message": { "text": error.to_string() },
+            });
+            if let Some(location) = &error.location {
+                notification["locations"] =
+                    json!([{ "physicalLocation": { "artifactLocation": { "uri": location } } }]);
+            }
+            notification
+        })
+        .collect();
+    if summary.skipped > 0 {
+        notifications.push(json!({
+            "level": "note",
+            "message": { "text": format!("Skipped {} class files larger than --max-class-size", summary.skipped) },
+        }));
+    }
+    if summary.not_class_files > 0 {
+        notifications.push(json!({
+            "level": "note",
+            "message": { "text": format!("Skipped {} .class files that are not class files", summary.not_class_files) },
+        }));
+    }
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "jmf",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rule_descriptors,
+                },
+            },
+            "results": results,
+            "invocations": [{
+                "executionSuccessful": true,
+                "toolExecutionNotifications": notifications,
+            }],
+        }],
+    })
+}
@@ -0,0 +1,154 @@
+//! Attribution of the methods the Scala compiler synthesizes to the definitions they belong to, so that a
+//! call in the body of a lambda or of an `object` reads like the Scala source:
+//!
+//! - `$anonfun$run$1`, the body of a function literal in `run`, is reported as `run`;
+//! - `$init$`, the initializer of a trait, as `<init>`;
+//! - the module class `Service$` of `object Service` as `Service`, as is the static forwarder `Service.run`
+//!   that delegates to `Service$.MODULE$.run`;
+//! - names encoding operators, as `$plus$plus`, are decoded to `++`.
+
+use cafebabe::{
+    attributes::AttributeData, bytecode::Opcode, ClassAccessFlags, ClassFile, MethodAccessFlags, MethodInfo,
+};
+
+/// The definition a method of a Scala class is reported as.
+#[derive(Debug, PartialEq)]
+pub struct LogicalCaller {
+    /// Slashed name of the class or object
+    pub class_name: String,
+    pub method_name: String,
+    /// How the definition was compiled, e.g. `anonymous function $anonfun$run$1`
+    pub note: String,
+}
+
+/// Whether a class was compiled by Scala: it carries the Scala signature attributes, or has the shape of a
+/// module class, of a companion class or of a class with function literals.
+pub fn is_scala(class_file: &ClassFile) -> bool {
+    class_file
+        .attributes
+        .iter()
+        .any(|attr| matches!(attr.name.as_ref(), "ScalaSig" | "Scala" | "ScalaInlineInfo" | "TASTY"))
+        || is_module_class(class_file)
+        || class_file.methods.iter().any(|method| is_forwarder(class_file, method))
+        || class_file
+            .methods
+            .iter()
+            .any(|method| method.name.starts_with("$anonfun$"))
+}
+
+/// The definition a method of a Scala class belongs to, or `None` when it is reported under its own name.
+pub fn logical_caller(class_file: &ClassFile, method: &MethodInfo) -> Option<LogicalCaller> {
+    let mut class_name = class_file.this_class.to_string();
+    let mut method_name = method.name.to_string();
+    let mut notes = Vec::new();
+    if is_module_class(class_file) {
+        class_name.pop();
+        notes.push(format!("object {}", class_file.this_class.replace('/', ".")));
+    } else if is_forwarder(class_file, method) {
+        notes.push("companion forwarder".to_string());
+    }
+    if let Some(function) = method_name.strip_prefix("$anonfun$") {
+        let function = function.strip_suffix("$adapted").unwrap_or(function);
+        let enclosing = function.trim_end_matches(|c: char| c.is_ascii_digit() || c == '$');
+        notes.push(format!("anonymous function {}", method.name));
+        method_name = if enclosing.is_empty() || enclosing == "new" {
+            "<init>".to_string()
+        } else {
+            enclosing.to_string()
+        };
+    } else if method_name == "$init$" && class_file.access_flags.contains(ClassAccessFlags::INTERFACE) {
+        notes.push("trait initializer".to_string());
+        method_name = "<init>".to_string();
+    }
+    let decoded = decode(&method_name);
+    if decoded != method_name {
+        notes.push(format!("encoded {}", method_name));
+        method_name = decoded;
+    }
+    (!notes.is_empty()).then(|| LogicalCaller {
+        class_name,
+        method_name,
+        note: notes.join(", "),
+    })
+}
+
+/// `object Service` compiles to the class `Service$`, holding its instance in `MODULE$`.
+fn is_module_class(class_file: &ClassFile) -> bool {
+    class_file.this_class.ends_with('$') && class_file.fields.iter().any(|field| field.name == "MODULE$")
+}
+
+/// A static method of a companion class calling the method of the same name on the companion object.
+fn is_forwarder(class_file: &ClassFile, method: &MethodInfo) -> bool {
+    if !method.access_flags.contains(MethodAccessFlags::STATIC) {
+        return false;
+    }
+    let module_class = format!("{}$", class_file.this_class);
+    let Some(bytecode) = method.attributes.iter().find_map(|attr| match &attr.data {
+        AttributeData::Code(code) => code.bytecode.as_ref(),
+        _ => None,
+    }) else {
+        return false;
+    };
+    let mut opcodes = bytecode.opcodes.iter().map(|(_, opcode)| opcode);
+    matches!(
+        opcodes.next(),
+        Some(Opcode::Getstatic(member_ref))
+            if member_ref.class_name == module_class && member_ref.name_and_type.name == "MODULE$"
+    ) && opcodes.any(|opcode| {
+        matches!(
+            opcode,
+            Opcode::Invokevirtual(member_ref)
+                if member_ref.class_name == module_class && member_ref.name_and_type.name == method.name
+        )
+    })
+}
+
+const OPERATORS: [(&str, char); 18] = [
+    ("$tilde", '~'),
+    ("$eq", '='),
+    ("$less", '<'),
+    ("$greater", '>'),
+    ("$bang", '!'),
+    ("$hash", '#'),
+    ("$percent", '%'),
+    ("$up", '^'),
+    ("$amp", '&'),
+    ("$bar", '|'),
+    ("$times", '*'),
+    ("$div", '/'),
+    ("$plus", '+'),
+    ("$minus", '-'),
+    ("$colon", ':'),
+    ("$bslash", '\\'),
+    ("$qmark", '?'),
+    ("$at", '@'),
+];
+
+/// A name as written in Scala, e.g. `++` for `$plus$plus` and `é` for `$u00E9`.
+pub fn decode(name: &str) -> String {
+    let mut decoded = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(index) = rest.find('$') {
+        decoded.push_str(&rest[..index]);
+        rest = &rest[index..];
+        if let Some((code, operator)) = OPERATORS.iter().find(|(code, _)| rest.starts_with(code)) {
+            decoded.push(*operator);
+            rest = &rest[code.len()..];
+            continue;
+        }
+        let unicode = rest
+            .get(2..6)
+            .filter(|_| rest.starts_with("$u"))
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .and_then(char::from_u32);
+        if let Some(c) = unicode {
+            decoded.push(c);
+            rest = &rest[6..];
+        } else {
+            decoded.push('$');
+            rest = &rest[1..];
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
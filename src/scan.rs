@@ -0,0 +1,1613 @@
+//! Discovery of the class files under the scan roots, shared by all subcommands.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufRead, Cursor, IsTerminal, Read},
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use cafebabe::{parse_class, parse_class_with_options, ClassFile, ParseError, ParseOptions};
+use clap::{Args, ValueEnum};
+use flate2::read::GzDecoder;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::{DirEntry, WalkBuilder, WalkState};
+use memmap2::Mmap;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, debug_span, error, Level};
+use zip::ZipArchive;
+
+use crate::{
+    analysis::{entry_capacity, is_archive, AnalysisError},
+    changes::ChangedSources,
+    color::ColorChoice,
+    graph::CallGraph,
+    image,
+    jar_index::{self, BloomFilter, JarIndex},
+    manifest::Manifest,
+    profile::{self, Phase},
+    remote::{self, ChecksumMode},
+    tar::{self, EntryKind},
+    verify::{self, Verifier},
+};
+
+#[derive(Args, Debug, Clone)]
+pub struct ScanArgs {
+    /// Directory, class file, jar, zip, tar, container image (`docker://REFERENCE`, `docker-archive:PATH`),
+    /// or URL of a jar or archive to scan for class files (repeatable); `-` reads a list of them, one per
+    /// line, from standard input
+    #[arg(short = 's', long = "scan", default_value = "./target/classes")]
+    pub scan_roots: Vec<PathBuf>,
+
+    /// Label results from a scan root with a name instead of its path, as `NAME=ROOT` (repeatable)
+    #[arg(long = "root-alias", value_name = "NAME=ROOT", value_parser = parse_root_alias)]
+    pub root_aliases: Vec<(String, PathBuf)>,
+
+    /// Only scan classes of at least this version, as a Java release (8) or major version (52)
+    #[arg(long = "min-class-version", value_parser = parse_class_version)]
+    pub min_class_version: Option<u16>,
+
+    /// Only scan classes of at most this version, as a Java release (8) or major version (52)
+    #[arg(long = "max-class-version", value_parser = parse_class_version)]
+    pub max_class_version: Option<u16>,
+
+    /// Follow symbolic links while walking directories; link cycles are detected and skipped
+    #[arg(long = "follow-symlinks")]
+    pub follow_symlinks: bool,
+
+    /// Descend at most this many directory levels below each scan root
+    #[arg(long = "max-depth")]
+    pub max_depth: Option<usize>,
+
+    /// How jars and archives downloaded from URLs are checked against the checksum files published with them
+    #[arg(long = "remote-checksum", value_enum, default_value_t = ChecksumMode::Auto)]
+    pub remote_checksum: ChecksumMode,
+
+    /// Fail unless the jar or archive scan root has this SHA-256 digest, given as `ROOT=DIGEST` when there
+    /// are several scan roots (repeatable)
+    #[arg(long = "verify-sha256", value_name = "[ROOT=]DIGEST", value_parser = verify::parse_checksum)]
+    pub verify_sha256: Vec<(Option<PathBuf>, String)>,
+
+    /// Fail unless every scan root is a signed jar whose signature `jarsigner -verify -strict` accepts
+    #[arg(long = "verify-signature")]
+    pub verify_signature: bool,
+
+    /// Keystore of the signers that `--verify-signature` trusts, instead of the JDK's `cacerts`; its
+    /// password, if any, is read from `JMF_KEYSTORE_PASSWORD`
+    #[arg(long = "signature-keystore", value_name = "PATH")]
+    pub signature_keystore: Option<PathBuf>,
+
+    /// Descend into archives nested in archive scan roots, such as the jars of a zip, this many levels deep
+    /// [default: 1, or 0 for jars]
+    #[arg(long = "archive-depth", value_name = "N")]
+    pub archive_depth: Option<usize>,
+
+    /// Also scan the jars and directories that the `Class-Path` of a jar scan root's manifest lists, and
+    /// theirs in turn
+    #[arg(long = "follow-manifest-classpath")]
+    pub follow_manifest_classpath: bool,
+
+    /// Do not cross file system boundaries while walking directories
+    #[arg(long = "one-file-system")]
+    pub one_file_system: bool,
+
+    /// Skip files matched by `.gitignore`, `.ignore` and git exclude files
+    #[arg(long = "respect-gitignore")]
+    pub respect_gitignore: bool,
+
+    /// Only scan the classes compiled from source files that git reports as changed since this revision,
+    /// committed or not, e.g. `origin/main`
+    #[arg(long = "changed-since", value_name = "REV")]
+    pub changed_since: Option<String>,
+
+    /// Only scan class files whose path matches this glob, e.g. `**/core/**` (repeatable)
+    #[arg(long = "path-include", value_parser = parse_glob)]
+    pub path_includes: Vec<Glob>,
+
+    /// Never read class files whose path matches this glob, e.g. `**/generated/**` (repeatable)
+    #[arg(long = "path-exclude", value_parser = parse_glob)]
+    pub path_excludes: Vec<Glob>,
+
+    /// Give up on a class file once analyzing it takes longer than this, e.g. `5s` or `500ms`; reading and
+    /// parsing count towards it, but are only checked once they are done
+    #[arg(long = "file-timeout", value_parser = parse_duration)]
+    pub file_timeout: Option<Duration>,
+
+    /// Print the scan roots and the class files that would be analyzed, without parsing them
+    #[arg(long = "dry-run")]
+    pub dry_run: bool,
+
+    /// Skip class files larger than this, e.g. `16M`; skipped files are counted in the summary
+    #[arg(long = "max-class-size", value_parser = parse_size)]
+    pub max_class_size: Option<u64>,
+
+    /// Directory of a persistent index of the classes each archive scan root references, with which searches
+    /// skip the archives that cannot call their targets; archives are indexed the first time they are scanned
+    #[arg(long = "jar-index", value_name = "DIR")]
+    pub jar_index: Option<PathBuf>,
+
+    /// Directory of a persistent cache of the calls searches find in each archive scan root, keyed by the
+    /// archive's contents and the query, so that unchanged archives are not analyzed again
+    #[arg(long = "result-cache", value_name = "DIR")]
+    pub result_cache: Option<PathBuf>,
+
+    /// Write a JSON manifest of every artifact and class file analyzed, with their SHA-256 hashes, sizes and
+    /// class file versions, as evidence of what a scan covered
+    #[arg(long = "emit-manifest", value_name = "PATH")]
+    pub emit_manifest: Option<PathBuf>,
+
+    /// How the paths of class files and archive entries appear in reports
+    #[arg(long = "paths", value_enum, default_value_t = PathDisplay::Relative)]
+    pub paths: PathDisplay,
+
+    /// When to color text output
+    #[arg(long = "color", value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Format of log lines
+    #[arg(long = "log-format", value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// Write log lines to this file instead of standard output
+    #[arg(long = "log-file")]
+    pub log_file: Option<PathBuf>,
+
+    /// Print a timing breakdown (walk, parse, match, output) to standard error
+    #[arg(long = "profile")]
+    pub profile: bool,
+
+    /// Scan on a single thread, walking directories in name order, so that verbose output and logs come out
+    /// the same on every run
+    #[arg(long = "deterministic")]
+    pub deterministic: bool,
+
+    /// Stop the scan at the first class file that cannot be analyzed, instead of reporting it with the results
+    #[arg(long = "fail-fast", overrides_with = "keep_going")]
+    pub fail_fast: bool,
+
+    /// Report the class files that cannot be analyzed with the results, the default; overrides an earlier
+    /// `--fail-fast`
+    #[arg(long = "keep-going", overrides_with = "fail_fast")]
+    pub keep_going: bool,
+
+    /// Retry class files that fail to parse without their bytecode, so that structural reports still see them
+    #[arg(long = "lenient")]
+    pub lenient: bool,
+
+    #[arg(short = 'v', long = "verbose")]
+    pub verbose: bool,
+}
+
+impl ScanArgs {
+    /// The options deciding which class files an archive lists, for the keys of what is stored per archive.
+    pub fn listing_options(&self) -> String {
+        let globs = |globs: &[Glob]| globs.iter().map(Glob::glob).collect::<Vec<_>>().join(",");
+        format!(
+            "{:?}\0{}\0{}\0{:?}",
+            self.archive_depth,
+            globs(&self.path_includes),
+            globs(&self.path_excludes),
+            self.max_class_size
+        )
+    }
+
+    /// Replaces the scan root `-` with the scan roots listed on standard input, one per line.
+    pub fn read_stdin_roots(&mut self) -> Result<()> {
+        let stdin_root = Path::new("-");
+        if !self.scan_roots.iter().any(|root| root == stdin_root) {
+            return Ok(());
+        }
+        let mut listed = Vec::new();
+        for line in io::stdin().lock().lines() {
+            let line = line.context("Failed to read scan roots from standard input")?;
+            let line = line.trim();
+            if !line.is_empty() {
+                listed.push(PathBuf::from(line));
+            }
+        }
+        for root in std::mem::take(&mut self.scan_roots) {
+            if root == stdin_root {
+                self.scan_roots.append(&mut listed);
+            } else {
+                self.scan_roots.push(root);
+            }
+        }
+        anyhow::ensure!(
+            !self.scan_roots.is_empty(),
+            "No scan roots were listed on standard input"
+        );
+        Ok(())
+    }
+
+    /// Label of the results from a class file: the symbolic name of its OSGi bundle, else the label of its
+    /// scan root.
+    pub fn source_label(&self, source: &ClassSource) -> String {
+        source
+            .bundle
+            .clone()
+            .unwrap_or_else(|| self.origin_label(&source.origin))
+    }
+
+    /// Location of a class file as `--paths` displays it: relative to its scan root, as `com/example/Foo.class`
+    /// or `lib/app.jar!/com/example/Foo.class`, below the absolute path of its root, or none.
+    pub fn display_location(&self, source: &ClassSource) -> Option<String> {
+        let location = source.location();
+        let root = source.origin.display().to_string();
+        let within_root = location.strip_prefix(&root).unwrap_or(&location);
+        match self.paths {
+            PathDisplay::None => None,
+            PathDisplay::Relative => {
+                let relative = within_root.trim_start_matches(['/', '!']);
+                // A class file scan root is its own location
+                Some(if relative.is_empty() {
+                    source.name.clone()
+                } else {
+                    relative.to_string()
+                })
+            }
+            PathDisplay::Absolute => {
+                let absolute = source.origin.canonicalize().unwrap_or_else(|_| source.origin.clone());
+                Some(format!("{}{}", absolute.display(), within_root))
+            }
+        }
+    }
+
+    /// Label of the results from a scan root: its `--root-alias`, the file name of a jar, or the path of a
+    /// directory.
+    pub fn origin_label(&self, origin: &Path) -> String {
+        if let Some((alias, _)) = self.root_aliases.iter().find(|(_, root)| root == origin) {
+            return alias.clone();
+        }
+        match origin.file_name() {
+            Some(file_name) if is_archive(origin) => file_name.to_string_lossy().into_owned(),
+            _ => origin.display().to_string(),
+        }
+    }
+}
+
+fn parse_root_alias(value: &str) -> Result<(String, PathBuf), String> {
+    match value.split_once('=') {
+        Some((alias, root)) if !alias.trim().is_empty() && !root.is_empty() => {
+            Ok((alias.trim().to_string(), PathBuf::from(root)))
+        }
+        _ => Err(format!("expected <name>=<root>, got '{}'", value)),
+    }
+}
+
+/// Major class file version of a Java release such as `8`, `1.4` or `17`; values from 45 up are taken as
+/// major versions already.
+fn parse_class_version(value: &str) -> Result<u16, String> {
+    let release = value.strip_prefix("1.").unwrap_or(value);
+    match release.parse::<u16>() {
+        Ok(major) if major >= 45 => Ok(major),
+        Ok(release) if release >= 1 => Ok(release + 44),
+        _ => Err(format!(
+            "expected a Java release or class file major version, got '{}'",
+            value
+        )),
+    }
+}
+
+/// Parses a duration such as `5s`, `500ms` or `2m`.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("expected a duration such as 5s or 500ms, got '{}'", value))?;
+    match unit {
+        "ms" => Ok(Duration::from_millis(amount)),
+        "s" | "" => Ok(Duration::from_secs(amount)),
+        "m" => amount
+            .checked_mul(60)
+            .map(Duration::from_secs)
+            .ok_or_else(|| format!("duration '{}' is too long", value)),
+        _ => Err(format!(
+            "unknown duration unit '{}' in '{}', expected ms, s or m",
+            unit, value
+        )),
+    }
+}
+
+/// Parses a size in bytes with an optional binary `K`, `M` or `G` suffix, e.g. `16M`.
+fn parse_size(value: &str) -> Result<u64, String> {
+    let (amount, multiplier) = match value.to_ascii_uppercase().trim_end_matches('B') {
+        amount if amount.ends_with('K') => (amount[..amount.len() - 1].to_string(), 1 << 10),
+        amount if amount.ends_with('M') => (amount[..amount.len() - 1].to_string(), 1 << 20),
+        amount if amount.ends_with('G') => (amount[..amount.len() - 1].to_string(), 1 << 30),
+        amount => (amount.to_string(), 1),
+    };
+    let amount = amount
+        .parse::<u64>()
+        .map_err(|_| format!("expected a size such as 16M or 512K, got '{}'", value))?;
+    amount
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("size '{}' is too large", value))
+}
+
+fn parse_glob(value: &str) -> Result<Glob, String> {
+    Glob::new(value).map_err(|e| e.to_string())
+}
+
+/// How the paths of class files appear in reports, from `--paths`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, ValueEnum)]
+pub enum PathDisplay {
+    /// With the scan root resolved to an absolute path
+    #[value(name = "absolute")]
+    Absolute,
+    /// Relative to the scan root, so that reports are alike across machines and checkouts
+    #[default]
+    #[value(name = "relative")]
+    Relative,
+    /// Without paths
+    #[value(name = "none")]
+    None,
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum)]
+pub enum LogFormat {
+    #[value(name = "text")]
+    Text,
+    #[value(name = "json")]
+    Json,
+}
+
+fn init_logging(args: &ScanArgs) -> Result<()> {
+    let level = if args.verbose { Level::DEBUG } else { Level::INFO };
+    let builder = tracing_subscriber::fmt().with_max_level(level).without_time();
+    // A second subscriber cannot be installed; the first one keeps logging
+    let _ = match (&args.log_file, args.log_format) {
+        (Some(log_file), format) => {
+            let file =
+                File::create(log_file).with_context(|| format!("Failed to create log file {}", log_file.display()))?;
+            let builder = builder.with_ansi(false).with_writer(Mutex::new(file));
+            match format {
+                LogFormat::Text => builder.try_init(),
+                LogFormat::Json => builder.json().try_init(),
+            }
+        }
+        (None, LogFormat::Text) => builder.with_ansi(io::stdout().is_terminal()).try_init(),
+        (None, LogFormat::Json) => builder.json().try_init(),
+    };
+    Ok(())
+}
+
+/// Logs to standard error instead, for modes that own standard output such as `jmf lsp`. Must run before
+/// the first `Scanner::new`.
+pub fn init_stderr_logging(args: &ScanArgs) -> Result<()> {
+    if args.log_file.is_some() {
+        return init_logging(args);
+    }
+    let level = if args.verbose { Level::DEBUG } else { Level::INFO };
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .without_time()
+        .with_writer(io::stderr);
+    let _ = match args.log_format {
+        LogFormat::Text => builder.with_ansi(io::stderr().is_terminal()).try_init(),
+        LogFormat::Json => builder.json().try_init(),
+    };
+    Ok(())
+}
+
+/// A class file found under a scan root, either on disk or inside a jar.
+#[derive(Debug)]
+pub struct ClassSource {
+    /// Scan root (directory or jar) the class was found under
+    pub origin: PathBuf,
+    /// Path of the class file relative to its root, e.g. `com/example/Foo.class`
+    pub name: String,
+    /// `Bundle-SymbolicName` of the OSGi bundle the class file is in
+    pub bundle: Option<String>,
+    content: Content,
+}
+
+/// Class files from this size up are memory-mapped rather than read into a buffer.
+const MMAP_THRESHOLD: u64 = 1 << 20;
+
+/// The bytes of a jar, shared by the readers of all its entries: memory-mapped for a scan root, in memory
+/// for a jar embedded in another.
+#[derive(Debug, Clone)]
+enum SharedBytes {
+    Mapped(Arc<Mmap>),
+    Owned(Arc<Vec<u8>>),
+}
+
+impl AsRef<[u8]> for SharedBytes {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            SharedBytes::Mapped(mmap) => mmap,
+            SharedBytes::Owned(bytes) => bytes,
+        }
+    }
+}
+
+type Jar = ZipArchive<Cursor<SharedBytes>>;
+
+#[derive(Debug)]
+enum Content {
+    File(PathBuf),
+    /// Entry of a jar, decompressed only when read
+    JarEntry(Jar, usize),
+    /// Entry of a jar embedded in the scan root at this path
+    EmbeddedJarEntry(Jar, usize, String),
+    /// Class file extracted from a container image or a tar, the latter embedded in the scan root at this path
+    Extracted(Arc<Vec<u8>>, Option<String>),
+}
+
+/// Contents of a class file, either read into memory or memory-mapped.
+pub enum ClassData {
+    Owned(Vec<u8>),
+    Mapped(Mmap),
+}
+
+impl Deref for ClassData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            ClassData::Owned(bytes) => bytes,
+            ClassData::Mapped(mmap) => mmap,
+        }
+    }
+}
+
+/// Memory-maps a file.
+fn map_file(file: &File) -> io::Result<Mmap> {
+    // SAFETY: scanned files are treated as read-only input; a file truncated while mapped fails the scan
+    // of that file but does not affect the others.
+    unsafe { Mmap::map(file) }
+}
+
+impl ClassSource {
+    pub fn read(&self) -> Result<ClassData> {
+        match &self.content {
+            Content::File(path) => {
+                let read = || -> io::Result<ClassData> {
+                    let file = File::open(path)?;
+                    if file.metadata()?.len() >= MMAP_THRESHOLD {
+                        return Ok(ClassData::Mapped(map_file(&file)?));
+                    }
+                    let mut bytes = Vec::new();
+                    (&file).read_to_end(&mut bytes)?;
+                    Ok(ClassData::Owned(bytes))
+                };
+                read().with_context(|| format!("Failed to read class file {}", path.display()))
+            }
+            Content::JarEntry(jar, index) | Content::EmbeddedJarEntry(jar, index, _) => {
+                let mut jar = jar.clone();
+                let mut entry = jar
+                    .by_index(*index)
+                    .with_context(|| format!("Failed to read {}", self.location()))?;
+                let mut bytes = Vec::with_capacity(entry_capacity(entry.size()));
+                entry
+                    .read_to_end(&mut bytes)
+                    .with_context(|| format!("Failed to read {}", self.location()))?;
+                Ok(ClassData::Owned(bytes))
+            }
+            Content::Extracted(bytes, _) => Ok(ClassData::Owned(bytes.to_vec())),
+        }
+    }
+
+    /// Location for messages, e.g. `target/classes/com/example/Foo.class` or `lib/app.jar!/com/example/Foo.class`.
+    /// The jar or directory the class file is in, as its location without the path within it, e.g.
+    /// `lib/app.jar!/lib/inner.jar` or `target/classes`.
+    pub fn artifact(&self) -> String {
+        let location = self.location();
+        location
+            .strip_suffix(&self.name)
+            .unwrap_or(&location)
+            .trim_end_matches(['/', '!'])
+            .to_string()
+    }
+
+    /// The archive scan root the class file is in, with the path of the class file within it, e.g.
+    /// `lib/inner.jar!/com/example/Foo.class`; `None` for directories, container images and downloads.
+    pub fn archive_entry(&self) -> Option<(&Path, String)> {
+        let entry = match &self.content {
+            Content::File(_) => return None,
+            Content::JarEntry(..) | Content::Extracted(_, None) => self.name.clone(),
+            Content::EmbeddedJarEntry(_, _, archive) | Content::Extracted(_, Some(archive)) => {
+                format!("{}!/{}", archive, self.name)
+            }
+        };
+        self.origin.is_file().then_some((self.origin.as_path(), entry))
+    }
+
+    pub fn location(&self) -> String {
+        match &self.content {
+            Content::File(path) => path.display().to_string(),
+            Content::JarEntry(..) | Content::Extracted(_, None) => format!("{}!/{}", self.origin.display(), self.name),
+            Content::EmbeddedJarEntry(_, _, archive) | Content::Extracted(_, Some(archive)) => {
+                format!("{}!/{}!/{}", self.origin.display(), archive, self.name)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveKind {
+    /// Jars and zips
+    Zip,
+    Tar,
+    TarGz,
+}
+
+/// The kind of archive a path names by its extension, if any.
+fn archive_kind(path: &Path) -> Option<ArchiveKind> {
+    let name = path.file_name()?.to_string_lossy();
+    if is_archive(path) {
+        Some(ArchiveKind::Zip)
+    } else if name.ends_with(".tar") {
+        Some(ArchiveKind::Tar)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveKind::TarGz)
+    } else {
+        None
+    }
+}
+
+/// `path`, or the path of an archive `embedded` in it, e.g. `dist.zip!/lib/app.jar`.
+fn nested_location(path: &Path, embedded: Option<&str>) -> String {
+    match embedded {
+        Some(embedded) => format!("{}!/{}", path.display(), embedded),
+        None => path.display().to_string(),
+    }
+}
+
+/// The path of an entry `name` of an archive `embedded` in a scan root, relative to the root.
+fn nested_name(embedded: Option<&str>, name: &str) -> String {
+    match embedded {
+        Some(embedded) => format!("{}!/{}", embedded, name),
+        None => name.to_string(),
+    }
+}
+
+/// The decompressed bytes of a jar entry, or `None` when the jar has no such entry.
+fn read_entry(archive: &mut Jar, name: &str) -> Result<Option<Vec<u8>>> {
+    let mut entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut bytes = Vec::with_capacity(entry_capacity(entry.size()));
+    entry.read_to_end(&mut bytes)?;
+    Ok(Some(bytes))
+}
+
+/// Newest major class file version that parsing is known to support, that of Java 25.
+const NEWEST_CLASS_VERSION: u16 = 69;
+
+/// Why a class file could not be analyzed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorKind {
+    /// The file or archive entry could not be read
+    Io,
+    /// The class file is malformed
+    Parse,
+    /// The class file is newer than the class file versions known to parse
+    UnsupportedVersion,
+    /// A method lacks the attributes that analysis needs, such as its bytecode
+    MissingAttribute,
+    /// Analysis took longer than `--file-timeout`
+    Timeout,
+    /// Anything else, such as a failing plugin
+    Other,
+}
+
+impl ErrorKind {
+    /// The kind of the cause of an error, from the types in its chain.
+    fn classify(error: &anyhow::Error, class_version: Option<u16>) -> Self {
+        let analysis = error.chain().find_map(|cause| cause.downcast_ref::<AnalysisError>());
+        if error
+            .chain()
+            .any(|cause| cause.is::<io::Error>() || cause.is::<zip::result::ZipError>())
+        {
+            Self::Io
+        } else if let Some(analysis) = analysis {
+            match analysis {
+                AnalysisError::Timeout(_) => Self::Timeout,
+                AnalysisError::MissingCode(_) | AnalysisError::MissingBytecode(_) => Self::MissingAttribute,
+            }
+        } else if class_version.is_some_and(|version| version > NEWEST_CLASS_VERSION) {
+            Self::UnsupportedVersion
+        } else if error.chain().any(|cause| cause.is::<ParseError>()) {
+            Self::Parse
+        } else {
+            Self::Other
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Io => "io",
+            Self::Parse => "parse",
+            Self::UnsupportedVersion => "unsupported version",
+            Self::MissingAttribute => "missing attribute",
+            Self::Timeout => "timeout",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// A class file that could not be analyzed, reported instead of silently reducing coverage.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanError {
+    /// Path of the class file, as `--paths` displays it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// Label of the scan root or jar the class file was found in, when several are scanned
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    /// Major version from the class file header, when it has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class_version: Option<u16>,
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl ScanError {
+    /// The error of a class file, whose major version is that of the header read before it failed.
+    pub fn new(source: &ClassSource, error: &anyhow::Error, class_version: Option<u16>, args: &ScanArgs) -> Self {
+        let mut scan_error = Self {
+            location: None,
+            origin: None,
+            class_version,
+            kind: ErrorKind::classify(error, class_version),
+            message: format!("{:#}", error),
+        };
+        scan_error.label(source, args);
+        scan_error
+    }
+
+    /// Sets the location and origin of the class file as this scan displays them, e.g. for an error from
+    /// the `--result-cache`.
+    pub fn label(&mut self, source: &ClassSource, args: &ScanArgs) {
+        self.location = args.display_location(source);
+        self.origin = (args.scan_roots.len() > 1 || source.bundle.is_some()).then(|| args.source_label(source));
+    }
+}
+
+impl std::fmt::Display for ScanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut subject = vec![self.location.clone().unwrap_or_else(|| "class file".to_string())];
+        if let Some(version) = self.class_version {
+            subject.push(format!("(class version {})", version));
+        }
+        if let Some(origin) = &self.origin {
+            subject.push(format!("[origin: {}]", origin));
+        }
+        write!(f, "{}: {}", subject.join(" "), self.message)
+    }
+}
+
+/// Class files that were not analyzed, reported alongside the results.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScanSummary {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ScanError>,
+    /// Class files skipped for exceeding `--max-class-size`
+    #[serde(skip_serializing_if = "is_zero")]
+    pub skipped: usize,
+    /// Files named `.class` skipped for not being class files
+    #[serde(skip_serializing_if = "is_zero")]
+    pub not_class_files: usize,
+    /// The scan was stopped by Ctrl-C, so the results are partial
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub interrupted: bool,
+    /// What each scan root or jar contributed, when several are scanned
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub by_origin: Vec<OriginSummary>,
+    /// Class files analyzed, whether they could be or not
+    #[serde(skip)]
+    pub classes: usize,
+    /// Class files identical to one under another scan root, whose calls and errors are reported once
+    #[serde(skip_serializing_if = "is_zero")]
+    pub identical: usize,
+}
+
+/// The class files a scan root or jar contributed and what came of them.
+#[derive(Debug, Clone, Serialize)]
+pub struct OriginSummary {
+    pub origin: String,
+    pub classes: usize,
+    pub matches: usize,
+    pub errors: usize,
+}
+
+impl OriginSummary {
+    pub fn new(origin: String) -> Self {
+        Self {
+            origin,
+            classes: 0,
+            matches: 0,
+            errors: 0,
+        }
+    }
+}
+
+fn is_zero(count: &usize) -> bool {
+    *count == 0
+}
+
+impl ScanSummary {
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+            && self.skipped == 0
+            && self.not_class_files == 0
+            && self.identical == 0
+            && !self.interrupted
+    }
+
+    /// A table of the classes, matches and errors of each origin, the origins with the most matches first.
+    pub fn origin_table(&self) -> String {
+        let mut origins: Vec<&OriginSummary> = self.by_origin.iter().collect();
+        origins.sort_by_key(|origin| std::cmp::Reverse(origin.matches));
+        let width = origins
+            .iter()
+            .map(|origin| origin.origin.chars().count())
+            .chain(["Origin".len()])
+            .max()
+            .unwrap_or_default();
+        let mut output = vec![format!(
+            "{:<width$}  {:>7}  {:>7}  {:>6}",
+            "Origin", "Classes", "Matches", "Errors"
+        )];
+        output.extend(origins.iter().map(|origin| {
+            format!(
+                "{:<width$}  {:>7}  {:>7}  {:>6}",
+                origin.origin, origin.classes, origin.matches, origin.errors
+            )
+        }));
+        output.join("\n")
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut output = Vec::new();
+        if self.interrupted {
+            output.push("Scan interrupted: results are partial".to_string());
+        }
+        if self.skipped > 0 {
+            output.push(format!(
+                "Skipped: {} class files larger than --max-class-size",
+                self.skipped
+            ));
+        }
+        if self.not_class_files > 0 {
+            output.push(format!(
+                "Skipped: {} .class files that are not class files",
+                self.not_class_files
+            ));
+        }
+        if self.identical > 0 {
+            output.push(format!(
+                "Deduplicated: {} class files identical to ones under other scan roots, analyzed once",
+                self.identical
+            ));
+        }
+        if !self.errors.is_empty() {
+            output.push(format!(
+                "Errors: {} class files could not be analyzed",
+                self.errors.len()
+            ));
+            output.extend(self.errors.iter().map(|error| format!(" - {}", error)));
+            let mut kinds: BTreeMap<ErrorKind, usize> = BTreeMap::new();
+            for error in &self.errors {
+                *kinds.entry(error.kind).or_default() += 1;
+            }
+            let kinds: Vec<String> = kinds
+                .into_iter()
+                .map(|(kind, count)| format!("{} {}", count, kind.name()))
+                .collect();
+            output.push(format!("By kind: {}", kinds.join(", ")));
+        }
+        output.join("\n")
+    }
+}
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Makes Ctrl-C stop the scan at the next class file instead of killing the process, so that the
+/// results found so far can still be printed.
+pub fn install_interrupt_handler() -> Result<()> {
+    ctrlc::set_handler(|| INTERRUPTED.store(true, Ordering::Relaxed)).context("Failed to install Ctrl-C handler")
+}
+
+/// Whether Ctrl-C was pressed during the scan.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+const CLASS_FILE_MAGIC: [u8; 4] = [0xCA, 0xFE, 0xBA, 0xBE];
+
+/// Major version from a class file header, read without parsing the rest of the file.
+pub fn header_version(data: &[u8]) -> Option<u16> {
+    match data {
+        [0xCA, 0xFE, 0xBA, 0xBE, _, _, major_high, major_low, ..] => {
+            Some(u16::from_be_bytes([*major_high, *major_low]))
+        }
+        _ => None,
+    }
+}
+
+/// `--path-include`/`--path-exclude` globs, matched against a class file's path relative to its root as
+/// well as its full location.
+struct PathFilter {
+    includes: Option<GlobSet>,
+    excludes: GlobSet,
+}
+
+impl PathFilter {
+    fn new(includes: &[Glob], excludes: &[Glob]) -> Result<Self> {
+        let build = |globs: &[Glob]| {
+            let mut builder = GlobSetBuilder::new();
+            for glob in globs {
+                builder.add(glob.clone());
+            }
+            builder.build()
+        };
+        Ok(Self {
+            includes: if includes.is_empty() {
+                None
+            } else {
+                Some(build(includes)?)
+            },
+            excludes: build(excludes)?,
+        })
+    }
+
+    fn accepts(&self, name: &str, location: &str) -> bool {
+        let matches = |set: &GlobSet| set.is_match(name) || set.is_match(location);
+        self.includes.as_ref().is_none_or(matches) && !matches(&self.excludes)
+    }
+}
+
+pub struct Scanner {
+    args: ScanArgs,
+    path_filter: PathFilter,
+    changed: Option<ChangedSources>,
+    jar_index: Option<JarIndex>,
+    verifier: Verifier,
+    /// Internal names of the classes and packages that `class_files` lists archives referencing, when
+    /// `--jar-index` is set
+    references: Option<Vec<String>>,
+    /// Class files skipped by the last `class_files` call for exceeding `--max-class-size`
+    skipped: AtomicUsize,
+    /// Files named `.class` skipped since the last `class_files` call for not starting with `0xCAFEBABE`
+    not_class_files: AtomicUsize,
+}
+
+impl Scanner {
+    pub fn new(args: ScanArgs) -> Result<Self> {
+        init_logging(&args)?;
+        if args.profile {
+            profile::enable();
+        }
+        let path_filter = PathFilter::new(&args.path_includes, &args.path_excludes)?;
+        let changed = args.changed_since.as_deref().map(ChangedSources::since).transpose()?;
+        // A filter must cover every class file an archive lists, so these options make part of its key
+        let jar_index = args
+            .jar_index
+            .as_deref()
+            .map(|directory| JarIndex::new(directory, args.listing_options()));
+        let verifier = Verifier::new(
+            &args.verify_sha256,
+            args.verify_signature,
+            args.signature_keystore.as_deref(),
+            &args.scan_roots,
+        )?;
+        Ok(Scanner {
+            args,
+            path_filter,
+            changed,
+            jar_index,
+            verifier,
+            references: None,
+            skipped: AtomicUsize::new(0),
+            not_class_files: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn log_debug(&self, message: &str) {
+        if self.args.verbose {
+            debug!("{}", message);
+        }
+    }
+
+    /// Whether the class passes the `--min-class-version`/`--max-class-version` and `--changed-since`
+    /// filters.
+    pub fn accepts(&self, class_file: &ClassFile) -> bool {
+        let version = class_file.major_version;
+        self.args.min_class_version.is_none_or(|min| version >= min)
+            && self.args.max_class_version.is_none_or(|max| version <= max)
+            && self.changed.as_ref().is_none_or(|changed| changed.contains(class_file))
+    }
+
+    /// Whether a class file passes the path globs, and may be from a changed file under `--changed-since`.
+    fn accepts_path(&self, name: &str, location: &str) -> bool {
+        self.path_filter.accepts(name, location)
+            && self.changed.as_ref().is_none_or(|changed| changed.may_contain(name))
+    }
+
+    /// Parses a class file, retrying without bytecode under `--lenient`.
+    pub fn parse<'a>(&self, data: &'a [u8]) -> Result<ClassFile<'a>, ParseError> {
+        self.parse_leniently(data).map(|(class_file, _)| class_file)
+    }
+
+    /// Parses a class file as `parse` does, along with the error of the first attempt when it took the
+    /// retry without bytecode.
+    pub fn parse_leniently<'a>(&self, data: &'a [u8]) -> Result<(ClassFile<'a>, Option<ParseError>), ParseError> {
+        let _timer = profile::start(Phase::Parse);
+        match parse_class(data) {
+            Err(e) if self.args.lenient => {
+                self.log_debug(&format!("Retrying without bytecode: {}", e));
+                let mut options = ParseOptions::default();
+                options.parse_bytecode(false);
+                parse_class_with_options(data, &options).map(|class_file| (class_file, Some(e)))
+            }
+            result => result.map(|class_file| (class_file, None)),
+        }
+    }
+
+    /// Under `--jar-index`, lets `class_files` skip the archives whose classes reference none of these
+    /// internal class and package names.
+    pub fn skip_archives_without(&mut self, references: Vec<String>) {
+        if self.jar_index.is_some() {
+            self.references = Some(references);
+        }
+    }
+
+    /// Every class file under the scan roots, in root order, followed by those of the manifest class paths.
+    pub fn class_files(&self) -> Result<Vec<ClassSource>> {
+        self.list_class_files(self.references.as_deref())
+    }
+
+    fn list_class_files(&self, references: Option<&[String]>) -> Result<Vec<ClassSource>> {
+        let _timer = profile::start(Phase::Walk);
+        self.not_class_files.store(0, Ordering::Relaxed);
+        let mut class_files = Vec::new();
+        let mut skipped = 0;
+        let mut roots = self.args.scan_roots.clone();
+        let mut index = 0;
+        // `--verify-sha256` values that an archive scan root was verified against
+        let mut verified = Vec::new();
+        while let Some(scan_path) = roots.get(index).cloned() {
+            index += 1;
+            let scan_path = &scan_path;
+            if image::is_image(scan_path) {
+                self.verifier.verify_other(scan_path)?;
+                self.log_debug(&format!("Start scanning image: {}", scan_path.display()));
+                let (image_classes, image_skipped) = self.read_image(scan_path)?;
+                class_files.extend(image_classes);
+                skipped += image_skipped;
+                continue;
+            }
+            if remote::is_remote(scan_path) {
+                let url = scan_path.to_string_lossy();
+                let kind = archive_kind(scan_path)
+                    .with_context(|| format!("URL scan roots must name a jar or an archive: {}", url))?;
+                self.log_debug(&format!("Start scanning archive: {}", url));
+                let local = remote::fetch(&url, self.args.remote_checksum)?;
+                verified.extend(
+                    self.verifier
+                        .verify_archive(scan_path, &local, kind == ArchiveKind::Zip)?,
+                );
+                let (archive_classes, archive_skipped) =
+                    self.read_indexed_archive(&local, scan_path, kind, references)?;
+                class_files.extend(archive_classes);
+                skipped += archive_skipped;
+                continue;
+            }
+            if !scan_path.exists() {
+                return Err(anyhow::anyhow!("Scan folder does not exist: {}", scan_path.display()));
+            }
+            if let Some(kind) = archive_kind(scan_path).filter(|_| scan_path.is_file()) {
+                self.log_debug(&format!("Start scanning archive: {}", scan_path.display()));
+                verified.extend(
+                    self.verifier
+                        .verify_archive(scan_path, scan_path, kind == ArchiveKind::Zip)?,
+                );
+                let (archive_classes, archive_skipped) =
+                    self.read_indexed_archive(scan_path, scan_path, kind, references)?;
+                class_files.extend(archive_classes);
+                skipped += archive_skipped;
+                if self.args.follow_manifest_classpath && kind == ArchiveKind::Zip {
+                    for entry in self.manifest_class_path(scan_path)? {
+                        // Class paths may list each other, or a jar by another path
+                        let Ok(canonical) = entry.canonicalize() else {
+                            self.log_debug(&format!("Class-Path entry not found: {}", entry.display()));
+                            continue;
+                        };
+                        if !roots
+                            .iter()
+                            .any(|root| root.canonicalize().is_ok_and(|root| root == canonical))
+                        {
+                            roots.push(entry);
+                        }
+                    }
+                }
+                continue;
+            }
+            self.verifier.verify_other(scan_path)?;
+            if scan_path.is_file() && scan_path.extension().is_some_and(|ext| ext == "class") {
+                let size = scan_path.metadata().map(|metadata| metadata.len()).unwrap_or_default();
+                if self.too_large(size) {
+                    self.log_debug(&format!("Skipping {} bytes: {}", size, scan_path.display()));
+                    skipped += 1;
+                    continue;
+                }
+                let source = ClassSource {
+                    origin: scan_path.clone(),
+                    bundle: None,
+                    name: scan_path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+                    content: Content::File(scan_path.clone()),
+                };
+                if self.accepts_path(&source.name, &source.location()) {
+                    class_files.push(source);
+                }
+                continue;
+            }
+            if !scan_path.is_dir() {
+                return Err(anyhow::anyhow!("Scan path is not a directory: {}", scan_path.display()));
+            }
+            self.log_debug(&format!("Start scanning folder: {}", scan_path.display()));
+
+            let (directory_classes, directory_skipped) = self.walk(scan_path);
+            class_files.extend(directory_classes);
+            skipped += directory_skipped;
+        }
+        self.verifier.ensure_used(&verified)?;
+
+        self.skipped.store(skipped, Ordering::Relaxed);
+        Ok(class_files)
+    }
+
+    /// The class files below a directory scan root, by name, along with the number skipped for their size.
+    /// Directories are read by a pool of threads, which shortens the listing of network file systems and
+    /// trees of millions of files, or in name order by a single thread under `--deterministic`. Parsing
+    /// starts once the whole root is listed, since the class files are sorted by name first.
+    fn walk(&self, scan_path: &Path) -> (Vec<ClassSource>, usize) {
+        let respect_gitignore = self.args.respect_gitignore;
+        let mut builder = WalkBuilder::new(scan_path);
+        builder
+            .standard_filters(false)
+            .git_ignore(respect_gitignore)
+            .git_exclude(respect_gitignore)
+            .ignore(respect_gitignore)
+            .require_git(false)
+            .follow_links(self.args.follow_symlinks)
+            .max_depth(self.args.max_depth)
+            .same_file_system(self.args.one_file_system);
+
+        let class_files = Mutex::new(Vec::new());
+        let skipped = AtomicUsize::new(0);
+        let visit = |entry: Result<DirEntry, ignore::Error>| {
+            if interrupted() {
+                return WalkState::Quit;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    self.log_debug(&format!("Skipping: {}", e));
+                    return WalkState::Continue;
+                }
+            };
+            if !entry.file_type().is_some_and(|file_type| file_type.is_file())
+                || entry.path().extension().is_none_or(|ext| ext != "class")
+            {
+                return WalkState::Continue;
+            }
+            let size = entry.metadata().map(|metadata| metadata.len()).unwrap_or_default();
+            if self.too_large(size) {
+                self.log_debug(&format!("Skipping {} bytes: {}", size, entry.path().display()));
+                skipped.fetch_add(1, Ordering::Relaxed);
+                return WalkState::Continue;
+            }
+            let source = ClassSource {
+                origin: scan_path.to_path_buf(),
+                bundle: None,
+                name: entry
+                    .path()
+                    .strip_prefix(scan_path)
+                    .unwrap_or(entry.path())
+                    .to_string_lossy()
+                    .replace('\\', "/"),
+                content: Content::File(entry.into_path()),
+            };
+            if self.accepts_path(&source.name, &source.location()) {
+                class_files.lock().unwrap().push(source);
+            }
+            WalkState::Continue
+        };
+        if self.args.deterministic {
+            for entry in builder.sort_by_file_name(|a, b| a.cmp(b)).build() {
+                if visit(entry) == WalkState::Quit {
+                    break;
+                }
+            }
+        } else {
+            builder
+                .threads(rayon::current_num_threads())
+                .build_parallel()
+                .run(|| Box::new(visit));
+        }
+
+        let mut class_files = class_files.into_inner().unwrap();
+        // The threads find the files in no particular order
+        class_files.sort_by(|a, b| a.name.cmp(&b.name));
+        (class_files, skipped.into_inner())
+    }
+
+    /// Number of class files the last scan skipped for exceeding `--max-class-size`.
+    pub fn skipped(&self) -> usize {
+        self.skipped.load(Ordering::Relaxed)
+    }
+
+    /// Whether a file starts with the `0xCAFEBABE` magic of class files. Other files named `.class`, such
+    /// as markers of build tools or partial downloads, are counted as skipped rather than reported as errors.
+    pub fn is_class_file(&self, data: &[u8], source: &ClassSource) -> bool {
+        if data.starts_with(&CLASS_FILE_MAGIC) {
+            return true;
+        }
+        self.skip_not_class_file(source);
+        false
+    }
+
+    /// Counts a file named `.class` that is not a class file, e.g. one the `--result-cache` remembers.
+    pub fn skip_not_class_file(&self, source: &ClassSource) {
+        self.log_debug(&format!("Skipping, not a class file: {}", source.location()));
+        self.not_class_files.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of files the last scan skipped for not being class files.
+    pub fn not_class_files(&self) -> usize {
+        self.not_class_files.load(Ordering::Relaxed)
+    }
+
+    fn too_large(&self, size: u64) -> bool {
+        self.args.max_class_size.is_some_and(|max| size > max)
+    }
+
+    /// The `Class-Path` entries of a jar's manifest, which are relative to the directory of the jar.
+    fn manifest_class_path(&self, jar: &Path) -> Result<Vec<PathBuf>> {
+        let file = File::open(jar).with_context(|| format!("Failed to open archive {}", jar.display()))?;
+        let mmap = map_file(&file).with_context(|| format!("Failed to map archive {}", jar.display()))?;
+        let mut archive = ZipArchive::new(Cursor::new(SharedBytes::Mapped(Arc::new(mmap))))
+            .with_context(|| format!("Failed to read jar {}", jar.display()))?;
+        let Some(manifest) = read_entry(&mut archive, "META-INF/MANIFEST.MF")
+            .with_context(|| format!("Failed to read the manifest of jar {}", jar.display()))?
+        else {
+            return Ok(Vec::new());
+        };
+        let directory = jar.parent().unwrap_or(Path::new(""));
+        Ok(Manifest::parse(&String::from_utf8_lossy(&manifest))
+            .class_path()
+            .iter()
+            .map(|entry| directory.join(entry.trim_end_matches('/')))
+            .collect())
+    }
+
+    /// Lists the class files of an archive scan root unless its filter in the `--jar-index` excludes all
+    /// `references`, indexing it if it has no filter yet.
+    fn read_indexed_archive(
+        &self,
+        file: &Path,
+        root: &Path,
+        kind: ArchiveKind,
+        references: Option<&[String]>,
+    ) -> Result<(Vec<ClassSource>, usize)> {
+        let (Some(index), Some(references)) = (&self.jar_index, references) else {
+            return self.read_archive(file, root, kind);
+        };
+        if let Some(filter) = index.load(file)? {
+            if !references.iter().any(|reference| filter.may_contain(reference)) {
+                self.log_debug(&format!(
+                    "Skipping archive without references to the targets: {}",
+                    root.display()
+                ));
+                return Ok((Vec::new(), 0));
+            }
+            return self.read_archive(file, root, kind);
+        }
+        let (class_files, skipped) = self.read_archive(file, root, kind)?;
+        // Classes of `--changed-since` are only some of the archive's
+        if self.changed.is_none() {
+            let mut names = Vec::new();
+            for source in &class_files {
+                // The scan reports the class itself; without it the filter would be incomplete
+                let class_data = match source.read() {
+                    Ok(class_data) => class_data,
+                    Err(e) => {
+                        error!("Not indexing archive {}: {:#}", root.display(), e);
+                        return Ok((class_files, skipped));
+                    }
+                };
+                for class in jar_index::referenced_classes(&class_data) {
+                    if let Some((package, _)) = class.rsplit_once('/') {
+                        names.push(package.to_string());
+                    }
+                    names.push(class);
+                }
+            }
+            names.sort();
+            names.dedup();
+            let mut filter = BloomFilter::new(names.len());
+            for name in &names {
+                filter.insert(name);
+            }
+            index.store(file, &filter)?;
+            self.log_debug(&format!(
+                "Indexed {} references of archive: {}",
+                names.len(),
+                root.display()
+            ));
+        }
+        Ok((class_files, skipped))
+    }
+
+    /// Lists the class files of an archive scan root: the entries of a memory-mapped jar or zip, which are
+    /// not decompressed, or the class files of a tar, along with the number of entries skipped for their size.
+    /// `file` is the scan root `root` itself, or its download.
+    fn read_archive(&self, file: &Path, root: &Path, kind: ArchiveKind) -> Result<(Vec<ClassSource>, usize)> {
+        let _span = debug_span!("archive", path = %root.display()).entered();
+        let archive = File::open(file).with_context(|| format!("Failed to open archive {}", file.display()))?;
+        let mmap = map_file(&archive).with_context(|| format!("Failed to map archive {}", file.display()))?;
+        let levels = self.archive_depth(root);
+        self.read_nested(kind, SharedBytes::Mapped(Arc::new(mmap)), root, None, None, levels)
+    }
+
+    /// `--archive-depth` for an archive scanned as a scan root. Jars are not descended into by default, as
+    /// they were not before archives nested in zips and tars were scanned.
+    fn archive_depth(&self, archive: &Path) -> usize {
+        let jar = archive.extension().is_some_and(|ext| ext == "jar");
+        self.args.archive_depth.unwrap_or(if jar { 0 } else { 1 })
+    }
+
+    /// The class files of an archive, either the scan root `path` or an archive `embedded` in it, and those
+    /// of the archives nested in it down to `levels` more levels.
+    fn read_nested(
+        &self,
+        kind: ArchiveKind,
+        bytes: SharedBytes,
+        path: &Path,
+        embedded: Option<&str>,
+        bundle: Option<&str>,
+        levels: usize,
+    ) -> Result<(Vec<ClassSource>, usize)> {
+        match kind {
+            ArchiveKind::Zip => self.read_zip(bytes, path, embedded, bundle, levels),
+            ArchiveKind::Tar | ArchiveKind::TarGz => self.read_tar(kind, bytes, path, embedded, bundle, levels),
+        }
+    }
+
+    /// The class entries of a jar, zip or Android library. The jars an OSGi bundle lists in its
+    /// `Bundle-ClassPath` are listed too, whatever their depth, and the classes labeled with its
+    /// `Bundle-SymbolicName`; so are the `classes.jar` and `libs/*.jar` of an Android library.
+    fn read_zip(
+        &self,
+        bytes: SharedBytes,
+        path: &Path,
+        embedded: Option<&str>,
+        bundle: Option<&str>,
+        levels: usize,
+    ) -> Result<(Vec<ClassSource>, usize)> {
+        let jar_location = nested_location(path, embedded);
+        let mut archive =
+            ZipArchive::new(Cursor::new(bytes)).with_context(|| format!("Failed to read jar {}", jar_location))?;
+        let manifest = read_entry(&mut archive, "META-INF/MANIFEST.MF")
+            .with_context(|| format!("Failed to read the manifest of jar {}", jar_location))?
+            .map(|bytes| Manifest::parse(&String::from_utf8_lossy(&bytes)))
+            .unwrap_or_default();
+        // Archives nested in a bundle belong to it unless they are bundles themselves
+        let bundle = manifest.bundle_symbolic_name().or(bundle).map(str::to_string);
+        let (mut class_files, mut skipped) = self.jar_classes(&archive, path, embedded, bundle.as_deref())?;
+        // Directories of the class path are entries of the bundle already
+        let mut class_path = manifest.bundle_class_path();
+        // An Android library keeps its classes in `classes.jar`, and the jars it bundles in `libs/`
+        if jar_location.ends_with(".aar") {
+            class_path.push("classes.jar".to_string());
+            class_path.extend(
+                archive
+                    .file_names()
+                    .filter(|name| name.starts_with("libs/") && name.ends_with(".jar"))
+                    .map(str::to_string),
+            );
+        }
+        for entry in class_path.iter().filter(|entry| is_archive(Path::new(entry))) {
+            let location = format!("{}!/{}", jar_location, entry);
+            let Some(bytes) =
+                read_entry(&mut archive, entry).with_context(|| format!("Failed to read {}", location))?
+            else {
+                self.log_debug(&format!("Class path entry not found: {}", location));
+                continue;
+            };
+            self.log_debug(&format!("Start scanning embedded jar: {}", location));
+            let embedded_archive = ZipArchive::new(Cursor::new(SharedBytes::Owned(Arc::new(bytes))))
+                .with_context(|| format!("Failed to read jar {}", location))?;
+            let (embedded_classes, embedded_skipped) = self.jar_classes(
+                &embedded_archive,
+                path,
+                Some(&nested_name(embedded, entry)),
+                bundle.as_deref(),
+            )?;
+            class_files.extend(embedded_classes);
+            skipped += embedded_skipped;
+        }
+        if levels == 0 {
+            return Ok((class_files, skipped));
+        }
+        for index in 0..archive.len() {
+            let mut entry = archive
+                .by_index(index)
+                .with_context(|| format!("Failed to read entry {} of jar {}", index, jar_location))?;
+            let name = entry.name().to_string();
+            let Some(kind) = archive_kind(Path::new(&name)).filter(|_| entry.is_file()) else {
+                continue;
+            };
+            if class_path.contains(&name) {
+                continue;
+            }
+            let location = format!("{}!/{}", jar_location, name);
+            self.log_debug(&format!("Start scanning nested archive: {}", location));
+            let mut bytes = Vec::with_capacity(entry_capacity(entry.size()));
+            entry
+                .read_to_end(&mut bytes)
+                .with_context(|| format!("Failed to read {}", location))?;
+            drop(entry);
+            let (nested_classes, nested_skipped) = self.read_nested(
+                kind,
+                SharedBytes::Owned(Arc::new(bytes)),
+                path,
+                Some(&nested_name(embedded, &name)),
+                bundle.as_deref(),
+                levels - 1,
+            )?;
+            class_files.extend(nested_classes);
+            skipped += nested_skipped;
+        }
+        Ok((class_files, skipped))
+    }
+
+    /// The class files of a tar, extracted into memory as they are read.
+    fn read_tar(
+        &self,
+        kind: ArchiveKind,
+        bytes: SharedBytes,
+        path: &Path,
+        embedded: Option<&str>,
+        bundle: Option<&str>,
+        levels: usize,
+    ) -> Result<(Vec<ClassSource>, usize)> {
+        let tar_location = nested_location(path, embedded);
+        let data: &[u8] = bytes.as_ref();
+        let reader: Box<dyn Read> = match kind {
+            ArchiveKind::TarGz => Box::new(GzDecoder::new(data)),
+            _ => Box::new(data),
+        };
+        let mut class_files = Vec::new();
+        let mut skipped = 0;
+        let mut nested = Vec::new();
+        tar::for_each(reader, |entry| {
+            if entry.kind != EntryKind::File {
+                return Ok(());
+            }
+            let location = format!("{}!/{}", tar_location, entry.name);
+            if let Some(kind) = archive_kind(Path::new(&entry.name)) {
+                if levels > 0 {
+                    self.log_debug(&format!("Start scanning nested archive: {}", location));
+                    let mut bytes = Vec::with_capacity(entry_capacity(entry.size));
+                    entry.data.read_to_end(&mut bytes)?;
+                    nested.push((entry.name, kind, bytes));
+                }
+                return Ok(());
+            }
+            // Whiteouts of image layers mark deleted files
+            let whiteout = entry
+                .name
+                .rsplit('/')
+                .next()
+                .is_some_and(|name| name.starts_with(".wh."));
+            if whiteout || !entry.name.ends_with(".class") || !self.accepts_path(&entry.name, &location) {
+                return Ok(());
+            }
+            if self.too_large(entry.size) {
+                self.log_debug(&format!("Skipping {} bytes: {}", entry.size, location));
+                skipped += 1;
+                return Ok(());
+            }
+            let mut bytes = Vec::with_capacity(entry_capacity(entry.size));
+            entry.data.read_to_end(&mut bytes)?;
+            class_files.push(ClassSource {
+                origin: path.to_owned(),
+                name: entry.name,
+                bundle: bundle.map(str::to_string),
+                content: Content::Extracted(Arc::new(bytes), embedded.map(str::to_string)),
+            });
+            Ok(())
+        })
+        .with_context(|| format!("Failed to read archive {}", tar_location))?;
+        for (name, kind, bytes) in nested {
+            let (nested_classes, nested_skipped) = self.read_nested(
+                kind,
+                SharedBytes::Owned(Arc::new(bytes)),
+                path,
+                Some(&nested_name(embedded, &name)),
+                bundle,
+                levels - 1,
+            )?;
+            class_files.extend(nested_classes);
+            skipped += nested_skipped;
+        }
+        Ok((class_files, skipped))
+    }
+
+    /// The class files of a container image, and those of the archives in it.
+    fn read_image(&self, root: &Path) -> Result<(Vec<ClassSource>, usize)> {
+        let _span = debug_span!("image", root = %root.display()).entered();
+        let files = image::files(root, |name| {
+            name.ends_with(".class") || archive_kind(Path::new(name)).is_some()
+        })?;
+        let mut class_files = Vec::new();
+        let mut skipped = 0;
+        for (name, bytes) in files {
+            // Archives in an image are scanned as scan roots would be
+            if let Some(kind) = archive_kind(Path::new(&name)) {
+                self.log_debug(&format!("Start scanning archive: {}!/{}", root.display(), name));
+                let (archive_classes, archive_skipped) = self.read_nested(
+                    kind,
+                    SharedBytes::Owned(Arc::new(bytes)),
+                    root,
+                    Some(&name),
+                    None,
+                    self.archive_depth(Path::new(&name)),
+                )?;
+                class_files.extend(archive_classes);
+                skipped += archive_skipped;
+                continue;
+            }
+            let location = format!("{}!/{}", root.display(), name);
+            if !self.accepts_path(&name, &location) {
+                continue;
+            }
+            if self.too_large(bytes.len() as u64) {
+                self.log_debug(&format!("Skipping {} bytes: {}", bytes.len(), location));
+                skipped += 1;
+                continue;
+            }
+            class_files.push(ClassSource {
+                origin: root.to_owned(),
+                name,
+                bundle: None,
+                content: Content::Extracted(Arc::new(bytes), None),
+            });
+        }
+        Ok((class_files, skipped))
+    }
+
+    /// The class entries of a jar, either the scan root `path` or a jar `embedded` in it.
+    fn jar_classes(
+        &self,
+        archive: &Jar,
+        path: &Path,
+        embedded: Option<&str>,
+        bundle: Option<&str>,
+    ) -> Result<(Vec<ClassSource>, usize)> {
+        let mut archive = archive.clone();
+        let jar_location = nested_location(path, embedded);
+        let mut class_files = Vec::new();
+        let mut skipped = 0;
+        for index in 0..archive.len() {
+            let entry = archive
+                .by_index_raw(index)
+                .with_context(|| format!("Failed to read entry {} of jar {}", index, jar_location))?;
+            if !entry.is_file() || !entry.name().ends_with(".class") {
+                continue;
+            }
+            let name = entry.name().to_string();
+            if !self.accepts_path(&name, &format!("{}!/{}", jar_location, name)) {
+                continue;
+            }
+            if self.too_large(entry.size()) {
+                self.log_debug(&format!("Skipping {} bytes: {}!/{}", entry.size(), jar_location, name));
+                skipped += 1;
+                continue;
+            }
+            drop(entry);
+            class_files.push(ClassSource {
+                origin: path.to_owned(),
+                name,
+                bundle: bundle.map(str::to_string),
+                content: match embedded {
+                    Some(embedded) => Content::EmbeddedJarEntry(archive.clone(), index, embedded.to_string()),
+                    None => Content::JarEntry(archive.clone(), index),
+                },
+            });
+        }
+        Ok((class_files, skipped))
+    }
+
+    /// Scan roots with their class file counts, listing every class file when verbose.
+    pub fn dry_run(&self) -> Result<String> {
+        let class_files = self.class_files()?;
+        let mut output = vec!["Scan roots:".to_string()];
+        let mut roots: Vec<&PathBuf> = self.args.scan_roots.iter().collect();
+        // Jars and directories of manifest class paths come after the scan roots listing them
+        for source in &class_files {
+            if !roots.contains(&&source.origin) {
+                roots.push(&source.origin);
+            }
+        }
+        for root in roots {
+            let kind = if image::is_image(root) {
+                "image"
+            } else if remote::is_remote(root) {
+                "download"
+            } else if is_archive(root) {
+                "jar"
+            } else if archive_kind(root).is_some() {
+                "archive"
+            } else if root.is_file() {
+                "class file"
+            } else {
+                "directory"
+            };
+            let mut sources: Vec<&ClassSource> = class_files.iter().filter(|source| &source.origin == root).collect();
+            sources.sort_by(|a, b| a.name.cmp(&b.name));
+            output.push(format!(
+                " - {} ({}, {} class files)",
+                root.display(),
+                kind,
+                sources.len()
+            ));
+            if self.args.verbose {
+                output.extend(sources.iter().map(|source| format!("   {}", source.location())));
+            }
+        }
+        output.push(format!("Total class files: {}", class_files.len()));
+        Ok(output.join("\n"))
+    }
+
+    /// Parses every class file in parallel and applies `f` to it. Files that fail to read or parse
+    /// are skipped with a debug message.
+    pub fn map_classes<T: Send>(&self, f: impl Fn(&ClassSource, &ClassFile) -> T + Sync) -> Result<Vec<T>> {
+        // Call graphs and reports over the classes need every archive
+        let class_files = self.list_class_files(None)?;
+        Ok(class_files
+            .par_iter()
+            .filter(|_| !interrupted())
+            .filter_map(|source| {
+                let _span = debug_span!("class", location = %source.location()).entered();
+                let class_data = source.read().ok()?;
+                if !self.is_class_file(&class_data, source) {
+                    return None;
+                }
+                match self.parse(&class_data) {
+                    Ok(class_file) if !self.accepts(&class_file) => None,
+                    Ok(class_file) => Some(profile::time(Phase::Match, || f(source, &class_file))),
+                    Err(e) => {
+                        self.log_debug(&format!("Skipping {}: {}", source.location(), e));
+                        None
+                    }
+                }
+            })
+            .collect())
+    }
+
+    pub fn call_graph(&self) -> Result<CallGraph> {
+        let graphs = self.map_classes(|_, class_file| {
+            let mut graph = CallGraph::default();
+            graph.add_class(class_file);
+            graph
+        })?;
+        Ok(graphs.into_iter().fold(CallGraph::default(), CallGraph::merge))
+    }
+}
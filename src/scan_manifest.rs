@@ -0,0 +1,102 @@
+//! `--emit-manifest`: the artifacts and class files a scan analyzed, with their SHA-256 hashes, sizes and
+//! class file versions, for audits that need evidence of exactly what was scanned.
+
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::{Context, Result};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use serde::Serialize;
+
+use crate::{
+    duplicates::sha256_hex,
+    scan::{self, ClassSource, ErrorKind},
+};
+
+#[derive(Debug, Serialize)]
+pub struct Artifact {
+    /// The scan root, as a directory, class file or archive path
+    pub path: String,
+    /// `directory` or `file`
+    pub kind: &'static str,
+    /// Hash and size of a file; directories have neither
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    pub classes: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnalyzedClass {
+    pub location: String,
+    pub artifact: String,
+    pub sha256: String,
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class_version: Option<u16>,
+    /// Kind of the error the class file failed with, if it could not be analyzed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorKind>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScanManifest {
+    pub artifacts: Vec<Artifact>,
+    pub classes: Vec<AnalyzedClass>,
+}
+
+impl ScanManifest {
+    /// The manifest of the class files a scan analyzed, with the kind of error of those that failed, in
+    /// scan order. Class files are read again for their hashes.
+    pub fn new(sources: &[(&ClassSource, Option<ErrorKind>)]) -> Result<Self> {
+        let classes = sources
+            .par_iter()
+            .map(|(source, error)| {
+                let data = source.read()?;
+                Ok(AnalyzedClass {
+                    location: source.location(),
+                    artifact: source.origin.display().to_string(),
+                    sha256: sha256_hex(&data),
+                    size: data.len() as u64,
+                    class_version: scan::header_version(&data),
+                    error: *error,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut artifacts: Vec<Artifact> = Vec::new();
+        let mut indices: HashMap<&Path, usize> = HashMap::new();
+        for (source, _) in sources {
+            let index = match indices.get(source.origin.as_path()) {
+                Some(&index) => index,
+                None => {
+                    artifacts.push(artifact(&source.origin)?);
+                    indices.insert(&source.origin, artifacts.len() - 1);
+                    artifacts.len() - 1
+                }
+            };
+            artifacts[index].classes += 1;
+        }
+        Ok(Self { artifacts, classes })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+fn artifact(path: &Path) -> Result<Artifact> {
+    let (kind, sha256, size) = if path.is_dir() {
+        ("directory", None, None)
+    } else {
+        let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        ("file", Some(sha256_hex(&bytes)), Some(bytes.len() as u64))
+    };
+    Ok(Artifact {
+        path: path.display().to_string(),
+        kind,
+        sha256,
+        size,
+        classes: 0,
+    })
+}
@@ -0,0 +1,550 @@
+//! `jmf serve`: a continuous-audit service running the scheduled scans of `jmf.toml`, persisting their
+//! results under `--results-dir` and answering a small JSON API over HTTP:
+//!
+//! - `GET /schedules`: the schedules and their last run;
+//! - `GET /schedules/NAME/latest`: the last persisted result, as `jmf run -f json` prints it;
+//! - `GET /schedules/NAME/calls?offset=&limit=&class=&method=`: a page of the calls of the last run since
+//!   the service started;
+//! - `GET /schedules/NAME/trend`: the calls per query over time, as `jmf trend -f json` prints them;
+//! - `POST /schedules/NAME/run`: queues a run of the scan, answering `202 Accepted` with its job, or
+//!   `429 Too Many Requests` while a run of it is already queued;
+//! - `GET /jobs/ID`: the job of a `POST /schedules/NAME/run`, with the run once it is done, while it is one
+//!   of the last `FINISHED_JOBS` done or failed.
+//!
+//! Requests are answered by a few worker threads, each connection having `CONNECTION_TIMEOUT` to send its
+//! request and read the response; up to `PENDING_CONNECTIONS` more wait for a worker, and the others are
+//! answered `503 Service Unavailable` and closed. The scans run one at a time on the scheduler thread, so
+//! minutes that pass while a scan runs are not checked for due schedules and those schedules are skipped.
+
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use java_method_finder::page::PageRequest;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tracing::{error, info};
+
+use crate::{
+    analysis::FoundCall,
+    config::{self, Schedule},
+    cron::Minute,
+    manifest::decode_url_path,
+    scan::{self, ScanArgs, ScanSummary},
+    trend::Trend,
+    Args, MethodFinder, PolicyResult,
+};
+
+/// How often the service checks for due schedules, queued jobs and Ctrl-C.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How long a client has to send its request, and to read each part of the response.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Threads answering requests, so that a slow client only holds up one of them.
+const WORKERS: usize = 4;
+
+/// Accepted connections waiting for a worker.
+const PENDING_CONNECTIONS: usize = 64;
+
+/// Longest request line and headers read, in bytes.
+const MAX_REQUEST_HEAD: u64 = 64 * 1024;
+
+/// Done and failed jobs kept for `GET /jobs/ID`.
+const FINISHED_JOBS: usize = 100;
+
+#[derive(clap::Args, Debug)]
+pub struct ServeArgs {
+    /// Configuration file with the schedules and the saved queries they run
+    #[arg(long = "config", default_value = "jmf.toml")]
+    pub config: PathBuf,
+
+    /// Address to answer the API on
+    #[arg(long = "listen", default_value = "127.0.0.1:7878")]
+    pub listen: String,
+
+    /// Directory the results are persisted in, one subdirectory per schedule
+    #[arg(long = "results-dir", default_value = ".jmf/results")]
+    pub results_dir: PathBuf,
+}
+
+/// Scan arguments of a schedule: its roots, with the defaults of every other option.
+#[derive(Parser)]
+struct ScheduledScan {
+    #[command(flatten)]
+    scan: ScanArgs,
+}
+
+struct Run {
+    timestamp: String,
+    calls: Vec<FoundCall>,
+    summary: ScanSummary,
+}
+
+#[derive(Serialize)]
+struct RunSummary<'a> {
+    timestamp: &'a str,
+    calls: usize,
+    #[serde(flatten)]
+    summary: &'a ScanSummary,
+}
+
+impl Run {
+    fn summary(&self) -> RunSummary<'_> {
+        RunSummary {
+            timestamp: &self.timestamp,
+            calls: self.calls.len(),
+            summary: &self.summary,
+        }
+    }
+}
+
+/// A run of a schedule requested over the API, by its id from 1.
+#[derive(Serialize)]
+struct Job {
+    id: usize,
+    schedule: String,
+    #[serde(flatten)]
+    state: JobState,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+enum JobState {
+    Queued,
+    Running,
+    Done { run: Value },
+    Failed { error: String },
+}
+
+struct Service {
+    schedules: Vec<Schedule>,
+    results_dir: PathBuf,
+    /// Last run of each schedule since the service started
+    runs: Mutex<HashMap<String, Run>>,
+    /// The jobs queued or running and the last `FINISHED_JOBS` others, in the order they were queued
+    jobs: Mutex<Vec<Job>>,
+    /// Id of the last job queued since the service started
+    last_job: AtomicUsize,
+}
+
+pub fn run(args: ServeArgs) -> Result<ExitCode> {
+    let schedules = config::load_schedules(&args.config)?;
+    anyhow::ensure!(!schedules.is_empty(), "No schedules in {}", args.config.display());
+    let listener = TcpListener::bind(&args.listen).with_context(|| format!("Failed to listen on {}", args.listen))?;
+    // Polled, so that Ctrl-C stops the service
+    listener.set_nonblocking(true)?;
+    let service = Arc::new(Service {
+        schedules,
+        results_dir: args.results_dir,
+        runs: Mutex::new(HashMap::new()),
+        jobs: Mutex::new(Vec::new()),
+        last_job: AtomicUsize::new(0),
+    });
+    println!("Listening on http://{}", listener.local_addr()?);
+    io::stdout().flush()?;
+    let scheduler = {
+        let service = service.clone();
+        thread::spawn(move || service.schedule())
+    };
+    let (connections, incoming) = mpsc::sync_channel::<TcpStream>(PENDING_CONNECTIONS);
+    let incoming = Arc::new(Mutex::new(incoming));
+    let workers: Vec<_> = (0..WORKERS)
+        .map(|_| {
+            let (service, incoming) = (service.clone(), incoming.clone());
+            thread::spawn(move || {
+                loop {
+                    // Released before answering, for the other workers to take the next connection
+                    let next = incoming.lock().unwrap().recv();
+                    // Ends once the accepting loop is done and the connections it passed on are
+                    let Ok(stream) = next else {
+                        return;
+                    };
+                    if let Err(e) = service.answer(stream) {
+                        error!("Failed to answer a request: {:#}", e);
+                    }
+                }
+            })
+        })
+        .collect();
+    while !scan::interrupted() {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                if let Err(mpsc::TrySendError::Full(stream)) = connections.try_send(stream) {
+                    reject(stream);
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+            Err(e) => error!("Failed to accept a connection: {}", e),
+        }
+    }
+    drop(connections);
+    for worker in workers {
+        let _ = worker.join();
+    }
+    let _ = scheduler.join();
+    Ok(ExitCode::SUCCESS)
+}
+
+impl Service {
+    /// Runs the queued jobs and the schedules due each minute until Ctrl-C.
+    fn schedule(&self) {
+        let mut last = Minute::now();
+        while !scan::interrupted() {
+            thread::sleep(POLL_INTERVAL);
+            self.run_jobs();
+            let now = Minute::now();
+            if now == last {
+                continue;
+            }
+            last = now;
+            for schedule in self.schedules.iter().filter(|schedule| schedule.cron.matches(&now)) {
+                info!("Running schedule '{}' ({})", schedule.name, schedule.cron);
+                if let Err(e) = self.run(schedule) {
+                    error!("Schedule '{}' failed: {:#}", schedule.name, e);
+                }
+            }
+        }
+    }
+
+    /// Runs the queued jobs, oldest first.
+    fn run_jobs(&self) {
+        loop {
+            let next = self
+                .jobs
+                .lock()
+                .unwrap()
+                .iter_mut()
+                .find(|job| matches!(job.state, JobState::Queued))
+                .map(|job| {
+                    job.state = JobState::Running;
+                    (job.id, job.schedule.clone())
+                });
+            let Some((id, name)) = next else {
+                return;
+            };
+            info!("Running job {} of schedule '{}'", id, name);
+            let state = match self.schedules.iter().find(|schedule| schedule.name == name) {
+                Some(schedule) => match self.run(schedule) {
+                    Ok(run) => JobState::Done { run },
+                    Err(e) => JobState::Failed {
+                        error: format!("{:#}", e),
+                    },
+                },
+                None => JobState::Failed {
+                    error: format!("No schedule '{}'", name),
+                },
+            };
+            let mut jobs = self.jobs.lock().unwrap();
+            if let Some(job) = jobs.iter_mut().find(|job| job.id == id) {
+                job.state = state;
+            }
+            // The oldest finished jobs are forgotten
+            let finished = |job: &Job| matches!(job.state, JobState::Done { .. } | JobState::Failed { .. });
+            let mut forgotten = jobs
+                .iter()
+                .filter(|job| finished(job))
+                .count()
+                .saturating_sub(FINISHED_JOBS);
+            jobs.retain(|job| {
+                let forget = forgotten > 0 && finished(job);
+                forgotten -= usize::from(forget);
+                !forget
+            });
+        }
+    }
+
+    /// Queues a run of the schedule for the scheduler thread, unless one is already queued.
+    fn enqueue(&self, schedule: &Schedule) -> Result<Value, ApiError> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let queued = jobs
+            .iter()
+            .find(|job| job.schedule == schedule.name && matches!(job.state, JobState::Queued));
+        if let Some(job) = queued {
+            return Err(ApiError::too_many_requests(format!(
+                "A run of schedule '{}' is already queued as job {}",
+                schedule.name, job.id
+            )));
+        }
+        let job = Job {
+            id: self.last_job.fetch_add(1, Ordering::Relaxed) + 1,
+            schedule: schedule.name.clone(),
+            state: JobState::Queued,
+        };
+        let value = json!(job);
+        jobs.push(job);
+        Ok(value)
+    }
+
+    fn job(&self, id: &str) -> Result<Value, ApiError> {
+        let jobs = self.jobs.lock().unwrap();
+        id.parse::<usize>()
+            .ok()
+            .and_then(|id| jobs.iter().find(|job| job.id == id))
+            .map(|job| json!(job))
+            .ok_or_else(|| ApiError::not_found(format!("No job '{}'", id)))
+    }
+
+    /// Scans the roots of a schedule and persists the result, named by its UTC time and, after the first
+    /// result of the same second, a count from 2.
+    fn run(&self, schedule: &Schedule) -> Result<Value> {
+        let mut command_line: Vec<OsString> = vec!["jmf".into()];
+        for root in &schedule.roots {
+            command_line.extend(["-s".into(), root.clone().into_os_string()]);
+        }
+        let scan = ScheduledScan::try_parse_from(command_line)?.scan;
+        let finder = MethodFinder::with_rules(Args::defaults(scan), schedule.rules.clone())?;
+        let (calls, summary) = finder.scan_folder()?;
+        let result = PolicyResult {
+            summary: summary.clone(),
+            ..PolicyResult::new(&finder.analyzer.rules, &calls)
+        };
+        let (timestamp, file_stem) = now();
+        let directory = self.results_dir.join(&schedule.name);
+        fs::create_dir_all(&directory).with_context(|| format!("Failed to create {}", directory.display()))?;
+        let mut count = 1;
+        let (path, mut file) = loop {
+            let path = match count {
+                1 => directory.join(format!("{}.json", file_stem)),
+                _ => directory.join(format!("{}-{}.json", file_stem, count)),
+            };
+            match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => break (path, file),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => count += 1,
+                Err(e) => return Err(e).with_context(|| format!("Failed to create {}", path.display())),
+            }
+        };
+        file.write_all(result.to_json().as_bytes())
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        let run = Run {
+            timestamp,
+            calls,
+            summary,
+        };
+        let run_summary = json!(run.summary());
+        self.runs.lock().unwrap().insert(schedule.name.clone(), run);
+        Ok(run_summary)
+    }
+
+    fn answer(&self, stream: TcpStream) -> Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+        stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+        let mut reader = BufReader::new((&stream).take(MAX_REQUEST_HEAD));
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        // The API takes no request bodies, so the headers are skipped
+        let mut header = String::new();
+        let mut complete = false;
+        while !complete && reader.read_line(&mut header)? > 0 {
+            complete = header.trim_end().is_empty();
+            header.clear();
+        }
+        let mut words = request_line.split_whitespace();
+        let (method, target) = (words.next().unwrap_or_default(), words.next().unwrap_or_default());
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let response = if !complete && reader.get_ref().limit() == 0 {
+            Err(ApiError(
+                "431 Request Header Fields Too Large",
+                format!("The request line and headers exceed {} bytes", MAX_REQUEST_HEAD),
+            ))
+        } else {
+            self.route(method, path, query)
+        };
+        let (status, body) = match response {
+            Ok(response) => response,
+            Err(ApiError(status, message)) => (status, json!({ "error": message })),
+        };
+        let body = serde_json::to_string_pretty(&body)?;
+        write!(
+            &stream,
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )?;
+        Ok(())
+    }
+
+    /// The status and body of the response to a request.
+    fn route(&self, method: &str, path: &str, query: &str) -> Result<(&'static str, Value), ApiError> {
+        const OK: &str = "200 OK";
+        let segments: Vec<String> = path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(decode_url_path)
+            .collect();
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+        match (method, &segments[..]) {
+            ("GET", ["schedules"]) => Ok((OK, self.list())),
+            ("GET", ["jobs", id]) => Ok((OK, self.job(id)?)),
+            (_, ["schedules", name, action]) => {
+                let schedule = self
+                    .schedules
+                    .iter()
+                    .find(|schedule| schedule.name == *name)
+                    .ok_or_else(|| ApiError::not_found(format!("No schedule '{}'", name)))?;
+                match (method, *action) {
+                    ("GET", "latest") => Ok((OK, self.latest(schedule)?)),
+                    ("GET", "calls") => Ok((OK, self.calls(schedule, query)?)),
+                    ("GET", "trend") => Trend::load(&self.results_dir.join(&schedule.name))
+                        .map(|trend| (OK, json!(trend)))
+                        .map_err(ApiError::internal),
+                    ("POST", "run") => Ok(("202 Accepted", self.enqueue(schedule)?)),
+                    _ => Err(ApiError::not_found(format!("No {} {}", method, path))),
+                }
+            }
+            _ => Err(ApiError::not_found(format!("No {} {}", method, path))),
+        }
+    }
+
+    fn list(&self) -> Value {
+        let runs = self.runs.lock().unwrap();
+        let schedules: Vec<Value> = self
+            .schedules
+            .iter()
+            .map(|schedule| {
+                json!({
+                    "name": schedule.name,
+                    "cron": schedule.cron.to_string(),
+                    "roots": schedule.roots,
+                    "queries": schedule.rules.iter().map(|rule| rule.id()).collect::<Vec<_>>(),
+                    "last_run": runs.get(&schedule.name).map(Run::summary),
+                })
+            })
+            .collect();
+        json!({ "schedules": schedules })
+    }
+
+    /// The newest persisted result, which outlives restarts of the service.
+    fn latest(&self, schedule: &Schedule) -> Result<Value, ApiError> {
+        let directory = self.results_dir.join(&schedule.name);
+        let newest = fs::read_dir(&directory)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .max_by_key(|path| result_order(path))
+            .ok_or_else(|| ApiError::not_found(format!("Schedule '{}' has no results yet", schedule.name)))?;
+        read_json(&newest).map_err(ApiError::internal)
+    }
+
+    fn calls(&self, schedule: &Schedule, query: &str) -> Result<Value, ApiError> {
+        let request = page_request(query)?;
+        let runs = self.runs.lock().unwrap();
+        let run = runs.get(&schedule.name).ok_or_else(|| {
+            ApiError::not_found(format!(
+                "Schedule '{}' has not run since the service started",
+                schedule.name
+            ))
+        })?;
+        let page = request.apply(&run.calls);
+        Ok(json!({ "timestamp": run.timestamp, "total": page.total, "offset": page.offset, "calls": page.calls }))
+    }
+}
+
+struct ApiError(&'static str, String);
+
+impl ApiError {
+    fn not_found(message: String) -> Self {
+        Self("404 Not Found", message)
+    }
+
+    fn bad_request(message: String) -> Self {
+        Self("400 Bad Request", message)
+    }
+
+    fn too_many_requests(message: String) -> Self {
+        Self("429 Too Many Requests", message)
+    }
+
+    fn internal(e: anyhow::Error) -> Self {
+        Self("500 Internal Server Error", format!("{:#}", e))
+    }
+}
+
+/// A page request from the parameters of a query string.
+fn page_request(query: &str) -> Result<PageRequest, ApiError> {
+    let mut request = PageRequest::default();
+    for parameter in query.split('&').filter(|parameter| !parameter.is_empty()) {
+        let (key, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+        let value = decode_url_path(&value.replace('+', " "));
+        let number = |value: &str| {
+            value
+                .parse::<usize>()
+                .map_err(|_| ApiError::bad_request(format!("Invalid {}: {}", key, value)))
+        };
+        match key {
+            "offset" => request.offset = number(&value)?,
+            "limit" => request.limit = Some(number(&value)?),
+            "class" => request.class = Some(value),
+            "method" => request.method = Some(value),
+            _ => return Err(ApiError::bad_request(format!("Unknown parameter: {}", key))),
+        }
+    }
+    Ok(request)
+}
+
+/// Answers a connection no worker can take `503 Service Unavailable` without waiting on the client, and
+/// closes it.
+fn reject(stream: TcpStream) {
+    let body = serde_json::to_string_pretty(&json!({ "error": "Too many pending connections" })).unwrap_or_default();
+    let _ = stream.set_nonblocking(true);
+    let _ = write!(
+        &stream,
+        "HTTP/1.1 503 Service Unavailable\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+}
+
+fn read_json(path: &Path) -> Result<Value> {
+    let text = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&text).with_context(|| format!("Invalid JSON in {}", path.display()))
+}
+
+/// The order results were persisted in: by the time in their names, then by their count within the second.
+fn result_order(path: &Path) -> (String, u32) {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    // A count follows a second dash, as in `20240501-123045-2`
+    match stem.match_indices('-').nth(1) {
+        Some((index, _)) => (stem[..index].to_string(), stem[index + 1..].parse().unwrap_or(0)),
+        None => (stem.into_owned(), 1),
+    }
+}
+
+/// The current UTC time, as `2024-05-01 12:30:45` and as the `20240501-123045` stem of result file names,
+/// which `jmf trend` reads the time from.
+fn now() -> (String, String) {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let minute = Minute::from_unix(seconds);
+    let second = seconds % 60;
+    (
+        format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            minute.year, minute.month, minute.day, minute.hour, minute.minute, second
+        ),
+        format!(
+            "{:04}{:02}{:02}-{:02}{:02}{:02}",
+            minute.year, minute.month, minute.day, minute.hour, minute.minute, second
+        ),
+    )
+}
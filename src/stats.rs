@@ -0,0 +1,133 @@
+//! Aggregated counts over the found calls, for `--stats` and `--top`.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::FoundCall;
+
+/// Number of caller methods listed under "Top callers".
+const TOP_CALLERS: usize = 10;
+
+#[derive(Debug, Serialize)]
+pub struct Count {
+    pub name: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub total_calls: usize,
+    pub caller_classes: usize,
+    pub caller_packages: usize,
+    pub by_package: Vec<Count>,
+    pub by_class: Vec<Count>,
+    pub top_callers: Vec<Count>,
+}
+
+impl Stats {
+    pub fn new(calls: &[FoundCall]) -> Self {
+        let by_class = count_by(calls, |call| call.class_name.replace('/', "."));
+        let by_package = count_by(calls, |call| match call.class_name.rsplit_once('/') {
+            Some((package, _)) => package.replace('/', "."),
+            None => "(default package)".to_string(),
+        });
+        let mut top_callers = count_by(calls, |call| {
+            format!("{}#{}", call.class_name.replace('/', "."), call.method_name)
+        });
+        top_callers.truncate(TOP_CALLERS);
+
+        Self {
+            total_calls: calls.len(),
+            caller_classes: by_class.len(),
+            caller_packages: by_package.len(),
+            by_package,
+            by_class,
+            top_callers,
+        }
+    }
+
+    pub fn to_text(&self, target: &str) -> String {
+        let mut output = vec![
+            target.to_string(),
+            format!("Total calls: {}", self.total_calls),
+            format!("Caller classes: {}", self.caller_classes),
+            format!("Caller packages: {}", self.caller_packages),
+        ];
+        for (title, counts) in [
+            ("By package:", &self.by_package),
+            ("By class:", &self.by_class),
+            ("Top callers:", &self.top_callers),
+        ] {
+            output.push(title.to_string());
+            output.extend(counts.iter().map(|count| format!(" - {}: {}", count.name, count.count)));
+        }
+        output.join("\n")
+    }
+}
+
+/// A caller's share of the calls, in percent with one decimal.
+#[derive(Debug, Serialize)]
+pub struct Hotspot {
+    pub name: String,
+    pub count: usize,
+    pub percent: f64,
+}
+
+/// The caller classes and methods with the most calls, for planning which to migrate first.
+#[derive(Debug, Serialize)]
+pub struct Hotspots {
+    pub total_calls: usize,
+    pub classes: Vec<Hotspot>,
+    pub methods: Vec<Hotspot>,
+}
+
+impl Hotspots {
+    pub fn new(calls: &[FoundCall], top: usize) -> Self {
+        let hotspots = |counts: Vec<Count>| {
+            counts
+                .into_iter()
+                .take(top)
+                .map(|count| Hotspot {
+                    percent: (count.count as f64 * 1000.0 / calls.len() as f64).round() / 10.0,
+                    name: count.name,
+                    count: count.count,
+                })
+                .collect()
+        };
+        Self {
+            total_calls: calls.len(),
+            classes: hotspots(count_by(calls, |call| call.class_name.replace('/', "."))),
+            methods: hotspots(count_by(calls, |call| {
+                format!("{}#{}", call.class_name.replace('/', "."), call.method_name)
+            })),
+        }
+    }
+
+    pub fn to_text(&self, target: &str) -> String {
+        let mut output = vec![target.to_string(), format!("Total calls: {}", self.total_calls)];
+        for (title, hotspots) in [
+            ("Top caller classes:", &self.classes),
+            ("Top caller methods:", &self.methods),
+        ] {
+            output.push(title.to_string());
+            output.extend(
+                hotspots
+                    .iter()
+                    .map(|hotspot| format!(" - {}: {} ({:.1}%)", hotspot.name, hotspot.count, hotspot.percent)),
+            );
+        }
+        output.join("\n")
+    }
+}
+
+/// Counts calls per key, heaviest first and by name among equal counts.
+fn count_by(calls: &[FoundCall], key: impl Fn(&FoundCall) -> String) -> Vec<Count> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for call in calls {
+        *counts.entry(key(call)).or_default() += 1;
+    }
+    let mut counts: Vec<Count> = counts.into_iter().map(|(name, count)| Count { name, count }).collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    counts
+}
@@ -0,0 +1,45 @@
+//! Calls as an async stream, for embedding in tokio-based servers.
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, Stream};
+
+use crate::analysis::{scanned_files, Analyzer, FoundCall};
+
+/// Calls waiting for the consumer before the scan pauses.
+const BUFFER: usize = 64;
+
+impl Analyzer {
+    /// `scan` on a blocking thread of the current tokio runtime, yielding each call as soon as its class
+    /// file is analyzed. The scan pauses while the consumer falls behind and stops once the stream is
+    /// dropped. A class file that cannot be analyzed is yielded as a
+    /// [`ClassError`](crate::analysis::ClassError) and the stream goes on; any other failure ends it with an
+    /// error.
+    pub fn scan_stream(self, path: PathBuf) -> impl Stream<Item = Result<FoundCall>> {
+        let (sender, receiver) = mpsc::channel(BUFFER);
+        tokio::task::spawn_blocking(move || {
+            let result = scanned_files(&path).and_then(|files| {
+                for file in files {
+                    let errors = self.scan_file(&file, &mut |call| {
+                        sender
+                            .blocking_send(Ok(call))
+                            .map_err(|_| anyhow!("The stream was dropped"))
+                    })?;
+                    for error in errors {
+                        sender
+                            .blocking_send(Err(error.into()))
+                            .map_err(|_| anyhow!("The stream was dropped"))?;
+                    }
+                }
+                Ok(())
+            });
+            if let Err(e) = result {
+                // Nobody is left to tell when the stream was dropped
+                let _ = sender.blocking_send(Err(e));
+            }
+        });
+        ReceiverStream::new(receiver)
+    }
+}
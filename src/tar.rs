@@ -0,0 +1,147 @@
+//! A streaming reader of tar archives, enough for the ustar, GNU and pax archives of container image
+//! layers and release bundles.
+
+use std::io::{self, Read};
+
+use anyhow::{Context, Result};
+
+const BLOCK: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Directory,
+    /// Links, devices and other entries without contents
+    Other,
+}
+
+/// An entry of an archive, its contents readable from `data` while `for_each` visits it.
+pub struct Entry<'a> {
+    /// Path in the archive, without a leading `./` or `/`
+    pub name: String,
+    pub kind: EntryKind,
+    pub size: u64,
+    /// Offset of the contents from the start of the archive
+    pub offset: u64,
+    pub data: &'a mut dyn Read,
+}
+
+/// Counts the bytes read, for the offsets of entries.
+struct Counting<R> {
+    inner: R,
+    position: u64,
+}
+
+impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+/// Applies `f` to every entry of an archive in order; contents `f` leaves unread are skipped.
+pub fn for_each(reader: impl Read, mut f: impl FnMut(Entry) -> Result<()>) -> Result<()> {
+    let mut reader = Counting {
+        inner: reader,
+        position: 0,
+    };
+    let mut long_name: Option<String> = None;
+    let mut header = [0; BLOCK];
+    loop {
+        if !read_block(&mut reader, &mut header)? || header.iter().all(|byte| *byte == 0) {
+            return Ok(());
+        }
+        let size = parse_size(&header[124..136]).context("Invalid tar entry size")?;
+        let offset = reader.position;
+        let mut data = (&mut reader).take(size);
+        match header[156] {
+            // GNU long name of the next entry
+            b'L' => {
+                let mut name = Vec::new();
+                data.read_to_end(&mut name)?;
+                long_name = Some(String::from_utf8_lossy(&name).trim_end_matches('\0').to_string());
+            }
+            // pax extended header of the next entry
+            b'x' => {
+                let mut records = String::new();
+                data.read_to_string(&mut records)?;
+                if let Some(path) = records.lines().find_map(|record| record.split_once(" path=")) {
+                    long_name = Some(path.1.to_string());
+                }
+            }
+            flag => {
+                let name = long_name.take().unwrap_or_else(|| header_name(&header));
+                let kind = match flag {
+                    b'0' | b'\0' | b'7' => EntryKind::File,
+                    b'5' => EntryKind::Directory,
+                    _ => EntryKind::Other,
+                };
+                f(Entry {
+                    name: normalize(&name),
+                    kind,
+                    size,
+                    offset,
+                    data: &mut data,
+                })?;
+            }
+        }
+        io::copy(&mut data, &mut io::sink())?;
+        let padding = (BLOCK - (size as usize % BLOCK)) % BLOCK;
+        io::copy(&mut (&mut reader).take(padding as u64), &mut io::sink())?;
+    }
+}
+
+/// Reads a whole block, or returns `false` at the end of the archive.
+fn read_block(reader: &mut impl Read, block: &mut [u8; BLOCK]) -> Result<bool> {
+    let mut filled = 0;
+    while filled < BLOCK {
+        match reader.read(&mut block[filled..])? {
+            0 if filled == 0 => return Ok(false),
+            0 => anyhow::bail!("Truncated tar header"),
+            read => filled += read,
+        }
+    }
+    Ok(true)
+}
+
+fn header_name(header: &[u8; BLOCK]) -> String {
+    let field = |bytes: &[u8]| {
+        let end = bytes.iter().position(|byte| *byte == 0).unwrap_or(bytes.len());
+        String::from_utf8_lossy(&bytes[..end]).into_owned()
+    };
+    let name = field(&header[..100]);
+    // ustar splits long paths into a prefix and a name
+    let prefix = if &header[257..262] == b"ustar" {
+        field(&header[345..500])
+    } else {
+        String::new()
+    };
+    if prefix.is_empty() {
+        name
+    } else {
+        format!("{}/{}", prefix, name)
+    }
+}
+
+/// Sizes are octal, or base-256 when the high bit of the first byte is set.
+fn parse_size(field: &[u8]) -> Option<u64> {
+    if field[0] & 0x80 != 0 {
+        return Some(
+            field[1..]
+                .iter()
+                .fold(u64::from(field[0] & 0x7f), |size, byte| size << 8 | u64::from(*byte)),
+        );
+    }
+    let digits = String::from_utf8_lossy(field);
+    let digits = digits.trim_matches(|c: char| c == '\0' || c == ' ');
+    if digits.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(digits, 8).ok()
+}
+
+fn normalize(name: &str) -> String {
+    let name = name.trim_start_matches("./").trim_start_matches('/');
+    name.trim_end_matches('/').to_string()
+}
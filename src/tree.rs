@@ -0,0 +1,80 @@
+//! Found calls as a package → class → method → line hierarchy, for `--format tree`.
+
+use std::collections::BTreeMap;
+
+use crate::{color, FoundCall};
+
+#[derive(Default)]
+struct Node {
+    count: usize,
+    children: BTreeMap<Key, Node>,
+}
+
+/// Lines sort numerically, everything else by name.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Key {
+    Name(String),
+    Line(u16),
+}
+
+impl Node {
+    fn insert(&mut self, path: Vec<Key>) {
+        self.count += 1;
+        let mut node = self;
+        for key in path {
+            node = node.children.entry(key).or_default();
+            node.count += 1;
+        }
+    }
+
+    fn render(&self, depth: usize, prefix: &str, output: &mut Vec<String>) {
+        let last = self.children.len().saturating_sub(1);
+        for (index, (key, child)) in self.children.iter().enumerate() {
+            let (branch, indent) = if index == last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            let label = match key {
+                Key::Name(name) if depth == 1 => color::cyan(name),
+                Key::Name(name) => name.clone(),
+                Key::Line(line) => color::dim(format!("L{}", line)),
+            };
+            // A line is only counted when it holds several calls
+            let count = match key {
+                Key::Line(_) if child.count == 1 => String::new(),
+                _ => format!(" ({})", child.count),
+            };
+            output.push(format!("{}{}{}{}", prefix, branch, label, count));
+            child.render(depth + 1, &format!("{}{}", prefix, indent), output);
+        }
+    }
+}
+
+pub fn to_tree<'a>(calls: impl IntoIterator<Item = &'a FoundCall>) -> String {
+    let mut root = Node::default();
+    for call in calls {
+        let class_name = call.class_name.replace('/', ".");
+        let (package, simple_name) = match class_name.rsplit_once('.') {
+            Some((package, simple_name)) => (package.to_string(), simple_name.to_string()),
+            None => ("(default package)".to_string(), class_name),
+        };
+        root.insert(vec![
+            Key::Name(package),
+            Key::Name(simple_name),
+            Key::Name(call.method_name.to_string()),
+            Key::Line(call.line_number),
+        ]);
+    }
+    if root.children.is_empty() {
+        return "No results".to_string();
+    }
+    let mut output = Vec::new();
+    for (key, package) in &root.children {
+        if let Key::Name(name) = key {
+            output.push(format!("{} ({})", name, package.count));
+        }
+        package.render(1, "", &mut output);
+    }
+    output.join("\n")
+}
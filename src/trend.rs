@@ -0,0 +1,219 @@
+//! Call site counts over time from saved JSON results, for `jmf trend`: the burn-down of a migration off a
+//! deprecated API.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::color::{self, ColorChoice};
+
+/// Width of the longest bar of the text chart.
+const BAR_WIDTH: usize = 40;
+
+#[derive(clap::Args, Debug)]
+pub struct TrendArgs {
+    /// Directory of JSON results (`-f json` of `jmf`, `jmf check` or `jmf run`), dated by the timestamp in
+    /// their file name, e.g. `2024-05-01.json`, or else by their modification time
+    #[arg(long = "history")]
+    pub history: PathBuf,
+
+    #[arg(short = 'f', long = "format", value_enum, default_value_t = crate::ReportFormatter::Txt)]
+    pub format: crate::ReportFormatter,
+
+    /// When to color text output
+    #[arg(long = "color", value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Point {
+    pub timestamp: String,
+    pub file: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TargetTrend {
+    pub target: String,
+    pub points: Vec<Point>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Trend {
+    pub targets: Vec<TargetTrend>,
+}
+
+impl Trend {
+    /// Reads the results of a history directory, oldest first.
+    pub fn load(history: &Path) -> Result<Self> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(history).with_context(|| format!("Failed to read {}", history.display()))? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+                files.push((timestamp(&path)?, path));
+            }
+        }
+        files.sort();
+        let mut targets: BTreeMap<String, Vec<Point>> = BTreeMap::new();
+        for (timestamp, path) in files {
+            let text = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            let result: Value =
+                serde_json::from_str(&text).with_context(|| format!("Invalid JSON in {}", path.display()))?;
+            let counts = counts(&result).with_context(|| format!("Not a jmf JSON result: {}", path.display()))?;
+            for (target, count) in counts {
+                targets.entry(target).or_default().push(Point {
+                    timestamp: timestamp.clone(),
+                    file: path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+                    count,
+                });
+            }
+        }
+        Ok(Self {
+            targets: targets
+                .into_iter()
+                .map(|(target, points)| TargetTrend { target, points })
+                .collect(),
+        })
+    }
+
+    /// A bar per result and target, with the change since the first result.
+    pub fn to_text(&self) -> String {
+        if self.targets.is_empty() {
+            return "No results".to_string();
+        }
+        let mut output = Vec::new();
+        for target in &self.targets {
+            output.push(color::bold(&target.target));
+            let max = target.points.iter().map(|point| point.count).max().unwrap_or_default();
+            let width = max.to_string().len();
+            let timestamp_width = target
+                .points
+                .iter()
+                .map(|point| point.timestamp.len())
+                .max()
+                .unwrap_or_default();
+            for point in &target.points {
+                let bar = if max == 0 {
+                    0
+                } else {
+                    (point.count * BAR_WIDTH).div_ceil(max)
+                };
+                output.push(format!(
+                    "  {}  {:>width$} {}",
+                    color::dim(format!("{:<timestamp_width$}", point.timestamp)),
+                    point.count,
+                    "#".repeat(bar),
+                ));
+            }
+            if let (Some(first), Some(last)) = (target.points.first(), target.points.last()) {
+                let change = last.count as i64 - first.count as i64;
+                output.push(format!(
+                    "  {} -> {} ({:+}) since {}",
+                    first.count, last.count, change, first.timestamp
+                ));
+            }
+        }
+        output.join("\n")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
+
+/// Calls per target of a result: of a search, a package search or a grouped search by its `target`, and of
+/// a policy check by each rule's. Grouped calls are counted once, even when listed under several groups.
+fn counts(result: &Value) -> Option<Vec<(String, usize)>> {
+    if let Some(rules) = result["rules"].as_array() {
+        return rules
+            .iter()
+            .map(|rule| Some((rule["target"].as_str()?.to_string(), rule["calls"].as_array()?.len())))
+            .collect();
+    }
+    let target = result["target"].as_str()?.to_string();
+    let groups = ["calls", "targets", "origins", "owners"]
+        .iter()
+        .find_map(|key| result[key].as_array())?;
+    let mut calls = HashSet::new();
+    for group in groups {
+        match group["calls"].as_array() {
+            Some(group_calls) => calls.extend(group_calls.iter().map(Value::to_string)),
+            None => {
+                calls.insert(group.to_string());
+            }
+        }
+    }
+    Some(vec![(target, calls.len())])
+}
+
+/// The timestamp in a file name, as `2024-05-01`, `20240501`, `2024-05-01T12:30:00` or `20240501-1230`,
+/// normalized to `2024-05-01` or `2024-05-01 12:30`; otherwise the modification time of the file.
+fn timestamp(path: &Path) -> Result<String> {
+    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+    if let Some(timestamp) = name
+        .char_indices()
+        .find_map(|(index, _)| parse_timestamp(&name[index..]))
+    {
+        return Ok(timestamp);
+    }
+    let modified = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .with_context(|| format!("Failed to read the modification time of {}", path.display()))?;
+    let seconds = modified.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    Ok(format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds % 86_400 / 3_600,
+        seconds % 3_600 / 60
+    ))
+}
+
+/// A date, possibly followed by a time, at the start of `text`.
+fn parse_timestamp(text: &str) -> Option<String> {
+    let compact: String = text
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || matches!(c, '-' | 'T' | '_' | ':'))
+        .filter(char::is_ascii_digit)
+        .collect();
+    if compact.len() < 8 || !text.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let (year, month, day) = (&compact[..4], &compact[4..6], &compact[6..8]);
+    let valid_date = (1..=12).contains(&month.parse::<u8>().ok()?) && (1..=31).contains(&day.parse::<u8>().ok()?);
+    if !valid_date || !year.starts_with(['1', '2']) {
+        return None;
+    }
+    let date = format!("{}-{}-{}", year, month, day);
+    match compact.get(8..12) {
+        Some(time) => Some(format!("{} {}:{}", date, &time[..2], &time[2..])),
+        None => Some(date),
+    }
+}
+
+/// Year, month and day of a number of days since 1970-01-01, after Howard Hinnant's `civil_from_days`.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,169 @@
+//! `--verify-sha256` and `--verify-signature`: checks that archive scan roots, downloaded or not, are
+//! exactly the published artifacts before any of their classes are read, so that a scan only reports on
+//! the bits it was meant to audit.
+//!
+//! Signatures are verified by the JDK's `jarsigner`, against its `cacerts` or a `--signature-keystore`.
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+};
+
+use anyhow::{Context, Result};
+use tracing::debug;
+
+use crate::remote;
+
+/// Environment variable holding the password of the `--signature-keystore`, if it has one.
+const KEYSTORE_PASSWORD_VARIABLE: &str = "JMF_KEYSTORE_PASSWORD";
+
+/// A `--verify-sha256` value: the hex digest, and the scan root it is for unless there is a single one.
+pub fn parse_checksum(value: &str) -> Result<(Option<PathBuf>, String), String> {
+    let (root, sum) = match value.rsplit_once('=') {
+        Some((root, sum)) => (Some(PathBuf::from(root)), sum),
+        None => (None, value),
+    };
+    if sum.len() != 64 || !sum.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!(
+            "expected a SHA-256 hex digest or <root>=<digest>, got '{}'",
+            value
+        ));
+    }
+    Ok((root, sum.to_ascii_lowercase()))
+}
+
+pub struct Verifier {
+    checksums: Vec<(Option<PathBuf>, String)>,
+    signatures: bool,
+    keystore: Option<PathBuf>,
+    /// Scan roots verified already, which the class files of later listings are read from as they were
+    verified: Mutex<HashSet<PathBuf>>,
+}
+
+impl Verifier {
+    pub fn new(
+        checksums: &[(Option<PathBuf>, String)],
+        signatures: bool,
+        keystore: Option<&Path>,
+        scan_roots: &[PathBuf],
+    ) -> Result<Self> {
+        anyhow::ensure!(
+            scan_roots.len() == 1 || checksums.iter().all(|(root, _)| root.is_some()),
+            "--verify-sha256 needs <root>=<digest> with several scan roots"
+        );
+        anyhow::ensure!(
+            keystore.is_none() || signatures,
+            "--signature-keystore needs --verify-signature"
+        );
+        Ok(Self {
+            checksums: checksums.to_vec(),
+            signatures,
+            keystore: keystore.map(Path::to_path_buf),
+            verified: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// The `--verify-sha256` values for the scan root, by index.
+    fn checksums_of<'a>(&'a self, root: &'a Path) -> impl Iterator<Item = (usize, &'a str)> {
+        self.checksums
+            .iter()
+            .enumerate()
+            .filter(move |(_, (named, _))| named.as_deref().is_none_or(|named| named == root))
+            .map(|(index, (_, sum))| (index, sum.as_str()))
+    }
+
+    /// Checks the archive scan root `root`, whose contents are in `file`, returning the indices of the
+    /// `--verify-sha256` values it was verified against. Only jars and zips can be signed.
+    pub fn verify_archive(&self, root: &Path, file: &Path, jar: bool) -> Result<Vec<usize>> {
+        let checksums: Vec<(usize, &str)> = self.checksums_of(root).collect();
+        if self.verified.lock().unwrap().contains(root) {
+            return Ok(checksums.into_iter().map(|(index, _)| index).collect());
+        }
+        if !checksums.is_empty() {
+            let actual = remote::file_digest(file, "sha256")?;
+            for (_, sum) in &checksums {
+                anyhow::ensure!(
+                    *sum == actual,
+                    "SHA-256 mismatch for {}: expected {}, found {}",
+                    root.display(),
+                    sum,
+                    actual
+                );
+            }
+            debug!("Verified SHA-256 of {}", root.display());
+        }
+        if self.signatures {
+            anyhow::ensure!(
+                jar,
+                "--verify-signature cannot verify {}, which is not a jar",
+                root.display()
+            );
+            self.verify_signature(root, file)?;
+        }
+        self.verified.lock().unwrap().insert(root.to_path_buf());
+        Ok(checksums.into_iter().map(|(index, _)| index).collect())
+    }
+
+    /// Fails for a scan root that is not an archive when signatures are verified, as it cannot be signed.
+    pub fn verify_other(&self, root: &Path) -> Result<()> {
+        anyhow::ensure!(
+            !self.signatures,
+            "--verify-signature cannot verify {}, which is not a jar",
+            root.display()
+        );
+        Ok(())
+    }
+
+    /// Fails for the `--verify-sha256` values that no archive scan root was verified against.
+    pub fn ensure_used(&self, used: &[usize]) -> Result<()> {
+        for (index, (root, sum)) in self.checksums.iter().enumerate() {
+            anyhow::ensure!(
+                used.contains(&index),
+                "--verify-sha256 {} names no jar or archive scan root",
+                match root {
+                    Some(root) => format!("{}={}", root.display(), sum),
+                    None => sum.clone(),
+                }
+            );
+        }
+        Ok(())
+    }
+
+    /// Runs `jarsigner -verify -strict`, which fails for unsigned jars, for entries that do not match their
+    /// signature and for signers that the keystore does not trust.
+    fn verify_signature(&self, root: &Path, file: &Path) -> Result<()> {
+        let mut command = Command::new("jarsigner");
+        command.args(["-verify", "-strict"]);
+        if let Some(keystore) = &self.keystore {
+            command.arg("-keystore").arg(keystore);
+            if std::env::var_os(KEYSTORE_PASSWORD_VARIABLE).is_some() {
+                command.args(["-storepass:env", KEYSTORE_PASSWORD_VARIABLE]);
+            }
+        }
+        let output = command
+            .arg(file)
+            .output()
+            .context("Failed to run jarsigner, which --verify-signature needs")?;
+        if !output.status.success() {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            // The verdict comes first, then the errors under an `Error:` heading and the warnings
+            let reasons: Vec<&str> = stderr
+                .lines()
+                .chain(stdout.lines())
+                .map(str::trim)
+                .take_while(|line| *line != "Warning:" && !line.starts_with("Re-run with"))
+                .filter(|line| !line.is_empty() && !line.ends_with(':'))
+                .collect();
+            anyhow::bail!(
+                "Signature verification failed for {}: {}",
+                root.display(),
+                reasons.join(" ")
+            );
+        }
+        debug!("Verified signature of {}", root.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+//! Distribution of class file versions over the scan set, for `jmf versions`.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::analysis::java_release;
+
+#[derive(Debug, Serialize)]
+pub struct VersionCount {
+    pub class_version: u16,
+    pub java_release: String,
+    pub classes: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Versions {
+    pub total_classes: usize,
+    pub versions: Vec<VersionCount>,
+}
+
+impl Versions {
+    /// Counts classes per major version, oldest first.
+    pub fn new(major_versions: &[u16]) -> Self {
+        let mut counts: BTreeMap<u16, usize> = BTreeMap::new();
+        for &major_version in major_versions {
+            *counts.entry(major_version).or_default() += 1;
+        }
+        Self {
+            total_classes: major_versions.len(),
+            versions: counts
+                .into_iter()
+                .map(|(class_version, classes)| VersionCount {
+                    class_version,
+                    java_release: java_release(class_version),
+                    classes,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        if self.versions.is_empty() {
+            return "No results".to_string();
+        }
+        let mut output = vec![format!("Total classes: {}", self.total_classes)];
+        output.extend(self.versions.iter().map(|version| {
+            format!(
+                " - Java {} ({}): {}",
+                version.java_release, version.class_version, version.classes
+            )
+        }));
+        output.join("\n")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
@@ -1,18 +1,28 @@
 use std::{
+    collections::BTreeMap,
     fs::{self, File},
     io::{self, Write},
-    path::PathBuf,
+    path::Path,
 };
 
 use assert_cmd::Command;
-use predicates::prelude::predicate;
+use java_method_finder::{analysis::Analyzer, page::PageRequest, policy::Rule};
+use predicates::prelude::{predicate, PredicateBooleanExt};
+use sha2::{Digest, Sha256};
 use tempfile::TempDir;
 
-fn copy_test_class(target_dir: &PathBuf) -> io::Result<()> {
-    let test_class_bytes = include_bytes!("resources/com/example/TestClass.class");
-    let target_file = target_dir.join("TestClass.class");
+fn copy_test_class(target_dir: &Path) -> io::Result<()> {
+    copy_class(
+        target_dir,
+        "TestClass.class",
+        include_bytes!("resources/com/example/TestClass.class"),
+    )
+}
+
+fn copy_class(target_dir: &Path, file_name: &str, class_bytes: &[u8]) -> io::Result<()> {
+    let target_file = target_dir.join(file_name);
     let mut file = File::create(target_file)?;
-    file.write_all(test_class_bytes)
+    file.write_all(class_bytes)
 }
 
 #[test]
@@ -73,3 +83,5384 @@ fn should_handle_verbose_mode() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn should_find_pattern_matches() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "LockClass.class",
+        include_bytes!("resources/com/example/LockClass.class"),
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-p",
+        "call java.util.concurrent.locks.Lock#lock !then call java.util.concurrent.locks.Lock#unlock",
+        "-s",
+        classes_dir.to_str().unwrap(),
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("- com.example.LockClass#leaked (L16)"))
+        .stdout(predicate::str::contains("LockClass#guarded").not());
+
+    Ok(())
+}
+
+#[test]
+fn should_flag_calls_inside_loops() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "LoopClass.class",
+        include_bytes!("resources/com/example/LoopClass.class"),
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        classes_dir.to_str().unwrap(),
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "- com.example.LoopClass#loopMethod (L8) [loop]",
+        ))
+        .stdout(predicate::str::contains("- com.example.LoopClass#loopMethod (L10)\n"));
+
+    Ok(())
+}
+
+#[test]
+fn should_flag_calls_inside_handlers_and_monitors() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "GuardedClass.class",
+        include_bytes!("resources/com/example/GuardedClass.class"),
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        classes_dir.to_str().unwrap(),
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "- com.example.GuardedClass#guardedMethod (L8) [try: java.lang.IllegalStateException]",
+        ))
+        .stdout(predicate::str::contains(
+            "- com.example.GuardedClass#guardedMethod (L13) [synchronized] [receiver: str]\n",
+        ))
+        .stdout(predicate::str::contains(
+            "- com.example.GuardedClass#guardedMethod (L15) [receiver: str]\n",
+        ))
+        .stdout(predicate::str::contains(
+            "- com.example.GuardedClass#synchronizedMethod (L19) [synchronized]",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn should_not_count_the_monitor_release_of_synchronized_blocks_as_try() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["-c", "java.util.Map", "-m", "put", "-s", "tests/resources/threads"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            " - com.example.threads.Inventory#sell (L18) [synchronized] [receiver: stock]\n",
+        ))
+        .stdout(predicate::str::contains(
+            " - com.example.threads.Inventory#reserve (L25) [try: any] [receiver: stock]\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn should_separate_reachable_calls() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "ReachableClass.class",
+        include_bytes!("resources/com/example/ReachableClass.class"),
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "reachable",
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "--entrypoints",
+        "main,@Deprecated",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Reachable:\n - com.example.ReachableClass#liveMethod (L9) [receiver: str]\n - com.example.ReachableClass#annotatedMethod (L18) [receiver: str]\nUnreachable:\n - com.example.ReachableClass#deadMethod (L13) [receiver: str]",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn should_explain_reachable_calls() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "ReachableClass.class",
+        include_bytes!("resources/com/example/ReachableClass.class"),
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "reachable",
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "--why",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "   via com.example.ReachableClass#main -> com.example.ReachableClass#liveMethod",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn should_tell_overloads_apart_when_finding_reachable_calls() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "reachable",
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "tests/resources/reachable",
+        "--entrypoints",
+        "main",
+        "--why",
+    ]);
+
+    // Only describe(int) is called from main
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Reachable:\n - com.example.OverloadedCaller#describe (L9)\n   via com.example.OverloadedCaller#main -> com.example.OverloadedCaller#describe\nUnreachable:\n - com.example.OverloadedCaller#describe (L13) [receiver: str]",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn should_apply_policy_severities() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+    let policy_file = temp_dir.path().join("policy.toml");
+    fs::write(
+        &policy_file,
+        r#"
+[[rule]]
+class = "java.lang.String"
+method = "toString"
+severity = "error"
+message = "strings are already strings"
+
+[[rule]]
+id = "arrays-as-list"
+class = "java.util.Arrays"
+method = "asList"
+severity = "warn"
+"#,
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "--policy",
+        policy_file.to_str().unwrap(),
+        "-s",
+        classes_dir.to_str().unwrap(),
+    ]);
+
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "[error] java.lang.String#toString: strings are already strings\n - com.example.TestClass#testMethod (L8)",
+        ))
+        .stdout(predicate::str::contains(
+            "[warn] java.util.Arrays#asList\n - com.example.TestClass#testMethod (L9)",
+        ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "--policy",
+        policy_file.to_str().unwrap(),
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "-f",
+        "sarif",
+    ]);
+
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains(r#""ruleId": "arrays-as-list""#))
+        .stdout(predicate::str::contains(r#""level": "warning""#))
+        .stdout(predicate::str::contains(r#""uri": "com/example/TestClass.java""#));
+
+    Ok(())
+}
+
+#[test]
+fn should_print_stats() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "LoopClass.class",
+        include_bytes!("resources/com/example/LoopClass.class"),
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "--stats",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Total calls: 4\nCaller classes: 2\nCaller packages: 1",
+        ))
+        .stdout(predicate::str::contains("By package:\n - com.example: 4"))
+        .stdout(predicate::str::contains(
+            "By class:\n - com.example.LoopClass: 2\n - com.example.TestClass: 2",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn should_print_top_callers() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "GuardedClass.class",
+        include_bytes!("resources/com/example/GuardedClass.class"),
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "--top",
+        "1",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Total calls: 6\nTop caller classes:\n - com.example.GuardedClass: 4 (66.7%)\nTop caller methods:\n",
+        ))
+        .stdout(predicate::str::contains("TestClass").not());
+
+    Ok(())
+}
+
+#[test]
+fn should_compute_fan_metrics() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "LockClass.class",
+        include_bytes!("resources/com/example/LockClass.class"),
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["metrics", "-s", classes_dir.to_str().unwrap()]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("kind,name,fan_in,fan_out\n"))
+        .stdout(predicate::str::contains("class,com.example.LockClass,0,2\n"))
+        .stdout(predicate::str::contains("method,com.example.LockClass#guarded,0,3\n"))
+        .stdout(predicate::str::contains("method,com.example.LockClass#work,2,0"));
+
+    Ok(())
+}
+
+#[test]
+fn should_list_external_api_inventory() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "LockClass.class",
+        include_bytes!("resources/com/example/LockClass.class"),
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "inventory",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "--external-prefix",
+        "java.util",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "java.util.concurrent.locks.Lock#lock (2)\njava.util.concurrent.locks.Lock#unlock (2)\njava.util.Arrays#asList (1)",
+        ))
+        .stdout(predicate::str::contains("java.lang.String").not())
+        .stdout(predicate::str::contains("LockClass#work").not());
+
+    Ok(())
+}
+
+#[test]
+fn should_group_package_calls_by_callee() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["-c", "java.util", "--package", "-s", classes_dir.to_str().unwrap()]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("java.util.*\n"))
+        .stdout(predicate::str::contains(
+            "java.util.Arrays#asList\n - com.example.TestClass#testMethod (L9)",
+        ))
+        .stdout(predicate::str::contains("String#toString").not());
+
+    Ok(())
+}
+
+#[test]
+fn should_report_architecture_violations_per_edge() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "LockClass.class",
+        include_bytes!("resources/com/example/LockClass.class"),
+    )?;
+    let policy_file = temp_dir.path().join("policy.toml");
+    fs::write(
+        &policy_file,
+        r#"
+[[rule]]
+from = "com.example.*"
+to = "java.util.concurrent.**"
+message = "use the executor"
+
+[[rule]]
+from = "com.*"
+to = "java.util.**"
+severity = "warn"
+"#,
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "--policy",
+        policy_file.to_str().unwrap(),
+        "-s",
+        classes_dir.to_str().unwrap(),
+    ]);
+
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "[error] com.example.* -> java.util.concurrent.**: use the executor\n",
+        ))
+        .stdout(predicate::str::contains(
+            " - com.example.LockClass#leaked (L16) [receiver: lock] -> java.util.concurrent.locks.Lock#lock",
+        ))
+        .stdout(predicate::str::contains("Arrays#asList").not())
+        .stdout(predicate::str::contains("[warn] com.* -> java.util.**\nNo results"));
+
+    Ok(())
+}
+
+#[test]
+fn should_match_relocated_classes() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "org.thirdparty.Arrays",
+        "-m",
+        "asList",
+        "--relocation",
+        "org.thirdparty->java.util",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "-f",
+        "json",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(r#""line_number": 9"#))
+        .stdout(predicate::str::contains(r#""relocated": "java.util.Arrays""#));
+
+    Ok(())
+}
+
+#[test]
+fn should_report_duplicate_classes_across_roots() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+    // The jar holds the same TestClass and a LoopClass compiled without debug information
+    copy_class(
+        &classes_dir,
+        "LoopClass.class",
+        include_bytes!("resources/com/example/LoopClass.class"),
+    )?;
+    let jar = Path::new("tests/resources/example.jar");
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "duplicates",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "-s",
+        jar.to_str().unwrap(),
+        "--fail",
+    ]);
+
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::starts_with("com.example.LoopClass\n"))
+        .stdout(predicate::str::contains("example.jar ("))
+        .stdout(predicate::str::contains("TestClass").not());
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["-c", "java.util.Arrays", "-m", "asList", "-s", jar.to_str().unwrap()]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("com.example.TestClass#testMethod (L9)"));
+
+    Ok(())
+}
+
+#[test]
+fn should_filter_and_report_class_versions() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "ModernClass.class",
+        include_bytes!("resources/com/example/ModernClass.class"),
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "--max-class-version",
+        "11",
+        "-f",
+        "json",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(r#""java_release": "8""#))
+        .stdout(predicate::str::contains("ModernClass").not());
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["versions", "-s", classes_dir.to_str().unwrap()]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Total classes: 2\n - Java 8 (52): 1\n - Java 17 (61): 1",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn should_report_unparseable_class_files() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+    // A class file header for Java 22 followed by an invalid constant pool
+    copy_class(
+        &classes_dir,
+        "Broken.class",
+        b"\xca\xfe\xba\xbe\x00\x00\x00\x42\x00\x05garbage",
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.util.Arrays",
+        "-m",
+        "asList",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "-f",
+        "json",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(r#""line_number": 9"#))
+        .stdout(predicate::str::contains(r#""errors": ["#))
+        .stdout(predicate::str::contains(r#""class_version": 66"#));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.util.Arrays",
+        "-m",
+        "asList",
+        "-s",
+        classes_dir.to_str().unwrap(),
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Errors: 1 class files could not be analyzed\n - ",
+    ));
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn should_control_directory_walking() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    let generated_dir = classes_dir.join("generated");
+    fs::create_dir_all(&generated_dir)?;
+    copy_test_class(&generated_dir)?;
+    fs::write(classes_dir.join(".gitignore"), "generated/\n")?;
+    let linked_dir = temp_dir.path().join("linked");
+    fs::create_dir_all(&linked_dir)?;
+    copy_test_class(&linked_dir)?;
+    std::os::unix::fs::symlink(&linked_dir, classes_dir.join("link"))?;
+    // A link cycle must not hang the walk
+    std::os::unix::fs::symlink(&classes_dir, linked_dir.join("cycle"))?;
+
+    let find = |extra: &[&str]| -> Result<Command, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("jmf")?;
+        cmd.args([
+            "-c",
+            "java.util.Arrays",
+            "-m",
+            "asList",
+            "-s",
+            classes_dir.to_str().unwrap(),
+        ]);
+        cmd.args(extra);
+        Ok(cmd)
+    };
+
+    find(&[])?
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("com.example.TestClass#testMethod (L9)"));
+    find(&["--respect-gitignore"])?
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No results"));
+    find(&["--max-depth", "1"])?
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No results"));
+    find(&["--respect-gitignore", "--follow-symlinks"])?
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("com.example.TestClass#testMethod (L9)"));
+
+    Ok(())
+}
+
+#[test]
+fn should_filter_class_file_paths() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    let generated_dir = classes_dir.join("generated");
+    let core_dir = classes_dir.join("core");
+    fs::create_dir_all(&generated_dir)?;
+    fs::create_dir_all(&core_dir)?;
+    copy_test_class(&generated_dir)?;
+    copy_class(
+        &core_dir,
+        "LoopClass.class",
+        include_bytes!("resources/com/example/LoopClass.class"),
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "--path-exclude",
+        "**/generated/**",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("LoopClass#loopMethod"))
+        .stdout(predicate::str::contains("TestClass").not());
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "tests/resources/example.jar",
+        "--path-include",
+        "**/TestClass.class",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("TestClass#testMethod"))
+        .stdout(predicate::str::contains("LoopClass").not());
+
+    Ok(())
+}
+
+#[test]
+fn should_resolve_the_target_method() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "com.example.TestClass",
+        "-m",
+        "testMethod",
+        "-s",
+        "tests/resources/example.jar",
+        "--resolve",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::starts_with(
+        "com.example.TestClass#testMethod resolves to:\n - public java.lang.String testMethod() [origin: example.jar]\n   descriptor: ()Ljava/lang/String;\n   flags: ACC_PUBLIC\n",
+    ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "tests/resources/example.jar",
+        "--resolve",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "java.lang.String#toString is not declared in the scanned classes",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn should_search_the_selected_overload_of_the_target_method() -> Result<(), Box<dyn std::error::Error>> {
+    let args = [
+        "-c",
+        "com.example.Repository",
+        "-m",
+        "save",
+        "-s",
+        "tests/resources/overload",
+    ];
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(args);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("(L5)"))
+        .stdout(predicate::str::contains("(L6)"))
+        .stdout(predicate::str::contains("(L7)"));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(args).args(["--select-overload", "2"]);
+    cmd.assert().success().stdout(predicate::eq(
+        "com.example.Repository#save(Ljava/lang/String;)V\n - com.example.RepositoryCaller#store (L6) [receiver: repository]\n",
+    ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(args).arg("--select-overload").write_stdin("3\n");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "com.example.Repository#save has 3 overloads:\n  1) public void save(java.lang.Object)  (Ljava/lang/Object;)V\n",
+        ))
+        .stdout(predicate::str::contains(
+            "com.example.Repository#save(Ljava/lang/String;I)V\n - com.example.RepositoryCaller#store (L7)",
+        ))
+        .stdout(predicate::str::contains("(L6)").not());
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(args).args(["--select-overload", "4"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "com.example.Repository#save has 3 overloads, not 4",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn should_search_the_targets_of_aliases() -> Result<(), Box<dyn std::error::Error>> {
+    let search = |alias: &str| -> Result<Command, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("jmf")?;
+        cmd.args([
+            "find",
+            alias,
+            "--alias-file",
+            "tests/resources/aliases.toml",
+            "-s",
+            "tests/resources/example.jar",
+            "-s",
+            "tests/resources/overload",
+        ]);
+        Ok(cmd)
+    };
+
+    search("to-string")?
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("java.lang.String#toString\n"))
+        .stdout(predicate::str::contains("com.example.TestClass#testMethod (L10)"));
+
+    search("save-string")?
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "com.example.Repository#save(Ljava/lang/String;)V\n - com.example.RepositoryCaller#store (L6)",
+        ))
+        .stdout(predicate::str::contains("(L5)").not());
+
+    search("util")?
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("java.util.Arrays#asList"));
+
+    search("as-list")?
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("com.example.TestClass#testMethod (L9)"));
+
+    search("logger-debug")?
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "No alias 'logger-debug' in tests/resources/aliases.toml; available: as-list, save-string, to-string, util",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn should_write_a_report_per_format_from_one_scan() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let json = temp_dir.path().join("calls.json");
+    let sarif = temp_dir.path().join("calls.sarif");
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "tests/resources/example.jar",
+        "--color",
+        "always",
+        "-f",
+        "json",
+        "-o",
+    ])
+    .arg(&json)
+    .args(["-f", "sarif", "-o"])
+    .arg(&sarif)
+    .args(["-f", "txt"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("com.example.TestClass\u{1b}[0m#testMethod"));
+    let report: serde_json::Value = serde_json::from_str(&fs::read_to_string(&json)?)?;
+    assert_eq!(report["target"], "java.lang.String#toString");
+    assert_eq!(report["calls"].as_array().map(Vec::len), Some(4));
+    let log: serde_json::Value = serde_json::from_str(&fs::read_to_string(&sarif)?)?;
+    assert_eq!(log["runs"][0]["results"].as_array().map(Vec::len), Some(4));
+
+    let text = temp_dir.path().join("calls.txt");
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "tests/resources/example.jar",
+        "--color",
+        "always",
+        "-o",
+    ])
+    .arg(&text);
+    cmd.assert().success().stdout(predicate::str::is_empty());
+    assert!(
+        fs::read_to_string(&text)?.starts_with("java.lang.String#toString\n - com.example.LoopClass#loopMethod (L8)")
+    );
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "tests/resources/example.jar",
+        "-f",
+        "json",
+        "-f",
+        "txt",
+    ]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Only one --format can be printed; give the others an --output",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn should_find_the_calls_a_library_upgrade_breaks() -> Result<(), Box<dyn std::error::Error>> {
+    let impact = || -> Result<Command, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("jmf")?;
+        cmd.args([
+            "impact",
+            "--old",
+            "tests/resources/impact/lib-1.0.jar",
+            "--new",
+            "tests/resources/impact/lib-2.0.jar",
+            "-s",
+            "tests/resources/impact/app",
+        ]);
+        Ok(cmd)
+    };
+
+    impact()?
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("6 API changes, 5 breaking 5 calls"))
+        .stdout(predicate::str::contains(
+            "[signature changed] com.example.lib.Greeter#greet(Ljava/lang/String;)Ljava/lang/String;, now greet(Ljava/lang/String;Z)Ljava/lang/String;\n - com.example.app.Client#run (L8)",
+        ))
+        .stdout(predicate::str::contains(
+            "[removed] com.example.lib.Greeter#wave()V\n - com.example.app.Client#run (L9)",
+        ))
+        .stdout(predicate::str::contains(
+            "[static changed] com.example.lib.Greeter#count()I, no longer static\n - com.example.app.Client#run (L10)",
+        ))
+        .stdout(predicate::str::contains(
+            "[removed] com.example.lib.Legacy#run()V, with its class\n - com.example.app.Client#run (L12)",
+        ))
+        .stdout(predicate::str::contains(
+            "[visibility reduced] com.example.lib.Greeter#unused(I)V\n - not called",
+        ))
+        // Inherited from a superclass in the new version
+        .stdout(predicate::str::contains("hello").not());
+
+    let output = impact()?.args(["-f", "json"]).output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let report: serde_json::Value = serde_json::from_str(&stdout[stdout.find('{').unwrap_or_default()..])?;
+    let changes = report["changes"].as_array().cloned().unwrap_or_default();
+    assert_eq!(changes.len(), 6);
+    let wave = changes.iter().find(|change| change["method"] == "wave").unwrap();
+    assert_eq!(wave["kind"], "removed");
+    assert_eq!(wave["descriptor"], "()V");
+    assert_eq!(wave["calls"][0]["line_number"], 9);
+
+    Ok(())
+}
+
+#[test]
+fn should_list_the_declared_api_of_a_jar() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["api", "tests/resources/impact/lib-2.0.jar"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "com.example.lib.Base [ACC_PUBLIC, ACC_SUPER]\n  public static final java.lang.String GREETING  Ljava/lang/String;\n  private int calls  I\n",
+        ))
+        .stdout(predicate::str::contains(
+            "com.example.lib.Greeter extends com.example.lib.Base [ACC_PUBLIC, ACC_SUPER]\n",
+        ))
+        .stdout(predicate::str::contains("  void unused(int)  (I)V"));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "api",
+        "tests/resources/impact/lib-2.0.jar",
+        "--public-only",
+        "-f",
+        "json",
+    ]);
+    let output = cmd.output()?;
+    assert!(output.status.success());
+    let api: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let greeter = &api["classes"][1];
+    assert_eq!(greeter["name"], "com.example.lib.Greeter");
+    assert_eq!(greeter["super_class"], "com.example.lib.Base");
+    let methods: Vec<&str> = greeter["methods"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|method| method["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(methods, ["<init>", "greet", "count"]);
+    assert_eq!(
+        greeter["methods"][1]["descriptor"],
+        "(Ljava/lang/String;Z)Ljava/lang/String;"
+    );
+    assert_eq!(greeter["methods"][1]["access_flags"], serde_json::json!(["ACC_PUBLIC"]));
+    let base_fields = api["classes"][0]["fields"].as_array().unwrap();
+    assert_eq!(base_fields.len(), 1);
+    assert_eq!(base_fields[0]["name"], "GREETING");
+
+    Ok(())
+}
+
+#[test]
+fn should_generate_a_policy_from_the_deprecated_and_internal_api_of_a_jar() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "policy",
+        "generate",
+        "--from",
+        "tests/resources/generate/shop.jar",
+        "--select",
+        "deprecated,internal",
+    ]);
+    let output = cmd.output()?;
+    assert!(output.status.success());
+    let policy = String::from_utf8(output.stdout)?;
+    // Only the deprecated overload of add, every other method whole
+    assert!(policy.contains(
+        "id = \"deprecated:com.example.shop.Cart#add(Ljava/lang/String;I)V\"\nclass = \"com.example.shop.Cart\"\nmethod = \"add\"\ndescriptor = \"(Ljava/lang/String;I)V\"\n"
+    ));
+    assert!(policy.contains("id = \"deprecated:com.example.shop.Cart#sum\"\n"));
+    assert!(policy.contains("id = \"deprecated:com.example.shop.LegacyCart#checkout\"\n"));
+    assert!(!policy.contains("Cart#total"));
+    assert!(policy.contains("package = \"com.example.shop.internal\"\n"));
+    assert!(policy.contains("message = \"Not exported by module com.example.shop of shop.jar\"\n"));
+
+    let temp_dir = TempDir::new()?;
+    let policy_file = temp_dir.path().join("policy.toml");
+    fs::write(&policy_file, policy)?;
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.arg("check")
+        .arg(&policy_file)
+        .args(["-s", "tests/resources/generate/app"]);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "[error] com.example.shop.Cart#clear: Deprecated in shop.jar\n - com.example.checkout.Checkout#run (L12)",
+        ))
+        .stdout(predicate::str::contains(
+            "[error] com.example.shop.internal.*: Internal to shop.jar\n - com.example.checkout.Checkout#run (L13) -> com.example.shop.internal.Pricing#price",
+        ))
+        .stdout(predicate::str::contains("5 errors, 0 warnings across 1 class"));
+    Ok(())
+}
+
+#[test]
+fn should_only_report_calls_from_callers_of_the_given_visibility() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "trim",
+        "-s",
+        "tests/resources/visibility",
+        "--caller-visibility",
+        "public,protected",
+    ]);
+    cmd.assert().success().stdout(
+        "java.lang.String#trim\n - com.example.visibility.Service#name (L5)\n - com.example.visibility.Service#label (L9) [receiver: label]\n",
+    );
+
+    // A public method of a package-private class is not exposed
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "query",
+        "calls(\"java.lang.String#trim\")",
+        "-s",
+        "tests/resources/visibility",
+        "--caller-visibility",
+        "package,private",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            " - com.example.visibility.Helper#clean (L5) [receiver: value]\n",
+        ))
+        .stdout(predicate::str::contains(" - com.example.visibility.Service#key (L13)"))
+        .stdout(predicate::str::contains(
+            " - com.example.visibility.Service#normalize (L17)",
+        ))
+        .stdout(predicate::str::contains("Service#name").not());
+    Ok(())
+}
+
+#[test]
+fn should_find_annotation_uses_by_element_values() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "--kind",
+        "annotation",
+        "-c",
+        "com.example.rest.Path",
+        "-s",
+        "tests/resources/annotations",
+        "--where",
+        "value ~ \"/admin/.*\"",
+    ]);
+    cmd.assert().success().stdout(
+        "@com.example.rest.Path where value ~ \"/admin/.*\"\n - com.example.rest.AdminResource#users (L7) [value = \"/admin/users\"]\n - com.example.rest.AdminResource#audit (L12) [value = \"/admin/audit\", methods = {\"GET\", \"DELETE\"}]\n",
+    );
+
+    // Array elements hold when any value does, and simple names find the annotation
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "--kind",
+        "annotation",
+        "-c",
+        "Path",
+        "-s",
+        "tests/resources/annotations",
+        "--where",
+        "methods == \"POST\" or value == \"/admin\"",
+        "-f",
+        "json",
+    ]);
+    let output = cmd.output()?;
+    assert!(output.status.success());
+    let search: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let uses = search["uses"].as_array().unwrap();
+    assert_eq!(uses.len(), 2);
+    assert_eq!(uses[0]["class_name"], "com.example.rest.AdminResource");
+    assert_eq!(uses[0]["element"], "class");
+    assert_eq!(uses[1]["member"], "cart");
+    assert_eq!(uses[1]["annotation"], "com.example.rest.Path");
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "--kind",
+        "annotation",
+        "-c",
+        "QueryParam",
+        "-s",
+        "tests/resources/annotations",
+    ]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        " - com.example.rest.AdminResource#audit parameter 1 (L12) [value = \"since\"]",
+    ));
+
+    // Calls have no element values to filter
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "tests/resources/annotations",
+        "--where",
+        "value == \"/admin\"",
+    ]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--where filters the uses of an annotation"));
+    Ok(())
+}
+
+#[test]
+fn should_flag_calls_outside_synchronized_code_for_review() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.util.Map",
+        "-m",
+        "put",
+        "-s",
+        "tests/resources/threads",
+        "--preset",
+        "thread-safety",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            " - com.example.threads.Inventory#audit (L32) [receiver: stock] -> java.util.Map#put\n - com.example.threads.Inventory#adjust (L36) [receiver: stock] -> java.util.Map#put\n",
+        ))
+        .stdout(predicate::str::contains("Inventory#restock").not())
+        .stdout(predicate::str::contains("Inventory#sell").not())
+        .stdout(predicate::str::contains("Inventory#reserve").not());
+
+    // Other lock calls replace the java.util.concurrent ones
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.util.Map",
+        "-m",
+        "put",
+        "-s",
+        "tests/resources/threads",
+        "--preset",
+        "thread-safety",
+        "--lock-call",
+        "com.example.Locks#acquire",
+    ]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        " - com.example.threads.Inventory#reserve (L25)",
+    ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "query",
+        "calls(\"java.util.Map#put\") and synchronized",
+        "-s",
+        "tests/resources/threads",
+    ]);
+    cmd.assert().success().stdout(
+        "calls(\"java.util.Map#put\") and synchronized\n - com.example.threads.Inventory#restock (L13) [synchronized] [receiver: stock]\n - com.example.threads.Inventory#sell (L18) [synchronized] [receiver: stock]\n",
+    );
+    Ok(())
+}
+
+#[test]
+fn should_report_clusters_of_identical_method_bodies() -> Result<(), Box<dyn std::error::Error>> {
+    // Strings has a constant pool of its own, shifted by a field TextUtils does not have
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["dupes", "-s", "tests/resources/dupes"]);
+    cmd.assert().success().stdout(
+        "2 methods with the same 21 instructions (19ff96b93dee)\n - com.example.batch.Strings#blank(Ljava/lang/String;)Z (L11)\n - com.example.web.TextUtils#isBlank(Ljava/lang/String;)Z (L5)\n",
+    );
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "dupes",
+        "-s",
+        "tests/resources/dupes",
+        "--min-instructions",
+        "1",
+        "-f",
+        "json",
+    ]);
+    let output = cmd.output()?;
+    assert!(output.status.success());
+    let dupes: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let clusters = dupes["clusters"].as_array().unwrap();
+    assert_eq!(clusters.len(), 2);
+    assert_eq!(clusters[1]["instructions"], 3);
+    assert_eq!(clusters[1]["methods"][0]["method_name"], "<init>");
+    assert_eq!(clusters[0]["methods"][1]["origin"], "tests/resources/dupes");
+    Ok(())
+}
+
+#[test]
+fn should_export_a_package_dependency_matrix() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["deps", "-s", "tests/resources/deps", "--level", "package"]);
+    cmd.assert().success().stdout(
+        ",com.example.model,com.example.service,com.example.web\ncom.example.model,0,0,0\ncom.example.service,4,0,0\ncom.example.web,2,3,0\n",
+    );
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["deps", "-s", "tests/resources/deps", "--level", "class", "-f", "json"]);
+    let output = cmd.output()?;
+    assert!(output.status.success());
+    let deps: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let dependency = &deps["dependencies"][0];
+    assert_eq!(dependency["from"], "com.example.service.OrderService");
+    assert_eq!(dependency["to"], "com.example.model.Order");
+    assert_eq!(
+        (&dependency["invocations"], &dependency["fields"], &dependency["types"]),
+        (&1.into(), &2.into(), &1.into())
+    );
+
+    // The JDK is only a column with --external
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["deps", "-s", "tests/resources/deps", "--external"]);
+    cmd.assert().success().stdout(predicate::str::starts_with(
+        ",com.example.model,com.example.service,com.example.web,java.lang\n",
+    ));
+    Ok(())
+}
+
+#[test]
+fn should_report_dependency_cycles_with_the_references_making_them() -> Result<(), Box<dyn std::error::Error>> {
+    // shipping depends on orders but nothing depends on shipping, so it is in no cycle
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["cycles", "-s", "tests/resources/cycles"]);
+    cmd.assert().success().stdout(
+        "2 packages in a cycle: com.example.billing, com.example.orders\n com.example.billing -> com.example.orders\n  - com.example.billing.Invoice#amount (L13) accesses com.example.orders.Order#total\n com.example.orders -> com.example.billing\n  - com.example.orders.Order#invoice (L15) references com.example.billing.Invoice\n  - com.example.orders.Order#invoice (L15) calls com.example.billing.Invoice#<init>\n",
+    );
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "cycles",
+        "-s",
+        "tests/resources/cycles",
+        "--level",
+        "class",
+        "-f",
+        "json",
+    ]);
+    let output = cmd.output()?;
+    assert!(output.status.success());
+    let cycles: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(cycles["cycles"].as_array().unwrap().len(), 1);
+    assert_eq!(
+        cycles["cycles"][0]["elements"],
+        serde_json::json!(["com.example.billing.Invoice", "com.example.orders.Order"])
+    );
+    assert_eq!(cycles["cycles"][0]["edges"][0]["references"][0]["kind"], "field");
+    Ok(())
+}
+
+#[test]
+fn should_write_calls_and_call_graph_edges_as_parquet() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let calls = temp_dir.path().join("calls.parquet");
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-s",
+        "tests/resources/deps",
+        "-c",
+        "com.example.model.Order",
+        "-m",
+        "describe",
+    ])
+    .args(["-f", "parquet", "-o"])
+    .arg(&calls);
+    cmd.assert().success();
+
+    // A file starts and ends with the magic bytes, the footer metadata with its length before the last ones
+    let file = fs::read(&calls)?;
+    assert_eq!(&file[..4], b"PAR1");
+    assert_eq!(&file[file.len() - 4..], b"PAR1");
+    let footer = u32::from_le_bytes(file[file.len() - 8..file.len() - 4].try_into()?) as usize;
+    let metadata = &file[file.len() - 8 - footer..file.len() - 8];
+    let contains =
+        |haystack: &[u8], needle: &str| haystack.windows(needle.len()).any(|window| window == needle.as_bytes());
+    for column in ["class_name", "method_name", "line_number", "fingerprint", "origin"] {
+        assert!(contains(metadata, column), "no {} column", column);
+    }
+    assert!(contains(&file, "com.example.web.OrderController"));
+    // PLAIN-encoded values are prefixed with their length, the line number is 4 bytes
+    assert!(contains(&file, "\u{4}\0\0\0post"));
+    assert!(file.windows(4).any(|window| window == 11i32.to_le_bytes()));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["graph", "-s", "tests/resources/deps", "-f", "parquet"]);
+    let output = cmd.output()?;
+    assert!(output.status.success());
+    assert!(output.stdout.starts_with(b"PAR1"));
+    assert!(contains(&output.stdout, "com.example.service.OrderService#place"));
+    Ok(())
+}
+
+#[test]
+fn should_export_scan_metrics_and_a_span_over_otlp() -> Result<(), Box<dyn std::error::Error>> {
+    let (url, bodies) = receive_posts()?;
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "tests/resources/com/example/TestClass.class",
+        "--otlp-endpoint",
+        &url,
+    ])
+    .env("OTEL_SERVICE_NAME", "nightly-scan")
+    .env("OTEL_RESOURCE_ATTRIBUTES", "deployment.environment=ci");
+    cmd.assert().success();
+
+    let metrics: serde_json::Value = serde_json::from_str(&bodies.recv()?)?;
+    let resource = &metrics["resourceMetrics"][0]["resource"]["attributes"];
+    assert_eq!(resource[0]["value"]["stringValue"], "nightly-scan");
+    assert_eq!(resource[1]["key"], "deployment.environment");
+    let metrics = metrics["resourceMetrics"][0]["scopeMetrics"][0]["metrics"]
+        .as_array()
+        .unwrap();
+    let metric = |name: &str| metrics.iter().find(|metric| metric["name"] == name).unwrap();
+    assert!(metric("jmf.scan.duration")["gauge"]["dataPoints"][0]["asDouble"].is_number());
+    assert_eq!(metric("jmf.scan.classes")["sum"]["dataPoints"][0]["asInt"], "1");
+    let matches = &metric("jmf.matches")["sum"]["dataPoints"][0];
+    assert_eq!(matches["asInt"], "2");
+    assert_eq!(
+        matches["attributes"][0]["value"]["stringValue"],
+        "java.lang.String#toString"
+    );
+
+    let traces: serde_json::Value = serde_json::from_str(&bodies.recv()?)?;
+    let span = &traces["resourceSpans"][0]["scopeSpans"][0]["spans"][0];
+    assert_eq!(span["name"], "jmf scan");
+    assert_eq!(span["traceId"].as_str().unwrap().len(), 32);
+
+    // The scan does not fail when the collector is down
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "tests/resources/com/example/TestClass.class",
+        "--otlp-endpoint",
+        "http://127.0.0.1:1",
+    ]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Failed to export to http://127.0.0.1:1/v1/metrics",
+    ));
+    Ok(())
+}
+
+#[test]
+fn should_gate_a_policy_against_a_baseline_with_a_markdown_summary() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let baseline = temp_dir.path().join("baseline.json");
+    let summary = temp_dir.path().join("summary.md");
+    let gate = |version: &str| -> Result<Command, Box<dyn std::error::Error>> {
+        let root = format!("tests/resources/gate/{}", version);
+        let mut cmd = Command::cargo_bin("jmf")?;
+        cmd.args([
+            "gate",
+            "tests/resources/gate/policy.toml",
+            "-s",
+            &root,
+            "--source-root",
+            &root,
+        ]);
+        Ok(cmd)
+    };
+
+    gate("before")?.arg("--result").arg(&baseline).assert().code(1);
+
+    // refund calls Date anew, legacyTotal no longer does and audit's call is ignored
+    gate("after")?
+        .arg("--baseline")
+        .arg(&baseline)
+        .arg("-o")
+        .arg(&summary)
+        .assert()
+        .code(1)
+        .stdout("");
+    assert_eq!(
+        fs::read_to_string(&summary)?,
+        "<!-- jmf gate -->\n### jmf gate: failed\n\n`tests/resources/gate/policy.toml` against the baseline: **1 new violation**, 1 fixed, 1 suppressed.\n\n\
+         | Rule | Severity | Calls | New | Fixed | Suppressed |\n\
+         | ---- | -------- | ----: | --: | ----: | ---------: |\n\
+         | `java.util.Date#<init>` | error | 2 | 1 | 1 | 1 |\n\
+         | `java.lang.Thread#stop` | error | 0 | 0 | 0 | 0 |\n\n\
+         #### New violations\n\n\
+         - **error** `java.util.Date#<init>` in `com.example.gate.Billing#refund` (L11): Use java.time\n"
+    );
+
+    // Once the baseline has them, the same calls pass
+    gate("after")?.arg("--result").arg(&baseline).assert().code(1);
+    gate("after")?
+        .arg("--baseline")
+        .arg(&baseline)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("### jmf gate: passed"))
+        .stdout(predicate::str::contains("**0 new violations**, 0 fixed, 1 suppressed."));
+    Ok(())
+}
+
+#[test]
+fn should_reuse_cached_results_of_unchanged_jars() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let jar = temp_dir.path().join("app.jar");
+    let cache_dir = temp_dir.path().join("cache");
+    fs::copy("tests/resources/example.jar", &jar)?;
+    let search = |method: &str, format: &str| -> Result<String, Box<dyn std::error::Error>> {
+        let output = Command::cargo_bin("jmf")?
+            .args(["-c", "java.lang.String", "-m", method, "-f", format, "-v", "-s"])
+            .arg(&jar)
+            .arg("--result-cache")
+            .arg(&cache_dir)
+            .output()?;
+        Ok(String::from_utf8(output.stdout)?)
+    };
+    let json = |output: &str| {
+        output
+            .lines()
+            .filter(|line| !line.contains("DEBUG "))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let first = search("toString", "json")?;
+    assert!(first.contains("Analyzing class file"), "{}", first);
+    assert!(first.contains("Cached results of archive"), "{}", first);
+    assert_eq!(fs::read_dir(&cache_dir)?.count(), 1);
+
+    let second = search("toString", "json")?;
+    assert!(second.contains("Using cached results of archive"), "{}", second);
+    assert!(!second.contains("Analyzing class file"), "{}", second);
+    assert_eq!(json(&second), json(&first));
+    assert!(json(&second).contains("\"line_number\""), "{}", second);
+
+    // Another query and a changed jar each get an entry of their own
+    let other = search("valueOf", "txt")?;
+    assert!(other.contains("Analyzing class file"), "{}", other);
+    assert_eq!(fs::read_dir(&cache_dir)?.count(), 2);
+    fs::copy("tests/resources/shifted.jar", &jar)?;
+    let changed = search("toString", "json")?;
+    assert!(changed.contains("Analyzing class file"), "{}", changed);
+    assert_eq!(fs::read_dir(&cache_dir)?.count(), 3);
+
+    Ok(())
+}
+
+#[test]
+fn should_verify_the_digest_and_signature_of_jars_before_scanning() -> Result<(), Box<dyn std::error::Error>> {
+    let digest = "e19fbf73ba4e46622789711d14463728e90e90344ab6f719ff028fc97254f86e";
+    let search = |jar: &str, options: &[&str]| {
+        let mut command = Command::cargo_bin("jmf").unwrap();
+        command
+            .args(["-c", "java.lang.String", "-m", "toString", "-s", jar])
+            .args(options)
+            .env("JMF_KEYSTORE_PASSWORD", "changeit");
+        command.assert()
+    };
+
+    search("tests/resources/example.jar", &["--verify-sha256", digest])
+        .success()
+        .stdout(predicate::str::contains("com.example.TestClass#testMethod (L8)"));
+    search(
+        "tests/resources/example.jar",
+        &[
+            "--verify-sha256",
+            &format!("tests/resources/example.jar={}", digest.to_uppercase()),
+        ],
+    )
+    .success();
+    search("tests/resources/example.jar", &["--verify-sha256", &"0".repeat(64)])
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "SHA-256 mismatch for tests/resources/example.jar: expected {}, found {}",
+            "0".repeat(64),
+            digest
+        )));
+    search(
+        "tests/resources/example.jar",
+        &["--verify-sha256", &format!("lib/other.jar={}", digest)],
+    )
+    .failure()
+    .stderr(predicate::str::contains(format!(
+        "--verify-sha256 lib/other.jar={} names no jar or archive scan root",
+        digest
+    )));
+
+    search("tests/resources/example.jar", &["--verify-signature"])
+        .failure()
+        .stderr(predicate::str::contains(
+            "Signature verification failed for tests/resources/example.jar: jar is unsigned.",
+        ));
+    // Signed with a self-signed certificate, which only the test keystore trusts
+    search("tests/resources/signed/signed.jar", &["--verify-signature"])
+        .failure()
+        .stderr(predicate::str::contains("signer certificate is self-signed"));
+    search(
+        "tests/resources/signed/signed.jar",
+        &[
+            "--verify-signature",
+            "--signature-keystore",
+            "tests/resources/signed/trust.p12",
+        ],
+    )
+    .success()
+    .stdout(predicate::str::contains("com.example.TestClass#testMethod (L8)"));
+
+    Ok(())
+}
+
+#[test]
+fn should_analyze_identical_classes_under_several_roots_once() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes = temp_dir.path().join("classes");
+    let package = classes.join("com/example");
+    fs::create_dir_all(&package)?;
+    // The same TestClass as the jar's, and a LoopClass compiled from other sources
+    copy_test_class(&package)?;
+    copy_class(
+        &package,
+        "LoopClass.class",
+        include_bytes!("resources/com/example/LoopClass.class"),
+    )?;
+    let search = |options: &[&str]| {
+        let mut command = Command::cargo_bin("jmf").unwrap();
+        command
+            .args(["-c", "java.lang.String", "-m", "toString", "-s"])
+            .arg(&classes)
+            .args(["-s", "tests/resources/example.jar", "--root-alias"])
+            .arg(format!("classes={}", classes.display()))
+            .args(options);
+        command.assert().success()
+    };
+
+    search(&[])
+        .stdout(predicate::str::contains(
+            "com.example.TestClass#testMethod (L8) [receiver: str] [origin: classes] [also in: example.jar]",
+        ))
+        .stdout(predicate::str::contains("TestClass#testMethod (L8) [receiver: str] [origin: example.jar]").not())
+        .stdout(predicate::str::contains(
+            "com.example.LoopClass#loopMethod (L10) [origin: example.jar]",
+        ))
+        .stdout(predicate::str::contains(
+            "Deduplicated: 1 class files identical to ones under other scan roots, analyzed once",
+        ))
+        .stdout(predicate::str::is_match(r"classes +2 +4 +0")?)
+        .stdout(predicate::str::is_match(r"example\.jar +2 +4 +0")?);
+    search(&["--stats"]).stdout(predicate::str::contains("Total calls: 6"));
+    search(&["-f", "json"])
+        .stdout(predicate::str::contains(
+            "\"also_in\": [\n        \"example.jar\"\n      ]",
+        ))
+        .stdout(predicate::str::contains("\"identical\": 1"));
+
+    Ok(())
+}
+
+#[test]
+fn should_scan_the_concrete_methods_of_interfaces_and_abstract_classes() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "trim",
+        "-s",
+        "tests/resources/abstract",
+        "--fail-fast",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("- com.example.abstracts.Api#describe (L7)"))
+        .stdout(predicate::str::contains(
+            "- com.example.abstracts.Impl#run (L10) [receiver: str]",
+        ))
+        .stdout(predicate::str::contains("- com.example.abstracts.Stripped#strip (L0)"))
+        .stdout(predicate::str::contains("Errors").not());
+
+    Ok(())
+}
+
+#[test]
+fn should_scan_past_class_files_the_library_cannot_analyze() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_class(&classes_dir, "Broken.class", &[0xca, 0xfe, 0xba, 0xbe, 0, 0])?;
+    copy_test_class(&classes_dir)?;
+    fs::copy(
+        "tests/resources/abstract/com/example/abstracts/Api.class",
+        classes_dir.join("Api.class"),
+    )?;
+
+    let scan = Analyzer::new(vec![
+        Rule::parse("java.lang.String#trim")?,
+        Rule::parse("java.util.Arrays#asList")?,
+    ])
+    .scan_all(&classes_dir)?;
+    let calls: Vec<String> = scan.calls.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        calls,
+        [
+            "com.example.abstracts.Api#describe (L7)",
+            "com.example.TestClass#testMethod (L9)",
+        ]
+    );
+    assert_eq!(scan.errors.len(), 1);
+    assert!(scan.errors[0].location.ends_with("Broken.class"));
+    assert!(scan.errors[0].message.starts_with("Failed to parse class file"));
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn should_stop_commands_without_partial_results_on_ctrl_c() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::process::ExitStatusExt;
+
+    // The language server waits on standard input until Ctrl-C
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("jmf"))
+        .args(["lsp", "-s", "tests/resources/abstract"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::null())
+        .spawn()?;
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let status = std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()?;
+    assert!(status.success());
+    let mut waited = 0;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if waited == 100 {
+            child.kill()?;
+            panic!("jmf lsp ignored Ctrl-C");
+        }
+        waited += 1;
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    };
+    assert_eq!(status.signal(), Some(2));
+
+    Ok(())
+}
+
+#[test]
+fn should_not_trust_the_declared_sizes_of_tar_entries() -> Result<(), Box<dyn std::error::Error>> {
+    // A truncated tar whose only entry claims 2^62 bytes, in base-256
+    let mut header = [0u8; 512];
+    header[..10].copy_from_slice(b"Huge.class");
+    header[124] = 0x80;
+    header[128] = 0x40;
+    header[156] = b'0';
+    let temp_dir = TempDir::new()?;
+    let tar = temp_dir.path().join("huge.tar");
+    fs::write(&tar, header)?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["-c", "java.lang.String", "-m", "toString", "-s"]).arg(&tar);
+    cmd.assert().success().stdout(predicate::str::contains("No results"));
+
+    Ok(())
+}
+
+/// A value of the Thrift compact protocol, decoded without knowing the struct it belongs to.
+#[derive(Debug)]
+enum Thrift {
+    Int(i64),
+    Binary(Vec<u8>),
+    List(Vec<Thrift>),
+    Struct(BTreeMap<i16, Thrift>),
+}
+
+impl Thrift {
+    fn field(&self, id: i16) -> &Thrift {
+        match self {
+            Thrift::Struct(fields) => &fields[&id],
+            _ => panic!("not a struct: {:?}", self),
+        }
+    }
+
+    fn int(&self) -> i64 {
+        match self {
+            Thrift::Int(value) => *value,
+            _ => panic!("not an integer: {:?}", self),
+        }
+    }
+
+    fn list(&self) -> &[Thrift] {
+        match self {
+            Thrift::List(values) => values,
+            _ => panic!("not a list: {:?}", self),
+        }
+    }
+
+    fn text(&self) -> String {
+        match self {
+            Thrift::Binary(bytes) => String::from_utf8(bytes.clone()).unwrap(),
+            _ => panic!("not binary: {:?}", self),
+        }
+    }
+}
+
+fn read_varint(bytes: &[u8], position: &mut usize) -> u64 {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = bytes[*position];
+        *position += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    value
+}
+
+fn read_thrift(bytes: &[u8], position: &mut usize, kind: u8) -> Thrift {
+    let zigzag = |value: u64| (value >> 1) as i64 ^ -((value & 1) as i64);
+    match kind {
+        // i16, i32, i64
+        4..=6 => Thrift::Int(zigzag(read_varint(bytes, position))),
+        8 => {
+            let length = read_varint(bytes, position) as usize;
+            *position += length;
+            Thrift::Binary(bytes[*position - length..*position].to_vec())
+        }
+        9 => {
+            let header = bytes[*position];
+            *position += 1;
+            let size = match header >> 4 {
+                15 => read_varint(bytes, position) as usize,
+                size => usize::from(size),
+            };
+            Thrift::List((0..size).map(|_| read_thrift(bytes, position, header & 0x0f)).collect())
+        }
+        12 => {
+            let mut fields = BTreeMap::new();
+            let mut id = 0;
+            loop {
+                let header = bytes[*position];
+                *position += 1;
+                if header == 0 {
+                    return Thrift::Struct(fields);
+                }
+                id = match header >> 4 {
+                    0 => zigzag(read_varint(bytes, position)) as i16,
+                    delta => id + i16::from(delta),
+                };
+                fields.insert(id, read_thrift(bytes, position, header & 0x0f));
+            }
+        }
+        kind => panic!("unexpected Thrift type {}", kind),
+    }
+}
+
+/// The rows of a Parquet file with one row group of PLAIN-encoded, uncompressed data pages, by column,
+/// as JSON values. The footer and the page headers are decoded as the format specifies, not as jmf writes
+/// them, so that a mistake in either shows up as unreadable values.
+fn read_parquet(file: &[u8]) -> BTreeMap<String, Vec<serde_json::Value>> {
+    assert_eq!(&file[..4], b"PAR1");
+    assert_eq!(&file[file.len() - 4..], b"PAR1");
+    let length = u32::from_le_bytes(file[file.len() - 8..file.len() - 4].try_into().unwrap()) as usize;
+    let footer_start = file.len() - 8 - length;
+    let mut position = footer_start;
+    let metadata = read_thrift(file, &mut position, 12);
+    assert_eq!(position, file.len() - 8, "the footer is longer than its metadata");
+
+    let rows = metadata.field(3).int() as usize;
+    let schema = metadata.field(2).list();
+    assert_eq!(schema[0].field(5).int() as usize, schema.len() - 1);
+    let row_groups = metadata.field(4).list();
+    assert_eq!(row_groups.len(), 1);
+    let chunks = row_groups[0].field(1).list();
+    assert_eq!(row_groups[0].field(3).int() as usize, rows);
+
+    let mut columns = BTreeMap::new();
+    for (element, chunk) in schema[1..].iter().zip(chunks) {
+        let name = element.field(4).text();
+        let optional = element.field(3).int() == 1;
+        let meta_data = chunk.field(3);
+        assert_eq!(meta_data.field(3).list()[0].text(), name);
+        assert_eq!(meta_data.field(4).int(), 0, "compressed {}", name);
+        let mut position = meta_data.field(9).int() as usize;
+        let header = read_thrift(file, &mut position, 12);
+        assert_eq!(header.field(1).int(), 0, "{} is not a data page", name);
+        let size = header.field(3).int() as usize;
+        assert_eq!(
+            position + size - meta_data.field(9).int() as usize,
+            meta_data.field(7).int() as usize
+        );
+        let page = &file[position..position + size];
+        assert_eq!(header.field(5).field(1).int() as usize, rows);
+        assert_eq!(header.field(5).field(2).int(), 0, "{} is not PLAIN-encoded", name);
+
+        // Definition levels of bit width 1, in the RLE/bit-packing hybrid encoding
+        let mut offset = 0;
+        let defined: Vec<bool> = if optional {
+            let length = u32::from_le_bytes(page[..4].try_into().unwrap()) as usize;
+            let levels = &page[4..4 + length];
+            offset = 4 + length;
+            let mut defined = Vec::new();
+            let mut position = 0;
+            while position < levels.len() {
+                let run = read_varint(levels, &mut position);
+                if run & 1 == 0 {
+                    defined.extend(std::iter::repeat_n(levels[position] == 1, (run >> 1) as usize));
+                    position += 1;
+                } else {
+                    for byte in &levels[position..position + (run >> 1) as usize] {
+                        defined.extend((0..8).map(|bit| byte >> bit & 1 == 1));
+                    }
+                    position += (run >> 1) as usize;
+                }
+            }
+            defined.truncate(rows);
+            defined
+        } else {
+            vec![true; rows]
+        };
+        assert_eq!(defined.len(), rows);
+
+        let mut values = Vec::new();
+        for defined in defined {
+            if !defined {
+                values.push(serde_json::Value::Null);
+                continue;
+            }
+            let value = match element.field(1).int() {
+                1 => {
+                    offset += 4;
+                    serde_json::json!(i32::from_le_bytes(page[offset - 4..offset].try_into().unwrap()))
+                }
+                2 => {
+                    offset += 8;
+                    serde_json::json!(i64::from_le_bytes(page[offset - 8..offset].try_into().unwrap()))
+                }
+                6 => {
+                    let length = u32::from_le_bytes(page[offset..offset + 4].try_into().unwrap()) as usize;
+                    offset += 4 + length;
+                    serde_json::json!(std::str::from_utf8(&page[offset - length..offset]).unwrap())
+                }
+                kind => panic!("unexpected physical type {} of {}", kind, name),
+            };
+            values.push(value);
+        }
+        assert_eq!(offset, page.len(), "{} has values past its rows", name);
+        columns.insert(name, values);
+    }
+    assert_eq!(columns.len(), schema.len() - 1);
+    columns
+}
+
+#[test]
+fn should_write_parquet_files_holding_the_calls_of_the_json_report() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let parquet = temp_dir.path().join("calls.parquet");
+    let json = temp_dir.path().join("calls.json");
+    // Two scan roots label the calls with their origin
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "tests/resources/example.jar",
+        "-s",
+        "tests/resources/abstract",
+        "-f",
+        "parquet",
+        "-f",
+        "json",
+        "-o",
+    ])
+    .arg(&parquet)
+    .arg("-o")
+    .arg(&json);
+    cmd.assert().success();
+
+    let columns = read_parquet(&fs::read(&parquet)?);
+    let report: serde_json::Value = serde_json::from_slice(&fs::read(&json)?)?;
+    let calls = report["calls"].as_array().unwrap();
+    assert!(calls.len() > 1);
+    for (index, call) in calls.iter().enumerate() {
+        let class_name = call["class_name"].as_str().unwrap().replace('/', ".");
+        assert_eq!(columns["class_name"][index], class_name);
+        for field in [
+            "method_name",
+            "line_number",
+            "offset",
+            "fingerprint",
+            "java_release",
+            "origin",
+        ] {
+            assert_eq!(columns[field][index], call[field], "{} of call {}", field, index);
+        }
+        assert_eq!(columns["target"][index], "java.lang.String#toString");
+        assert_eq!(columns["rule"][index], serde_json::Value::Null);
+    }
+
+    // A real reader, where one is installed
+    let pyarrow = std::process::Command::new("python3")
+        .args([
+            "-c",
+            "import sys, pyarrow.parquet; print(pyarrow.parquet.read_table(sys.argv[1]).num_rows)",
+        ])
+        .arg(&parquet)
+        .output();
+    if let Some(output) = pyarrow.ok().filter(|output| output.status.success()) {
+        assert_eq!(String::from_utf8(output.stdout)?.trim(), calls.len().to_string());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn should_report_class_files_read_without_bytecode_by_lenient_searches() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    // The `pop` after the first `toString` call replaced by the undefined opcode 0xcb
+    let class_bytes = include_bytes!("resources/com/example/TestClass.class");
+    let pop = class_bytes
+        .windows(5)
+        .position(|window| window == b"\xb6\x00\x09\x57\x04")
+        .unwrap()
+        + 3;
+    let mut broken = class_bytes.to_vec();
+    broken[pop] = 0xcb;
+    copy_class(&classes_dir, "TestClass.class", &broken)?;
+
+    let find = |extra: &[&str]| -> Result<Command, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("jmf")?;
+        cmd.args([
+            "-c",
+            "java.util.Arrays",
+            "-m",
+            "asList",
+            "-s",
+            classes_dir.to_str().unwrap(),
+            "--lenient",
+        ]);
+        cmd.args(extra);
+        Ok(cmd)
+    };
+
+    find(&[])?
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Errors: 1 class files could not be analyzed\n - TestClass.class (class version 52): Failed to parse \
+             class file, read without its bytecode under --lenient: Unexpected opcode 203",
+        ))
+        .stdout(predicate::str::contains("By kind: 1 parse\n"));
+    // The class version filters see the class read without bytecode
+    find(&["--max-class-version", "51"])?
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No results"))
+        .stdout(predicate::str::contains("Errors:").not());
+
+    Ok(())
+}
+
+#[test]
+fn should_report_no_errors_for_a_clean_jar_of_interfaces_and_abstract_classes() -> Result<(), Box<dyn std::error::Error>>
+{
+    let temp_dir = TempDir::new()?;
+    let jar = temp_dir.path().join("clean.jar");
+    let mut writer = zip::ZipWriter::new(File::create(&jar)?);
+    for name in ["Api", "Impl", "Stripped"] {
+        let entry = format!("com/example/abstracts/{}.class", name);
+        writer.start_file(entry.as_str(), zip::write::SimpleFileOptions::default())?;
+        writer.write_all(&fs::read(format!("tests/resources/abstract/{}", entry))?)?;
+    }
+    writer.finish()?;
+    let json = temp_dir.path().join("calls.json");
+    let sarif = temp_dir.path().join("calls.sarif");
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["-c", "java.lang.String", "-m", "trim", "-s"])
+        .arg(&jar)
+        .args(["-f", "json", "-o"])
+        .arg(&json)
+        .args(["-f", "sarif", "-o"])
+        .arg(&sarif)
+        .args(["-f", "txt"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("com.example.abstracts.Impl#run (L10)"))
+        .stdout(predicate::str::contains("Errors").not());
+    let report: serde_json::Value = serde_json::from_str(&fs::read_to_string(&json)?)?;
+    assert_eq!(report["calls"].as_array().map(Vec::len), Some(3));
+    assert!(report["errors"].is_null());
+    let log: serde_json::Value = serde_json::from_str(&fs::read_to_string(&sarif)?)?;
+    assert_eq!(log["runs"][0]["results"].as_array().map(Vec::len), Some(3));
+    assert_eq!(
+        log["runs"][0]["invocations"][0]["toolExecutionNotifications"]
+            .as_array()
+            .map(Vec::len),
+        Some(0)
+    );
+
+    // A gate that stops at the first class file it cannot analyze passes
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["gate", "tests/resources/gate/policy.toml", "--fail-fast", "-s"])
+        .arg(&jar);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("### jmf gate: passed"));
+
+    Ok(())
+}
+
+#[test]
+fn should_restore_the_skipped_counts_of_cached_archives() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let jar = temp_dir.path().join("app.jar");
+    let mut writer = zip::ZipWriter::new(File::create(&jar)?);
+    let entries: [(&str, &[u8]); 3] = [
+        (
+            "com/example/TestClass.class",
+            include_bytes!("resources/com/example/TestClass.class"),
+        ),
+        ("com/example/Marker.class", b"not a class file"),
+        (
+            "com/example/HandleClass.class",
+            include_bytes!("resources/com/example/HandleClass.class"),
+        ),
+    ];
+    for (name, bytes) in entries {
+        writer.start_file(name, zip::write::SimpleFileOptions::default())?;
+        writer.write_all(bytes)?;
+    }
+    writer.finish()?;
+    let search = || -> Result<String, Box<dyn std::error::Error>> {
+        let output = Command::cargo_bin("jmf")?
+            .args([
+                "-c",
+                "java.lang.String",
+                "-m",
+                "toString",
+                "--max-class-size",
+                "1000",
+                "-s",
+            ])
+            .arg(&jar)
+            .arg("--result-cache")
+            .arg(temp_dir.path().join("cache"))
+            .output()?;
+        Ok(String::from_utf8(output.stdout)?)
+    };
+
+    let first = search()?;
+    assert!(
+        first.contains("Skipped: 1 class files larger than --max-class-size"),
+        "{}",
+        first
+    );
+    assert!(
+        first.contains("Skipped: 1 .class files that are not class files"),
+        "{}",
+        first
+    );
+    assert_eq!(search()?, first);
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn should_keep_secrets_off_the_command_line_of_curl() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Stands in for curl, recording its arguments and the config it reads from standard input
+    let temp_dir = TempDir::new()?;
+    let curl = temp_dir.path().join("curl");
+    fs::write(
+        &curl,
+        "#!/bin/sh\necho \"$@\" > \"$JMF_TEST_DIR/args\"\ncat > \"$JMF_TEST_DIR/config\"\n",
+    )?;
+    fs::set_permissions(&curl, fs::Permissions::from_mode(0o755))?;
+    let path = format!(
+        "{}:{}",
+        temp_dir.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let webhook = "https://hooks.example.com/services/T0/B0/secret\"token";
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.env("PATH", &path)
+        .env("JMF_TEST_DIR", temp_dir.path())
+        .args(["-c", "java.lang.String", "-m", "toString", "-s"])
+        .arg("tests/resources/com/example/TestClass.class")
+        .args(["--notify-webhook", webhook]);
+    cmd.assert().success();
+    let args = fs::read_to_string(temp_dir.path().join("args"))?;
+    assert!(!args.contains("secret"), "{}", args);
+    assert!(args.contains("--config -"), "{}", args);
+    let config = fs::read_to_string(temp_dir.path().join("config"))?;
+    assert!(
+        config.contains("url = \"https://hooks.example.com/services/T0/B0/secret\\\"token\"\n"),
+        "{}",
+        config
+    );
+
+    // So are the API keys of OTLP exports
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.env("PATH", &path)
+        .env("JMF_TEST_DIR", temp_dir.path())
+        .env("OTEL_EXPORTER_OTLP_HEADERS", "x-honeycomb-team=secret-key")
+        .args(["-c", "java.lang.String", "-m", "toString", "-s"])
+        .arg("tests/resources/com/example/TestClass.class")
+        .args(["--otlp-endpoint", "http://127.0.0.1:4318"]);
+    cmd.assert().success();
+    let args = fs::read_to_string(temp_dir.path().join("args"))?;
+    assert!(!args.contains("secret-key"), "{}", args);
+    let config = fs::read_to_string(temp_dir.path().join("config"))?;
+    assert!(
+        config.contains("header = \"x-honeycomb-team: secret-key\"\n"),
+        "{}",
+        config
+    );
+
+    Ok(())
+}
+
+#[test]
+fn should_reject_sizes_too_large_to_count_in_bytes() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "tests/resources/example.jar",
+        "--max-class-size",
+        "18000000000G",
+    ]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("size '18000000000G' is too large"));
+
+    Ok(())
+}
+
+#[test]
+fn should_reject_timeouts_too_long_to_count_in_seconds() -> Result<(), Box<dyn std::error::Error>> {
+    let search = |timeout: &str| -> Result<Command, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("jmf")?;
+        cmd.args([
+            "-c",
+            "java.lang.String",
+            "-m",
+            "toString",
+            "-s",
+            "tests/resources/example.jar",
+        ])
+        .args(["--file-timeout", timeout]);
+        Ok(cmd)
+    };
+    search("307445734561825861m")?
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("duration '307445734561825861m' is too long"));
+    // Too long to pass, but not to count
+    search("18446744073709551615s")?
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("com.example.TestClass#testMethod (L8)"));
+
+    Ok(())
+}
+
+#[test]
+fn should_number_chained_calls_on_one_line() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "ChainClass.class",
+        include_bytes!("resources/com/example/ChainClass.class"),
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.StringBuilder",
+        "-m",
+        "append",
+        "-s",
+        classes_dir.to_str().unwrap(),
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        " - com.example.ChainClass#chainMethod (L5 #1 @9)\n - com.example.ChainClass#chainMethod (L5 #2 @13)\n - com.example.ChainClass#chainMethod (L5 #3 @18)",
+    ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.StringBuilder",
+        "-m",
+        "toString",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "-f",
+        "json",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"offset\": 21"))
+        .stdout(predicate::str::contains("occurrence").not());
+
+    Ok(())
+}
+
+#[test]
+fn should_name_the_receiver_of_calls() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "ReceiverClass.class",
+        include_bytes!("resources/com/example/ReceiverClass.class"),
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.StringBuilder",
+        "-m",
+        "append",
+        "-s",
+        classes_dir.to_str().unwrap(),
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            " - com.example.ReceiverClass#describe (L9) [receiver: buffer]\n",
+        ))
+        .stdout(predicate::str::contains(
+            " - com.example.ReceiverClass#describe (L10 #1 @25)\n",
+        ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.Object",
+        "-m",
+        "toString",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "-f",
+        "json",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"receiver\": \"this\""));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "com.example.app.UserService",
+        "-m",
+        "save",
+        "-s",
+        "tests/resources/iface",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        " - com.example.app.UserController#register (L11) [receiver: userService]\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn should_note_compiler_generated_members() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "MemberClass.class",
+        include_bytes!("resources/com/example/MemberClass.class"),
+    )?;
+    copy_class(
+        &classes_dir,
+        "MemberClass$Color.class",
+        include_bytes!("resources/com/example/MemberClass$Color.class"),
+    )?;
+    copy_class(
+        &classes_dir,
+        "MemberClass$Point.class",
+        include_bytes!("resources/com/example/MemberClass$Point.class"),
+    )?;
+
+    for (class, method, note) in [
+        ("com.example.MemberClass$Color", "values", "enum values()"),
+        ("com.example.MemberClass$Color", "valueOf", "enum valueOf(String)"),
+        ("com.example.MemberClass$Point", "x", "accessor of record component x"),
+        ("com.example.MemberClass$Point", "toString", "record toString()"),
+    ] {
+        let mut cmd = Command::cargo_bin("jmf")?;
+        cmd.args(["-c", class, "-m", method, "-s", classes_dir.to_str().unwrap()]);
+
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains(format!("[generated: {}]", note)));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn should_report_handle_references() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "HandleClass.class",
+        include_bytes!("resources/com/example/HandleClass.class"),
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "trim",
+        "-s",
+        classes_dir.to_str().unwrap(),
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "com.example.HandleClass#trimmer (L7) [handle reference]",
+        ))
+        .stdout(predicate::str::contains(
+            "com.example.HandleClass#trim (L11) [receiver: value]",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn should_report_dynamic_groovy_calls() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "GroovyClass.class",
+        include_bytes!("resources/com/example/GroovyClass.class"),
+    )?;
+    let scan = classes_dir.to_str().unwrap();
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["-c", "java.lang.String", "-m", "trim", "-s", scan, "--groovy"]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "com.example.GroovyClass#clean (L7) [groovy dynamic call]",
+    ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["-c", "java.lang.String", "-m", "trim", "-s", scan]);
+    cmd.assert().success().stdout(predicate::str::contains("No results"));
+
+    Ok(())
+}
+
+#[test]
+fn should_attribute_scala_synthetic_methods() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "ScalaApp.class",
+        include_bytes!("resources/com/example/ScalaApp.class"),
+    )?;
+    copy_class(
+        &classes_dir,
+        "ScalaApp$.class",
+        include_bytes!("resources/com/example/ScalaApp$.class"),
+    )?;
+    let scan = classes_dir.to_str().unwrap();
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["-c", "java.lang.String", "-m", "trim", "-s", scan]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "com.example.ScalaApp#run (L12) [receiver: value] \
+             [scala: object com.example.ScalaApp$, anonymous function $anonfun$run$1]",
+        ))
+        .stdout(predicate::str::contains(
+            "com.example.ScalaApp#++ (L16) [receiver: value] [scala: object com.example.ScalaApp$, encoded $plus$plus]",
+        ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["-c", "com.example.ScalaApp$", "-m", "run", "-s", scan]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "com.example.ScalaApp#run (L6) [receiver: MODULE$] [scala: companion forwarder]",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn should_scan_the_bundle_class_path_of_osgi_bundles() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "trim",
+        "-s",
+        "tests/resources/bundle.jar",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "com.example.HandleClass#trim (L11) [receiver: value] [origin: com.example.bundle]",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn should_scan_the_jars_of_android_archives() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "trim",
+        "-s",
+        "tests/resources/example.aar",
+        "--archive-depth",
+        "0",
+        "--dry-run",
+        "-v",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "tests/resources/example.aar!/classes.jar!/com/example/TestClass.class",
+        ))
+        .stdout(predicate::str::contains(
+            "tests/resources/example.aar!/libs/handle.jar!/com/example/HandleClass.class",
+        ))
+        .stdout(predicate::str::contains("AndroidManifest").not());
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "trim",
+        "-s",
+        "tests/resources/example.aar",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "com.example.HandleClass#trim (L11) [receiver: value]",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn should_follow_the_class_path_of_jar_manifests() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "tests/resources/launcher.jar",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("TestClass").not());
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "tests/resources/launcher.jar",
+        "--follow-manifest-classpath",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "com.example.TestClass#testMethod (L8) [receiver: str] [origin: example.jar]",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn should_read_scan_roots_from_stdin() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["-c", "java.lang.String", "-m", "toString", "-s", "-", "--dry-run", "-v"])
+        .write_stdin("tests/resources/com/example/ChainClass.class\n\ntests/resources/example.jar\n");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            " - tests/resources/com/example/ChainClass.class (class file, 1 class files)",
+        ))
+        .stdout(predicate::str::contains(
+            " - tests/resources/example.jar (jar, 2 class files)",
+        ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["-c", "java.lang.StringBuilder", "-m", "toString", "-s", "-"])
+        .write_stdin("tests/resources/com/example/ChainClass.class\n");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("com.example.ChainClass#chainMethod (L5)"));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["-c", "java.lang.String", "-m", "toString", "-s", "-"])
+        .write_stdin("");
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("No scan roots were listed on standard input"));
+
+    Ok(())
+}
+
+#[test]
+fn should_only_scan_classes_of_sources_changed_since_a_revision() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let sources = temp_dir.path().join("src/main/java/com/example");
+    let classes = temp_dir.path().join("classes/com/example");
+    fs::create_dir_all(&sources)?;
+    fs::create_dir_all(&classes)?;
+    for name in ["ChainClass", "TestClass"] {
+        fs::copy(
+            format!("tests/resources/com/example/{}.java", name),
+            sources.join(format!("{}.java", name)),
+        )?;
+        fs::copy(
+            format!("tests/resources/com/example/{}.class", name),
+            classes.join(format!("{}.class", name)),
+        )?;
+    }
+    let git = |args: &[&str]| {
+        std::process::Command::new("git")
+            .args(["-c", "user.name=jmf", "-c", "user.email=jmf@example.com"])
+            .args(args)
+            .current_dir(temp_dir.path())
+            .output()
+    };
+    git(&["init", "--quiet"])?;
+    git(&["add", "src"])?;
+    git(&["commit", "--quiet", "-m", "Initial"])?;
+    let chain_class = sources.join("ChainClass.java");
+    fs::write(&chain_class, fs::read_to_string(&chain_class)? + "\n")?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.current_dir(temp_dir.path()).args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "classes",
+        "--changed-since",
+        "HEAD",
+        "--dry-run",
+        "-v",
+    ]);
+
+    // The path only narrows the scan to changed packages, the SourceFile attribute decides
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("(directory, 2 class files)"));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.current_dir(temp_dir.path()).args([
+        "-c",
+        "java.lang.StringBuilder",
+        "-m",
+        "toString",
+        "-s",
+        "classes",
+        "--changed-since",
+        "HEAD",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("com.example.ChainClass#chainMethod (L5)"));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.current_dir(temp_dir.path()).args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "classes",
+        "--changed-since",
+        "HEAD",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("TestClass").not());
+
+    Ok(())
+}
+
+#[test]
+fn should_annotate_calls_with_git_blame() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let sources = temp_dir.path().join("src/main/java/com/example");
+    let classes = temp_dir.path().join("classes/com/example");
+    fs::create_dir_all(&sources)?;
+    fs::create_dir_all(&classes)?;
+    fs::copy(
+        "tests/resources/com/example/ChainClass.java",
+        sources.join("ChainClass.java"),
+    )?;
+    fs::copy(
+        "tests/resources/com/example/ChainClass.class",
+        classes.join("ChainClass.class"),
+    )?;
+    let git = |args: &[&str]| {
+        std::process::Command::new("git")
+            .args(["-c", "user.name=Jane Doe", "-c", "user.email=jane@example.com"])
+            .args(args)
+            .current_dir(temp_dir.path())
+            .output()
+    };
+    git(&["init", "--quiet"])?;
+    git(&["add", "src"])?;
+    git(&["commit", "--quiet", "-m", "Add ChainClass"])?;
+    let commit = String::from_utf8(git(&["rev-parse", "--short=7", "HEAD"])?.stdout)?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.current_dir(temp_dir.path()).args([
+        "-c",
+        "java.lang.StringBuilder",
+        "-m",
+        "toString",
+        "-s",
+        "classes",
+        "--blame",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(format!(
+        "com.example.ChainClass#chainMethod (L5) [blame: {} Jane Doe]",
+        commit.trim()
+    )));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.current_dir(temp_dir.path()).args([
+        "-c",
+        "java.lang.StringBuilder",
+        "-m",
+        "toString",
+        "-s",
+        "classes",
+        "--blame",
+        "--source-root",
+        "src/test/java",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains("blame").not());
+
+    Ok(())
+}
+
+#[test]
+fn should_group_calls_by_code_owners() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes = temp_dir.path().join("classes/com/example");
+    fs::create_dir_all(&classes)?;
+    fs::create_dir_all(temp_dir.path().join(".github"))?;
+    for name in ["LoopClass", "TestClass", "ChainClass"] {
+        fs::copy(
+            format!("tests/resources/com/example/{}.class", name),
+            classes.join(format!("{}.class", name)),
+        )?;
+    }
+    fs::write(
+        temp_dir.path().join(".github/CODEOWNERS"),
+        "# Owners\n*.java @org/everyone\n/src/main/java/com/example/Test*.java @org/core\n**/LoopClass.java @org/loops @org/perf\n",
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.current_dir(temp_dir.path()).args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "classes",
+        "--owners",
+        ".github/CODEOWNERS",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "@org/core: 2 hits\n - com.example.TestClass#testMethod (L8) [receiver: str]\n - com.example.TestClass#testMethod (L10) [receiver: str]\n@org/loops: 2 hits\n",
+    ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.current_dir(temp_dir.path()).args([
+        "-c",
+        "java.lang.StringBuilder",
+        "-m",
+        "toString",
+        "-s",
+        "classes",
+        "--owners",
+        ".github/CODEOWNERS",
+        "-f",
+        "json",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "\"owner\": \"@org/everyone\",\n      \"count\": 1",
+    ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.current_dir(temp_dir.path()).args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "classes",
+        "--group-by",
+        "owner",
+    ]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--group-by owner needs --owners"));
+
+    Ok(())
+}
+
+#[test]
+fn should_keep_fingerprints_when_lines_shift() -> Result<(), Box<dyn std::error::Error>> {
+    let fingerprints = |scan_root: &str| -> Result<Vec<(u64, String)>, Box<dyn std::error::Error>> {
+        let output = Command::cargo_bin("jmf")?
+            .args(["-c", "java.lang.String", "-m", "trim", "-s", scan_root, "-f", "json"])
+            .output()?;
+        let result: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+        Ok(result["calls"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|call| {
+                (
+                    call["line_number"].as_u64().unwrap(),
+                    call["fingerprint"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect())
+    };
+
+    let before = fingerprints("tests/resources/com/example/ShiftClass.class")?;
+    // The same class with lines and calls added above the trims
+    let after = fingerprints("tests/resources/shifted.jar")?;
+
+    assert_eq!(before.iter().map(|(line, _)| *line).collect::<Vec<_>>(), [5, 6]);
+    assert_eq!(after.iter().map(|(line, _)| *line).collect::<Vec<_>>(), [11, 12]);
+    assert_ne!(before[0].1, before[1].1);
+    assert_eq!(
+        before.iter().map(|(_, fingerprint)| fingerprint).collect::<Vec<_>>(),
+        after.iter().map(|(_, fingerprint)| fingerprint).collect::<Vec<_>>()
+    );
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "trim",
+        "-s",
+        "tests/resources/shifted.jar",
+        "-f",
+        "sarif",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(format!(
+        "\"jmfCallSite/v1\": \"{}\"",
+        before[0].1
+    )));
+
+    Ok(())
+}
+
+#[test]
+fn should_chart_call_counts_over_time() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    for (file_name, scan_root) in [
+        ("calls-2024-03-01.json", "tests/resources/example.jar"),
+        (
+            "calls-2024-05-01T09:30.json",
+            "tests/resources/com/example/TestClass.class",
+        ),
+    ] {
+        let output = Command::cargo_bin("jmf")?
+            .args([
+                "-c",
+                "java.lang.String",
+                "-m",
+                "toString",
+                "-s",
+                scan_root,
+                "-f",
+                "json",
+            ])
+            .output()?;
+        fs::write(temp_dir.path().join(file_name), output.stdout)?;
+    }
+    fs::write(
+        temp_dir.path().join("20240401.json"),
+        r#"{"rules": [{"id": "string", "target": "java.lang.String#toString", "calls": [{}, {}, {}]}]}"#,
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["trend", "--history", temp_dir.path().to_str().unwrap()]);
+
+    cmd.assert().success().stdout(predicate::str::diff(
+        "java.lang.String#toString\n  2024-03-01        4 ########################################\n  2024-04-01        3 ##############################\n  2024-05-01 09:30  2 ####################\n  4 -> 2 (-2) since 2024-03-01\n",
+    ));
+
+    fs::write(temp_dir.path().join("notes.json"), "{}")?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["trend", "--history", temp_dir.path().to_str().unwrap()]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Not a jmf JSON result"));
+
+    Ok(())
+}
+
+#[test]
+fn should_scan_saved_container_images() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "docker-archive:tests/resources/image.tar",
+        "--dry-run",
+        "-v",
+    ]);
+
+    // The second layer deletes HandleClass and adds ChainClass
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            " - docker-archive:tests/resources/image.tar (image, 3 class files)",
+        ))
+        .stdout(predicate::str::contains(
+            "docker-archive:tests/resources/image.tar!/app/classes/com/example/ChainClass.class",
+        ))
+        .stdout(predicate::str::contains(
+            "docker-archive:tests/resources/image.tar!/app/lib/example.jar!/com/example/TestClass.class",
+        ))
+        .stdout(predicate::str::contains("HandleClass").not());
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "docker-archive:tests/resources/image.tar",
+    ]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "com.example.TestClass#testMethod (L8) [receiver: str]",
+    ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["-c", "java.lang.String", "-m", "toString", "-s", "docker://--help"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid image reference --help"));
+
+    Ok(())
+}
+
+#[test]
+fn should_descend_into_the_jars_of_jars_only_with_an_archive_depth() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let jar = temp_dir.path().join("app.jar");
+    let mut writer = zip::ZipWriter::new(File::create(&jar)?);
+    writer.start_file("lib/example.jar", zip::write::SimpleFileOptions::default())?;
+    writer.write_all(&fs::read("tests/resources/example.jar")?)?;
+    writer.finish()?;
+    let search = |archive_depth: Option<&str>| -> Result<String, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("jmf")?;
+        cmd.args(["-c", "java.lang.String", "-m", "toString", "-s"]).arg(&jar);
+        if let Some(archive_depth) = archive_depth {
+            cmd.args(["--archive-depth", archive_depth]);
+        }
+        Ok(String::from_utf8(cmd.output()?.stdout)?)
+    };
+
+    assert!(search(None)?.contains("No results"));
+    assert!(search(Some("1"))?.contains("com.example.TestClass#testMethod (L8) [receiver: str]"));
+
+    Ok(())
+}
+
+/// A ustar archive of regular files.
+fn tar_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    for (name, data) in files {
+        let mut header = [0u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[124..135].copy_from_slice(format!("{:011o}", data.len()).as_bytes());
+        header[156] = b'0';
+        archive.extend_from_slice(&header);
+        archive.extend_from_slice(data);
+        archive.resize(archive.len().div_ceil(512) * 512, 0);
+    }
+    archive.resize(archive.len() + 1024, 0);
+    archive
+}
+
+#[test]
+fn should_apply_opaque_whiteouts_only_to_lower_layers() -> Result<(), Box<dyn std::error::Error>> {
+    let handle_class = fs::read("tests/resources/com/example/HandleClass.class")?;
+    let chain_class = fs::read("tests/resources/com/example/ChainClass.class")?;
+    // The second layer replaces the classes of the first, listing its own before the whiteout
+    let lower = tar_archive(&[("app/classes/com/example/HandleClass.class", &handle_class)]);
+    let upper = tar_archive(&[
+        ("app/classes/com/example/ChainClass.class", &chain_class),
+        ("app/classes/.wh..wh..opq", b""),
+    ]);
+    let manifest = br#"[{"Layers": ["lower/layer.tar", "upper/layer.tar"]}]"#;
+    let temp_dir = TempDir::new()?;
+    let image = temp_dir.path().join("image.tar");
+    fs::write(
+        &image,
+        tar_archive(&[
+            ("manifest.json", manifest),
+            ("lower/layer.tar", &lower),
+            ("upper/layer.tar", &upper),
+        ]),
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["-c", "java.lang.String", "-m", "toString", "--dry-run", "-v", "-s"])
+        .arg(format!("docker-archive:{}", image.display()));
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("(image, 1 class files)"))
+        .stdout(predicate::str::contains("ChainClass.class"))
+        .stdout(predicate::str::contains("HandleClass").not());
+
+    // A layer whose declared size runs past the end of the archive
+    let mut truncated = tar_archive(&[("manifest.json", br#"[{"Layers": ["layer.tar"]}]"#)]);
+    truncated.truncate(truncated.len() - 1024);
+    truncated.extend_from_slice(&tar_archive(&[("layer.tar", &[0; 4096])])[..1024]);
+    fs::write(&image, truncated)?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["-c", "java.lang.String", "-m", "toString", "-s"])
+        .arg(format!("docker-archive:{}", image.display()));
+    cmd.assert()
+        .stderr(predicate::str::contains("Layer layer.tar is truncated"))
+        .stderr(predicate::str::contains("panicked").not());
+
+    Ok(())
+}
+
+#[test]
+fn should_scan_tar_archives_and_their_nested_jars() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "tests/resources/release.tar.gz",
+    ]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "com.example.TestClass#testMethod (L8) [receiver: str]",
+    ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "trim",
+        "-s",
+        "tests/resources/release.tar.gz",
+        "--archive-depth",
+        "0",
+        "--dry-run",
+        "-v",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "tests/resources/release.tar.gz!/dist/classes/com/example/HandleClass.class",
+        ))
+        .stdout(predicate::str::contains("example.jar").not());
+
+    Ok(())
+}
+
+/// Serves `files` by path over HTTP from a background thread, returning the base URL; paths below
+/// `/unavailable/` answer 503.
+fn serve(files: Vec<(String, Vec<u8>)>) -> io::Result<String> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let base_url = format!("http://{}", listener.local_addr()?);
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                match io::Read::read(&mut stream, &mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(read) => request.extend_from_slice(&buffer[..read]),
+                }
+            }
+            let request = String::from_utf8_lossy(&request);
+            let path = request.split_whitespace().nth(1).unwrap_or_default();
+            let _ = match files.iter().find(|(file, _)| file == path) {
+                None if path.starts_with("/unavailable/") => {
+                    stream.write_all(b"HTTP/1.0 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n")
+                }
+                Some((_, body)) => write!(stream, "HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n", body.len())
+                    .and_then(|_| stream.write_all(body)),
+                None => stream.write_all(b"HTTP/1.0 404 Not Found\r\nContent-Length: 0\r\n\r\n"),
+            };
+        }
+    });
+    Ok(base_url)
+}
+
+#[test]
+fn should_scan_downloaded_jars_and_verify_their_checksums() -> Result<(), Box<dyn std::error::Error>> {
+    let jar = include_bytes!("resources/example.jar").to_vec();
+    let sha256: String = Sha256::digest(&jar)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    let base_url = serve(vec![
+        ("/good/example.jar".to_string(), jar.clone()),
+        (
+            "/good/example.jar.sha256".to_string(),
+            format!("{}  example.jar", sha256).into_bytes(),
+        ),
+        ("/bad/example.jar".to_string(), jar.clone()),
+        ("/bad/example.jar.sha256".to_string(), "0".repeat(64).into_bytes()),
+        ("/unsigned/example.jar".to_string(), jar.clone()),
+        ("/unavailable/example.jar".to_string(), jar),
+    ])?;
+    // Downloads are cached per user
+    let cache_dir = TempDir::new()?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.env("XDG_CACHE_HOME", cache_dir.path())
+        .args(["-c", "java.lang.String", "-m", "toString", "-s"])
+        .arg(format!("{}/good/example.jar", base_url));
+    cmd.assert().success().stdout(predicate::str::contains(
+        "com.example.TestClass#testMethod (L8) [receiver: str]",
+    ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.env("XDG_CACHE_HOME", cache_dir.path())
+        .args(["-c", "java.lang.String", "-m", "toString", "-s"])
+        .arg(format!("{}/bad/example.jar", base_url));
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Checksum mismatch"));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.env("XDG_CACHE_HOME", cache_dir.path())
+        .args([
+            "-c",
+            "java.lang.String",
+            "-m",
+            "toString",
+            "--remote-checksum",
+            "require",
+            "-s",
+        ])
+        .arg(format!("{}/unsigned/example.jar", base_url));
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("No .sha512 or .sha256 checksum is published"));
+
+    // Only a missing checksum file skips the verification
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.env("XDG_CACHE_HOME", cache_dir.path())
+        .args(["-c", "java.lang.String", "-m", "toString", "-s"])
+        .arg(format!("{}/unavailable/example.jar", base_url));
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("HTTP status 503"));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let downloads = cache_dir.path().join("jmf").join("downloads");
+        assert_eq!(fs::metadata(downloads)?.permissions().mode() & 0o777, 0o700);
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn should_scan_objects_copied_from_object_stores() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Stands in for the AWS CLI: `aws s3 cp --only-show-errors SOURCE DESTINATION`, copies are verified
+    // against the KEY.sha256 objects next to them
+    let temp_dir = TempDir::new()?;
+    let bin_dir = temp_dir.path().join("bin");
+    let bucket_dir = temp_dir.path().join("bucket");
+    fs::create_dir_all(&bin_dir)?;
+    fs::create_dir_all(&bucket_dir)?;
+    let jar = include_bytes!("resources/example.jar");
+    let sha256: String = Sha256::digest(jar).iter().map(|byte| format!("{:02x}", byte)).collect();
+    fs::write(bucket_dir.join("example.jar"), jar)?;
+    fs::write(bucket_dir.join("example.jar.sha256"), &sha256)?;
+    fs::write(bucket_dir.join("tampered.jar"), jar)?;
+    fs::write(bucket_dir.join("tampered.jar.sha256"), "0".repeat(64))?;
+    let aws = bin_dir.join("aws");
+    fs::write(
+        &aws,
+        "#!/bin/sh\nobject=\"$JMF_TEST_BUCKET/$(basename \"$4\")\"\n\
+         [ -f \"$object\" ] || { echo 'fatal error: An error occurred (404) when calling the HeadObject operation: Not Found' >&2; exit 1; }\n\
+         cp \"$object\" \"$5\"\n",
+    )?;
+    fs::set_permissions(&aws, fs::Permissions::from_mode(0o755))?;
+    let path = format!("{}:{}", bin_dir.display(), std::env::var("PATH").unwrap_or_default());
+    let cache_dir = temp_dir.path().join("cache");
+
+    // The second scan reuses the verified copy
+    for _ in 0..2 {
+        let mut cmd = Command::cargo_bin("jmf")?;
+        cmd.env("PATH", &path)
+            .env("JMF_TEST_BUCKET", &bucket_dir)
+            .env("XDG_CACHE_HOME", &cache_dir)
+            .args([
+                "-c",
+                "java.lang.String",
+                "-m",
+                "toString",
+                "-s",
+                "s3://artifacts/example.jar",
+            ]);
+        cmd.assert().success().stdout(predicate::str::contains(
+            "com.example.TestClass#testMethod (L8) [receiver: str]",
+        ));
+    }
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.env("PATH", &path)
+        .env("JMF_TEST_BUCKET", &bucket_dir)
+        .env("XDG_CACHE_HOME", &cache_dir)
+        .args([
+            "-c",
+            "java.lang.String",
+            "-m",
+            "toString",
+            "-s",
+            "s3://artifacts/tampered.jar",
+        ]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Checksum mismatch"));
+
+    Ok(())
+}
+
+#[test]
+fn should_explain_calls_with_a_disassembly() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "tests/resources/example.jar",
+        "--path-include",
+        "**/TestClass.class",
+        "--explain",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        " - com.example.TestClass#testMethod (L8) [receiver: str]\n         0: ldc String \"test\"\n         2: astore 1\n         3: aload 1\n   >     4: invokevirtual Method java/lang/String.toString:()Ljava/lang/String;\n         7: pop\n         8: iconst_1\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn should_group_calls_by_origin() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+    let alias = format!("payments={}", classes_dir.to_str().unwrap());
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "-s",
+        "tests/resources/example.jar",
+        "--root-alias",
+        &alias,
+        "--group-by",
+        "origin",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "example.jar: 4 hits\n - com.example.LoopClass#loopMethod (L8) [loop]\n",
+        ))
+        .stdout(predicate::str::contains(
+            "payments: 2 hits\n - com.example.TestClass#testMethod (L8) [receiver: str]\n",
+        ))
+        .stdout(predicate::str::contains("[origin:").not());
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "-s",
+        "tests/resources/example.jar",
+        "--root-alias",
+        &alias,
+        "-f",
+        "json",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"origin\": \"payments\""))
+        .stdout(predicate::str::contains("\"origin\": \"example.jar\""));
+
+    Ok(())
+}
+
+#[test]
+fn should_list_scan_roots_in_dry_run() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+    // Never parsed in a dry run
+    copy_class(&classes_dir, "Broken.class", b"not a class file")?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "-s",
+        "tests/resources/example.jar",
+        "--dry-run",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("(directory, 2 class files)"))
+        .stdout(predicate::str::contains(
+            " - tests/resources/example.jar (jar, 2 class files)\nTotal class files: 4",
+        ))
+        .stdout(predicate::str::contains("TestClass#testMethod").not())
+        .stdout(predicate::str::contains("Errors").not());
+
+    Ok(())
+}
+
+#[test]
+fn should_report_class_files_exceeding_the_timeout() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "--file-timeout",
+        "0ms",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("No results"))
+        .stdout(predicate::str::contains("Errors: 1 class files could not be analyzed"))
+        .stdout(predicate::str::contains("Timed out after"));
+
+    // The kind comes from the type of the error, not from its message
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "--file-timeout",
+        "0ms",
+        "-f",
+        "json",
+    ]);
+    let stdout = String::from_utf8(cmd.output()?.stdout)?;
+    let report: serde_json::Value = serde_json::from_str(&stdout[stdout.find('{').unwrap()..])?;
+    assert_eq!(report["errors"][0]["kind"], "timeout");
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "--file-timeout",
+        "5s",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("- com.example.TestClass#testMethod (L8)"));
+
+    Ok(())
+}
+
+#[test]
+fn should_skip_class_files_above_the_size_limit() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "LoopClass.class",
+        include_bytes!("resources/com/example/LoopClass.class"),
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "--max-class-size",
+        "700",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("- com.example.TestClass#testMethod (L8)"))
+        .stdout(predicate::str::contains("LoopClass").not())
+        .stdout(predicate::str::contains(
+            "Skipped: 1 class files larger than --max-class-size",
+        ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "--max-class-size",
+        "700",
+        "-f",
+        "json",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(r#""skipped": 1"#));
+
+    Ok(())
+}
+
+#[test]
+fn should_read_large_class_files_through_a_mapping() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    // Padded past the memory-mapping threshold, so the header is read from the mapping
+    let mut class_bytes = include_bytes!("resources/com/example/TestClass.class").to_vec();
+    class_bytes.resize(class_bytes.len() + (1 << 20), 0);
+    copy_class(&classes_dir, "TestClass.class", &class_bytes)?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "versions",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "-s",
+        "tests/resources/example.jar",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Total classes: 2\n - Java 8 (52): 2"));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.util.Arrays",
+        "-m",
+        "asList",
+        "-s",
+        classes_dir.to_str().unwrap(),
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "TestClass.class (class version 52): Failed to parse class file",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn should_write_json_logs_and_profile() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+    let log_file = temp_dir.path().join("jmf.log");
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "-v",
+        "--log-format",
+        "json",
+        "--log-file",
+        log_file.to_str().unwrap(),
+        "--profile",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("DEBUG").not())
+        .stderr(predicate::str::contains("Profile:\n - walk:").and(predicate::str::contains(" - output:")));
+    let log = fs::read_to_string(&log_file)?;
+    assert!(log.contains(r#""level":"DEBUG""#));
+    assert!(log.contains(r#""name":"class""#));
+
+    Ok(())
+}
+
+#[test]
+fn should_color_text_output_on_request() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.util.Arrays",
+        "-m",
+        "asList",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "--color",
+        "always",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "\x1b[1mjava.util.Arrays#asList\x1b[0m\n - \x1b[36mcom.example.TestClass\x1b[0m#testMethod \x1b[2m(L9)\x1b[0m",
+    ));
+
+    // Output that is not a terminal stays plain
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.util.Arrays",
+        "-m",
+        "asList",
+        "-s",
+        classes_dir.to_str().unwrap(),
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains("\x1b[").not());
+
+    Ok(())
+}
+
+#[test]
+fn should_print_calls_as_a_tree() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "LockClass.class",
+        include_bytes!("resources/com/example/LockClass.class"),
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "--package",
+        "-c",
+        "java.util.concurrent.locks",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "-f",
+        "tree",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "com.example (4)
+└── LockClass (4)
+    ├── guarded (3)
+    │   ├── L7
+    │   └── L11 (2)
+    └── leaked (1)
+        └── L16",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn should_print_null_delimited_records() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.util.Arrays",
+        "-m",
+        "asList",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "--print0",
+    ]);
+
+    cmd.assert().success().stdout("com.example.TestClass\ttestMethod\t9\0");
+
+    Ok(())
+}
+
+#[test]
+fn should_run_subcommands() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "LockClass.class",
+        include_bytes!("resources/com/example/LockClass.class"),
+    )?;
+    let policy_file = temp_dir.path().join("policy.toml");
+    fs::write(
+        &policy_file,
+        r#"
+[[rule]]
+class = "java.util.Arrays"
+method = "asList"
+severity = "error"
+"#,
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "find",
+        "-c",
+        "java.util.Arrays",
+        "-m",
+        "asList",
+        "-s",
+        classes_dir.to_str().unwrap(),
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "java.util.Arrays#asList\n - com.example.TestClass#testMethod (L9)",
+    ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "check",
+        policy_file.to_str().unwrap(),
+        "-s",
+        classes_dir.to_str().unwrap(),
+    ]);
+
+    cmd.assert().code(1).stdout(predicate::str::contains(
+        "[error] java.util.Arrays#asList\n - com.example.TestClass#testMethod (L9)",
+    ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["graph", "-s", classes_dir.to_str().unwrap(), "--internal"]);
+
+    cmd.assert().success().stdout(
+        "com.example.LockClass#guarded -> com.example.LockClass#work\n\
+         com.example.LockClass#leaked -> com.example.LockClass#work\n",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn should_evaluate_queries() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "ReachableClass.class",
+        include_bytes!("resources/com/example/ReachableClass.class"),
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "query",
+        r#"calls("java.lang.String#toString") and caller(class ~ "com.example.*" and not method == "liveMethod") and not caller(annotated("Deprecated"))"#,
+        "-s",
+        classes_dir.to_str().unwrap(),
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("com.example.TestClass#testMethod (L8)"))
+        .stdout(predicate::str::contains("com.example.ReachableClass#deadMethod (L13)"))
+        .stdout(predicate::str::contains("ReachableClass#liveMethod").not())
+        .stdout(predicate::str::contains("ReachableClass#annotatedMethod").not());
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-q",
+        r#"calls("java.util.*") or caller(method == "missing""#,
+        "-s",
+        classes_dir.to_str().unwrap(),
+    ]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("expected ')', found the end of the query"));
+
+    Ok(())
+}
+
+#[test]
+fn should_run_saved_queries() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+    let config_file = temp_dir.path().join("jmf.toml");
+    fs::write(
+        &config_file,
+        r#"
+[queries.as-list]
+query = 'calls("java.util.Arrays#asList")'
+severity = "warn"
+
+[queries.to-string]
+class = "java.lang.String"
+method = "toString"
+message = "strings are already strings"
+"#,
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "run",
+        "as-list",
+        "--config",
+        config_file.to_str().unwrap(),
+        "-s",
+        classes_dir.to_str().unwrap(),
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(
+            "[warn] calls(\"java.util.Arrays#asList\")\n - com.example.TestClass#testMethod (L9) -> java.util.Arrays#asList\n",
+        );
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "run",
+        "--all",
+        "--config",
+        config_file.to_str().unwrap(),
+        "-s",
+        classes_dir.to_str().unwrap(),
+    ]);
+
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "[error] java.lang.String#toString: strings are already strings",
+        ))
+        .stdout(predicate::str::contains("[warn] calls(\"java.util.Arrays#asList\")"));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["run", "missing", "--config", config_file.to_str().unwrap()]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("No query 'missing' in"));
+
+    Ok(())
+}
+
+#[test]
+fn should_load_plugins() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "GuardedClass.class",
+        include_bytes!("resources/com/example/GuardedClass.class"),
+    )?;
+    let plugin = temp_dir.path().join(format!(
+        "{}monitors{}",
+        std::env::consts::DLL_PREFIX,
+        std::env::consts::DLL_SUFFIX
+    ));
+    let status = std::process::Command::new(std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string()))
+        .args(["--edition", "2021", "--crate-type", "cdylib", "-o"])
+        .arg(&plugin)
+        .arg("tests/resources/plugins/monitors.rs")
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env("TMPDIR", temp_dir.path())
+        .status()?;
+    assert!(status.success());
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "--plugin",
+        plugin.to_str().unwrap(),
+        "-s",
+        classes_dir.to_str().unwrap(),
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "plugin:monitors\n - com.example.GuardedClass#guardedMethod (L12)",
+    ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "--plugin",
+        plugin.to_str().unwrap(),
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "-f",
+        "plugin",
+    ]);
+
+    cmd.assert().success().stdout("monitors: 1 calls\n");
+
+    Ok(())
+}
+
+#[test]
+fn should_load_wasm_plugins() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "GuardedClass.class",
+        include_bytes!("resources/com/example/GuardedClass.class"),
+    )?;
+
+    // monitors.rs built for wasm32-unknown-unknown
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "--plugin",
+        "tests/resources/plugins/monitors.wasm",
+        "-s",
+        classes_dir.to_str().unwrap(),
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "plugin:monitors\n - com.example.GuardedClass#guardedMethod (L12)",
+    ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "--plugin",
+        "tests/resources/plugins/monitors.wasm",
+        "-s",
+        classes_dir.to_str().unwrap(),
+        "-f",
+        "plugin",
+    ]);
+
+    cmd.assert().success().stdout("monitors: 1 calls\n");
+
+    Ok(())
+}
+
+#[test]
+fn should_analyze_class_bytes_with_the_library() -> Result<(), Box<dyn std::error::Error>> {
+    let analyzer = Analyzer::new(vec![Rule::method("java.lang.String", "toString")]);
+
+    let calls = analyzer.find_calls(include_bytes!("resources/com/example/TestClass.class"))?;
+    let calls: Vec<String> = calls.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        calls,
+        [
+            "com.example.TestClass#testMethod (L8) [receiver: str]",
+            "com.example.TestClass#testMethod (L10) [receiver: str]"
+        ]
+    );
+
+    let analyzer = Analyzer::new(vec![Rule::method("java.util.Arrays", "asList")]);
+    let calls = analyzer.find_calls_in_jar(include_bytes!("resources/example.jar"))?;
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].to_string(), "com.example.TestClass#testMethod (L9)");
+    assert_eq!(&*calls[0].java_release, "8");
+
+    Ok(())
+}
+
+#[test]
+fn should_scan_paths_with_the_library() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(classes_dir.join("nested"))?;
+    copy_test_class(&classes_dir)?;
+    fs::copy("tests/resources/example.jar", classes_dir.join("nested/example.jar"))?;
+
+    let analyzer = Analyzer::new(vec![Rule::parse("java.util.*")?]);
+    let calls = analyzer.scan(&classes_dir)?;
+    let calls: Vec<String> = calls
+        .iter()
+        .map(|call| format!("{} -> {}", call, call.callee.as_deref().unwrap_or_default()))
+        .collect();
+    assert_eq!(
+        calls,
+        [
+            "com.example.TestClass#testMethod (L9) -> java.util.Arrays#asList",
+            "com.example.LoopClass#loopMethod (L7) -> java.util.List#iterator",
+            "com.example.LoopClass#loopMethod (L7) [loop] -> java.util.Iterator#hasNext",
+            "com.example.LoopClass#loopMethod (L7) [loop] -> java.util.Iterator#next",
+            "com.example.TestClass#testMethod (L9) -> java.util.Arrays#asList",
+        ]
+    );
+
+    assert!(Rule::parse("java.lang.String").is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn should_stream_calls_with_the_library() -> Result<(), Box<dyn std::error::Error>> {
+    use tokio_stream::StreamExt;
+
+    let analyzer = Analyzer::new(vec![Rule::parse("java.util.*")?]);
+    let calls: Vec<String> = analyzer
+        .scan_stream("tests/resources/example.jar".into())
+        .map(|call| call.map(|call| call.to_string()))
+        .collect::<Result<_, _>>()
+        .await?;
+    assert_eq!(
+        calls,
+        [
+            "com.example.LoopClass#loopMethod (L7)",
+            "com.example.LoopClass#loopMethod (L7) [loop]",
+            "com.example.LoopClass#loopMethod (L7) [loop]",
+            "com.example.TestClass#testMethod (L9)",
+        ]
+    );
+
+    let analyzer = Analyzer::new(Vec::new());
+    let mut calls = analyzer.scan_stream("/invalid/path".into());
+    assert!(calls.next().await.is_some_and(|call| call.is_err()));
+    assert!(calls.next().await.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn should_iterate_calls_with_the_library() -> Result<(), Box<dyn std::error::Error>> {
+    let rules = || vec![Rule::parse("java.lang.String#toString").unwrap()];
+    let path = Path::new("tests/resources");
+
+    let mut expected: Vec<String> = Analyzer::new(rules())
+        .scan(path)?
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    let mut calls = Analyzer::new(rules())
+        .iter_calls(path.to_path_buf())
+        .map(|call| call.map(|call| call.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    expected.sort();
+    calls.sort();
+    assert_eq!(calls.len(), 23);
+    assert_eq!(calls, expected);
+
+    let mut calls = Analyzer::new(rules()).iter_calls("/invalid/path".into());
+    assert!(calls.next().is_some_and(|call| call.is_err()));
+    assert!(calls.next().is_none());
+
+    // The workers have a pool of their own, so a consumer keeping the global pool busy does not hold them up
+    let gate = std::sync::Arc::new(std::sync::RwLock::new(()));
+    let closed = gate.write().unwrap();
+    for _ in 0..rayon::current_num_threads() {
+        let gate = gate.clone();
+        rayon::spawn(move || drop(gate.read().unwrap()));
+    }
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || sender.send(Analyzer::new(rules()).iter_calls(path.to_path_buf()).count()));
+    let calls = receiver.recv_timeout(std::time::Duration::from_secs(60));
+    drop(closed);
+    assert_eq!(calls, Ok(23));
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn should_not_follow_directory_links_when_scanning_with_the_library() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+    // A link cycle must not hang the scan
+    std::os::unix::fs::symlink(&classes_dir, classes_dir.join("cycle"))?;
+
+    let calls = Analyzer::new(vec![Rule::parse("java.util.Arrays#asList")?]).scan(&classes_dir)?;
+    let lines: Vec<String> = calls.iter().map(ToString::to_string).collect();
+    assert_eq!(lines, ["com.example.TestClass#testMethod (L9)"]);
+
+    Ok(())
+}
+
+#[test]
+fn should_page_through_calls_with_the_library() -> Result<(), Box<dyn std::error::Error>> {
+    let calls =
+        Analyzer::new(vec![Rule::parse("java.lang.String#toString")?]).scan(Path::new("tests/resources/com"))?;
+
+    let request: PageRequest = serde_json::from_str(r#"{"offset": 1, "limit": 2, "class": "com.example.Guarded*"}"#)?;
+    let page = request.apply(&calls);
+    assert_eq!(page.total, 4);
+    let lines: Vec<String> = page.calls.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        lines,
+        [
+            "com.example.GuardedClass#guardedMethod (L13) [synchronized] [receiver: str]",
+            "com.example.GuardedClass#guardedMethod (L15) [receiver: str]",
+        ]
+    );
+
+    let page = PageRequest {
+        offset: 10,
+        ..PageRequest::default()
+    }
+    .apply(&calls);
+    assert_eq!((page.total, page.calls.len()), (12, 2));
+
+    assert!(serde_json::from_str::<PageRequest>(r#"{"page": 2}"#).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn should_answer_references_as_a_language_server() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "ReachableClass.class",
+        include_bytes!("resources/com/example/ReachableClass.class"),
+    )?;
+
+    let document = "file:///work/src/main/java/com/example/ReachableClass.java";
+    let messages = [
+        serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": document, "text": include_str!("resources/com/example/ReachableClass.java") } },
+        }),
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "textDocument/references",
+            "params": { "textDocument": { "uri": document }, "position": { "line": 7, "character": 20 } },
+        }),
+        serde_json::json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" }),
+        serde_json::json!({ "jsonrpc": "2.0", "method": "exit" }),
+    ];
+    let input: String = messages
+        .iter()
+        .map(|message| {
+            let body = message.to_string();
+            format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+        })
+        .collect();
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["lsp", "-s", classes_dir.to_str().unwrap()])
+        .write_stdin(input);
+
+    // Without sources the call site links to its class file
+    let location = format!(
+        r#"{{"range":{{"end":{{"character":0,"line":4}},"start":{{"character":0,"line":4}}}},"uri":"file://{}/ReachableClass.class"}}"#,
+        classes_dir.display()
+    );
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(r#""referencesProvider":true"#))
+        .stdout(predicate::str::contains(format!(
+            r#"{{"id":2,"jsonrpc":"2.0","result":[{}]}}"#,
+            location
+        )))
+        .stdout(predicate::str::contains(r#"{"id":3,"jsonrpc":"2.0","result":null}"#));
+
+    Ok(())
+}
+
+#[test]
+fn should_reject_language_server_messages_over_the_limit() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["lsp", "-s", "tests/resources/com"])
+        .write_stdin("Content-Length: 1000000000000\r\n\r\n{}");
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Content-Length 1000000000000 exceeds 16777216 bytes",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn should_print_a_shields_badge() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "tests/resources/example.jar",
+        "-f",
+        "badge",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::diff(
+        "{\"schemaVersion\":1,\"label\":\"legacy API calls\",\"message\":\"4\",\"color\":\"yellow\"}\n",
+    ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.Thread",
+        "-m",
+        "stop",
+        "-s",
+        "tests/resources/example.jar",
+        "-f",
+        "badge",
+        "--badge-label",
+        "Thread#stop",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::diff(
+        "{\"schemaVersion\":1,\"label\":\"Thread#stop\",\"message\":\"0\",\"color\":\"brightgreen\"}\n",
+    ));
+
+    Ok(())
+}
+
+/// Accepts POST requests from a background thread, returning the URL and a channel of the request bodies.
+fn receive_posts() -> io::Result<(String, std::sync::mpsc::Receiver<String>)> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}/hook", listener.local_addr()?);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            let body_start = loop {
+                if let Some(end) = request.windows(4).position(|window| window == b"\r\n\r\n") {
+                    break end + 4;
+                }
+                match io::Read::read(&mut stream, &mut buffer) {
+                    Ok(0) | Err(_) => break request.len(),
+                    Ok(read) => request.extend_from_slice(&buffer[..read]),
+                }
+            };
+            let headers = String::from_utf8_lossy(&request[..body_start]).to_lowercase();
+            let length: usize = headers
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length:"))
+                .and_then(|length| length.trim().parse().ok())
+                .unwrap_or_default();
+            while request.len() < body_start + length {
+                match io::Read::read(&mut stream, &mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(read) => request.extend_from_slice(&buffer[..read]),
+                }
+            }
+            let _ = stream.write_all(b"HTTP/1.0 200 OK\r\nContent-Length: 0\r\n\r\n");
+            let _ = sender.send(String::from_utf8_lossy(&request[body_start..]).into_owned());
+        }
+    });
+    Ok((url, receiver))
+}
+
+#[test]
+fn should_notify_a_webhook_when_calls_exceed_the_threshold() -> Result<(), Box<dyn std::error::Error>> {
+    let (url, bodies) = receive_posts()?;
+    let args = [
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "tests/resources/com/example/TestClass.class",
+        "--notify-webhook",
+        &url,
+    ];
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(args);
+    cmd.assert().success();
+    let payload: serde_json::Value = serde_json::from_str(&bodies.recv()?)?;
+    assert_eq!(
+        payload,
+        serde_json::json!({
+            "target": "java.lang.String#toString",
+            "count": 2,
+            "threshold": 0,
+            "calls": ["com.example.TestClass#testMethod (L8)", "com.example.TestClass#testMethod (L10)"],
+        })
+    );
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(args).args(["--notify-format", "slack"]);
+    cmd.assert().success();
+    let payload: serde_json::Value = serde_json::from_str(&bodies.recv()?)?;
+    assert_eq!(
+        payload["text"],
+        "*jmf*: 2 calls to `java.lang.String#toString` (threshold 0)\n• `com.example.TestClass#testMethod (L8)`\n• `com.example.TestClass#testMethod (L10)`"
+    );
+
+    // Two calls do not exceed a threshold of two
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(args).args(["--notify-threshold", "2"]);
+    cmd.assert().success();
+    assert!(bodies.recv_timeout(std::time::Duration::from_millis(200)).is_err());
+
+    Ok(())
+}
+
+/// Sends an HTTP request without a body, returning the status line and the body of the response.
+fn http_request(address: &str, method: &str, path: &str) -> io::Result<(String, String)> {
+    let mut stream = std::net::TcpStream::connect(address)?;
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: 0\r\n\r\n",
+        method, path, address
+    )?;
+    let mut response = String::new();
+    io::Read::read_to_string(&mut stream, &mut response)?;
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    Ok((head.lines().next().unwrap_or_default().to_string(), body.to_string()))
+}
+
+#[test]
+fn should_serve_the_results_of_scheduled_scans() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+    let config_file = temp_dir.path().join("jmf.toml");
+    fs::write(
+        &config_file,
+        format!(
+            r#"
+[queries.to-string]
+class = "java.lang.String"
+method = "toString"
+
+[schedules.yearly]
+cron = "0 0 1 1 *"
+roots = ["{}"]
+"#,
+            classes_dir.display()
+        ),
+    )?;
+    let results_dir = temp_dir.path().join("results");
+
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("jmf"))
+        .args(["serve", "--listen", "127.0.0.1:0", "--config"])
+        .arg(&config_file)
+        .arg("--results-dir")
+        .arg(&results_dir)
+        .stdout(std::process::Stdio::piped())
+        .spawn()?;
+    let mut line = String::new();
+    io::BufRead::read_line(&mut io::BufReader::new(child.stdout.take().unwrap()), &mut line)?;
+    let address = line.trim().trim_start_matches("Listening on http://").to_string();
+
+    let responses = (|| -> Result<_, Box<dyn std::error::Error>> {
+        let (status, _) = http_request(&address, "GET", "/schedules/yearly/latest")?;
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+
+        // A client that never completes its request holds up none of the others
+        let _idle = std::net::TcpStream::connect(&address)?;
+
+        let (status, body) = http_request(&address, "POST", "/schedules/yearly/run")?;
+        assert_eq!(status, "HTTP/1.1 202 Accepted");
+        let job: serde_json::Value = serde_json::from_str(&body)?;
+        assert_eq!(job["id"], 1);
+        let job = loop {
+            let (_, body) = http_request(&address, "GET", "/jobs/1")?;
+            let job: serde_json::Value = serde_json::from_str(&body)?;
+            if job["status"] == "done" {
+                break job;
+            }
+            assert_ne!(job["status"], "failed");
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        };
+        assert_eq!(job["run"]["calls"], 2);
+        let (status, _) = http_request(&address, "GET", "/jobs/2")?;
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+
+        let (_, body) = http_request(&address, "GET", "/schedules")?;
+        let schedules: serde_json::Value = serde_json::from_str(&body)?;
+        assert_eq!(schedules["schedules"][0]["name"], "yearly");
+        assert_eq!(schedules["schedules"][0]["queries"], serde_json::json!(["to-string"]));
+        assert_eq!(schedules["schedules"][0]["last_run"]["calls"], 2);
+
+        let (_, body) = http_request(&address, "GET", "/schedules/yearly/calls?offset=1&limit=5")?;
+        let page: serde_json::Value = serde_json::from_str(&body)?;
+        assert_eq!(page["total"], 2);
+        assert_eq!(page["calls"].as_array().map(Vec::len), Some(1));
+        assert_eq!(page["calls"][0]["line_number"], 10);
+
+        let (_, body) = http_request(&address, "GET", "/schedules/yearly/latest")?;
+        let latest: serde_json::Value = serde_json::from_str(&body)?;
+        assert_eq!(latest["rules"][0]["calls"].as_array().map(Vec::len), Some(2));
+
+        let (_, body) = http_request(&address, "GET", "/schedules/yearly/trend")?;
+        let trend: serde_json::Value = serde_json::from_str(&body)?;
+        assert_eq!(trend["targets"][0]["points"][0]["count"], 2);
+
+        let (status, _) = http_request(&address, "GET", "/schedules/weekly/latest")?;
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+
+        // A schedule is queued once at a time, until the scheduler thread takes its run
+        let mut accepted = Vec::new();
+        let rejected = loop {
+            let (status, body) = http_request(&address, "POST", "/schedules/yearly/run")?;
+            if status == "HTTP/1.1 429 Too Many Requests" {
+                break body;
+            }
+            assert_eq!(status, "HTTP/1.1 202 Accepted");
+            accepted.push(serde_json::from_str::<serde_json::Value>(&body)?["id"].clone());
+            assert!(accepted.len() < 50, "no run was ever still queued");
+        };
+        assert!(rejected.contains("already queued as job"), "{}", rejected);
+        for id in &accepted {
+            while !http_request(&address, "GET", &format!("/jobs/{}", id))?
+                .1
+                .contains("\"done\"")
+            {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+            }
+        }
+
+        // The request line and headers are read up to 64 KiB
+        let mut stream = std::net::TcpStream::connect(&address)?;
+        let request_line = "GET /schedules HTTP/1.1\r\nX-Padding: ";
+        write!(stream, "{}{}", request_line, "a".repeat(64 * 1024 - request_line.len()))?;
+        let mut response = String::new();
+        io::Read::read_to_string(&mut stream, &mut response)?;
+        assert!(
+            response.starts_with("HTTP/1.1 431 Request Header Fields Too Large"),
+            "{}",
+            response
+        );
+        Ok(accepted.len())
+    })();
+    child.kill()?;
+    child.wait()?;
+    let accepted = responses?;
+
+    // The results are persisted for jmf trend, those of the same second too
+    assert_eq!(fs::read_dir(results_dir.join("yearly"))?.count(), 1 + accepted);
+
+    fs::write(
+        &config_file,
+        "[schedules.broken]\ncron = \"61 * * * *\"\nroots = [\"classes\"]\n",
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["serve", "--config", config_file.to_str().unwrap()]);
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Invalid minute '61' in cron expression '61 * * * *'",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn should_match_every_rule_in_a_shared_scan_as_alone() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let rules = [
+        "[[rule]]\nclass = \"java.lang.String\"\nmethod = \"toString\"\n",
+        "[[rule]]\nclass = \"java.lang.StringBuilder\"\nmethod = \"append\"\n",
+        "[[rule]]\npackage = \"java.util\"\n",
+        "[[rule]]\nfrom = \"com.example.*Class\"\nto = \"java.lang.**\"\n",
+        "[[rule]]\npattern = \"call java.lang.StringBuilder#append then call java.lang.StringBuilder#toString\"\n",
+    ];
+    let check = |policy: &str| -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let policy_file = temp_dir.path().join("policy.toml");
+        fs::write(&policy_file, policy)?;
+        let output = Command::cargo_bin("jmf")?
+            .args([
+                "check",
+                policy_file.to_str().unwrap(),
+                "-s",
+                "tests/resources/com",
+                "-f",
+                "json",
+            ])
+            .output()?;
+        Ok(serde_json::from_slice(&output.stdout)?)
+    };
+
+    let shared = check(&rules.concat())?;
+    for (index, rule) in rules.iter().enumerate() {
+        let alone = check(rule)?;
+        assert_eq!(shared["rules"][index], alone["rules"][0], "{}", rule);
+    }
+    let calls = |index: usize| shared["rules"][index]["calls"].as_array().map_or(0, Vec::len);
+    assert!((0..rules.len()).all(|index| calls(index) > 0), "{}", shared);
+
+    Ok(())
+}
+
+#[test]
+fn should_match_method_rules_on_interface_calls() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "com.example.app.UserService",
+        "-m",
+        "save",
+        "-s",
+        "tests/resources/iface",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "com.example.app.UserService#save\n - com.example.app.UserController#register (L11)",
+    ));
+
+    let policy_file = temp_dir.path().join("policy.toml");
+    fs::write(
+        &policy_file,
+        "[[rule]]\nclass = \"com.example.app.UserService\"\nmethod = \"save\"\n",
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["check", policy_file.to_str().unwrap(), "-s", "tests/resources/iface"]);
+
+    cmd.assert().code(1).stdout(predicate::str::contains(
+        "[error] com.example.app.UserService#save\n - com.example.app.UserController#register (L11)",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn should_walk_large_trees_in_parallel_in_name_order() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let mut expected = Vec::new();
+    for module in 0..20 {
+        for package in 0..5 {
+            let directory = temp_dir.path().join(format!("m{:02}/p{}", module, package));
+            fs::create_dir_all(&directory)?;
+            copy_test_class(&directory)?;
+            expected.push(format!("m{:02}/p{}/TestClass.class", module, package));
+        }
+    }
+    fs::write(temp_dir.path().join("m00/README"), "not a class file")?;
+
+    let output = Command::cargo_bin("jmf")?
+        .args(["-c", "java.lang.String", "-m", "toString", "--dry-run", "-v", "-s"])
+        .arg(temp_dir.path())
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let root = temp_dir.path().display().to_string();
+    let listed: Vec<&str> = stdout
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix(root.as_str()))
+        .map(|path| path.trim_start_matches('/'))
+        .filter(|path| !path.is_empty() && !path.starts_with('('))
+        .collect();
+    assert!(stdout.contains("(directory, 100 class files)"), "{}", stdout);
+    assert_eq!(listed, expected);
+
+    Ok(())
+}
+
+#[test]
+fn should_skip_indexed_jars_that_cannot_call_the_target() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let index_dir = temp_dir.path().join("index");
+    let search = |class: &str, method: &str, index: bool| -> Result<String, Box<dyn std::error::Error>> {
+        let mut command = Command::cargo_bin("jmf")?;
+        command.args(["-s", "tests/resources/example.jar", "-c", class, "-m", method, "-v"]);
+        if index {
+            command.arg("--jar-index").arg(&index_dir);
+        }
+        Ok(String::from_utf8(command.output()?.stdout)?)
+    };
+    let calls = |output: &str| -> Vec<String> {
+        output
+            .lines()
+            .filter(|line| line.starts_with(" - "))
+            .map(str::to_string)
+            .collect()
+    };
+
+    let unindexed = search("java.lang.String", "toString", false)?;
+    let indexing = search("java.lang.String", "toString", true)?;
+    assert!(indexing.contains("Indexed"), "{}", indexing);
+    assert_eq!(fs::read_dir(&index_dir)?.count(), 1);
+    assert_eq!(calls(&indexing), calls(&unindexed));
+    assert_eq!(calls(&unindexed).len(), 4, "{}", unindexed);
+
+    let skipping = search("java.util.ArrayList", "add", true)?;
+    assert!(
+        skipping.contains("Skipping archive without references to the targets: tests/resources/example.jar"),
+        "{}",
+        skipping
+    );
+    let indexed = search("java.lang.String", "toString", true)?;
+    assert!(!indexed.contains("Skipping archive"), "{}", indexed);
+    assert_eq!(calls(&indexed), calls(&unindexed));
+
+    Ok(())
+}
+
+#[test]
+fn should_scan_jars_that_cannot_be_indexed() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let jar = temp_dir.path().join("corrupt.jar");
+    let index_dir = temp_dir.path().join("index");
+    let mut writer = zip::ZipWriter::new(File::create(&jar)?);
+    writer.start_file("com/example/TestClass.class", zip::write::SimpleFileOptions::default())?;
+    writer.write_all(include_bytes!("resources/com/example/TestClass.class"))?;
+    writer.start_file("com/example/Bad.class", zip::write::SimpleFileOptions::default())?;
+    writer.write_all(&[0xca; 1000])?;
+    writer.finish()?;
+    // Garbles the deflated data of Bad.class
+    let mut bytes = fs::read(&jar)?;
+    let name = bytes
+        .windows(21)
+        .position(|window| window == b"com/example/Bad.class")
+        .unwrap();
+    let extra = u16::from_le_bytes([bytes[name - 2], bytes[name - 1]]) as usize;
+    let data = name + 21 + extra;
+    bytes[data..data + 4].fill(0xff);
+    fs::write(&jar, bytes)?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["-c", "java.lang.String", "-m", "toString", "-v", "-s"])
+        .arg(&jar)
+        .arg("--jar-index")
+        .arg(&index_dir);
+    cmd.assert()
+        .stdout(predicate::str::contains("Not indexing archive"))
+        .stdout(predicate::str::contains(
+            "com.example.TestClass#testMethod (L8) [receiver: str]",
+        ));
+    assert!(!index_dir.exists() || fs::read_dir(&index_dir)?.next().is_none());
+
+    Ok(())
+}
+
+#[test]
+fn should_benchmark_repeated_scans() -> Result<(), Box<dyn std::error::Error>> {
+    let output = Command::cargo_bin("jmf")?
+        .args([
+            "bench",
+            "-s",
+            "tests/resources/example.jar",
+            "-c",
+            "java.lang.String",
+            "-m",
+            "toString",
+        ])
+        .args(["--iterations", "3", "-f", "json"])
+        .output()?;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    // `--profile` is not implied by the measurements
+    assert!(!String::from_utf8(output.stderr)?.contains("Profile:"));
+    let bench: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(bench["target"], "java.lang.String#toString");
+    assert_eq!(bench["class_files"], 2);
+    assert_eq!(bench["calls"], 4);
+    let iterations = bench["iterations"].as_array().unwrap();
+    assert_eq!(iterations.len(), 3);
+    for iteration in iterations {
+        assert!(iteration["wall_nanos"].as_u64() > Some(0), "{}", iteration);
+        assert!(iteration["parse_nanos"].as_u64() > Some(0), "{}", iteration);
+        assert!(iteration["allocations"].as_u64() > Some(0), "{}", iteration);
+    }
+    assert!(bench["classes_per_second"].as_f64() > Some(0.0));
+    assert!(bench["bytes_per_second"].as_f64() > Some(0.0));
+
+    let text = Command::cargo_bin("jmf")?
+        .args([
+            "bench",
+            "-s",
+            "tests/resources/com",
+            "--iterations",
+            "1",
+            "--warmup",
+            "0",
+        ])
+        .output()?;
+    let text = String::from_utf8(text.stdout)?;
+    assert!(
+        text.starts_with("Benchmark of no target over 16 class files"),
+        "{}",
+        text
+    );
+    assert!(text.contains("classes/sec"), "{}", text);
+
+    Ok(())
+}
+
+#[test]
+fn should_benchmark_without_the_result_cache_and_past_unreadable_classes() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let jar = temp_dir.path().join("app.jar");
+    let class_bytes = include_bytes!("resources/com/example/TestClass.class");
+    let mut writer = zip::ZipWriter::new(File::create(&jar)?);
+    let stored = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    writer.start_file("com/example/TestClass.class", stored)?;
+    writer.write_all(class_bytes)?;
+    writer.start_file("com/example/Broken.class", stored)?;
+    writer.write_all(class_bytes)?;
+    writer.finish()?;
+    // A flipped byte in the stored data of the second entry fails its checksum
+    let mut bytes = fs::read(&jar)?;
+    let name = b"com/example/Broken.class";
+    let data = bytes.windows(name.len()).position(|window| window == name).unwrap() + name.len();
+    bytes[data + 20] ^= 0xff;
+    fs::write(&jar, bytes)?;
+
+    let output = Command::cargo_bin("jmf")?
+        .args([
+            "bench",
+            "-s",
+            jar.to_str().unwrap(),
+            "-c",
+            "java.util.Arrays",
+            "-m",
+            "asList",
+        ])
+        .args(["--iterations", "2", "-f", "json", "--result-cache"])
+        .arg(temp_dir.path().join("cache"))
+        // Keeps the error logged for the unreadable class out of the JSON
+        .arg("--log-file")
+        .arg(temp_dir.path().join("jmf.log"))
+        .output()?;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let bench: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(bench["class_files"], 1);
+    assert_eq!(bench["skipped"], 1);
+    assert_eq!(bench["calls"], 1);
+    // Every iteration parses the class instead of reading the cached results
+    for iteration in bench["iterations"].as_array().unwrap() {
+        assert!(iteration["parse_nanos"].as_u64() > Some(0), "{}", iteration);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn should_scan_in_a_reproducible_order_when_deterministic() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    for module in 0..8 {
+        let directory = temp_dir.path().join(format!("m{}", module));
+        fs::create_dir_all(&directory)?;
+        for entry in fs::read_dir("tests/resources/com/example")? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "class") {
+                fs::copy(&path, directory.join(path.file_name().unwrap()))?;
+            }
+        }
+    }
+    let scan = || -> Result<String, Box<dyn std::error::Error>> {
+        let output = Command::cargo_bin("jmf")?
+            .args([
+                "-c",
+                "java.lang.String",
+                "-m",
+                "toString",
+                "-v",
+                "--deterministic",
+                "-s",
+            ])
+            .arg(temp_dir.path())
+            // Several workers would analyze the classes in any order
+            .env("RAYON_NUM_THREADS", "4")
+            .output()?;
+        Ok(String::from_utf8(output.stdout)?)
+    };
+
+    let first = scan()?;
+    for _ in 0..3 {
+        assert_eq!(scan()?, first);
+    }
+    let analyzed: Vec<&str> = first
+        .lines()
+        .filter_map(|line| line.split_once("Analyzing class file: ").map(|(_, path)| path))
+        .collect();
+    assert_eq!(analyzed.len(), 8 * 16, "{}", first);
+    let mut sorted = analyzed.clone();
+    sorted.sort();
+    assert_eq!(analyzed, sorted);
+
+    Ok(())
+}
+
+#[test]
+fn should_classify_errors_and_stop_at_the_first_with_fail_fast() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+    // Java 8 and Java 36 headers followed by invalid constant pools
+    copy_class(
+        &classes_dir,
+        "Broken.class",
+        b"\xca\xfe\xba\xbe\x00\x00\x00\x34\x00\x05garbage",
+    )?;
+    copy_class(
+        &classes_dir,
+        "Future.class",
+        b"\xca\xfe\xba\xbe\x00\x00\x00\x50\x00\x05garbage",
+    )?;
+    let search = |extra: &[&str]| -> Result<std::process::Output, Box<dyn std::error::Error>> {
+        Ok(Command::cargo_bin("jmf")?
+            .args(["-c", "java.lang.String", "-m", "toString", "-s"])
+            .arg(&classes_dir)
+            .args(extra)
+            .output()?)
+    };
+
+    let output = search(&["-f", "json"])?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    // Error log lines come before the result
+    let result: serde_json::Value = serde_json::from_str(&stdout[stdout.find('{').unwrap()..])?;
+    let kinds: Vec<&str> = result["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|error| error["kind"].as_str())
+        .collect();
+    assert_eq!(kinds, ["parse", "unsupported-version"]);
+    assert_eq!(result["calls"].as_array().map(Vec::len), Some(2));
+
+    let output = search(&[])?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("By kind: 1 parse, 1 unsupported version"), "{}", stdout);
+
+    let output = search(&["--fail-fast"])?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("(--fail-fast)"), "{}", stderr);
+    assert!(stderr.contains("Broken.class"), "{}", stderr);
+
+    let output = search(&["--fail-fast", "--keep-going"])?;
+    assert!(output.status.success());
+
+    Ok(())
+}
+
+#[test]
+fn should_skip_files_without_the_class_file_magic() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+    // A Scala marker and a truncated download
+    copy_class(&classes_dir, "package.class", b"")?;
+    copy_class(&classes_dir, "Partial.class", b"\xca\xfe")?;
+
+    let output = Command::cargo_bin("jmf")?
+        .args(["-c", "java.lang.String", "-m", "toString", "-s"])
+        .arg(&classes_dir)
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(!stdout.contains("ERROR"), "{}", stdout);
+    assert!(!stdout.contains("Errors:"), "{}", stdout);
+    assert!(
+        stdout.contains("Skipped: 2 .class files that are not class files"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("TestClass#testMethod (L8)"), "{}", stdout);
+
+    let output = Command::cargo_bin("jmf")?
+        .args(["-c", "java.lang.String", "-m", "toString", "-f", "json", "-s"])
+        .arg(&classes_dir)
+        .output()?;
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(result["not_class_files"], 2);
+    assert!(result.get("errors").is_none(), "{}", result);
+
+    Ok(())
+}
+
+#[test]
+fn should_report_the_line_range_of_the_caller_method() -> Result<(), Box<dyn std::error::Error>> {
+    let output = Command::cargo_bin("jmf")?
+        .args([
+            "-c",
+            "java.lang.String",
+            "-m",
+            "toString",
+            "-s",
+            "tests/resources/example.jar",
+            "-f",
+            "json",
+        ])
+        .output()?;
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let calls = result["calls"].as_array().unwrap();
+    assert_eq!(calls.len(), 4);
+    for call in calls {
+        let (first, last) = (&call["method_lines"]["first"], &call["method_lines"]["last"]);
+        assert!(first.as_u64() <= call["line_number"].as_u64(), "{}", call);
+        assert!(call["line_number"].as_u64() <= last.as_u64(), "{}", call);
+    }
+    let test_method = calls.iter().find(|call| call["method_name"] == "testMethod").unwrap();
+    assert_eq!(
+        test_method["method_lines"],
+        serde_json::json!({ "first": 7, "last": 10 })
+    );
+
+    let output = Command::cargo_bin("jmf")?
+        .args([
+            "-c",
+            "java.lang.String",
+            "-m",
+            "toString",
+            "-s",
+            "tests/resources/example.jar",
+            "-f",
+            "sarif",
+        ])
+        .output()?;
+    let sarif: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let location = &sarif["runs"][0]["results"][0]["locations"][0]["physicalLocation"];
+    assert_eq!(location["contextRegion"]["startLine"], 7);
+    assert_eq!(location["contextRegion"]["endLine"], 11);
+
+    Ok(())
+}
+
+#[test]
+fn should_display_class_file_paths_as_configured() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(classes_dir.join("com/example"))?;
+    copy_test_class(&classes_dir)?;
+    copy_class(
+        &classes_dir.join("com/example"),
+        "Broken.class",
+        b"\xca\xfe\xba\xbe\x00\x00\x00\x34\x00\x05garbage",
+    )?;
+    let errors = |paths: &str| -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let output = Command::cargo_bin("jmf")?
+            .args([
+                "-c",
+                "java.lang.String",
+                "-m",
+                "toString",
+                "-f",
+                "json",
+                "--paths",
+                paths,
+                "-s",
+            ])
+            .arg(&classes_dir)
+            .output()?;
+        let stdout = String::from_utf8(output.stdout)?;
+        let result: serde_json::Value = serde_json::from_str(&stdout[stdout.find('{').unwrap()..])?;
+        Ok(result["errors"][0].clone())
+    };
+
+    assert_eq!(errors("relative")?["location"], "com/example/Broken.class");
+    let absolute = classes_dir.canonicalize()?.join("com/example/Broken.class");
+    assert_eq!(errors("absolute")?["location"], absolute.display().to_string());
+    let error = errors("none")?;
+    assert!(error.get("location").is_none(), "{}", error);
+    assert!(!error["message"].as_str().unwrap().contains("Broken"), "{}", error);
+
+    let output = Command::cargo_bin("jmf")?
+        .args(["-c", "java.lang.String", "-m", "toString", "-s"])
+        .arg(&classes_dir)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(
+        stdout.contains(" - com/example/Broken.class (class version 52): Failed to parse class file: "),
+        "{}",
+        stdout
+    );
+
+    Ok(())
+}
+
+#[test]
+fn should_summarize_each_origin_after_the_results() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "tests/resources/example.jar",
+        "-s",
+        "tests/resources/com",
+    ]);
+    cmd.assert().success().stdout(predicate::str::is_match(
+        r"Origin +Classes +Matches +Errors\ntests/resources/com +16 +12 +0\nexample.jar +2 +4 +0",
+    )?);
+
+    let output = Command::cargo_bin("jmf")?
+        .args([
+            "-c",
+            "java.lang.String",
+            "-m",
+            "toString",
+            "-f",
+            "json",
+            "-s",
+            "tests/resources/example.jar",
+            "-s",
+            "tests/resources/com",
+        ])
+        .output()?;
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let origins = result["by_origin"].as_array().unwrap();
+    assert_eq!(origins.len(), 2, "{}", result);
+    assert_eq!(origins[0]["origin"], "example.jar");
+    assert_eq!(origins[0]["classes"], 2);
+    assert_eq!(origins[0]["matches"], 4);
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args(["-c", "java.lang.String", "-m", "toString", "-s", "tests/resources/com"]);
+    cmd.assert().success().stdout(predicate::str::contains("Origin").not());
+    Ok(())
+}
+
+#[test]
+fn should_emit_a_manifest_of_the_scanned_artifacts_and_classes() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let manifest_path = temp_dir.path().join("manifest.json");
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "-c",
+        "java.lang.String",
+        "-m",
+        "toString",
+        "-s",
+        "tests/resources/example.jar",
+        "-s",
+        "tests/resources/com",
+        "--emit-manifest",
+    ])
+    .arg(&manifest_path);
+    cmd.assert().success();
+
+    let manifest: serde_json::Value = serde_json::from_str(&fs::read_to_string(&manifest_path)?)?;
+    let artifacts = manifest["artifacts"].as_array().unwrap();
+    let jar = artifacts
+        .iter()
+        .find(|artifact| artifact["path"] == "tests/resources/example.jar")
+        .unwrap();
+    assert_eq!(jar["kind"], "file");
+    assert_eq!(jar["size"], fs::metadata("tests/resources/example.jar")?.len());
+    assert_eq!(jar["sha256"].as_str().unwrap().len(), 64);
+    assert_eq!(jar["classes"], 2);
+    let directory = artifacts
+        .iter()
+        .find(|artifact| artifact["path"] == "tests/resources/com")
+        .unwrap();
+    assert_eq!(directory["kind"], "directory");
+    assert!(directory.get("sha256").is_none());
+
+    let classes = manifest["classes"].as_array().unwrap();
+    assert_eq!(classes.len(), 18);
+    let test_class = classes
+        .iter()
+        .find(|class| class["location"] == "tests/resources/com/example/TestClass.class")
+        .unwrap();
+    assert_eq!(test_class["artifact"], "tests/resources/com");
+    assert_eq!(
+        test_class["size"],
+        fs::metadata("tests/resources/com/example/TestClass.class")?.len()
+    );
+    assert_eq!(test_class["class_version"], 52);
+    Ok(())
+}
+
+#[test]
+fn should_list_modules_and_report_the_module_of_each_call() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.args([
+        "modules",
+        "-s",
+        "tests/resources/app-module.jar",
+        "-s",
+        "tests/resources/example.jar",
+    ]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "com.example.app (tests/resources/app-module.jar)\n - requires java.base\n - requires transitive java.logging\n - exports com.example.app.api\n - exports com.example.app.spi to com.example.friend\n - opens com.example.app.internal",
+    ));
+
+    let output = Command::cargo_bin("jmf")?
+        .args(["modules", "-f", "json", "-s", "tests/resources/app-module.jar"])
+        .output()?;
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let module = &result["modules"][0];
+    assert_eq!(module["name"], "com.example.app");
+    assert_eq!(module["requires"][1]["transitive"], true);
+    assert_eq!(module["exports"][1]["to"], serde_json::json!(["com.example.friend"]));
+
+    let output = Command::cargo_bin("jmf")?
+        .args([
+            "-c",
+            "java.lang.String",
+            "-m",
+            "trim",
+            "-s",
+            "tests/resources/app-module.jar",
+            "-s",
+            "tests/resources/com",
+        ])
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(
+        stdout.contains("com.example.app.internal.Hidden#render (L5) [receiver: text] [module: com.example.app]"),
+        "{}",
+        stdout
+    );
+    // The classes outside the module are in no module
+    assert_eq!(stdout.matches("[module: ").count(), 1, "{}", stdout);
+    Ok(())
+}
+
+#[test]
+fn should_flag_calls_into_packages_their_module_does_not_export() -> Result<(), Box<dyn std::error::Error>> {
+    let calls = |package: &str| -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let output = Command::cargo_bin("jmf")?
+            .args([
+                "-c",
+                package,
+                "--package",
+                "-f",
+                "json",
+                "-s",
+                "tests/resources/app-module.jar",
+                "-s",
+                "tests/resources/client.jar",
+            ])
+            .output()?;
+        let result: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+        Ok(result["targets"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .flat_map(|target| target["calls"].as_array().unwrap().clone())
+            .collect())
+    };
+    let caller = |calls: &serde_json::Value, class: &str| {
+        calls
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|call| call["class_name"] == class)
+            .cloned()
+            .unwrap()
+    };
+
+    let internal = calls("com.example.app.internal")?;
+    assert_eq!(
+        caller(&internal, "com/example/client/Client")["illegal_access"],
+        "com.example.app does not export com.example.app.internal"
+    );
+    // Classes of the module itself access all of its packages
+    assert!(caller(&internal, "com/example/app/api/Api")
+        .get("illegal_access")
+        .is_none());
+
+    let spi = calls("com.example.app.spi")?;
+    assert_eq!(
+        caller(&spi, "com/example/client/Client")["illegal_access"],
+        "com.example.app exports com.example.app.spi only to com.example.friend"
+    );
+    let api = calls("com.example.app.api")?;
+    assert!(caller(&api, "com/example/client/Client")
+        .get("illegal_access")
+        .is_none());
+    Ok(())
+}
+
+#[test]
+fn should_summarize_policy_severities_and_fail_only_on_errors() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let policy_file = temp_dir.path().join("policy.toml");
+    fs::write(
+        &policy_file,
+        r#"
+[[rule]]
+class = "java.lang.String"
+method = "toString"
+severity = "warn"
+
+[[rule]]
+class = "java.lang.String"
+method = "trim"
+severity = "info"
+"#,
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.arg("check").arg(&policy_file).args(["-s", "tests/resources/com"]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "0 errors, 12 warnings, 6 infos across 8 classes\n \
+         - [warn] java.lang.String#toString: 12 calls in 5 classes\n \
+         - [info] java.lang.String#trim: 6 calls in 3 classes",
+    ));
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.arg("check")
+        .arg(&policy_file)
+        .args(["-s", "tests/resources/com", "--warnings-as-errors"]);
+    cmd.assert().code(1);
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.arg("check")
+        .arg(&policy_file)
+        .args(["-s", "tests/resources/com", "--color", "always"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("[\x1b[33mwarn\x1b[0m]"))
+        .stdout(predicate::str::contains("0 errors, \x1b[33m12 warnings\x1b[0m"));
+    Ok(())
+}
+
+#[test]
+fn should_report_calls_of_annotated_callers_as_suppressed() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_class(
+        &classes_dir,
+        "SuppressedClass.class",
+        include_bytes!("resources/suppress/com/example/SuppressedClass.class"),
+    )?;
+    let policy_file = temp_dir.path().join("policy.toml");
+    fs::write(
+        &policy_file,
+        r#"
+[[rule]]
+class = "java.lang.Object"
+method = "toString"
+severity = "warn"
+
+[[rule]]
+id = "legacy-trim"
+class = "java.lang.String"
+method = "trim"
+
+[[rule]]
+class = "java.lang.String"
+method = "toString"
+severity = "info"
+"#,
+    )?;
+
+    // The annotation without rule ids suppresses every rule, the one naming `jmf:legacy-trim` only that one
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.arg("check").arg(&policy_file).arg("-s").arg(&classes_dir).args([
+        "--suppress-annotation",
+        "ApprovedUsage",
+        "--warnings-as-errors",
+    ]);
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "[warn] java.lang.Object#toString\n \
+             - com.example.SuppressedClass#approved (L6) [receiver: value] [suppressed by @com.example.ApprovedUsage]\n \
+             - com.example.SuppressedClass#unapproved (L15) [receiver: value]\n\
+             [error] java.lang.String#trim\n \
+             - com.example.SuppressedClass#approvedTrim (L11) [receiver: value] [suppressed by @com.example.ApprovedUsage]\n\
+             [info] java.lang.String#toString\n \
+             - com.example.SuppressedClass#approvedTrim (L11) [receiver: value]\n",
+        ))
+        .stdout(predicate::str::contains(
+            " - [warn] java.lang.Object#toString: 1 call in 1 class, 1 suppressed\n \
+             - [error] java.lang.String#trim: 0 calls in 0 classes, 1 suppressed",
+        ));
+
+    // Suppressed error calls do not fail the run
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.arg("check").arg(&policy_file).arg("-s").arg(&classes_dir).args([
+        "--suppress-annotation",
+        "com.example.ApprovedUsage",
+        "-f",
+        "sarif",
+    ]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let sarif: serde_json::Value = serde_json::from_slice(&output)?;
+    let suppressed = sarif["runs"][0]["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|result| result["suppressions"][0]["kind"] == "inSource")
+        .count();
+    assert_eq!(suppressed, 2);
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.arg("check").arg(&policy_file).arg("-s").arg(&classes_dir);
+    cmd.assert().code(1);
+    Ok(())
+}
+
+#[test]
+fn should_suppress_calls_acknowledged_by_ignore_comments() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let classes_dir = temp_dir.path().join("classes");
+    fs::create_dir_all(&classes_dir)?;
+    copy_test_class(&classes_dir)?;
+    let source_dir = temp_dir.path().join("src/com/example");
+    fs::create_dir_all(&source_dir)?;
+    // Only the lines of the source matter: a comment line before the call at L8, a trailing comment at L9
+    let source = fs::read_to_string("tests/resources/com/example/TestClass.java")?
+        .replace("        String str = \"test\";\n", "        // jmf:ignore\n")
+        .replace(
+            "        Arrays.asList(str);\n",
+            "        Arrays.asList(str); /* jmf:ignore until 2.0 */\n",
+        );
+    fs::write(source_dir.join("TestClass.java"), source)?;
+    let policy_file = temp_dir.path().join("policy.toml");
+    fs::write(
+        &policy_file,
+        r#"
+[[rule]]
+class = "java.lang.String"
+method = "toString"
+
+[[rule]]
+class = "java.util.Arrays"
+method = "asList"
+severity = "warn"
+"#,
+    )?;
+
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.arg("check")
+        .arg(&policy_file)
+        .arg("-s")
+        .arg(&classes_dir)
+        .arg("--source-root")
+        .arg(temp_dir.path().join("src"));
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "[error] java.lang.String#toString\n \
+             - com.example.TestClass#testMethod (L8) [receiver: str] [suppressed by // jmf:ignore]\n \
+             - com.example.TestClass#testMethod (L10) [receiver: str]\n\
+             [warn] java.util.Arrays#asList\n \
+             - com.example.TestClass#testMethod (L9) [suppressed by // jmf:ignore]\n",
+        ))
+        .stdout(predicate::str::contains(
+            " - [error] java.lang.String#toString: 1 call in 1 class, 1 suppressed\n \
+             - [warn] java.util.Arrays#asList: 0 calls in 0 classes, 1 suppressed",
+        ));
+
+    // Without the sources, no call is suppressed
+    let mut cmd = Command::cargo_bin("jmf")?;
+    cmd.arg("check").arg(&policy_file).arg("-s").arg(&classes_dir);
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains("suppressed by").not());
+    Ok(())
+}
+
+#[test]
+fn should_link_rules_to_their_documentation() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let policy_file = temp_dir.path().join("policy.toml");
+    fs::write(
+        &policy_file,
+        r#"
+[[rule]]
+class = "java.util.Arrays"
+method = "asList"
+message = "use List.of instead"
+help_url = "https://wiki.example.com/migrations/list-of"
+"#,
+    )?;
+    let check = |format: &str| -> Result<String, Box<dyn std::error::Error>> {
+        let output = Command::cargo_bin("jmf")?
+            .arg("check")
+            .arg(&policy_file)
+            .args(["-s", "tests/resources/com/example/TestClass.class", "-f", format])
+            .output()?;
+        Ok(String::from_utf8(output.stdout)?)
+    };
+
+    assert!(check("txt")?.starts_with(
+        "[error] java.util.Arrays#asList: use List.of instead (see https://wiki.example.com/migrations/list-of)\n"
+    ));
+    let json: serde_json::Value = serde_json::from_str(&check("json")?)?;
+    assert_eq!(
+        json["rules"][0]["help_url"],
+        "https://wiki.example.com/migrations/list-of"
+    );
+    let sarif: serde_json::Value = serde_json::from_str(&check("sarif")?)?;
+    assert_eq!(
+        sarif["runs"][0]["tool"]["driver"]["rules"][0]["helpUri"],
+        "https://wiki.example.com/migrations/list-of"
+    );
+    Ok(())
+}
@@ -0,0 +1,9 @@
+package com.example.abstracts;
+
+public interface Api {
+    String name();
+
+    default String describe() {
+        return name().trim();
+    }
+}
@@ -0,0 +1,12 @@
+package com.example.abstracts;
+
+public abstract class Impl implements Api {
+    protected abstract String prefix();
+
+    public static native long nativeTime();
+
+    public String run() {
+        String str = prefix();
+        return str.trim();
+    }
+}
@@ -0,0 +1,8 @@
+package com.example.abstracts;
+
+// Compiled with -g:none, so it has no LineNumberTable
+public class Stripped {
+    public String strip(String str) {
+        return str.trim();
+    }
+}
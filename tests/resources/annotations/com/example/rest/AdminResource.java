@@ -0,0 +1,14 @@
+package com.example.rest;
+
+@Path("/admin")
+public class AdminResource {
+    @Path("/admin/users")
+    public String users() {
+        return "users";
+    }
+
+    @Path(value = "/admin/audit", methods = {"GET", "DELETE"})
+    public String audit(@QueryParam("since") String since) {
+        return since;
+    }
+}
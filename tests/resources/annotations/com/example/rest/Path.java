@@ -0,0 +1,11 @@
+package com.example.rest;
+
+import java.lang.annotation.Retention;
+import java.lang.annotation.RetentionPolicy;
+
+@Retention(RetentionPolicy.RUNTIME)
+public @interface Path {
+    String value();
+
+    String[] methods() default {"GET"};
+}
@@ -0,0 +1,9 @@
+package com.example.rest;
+
+import java.lang.annotation.Retention;
+import java.lang.annotation.RetentionPolicy;
+
+@Retention(RetentionPolicy.RUNTIME)
+public @interface QueryParam {
+    String value();
+}
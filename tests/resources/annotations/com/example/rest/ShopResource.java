@@ -0,0 +1,9 @@
+package com.example.rest;
+
+@Path("/shop")
+public class ShopResource {
+    @Path(value = "/shop/cart", methods = {"GET", "POST"})
+    public String cart() {
+        return "cart";
+    }
+}
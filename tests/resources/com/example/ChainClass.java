@@ -0,0 +1,7 @@
+package com.example;
+
+public class ChainClass {
+    public String chainMethod(String name) {
+        return new StringBuilder().append("Hello, ").append(name).append('!').toString();
+    }
+}
@@ -0,0 +1,24 @@
+package com.example;
+
+/** The shape of a class compiled by Groovy with call-site caching. */
+public class GroovyClass {
+    public Object clean(Object value) {
+        Object[] callSites = $getCallSiteArray();
+        return call(callSites[1], value);
+    }
+
+    private static Object call(Object callSite, Object receiver) {
+        return receiver;
+    }
+
+    private static void $createCallSiteArray_1(String[] names) {
+        names[0] = "println";
+        names[1] = "trim";
+    }
+
+    private static Object[] $getCallSiteArray() {
+        String[] names = new String[2];
+        $createCallSiteArray_1(names);
+        return names;
+    }
+}
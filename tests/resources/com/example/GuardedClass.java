@@ -0,0 +1,21 @@
+package com.example;
+
+public class GuardedClass {
+    private final Object lock = new Object();
+
+    public void guardedMethod(String str) {
+        try {
+            str.toString();
+        } catch (IllegalStateException e) {
+            return;
+        }
+        synchronized (lock) {
+            str.toString();
+        }
+        str.toString();
+    }
+
+    public synchronized void synchronizedMethod(String str) {
+        str.toString();
+    }
+}
@@ -0,0 +1,13 @@
+package com.example;
+
+import java.util.function.Function;
+
+public class HandleClass {
+    public Function<String, String> trimmer() {
+        return String::trim;
+    }
+
+    public String trim(String value) {
+        return value.trim();
+    }
+}
@@ -0,0 +1,22 @@
+package com.example;
+
+import java.util.concurrent.locks.Lock;
+
+public class LockClass {
+    public void guarded(Lock lock) {
+        lock.lock();
+        try {
+            work();
+        } finally {
+            lock.unlock();
+        }
+    }
+
+    public void leaked(Lock lock) {
+        lock.lock();
+        work();
+    }
+
+    private void work() {
+    }
+}
@@ -0,0 +1,12 @@
+package com.example;
+
+import java.util.List;
+
+public class LoopClass {
+    public void loopMethod(List<String> items) {
+        for (String item : items) {
+            item.toString();
+        }
+        "done".toString();
+    }
+}
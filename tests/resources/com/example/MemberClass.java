@@ -0,0 +1,14 @@
+package com.example;
+
+public class MemberClass {
+    enum Color { RED, GREEN }
+
+    record Point(int x, int y) {}
+
+    public String memberMethod() {
+        Color[] colors = Color.values();
+        Color red = Color.valueOf("RED");
+        Point point = new Point(colors.length, red.ordinal());
+        return point.x() + point.toString();
+    }
+}
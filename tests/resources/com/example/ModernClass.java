@@ -0,0 +1,8 @@
+package com.example;
+
+public class ModernClass {
+    public String modernMethod() {
+        var str = "modern";
+        return str.toString();
+    }
+}
@@ -0,0 +1,20 @@
+package com.example;
+
+public class ReachableClass {
+    public static void main(String[] args) {
+        new ReachableClass().liveMethod("main");
+    }
+
+    private void liveMethod(String str) {
+        str.toString();
+    }
+
+    private void deadMethod(String str) {
+        str.toString();
+    }
+
+    @Deprecated
+    public void annotatedMethod(String str) {
+        str.toString();
+    }
+}
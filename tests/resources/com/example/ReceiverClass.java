@@ -0,0 +1,12 @@
+package com.example;
+
+import java.util.List;
+
+public class ReceiverClass {
+    private final StringBuilder buffer = new StringBuilder();
+
+    public String describe(List<String> names) {
+        buffer.append(names.size());
+        return this.toString() + buffer.toString();
+    }
+}
@@ -0,0 +1,18 @@
+package com.example;
+
+/** The shape of the module class Scala compiles `object ScalaApp` to. */
+public final class ScalaApp$ {
+    public static final ScalaApp$ MODULE$ = new ScalaApp$();
+
+    public String run(String value) {
+        return $anonfun$run$1(value);
+    }
+
+    public static String $anonfun$run$1(String value) {
+        return value.trim();
+    }
+
+    public String $plus$plus(String value) {
+        return value.trim() + value;
+    }
+}
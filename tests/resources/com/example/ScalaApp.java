@@ -0,0 +1,8 @@
+package com.example;
+
+/** The shape of the companion class of `object ScalaApp`, with its static forwarders. */
+public final class ScalaApp {
+    public static String run(String value) {
+        return ScalaApp$.MODULE$.run(value);
+    }
+}
@@ -0,0 +1,8 @@
+package com.example;
+
+public class ShiftClass {
+    public String shift(String value) {
+        String first = value.trim();
+        return first.trim();
+    }
+}
@@ -0,0 +1,15 @@
+package com.example.billing;
+
+import com.example.orders.Order;
+
+public class Invoice {
+    private final Order order;
+
+    public Invoice(Order order) {
+        this.order = order;
+    }
+
+    public long amount() {
+        return order.total;
+    }
+}
@@ -0,0 +1,17 @@
+package com.example.orders;
+
+import com.example.billing.Invoice;
+
+public class Order {
+    public final String id;
+    public final long total;
+
+    public Order(String id, long total) {
+        this.id = id;
+        this.total = total;
+    }
+
+    public Invoice invoice() {
+        return new Invoice(this);
+    }
+}
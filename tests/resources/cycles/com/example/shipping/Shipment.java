@@ -0,0 +1,9 @@
+package com.example.shipping;
+
+import com.example.orders.Order;
+
+public class Shipment {
+    public String label(Order order) {
+        return "Order " + order.id;
+    }
+}
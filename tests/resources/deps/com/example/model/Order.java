@@ -0,0 +1,15 @@
+package com.example.model;
+
+public class Order {
+    public String id;
+    public int quantity;
+
+    public Order(String id, int quantity) {
+        this.id = id;
+        this.quantity = quantity;
+    }
+
+    public String describe() {
+        return id + " x" + quantity;
+    }
+}
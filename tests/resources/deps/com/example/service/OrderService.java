@@ -0,0 +1,11 @@
+package com.example.service;
+
+import com.example.model.Order;
+
+public class OrderService {
+    public Order place(String id, int quantity) {
+        Order order = new Order(id, quantity);
+        order.quantity = Math.max(order.quantity, 1);
+        return order;
+    }
+}
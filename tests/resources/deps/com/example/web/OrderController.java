@@ -0,0 +1,13 @@
+package com.example.web;
+
+import com.example.model.Order;
+import com.example.service.OrderService;
+
+public class OrderController {
+    private final OrderService service = new OrderService();
+
+    public String post(Object body) {
+        Order order = service.place((String) body, 1);
+        return order.describe() + " " + order.id;
+    }
+}
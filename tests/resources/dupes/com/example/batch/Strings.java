@@ -0,0 +1,28 @@
+package com.example.batch;
+
+public class Strings {
+    private static final String EMPTY = "";
+
+    static String orEmpty(String value) {
+        return value == null ? EMPTY : value;
+    }
+
+    public static boolean blank(String text) {
+        if (text == null) {
+            return true;
+        }
+        for (int i = 0; i < text.length(); i++) {
+            if (!Character.isWhitespace(text.charAt(i))) {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    public static String upperFirst(String value) {
+        if (value == null || value.isEmpty()) {
+            return value;
+        }
+        return Character.toTitleCase(value.charAt(0)) + value.substring(1);
+    }
+}
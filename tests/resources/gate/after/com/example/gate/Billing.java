@@ -0,0 +1,18 @@
+package com.example.gate;
+
+import java.util.Date;
+
+public class Billing {
+    public Date invoice() {
+        return new Date();
+    }
+
+    public Date refund() {
+        return new Date();
+    }
+
+    public Date audit() {
+        // jmf:ignore
+        return new Date();
+    }
+}
@@ -0,0 +1,13 @@
+package com.example.gate;
+
+import java.util.Date;
+
+public class Billing {
+    public Date invoice() {
+        return new Date();
+    }
+
+    public long legacyTotal() {
+        return new Date().getTime();
+    }
+}
@@ -0,0 +1,15 @@
+package com.example.checkout;
+
+import com.example.shop.Cart;
+import com.example.shop.LegacyCart;
+import com.example.shop.internal.Pricing;
+
+public class Checkout {
+    public int run(Cart cart) {
+        cart.add("apple");
+        cart.add("pear", 2);
+        new LegacyCart().checkout();
+        cart.clear();
+        return Pricing.price(3) + cart.total();
+    }
+}
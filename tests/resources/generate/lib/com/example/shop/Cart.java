@@ -0,0 +1,32 @@
+package com.example.shop;
+
+import com.example.shop.internal.Pricing;
+
+public class Cart {
+    private int items;
+
+    public void add(String sku) {
+        items++;
+    }
+
+    @Deprecated
+    public void add(String sku, int quantity) {
+        items += quantity;
+    }
+
+    @Deprecated
+    public void clear() {
+        items = 0;
+    }
+
+    /**
+     * @deprecated use {@link #total()}
+     */
+    public int sum() {
+        return total();
+    }
+
+    public int total() {
+        return Pricing.price(items);
+    }
+}
@@ -0,0 +1,7 @@
+package com.example.shop;
+
+@Deprecated
+public class LegacyCart {
+    public void checkout() {
+    }
+}
@@ -0,0 +1,7 @@
+package com.example.shop.internal;
+
+public class Pricing {
+    public static int price(int items) {
+        return items * 100;
+    }
+}
@@ -0,0 +1,7 @@
+package com.example.shop.spi;
+
+public class Discount {
+    public int apply(int total) {
+        return total;
+    }
+}
@@ -0,0 +1,4 @@
+module com.example.shop {
+    exports com.example.shop;
+    exports com.example.shop.spi to com.example.plugins;
+}
@@ -0,0 +1,13 @@
+package com.example.app;
+
+public class UserController {
+    private final UserService userService;
+
+    public UserController(UserService userService) {
+        this.userService = userService;
+    }
+
+    public void register(String name) {
+        userService.save(name);
+    }
+}
@@ -0,0 +1,5 @@
+package com.example.app;
+
+public interface UserService {
+    void save(String name);
+}
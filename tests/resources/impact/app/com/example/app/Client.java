@@ -0,0 +1,14 @@
+package com.example.app;
+
+import com.example.lib.Greeter;
+import com.example.lib.Legacy;
+
+public class Client {
+    public void run(Greeter greeter) {
+        greeter.greet("world");
+        greeter.wave();
+        Greeter.count();
+        greeter.hello();
+        new Legacy().run();
+    }
+}
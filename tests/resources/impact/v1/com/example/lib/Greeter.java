@@ -0,0 +1,21 @@
+package com.example.lib;
+
+public class Greeter {
+    public String greet(String name) {
+        return "Hello, " + name;
+    }
+
+    public void wave() {
+    }
+
+    public static int count() {
+        return 0;
+    }
+
+    public String hello() {
+        return "hello";
+    }
+
+    public void unused(int times) {
+    }
+}
@@ -0,0 +1,6 @@
+package com.example.lib;
+
+public class Legacy {
+    public void run() {
+    }
+}
@@ -0,0 +1,12 @@
+package com.example.lib;
+
+public class Base {
+    public static final String GREETING = "hello";
+
+    private int calls;
+
+    public String hello() {
+        calls++;
+        return GREETING;
+    }
+}
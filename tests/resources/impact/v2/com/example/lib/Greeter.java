@@ -0,0 +1,14 @@
+package com.example.lib;
+
+public class Greeter extends Base {
+    public String greet(String name, boolean formal) {
+        return (formal ? "Good day, " : "Hello, ") + name;
+    }
+
+    public int count() {
+        return 0;
+    }
+
+    void unused(int times) {
+    }
+}
@@ -0,0 +1,9 @@
+package com.example.app.api;
+
+import com.example.app.internal.Hidden;
+
+public class Api {
+    public String describe(Object value) {
+        return Hidden.render(value.toString());
+    }
+}
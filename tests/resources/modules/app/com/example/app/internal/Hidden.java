@@ -0,0 +1,7 @@
+package com.example.app.internal;
+
+public class Hidden {
+    public static String render(String text) {
+        return text.trim();
+    }
+}
@@ -0,0 +1,7 @@
+package com.example.app.spi;
+
+public class Plugin {
+    public String name() {
+        return "plugin";
+    }
+}
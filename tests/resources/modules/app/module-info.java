@@ -0,0 +1,6 @@
+module com.example.app {
+    requires transitive java.logging;
+    exports com.example.app.api;
+    exports com.example.app.spi to com.example.friend;
+    opens com.example.app.internal;
+}
@@ -0,0 +1,12 @@
+package com.example.client;
+
+import com.example.app.api.Api;
+import com.example.app.internal.Hidden;
+import com.example.app.spi.Plugin;
+
+public class Client {
+    public String run(Plugin plugin) {
+        String name = plugin.name();
+        return new Api().describe(name) + Hidden.render(name);
+    }
+}
@@ -0,0 +1,12 @@
+package com.example;
+
+public class Repository {
+    public void save(Object entity) {
+    }
+
+    public void save(String name) {
+    }
+
+    public void save(String name, int version) {
+    }
+}
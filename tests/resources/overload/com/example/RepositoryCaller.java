@@ -0,0 +1,9 @@
+package com.example;
+
+public class RepositoryCaller {
+    public void store(Repository repository) {
+        repository.save(new Object());
+        repository.save("name");
+        repository.save("name", 2);
+    }
+}
@@ -0,0 +1,50 @@
+//! Sample jmf plugin reporting every `monitorenter` and printing the number of hits.
+//!
+//! rustc --edition 2021 --crate-type cdylib monitors.rs
+//! rustc --edition 2021 --crate-type cdylib --target wasm32-unknown-unknown -C opt-level=s -C strip=symbols \
+//!     -C panic=abort -C lto monitors.rs
+
+use std::ffi::{c_char, CStr, CString};
+
+#[no_mangle]
+pub extern "C" fn jmf_plugin_name() -> *const c_char {
+    c"monitors".as_ptr()
+}
+
+/// # Safety
+/// `method` is a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn jmf_plugin_match(method: *const c_char) -> *mut c_char {
+    let method = CStr::from_ptr(method).to_string_lossy();
+    // Opcodes are flat JSON objects, so each one ends at the next '}'
+    let offsets: Vec<&str> = method
+        .split('{')
+        .filter_map(|object| object.split('}').next())
+        .filter(|opcode| opcode.contains("\"opcode\":\"monitorenter\""))
+        .filter_map(|opcode| opcode.split("\"offset\":").nth(1))
+        .filter_map(|rest| rest.split(',').next())
+        .collect();
+    CString::new(format!("[{}]", offsets.join(","))).unwrap().into_raw()
+}
+
+/// # Safety
+/// `result` is a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn jmf_plugin_format(result: *const c_char) -> *mut c_char {
+    let result = CStr::from_ptr(result).to_string_lossy();
+    let calls = result.matches("\"line_number\"").count();
+    CString::new(format!("monitors: {} calls", calls)).unwrap().into_raw()
+}
+
+/// Buffer of `size` bytes for a NUL-terminated string passed in by a WebAssembly host.
+#[no_mangle]
+pub extern "C" fn jmf_plugin_alloc(size: usize) -> *mut c_char {
+    CString::new(vec![b' '; size.saturating_sub(1)]).unwrap().into_raw()
+}
+
+/// # Safety
+/// `string` was returned by one of the functions above, or by `jmf_plugin_alloc` and filled.
+#[no_mangle]
+pub unsafe extern "C" fn jmf_plugin_free(string: *mut c_char) {
+    drop(CString::from_raw(string));
+}
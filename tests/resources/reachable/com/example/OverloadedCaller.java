@@ -0,0 +1,15 @@
+package com.example;
+
+public class OverloadedCaller {
+    public static void main(String[] args) {
+        describe(args.length);
+    }
+
+    private static void describe(int count) {
+        String.valueOf(count).toString();
+    }
+
+    private static void describe(String str) {
+        str.toString();
+    }
+}
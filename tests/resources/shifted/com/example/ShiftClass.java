@@ -0,0 +1,14 @@
+package com.example;
+
+/**
+ * The next build of ShiftClass, with lines and calls added before the trims.
+ */
+public class ShiftClass {
+    public String shift(String value) {
+        if (value.isEmpty()) {
+            return value;
+        }
+        String first = value.trim();
+        return first.trim();
+    }
+}
@@ -0,0 +1,17 @@
+package com.example;
+
+public class SuppressedClass {
+    @ApprovedUsage
+    public String approved(Object value) {
+        return value.toString();
+    }
+
+    @ApprovedUsage("jmf:legacy-trim")
+    public String approvedTrim(String value) {
+        return value.trim() + value.toString();
+    }
+
+    public String unapproved(Object value) {
+        return value.toString();
+    }
+}
@@ -0,0 +1,41 @@
+package com.example.threads;
+
+import java.util.HashMap;
+import java.util.Map;
+import java.util.concurrent.locks.Lock;
+import java.util.concurrent.locks.ReentrantLock;
+
+public class Inventory {
+    private final Map<String, Integer> stock = new HashMap<>();
+    private final Lock lock = new ReentrantLock();
+
+    public synchronized void restock(String item) {
+        stock.put(item, 10);
+    }
+
+    public void sell(String item) {
+        synchronized (this) {
+            stock.put(item, 0);
+        }
+    }
+
+    public void reserve(String item) {
+        lock.lock();
+        try {
+            stock.put(item, 1);
+        } finally {
+            lock.unlock();
+        }
+    }
+
+    public void audit(String item) {
+        stock.put(item, -1);
+    }
+
+    public void adjust(String item) {
+        stock.put(item, 2);
+        synchronized (this) {
+            stock.remove(item);
+        }
+    }
+}
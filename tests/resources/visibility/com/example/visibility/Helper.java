@@ -0,0 +1,7 @@
+package com.example.visibility;
+
+class Helper {
+    public String clean(String value) {
+        return value.trim();
+    }
+}
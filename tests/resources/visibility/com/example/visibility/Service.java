@@ -0,0 +1,19 @@
+package com.example.visibility;
+
+public class Service {
+    public String name(String name) {
+        return normalize(name).trim();
+    }
+
+    protected String label(String label) {
+        return label.trim();
+    }
+
+    String key(String key) {
+        return key.trim();
+    }
+
+    private String normalize(String value) {
+        return value.trim().toLowerCase();
+    }
+}